@@ -0,0 +1,291 @@
+//! # Asynchronous Process Runner
+//!
+//! [`TokioProcessRunner`] implements [`AsyncProcessRunner`] with `tokio::process`
+//! and `tokio::time`. It runs the same `taskkill` and `tasklist` commands as
+//! [`SystemProcessRunner`](crate::SystemProcessRunner), but awaits them instead of
+//! blocking the thread. [`BackendRunner`] is the asynchronous runner of a
+//! [`Backend`](crate::Backend), created by [`Backend::async_runner`](crate::Backend::async_runner).
+//!
+//! This module requires the `async` feature, which `mcp` enables.
+
+use crate::{
+    AsyncProcessRunner, Outcome, ProcessInfo, StuckbarError, TargetSpec, launch_command, launched,
+    posted_taskbar_exit, taskkill_command, taskkill_pid_command, taskkill_result, tasklist_command,
+    tasklist_current_process, tasklist_pids, tasklist_processes,
+};
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Process runner that awaits `taskkill` and `tasklist` on the Tokio runtime
+pub struct TokioProcessRunner;
+
+impl AsyncProcessRunner for TokioProcessRunner {
+    async fn kill_process(&self, process_name: &str) -> Result<Outcome, StuckbarError> {
+        taskkill(&["/F"], process_name).await?;
+        Ok(Outcome::new(format!(
+            "Successfully terminated {}",
+            process_name
+        )))
+    }
+
+    fn start_process(
+        &self,
+        target: &TargetSpec,
+    ) -> impl Future<Output = Result<Outcome, StuckbarError>> + Send {
+        spawn(target)
+    }
+
+    async fn list_pids(&self, process_name: &str) -> Result<Vec<u32>, StuckbarError> {
+        let output = Command::from(tasklist_command(process_name, false))
+            .output()
+            .await;
+        tasklist_pids(process_name, output)
+    }
+
+    async fn sleep_ms(&self, ms: u64) {
+        tokio::time::sleep(Duration::from_millis(ms)).await
+    }
+
+    async fn kill_pid(&self, pid: u32, process_name: &str) -> Result<(), StuckbarError> {
+        let output = Command::from(taskkill_pid_command(pid)).output().await;
+        taskkill_result(process_name, output)
+    }
+
+    async fn list_processes(&self, process_name: &str) -> Result<Vec<ProcessInfo>, StuckbarError> {
+        let output = Command::from(tasklist_command(process_name, true))
+            .output()
+            .await;
+        tasklist_processes(process_name, output)
+    }
+
+    /// Looked up once with `tasklist`, on a blocking thread
+    fn current_process(&self) -> impl Future<Output = Option<ProcessInfo>> + Send {
+        blocking(tasklist_current_process)
+    }
+
+    /// Posts the taskbar's exit message for explorer, like
+    /// [`SystemProcessRunner`](crate::SystemProcessRunner)
+    fn request_exit(
+        &self,
+        process_name: &str,
+    ) -> impl Future<Output = Result<(), StuckbarError>> + Send {
+        request_exit(process_name)
+    }
+
+    fn run_blocking<T: Send + 'static>(
+        &self,
+        work: Box<dyn FnOnce() -> T + Send>,
+    ) -> impl Future<Output = T> + Send {
+        blocking(work)
+    }
+}
+
+/// Terminates processes through the Win32 API and awaits everything else
+///
+/// Enumerating, terminating and posting exit requests are short system calls,
+/// so they run inline; launching, sleeping and other blocking work go through
+/// Tokio.
+#[cfg(feature = "native")]
+impl AsyncProcessRunner for crate::native::NativeProcessRunner {
+    fn kill_process(
+        &self,
+        process_name: &str,
+    ) -> impl Future<Output = Result<Outcome, StuckbarError>> + Send {
+        std::future::ready(crate::ProcessRunner::kill_process(self, process_name))
+    }
+
+    fn start_process(
+        &self,
+        target: &TargetSpec,
+    ) -> impl Future<Output = Result<Outcome, StuckbarError>> + Send {
+        spawn(target)
+    }
+
+    fn list_pids(
+        &self,
+        process_name: &str,
+    ) -> impl Future<Output = Result<Vec<u32>, StuckbarError>> + Send {
+        std::future::ready(crate::ProcessRunner::list_pids(self, process_name))
+    }
+
+    async fn sleep_ms(&self, ms: u64) {
+        tokio::time::sleep(Duration::from_millis(ms)).await
+    }
+
+    fn kill_pid(
+        &self,
+        pid: u32,
+        process_name: &str,
+    ) -> impl Future<Output = Result<(), StuckbarError>> + Send {
+        std::future::ready(crate::ProcessRunner::kill_pid(self, pid, process_name))
+    }
+
+    fn list_processes(
+        &self,
+        process_name: &str,
+    ) -> impl Future<Output = Result<Vec<ProcessInfo>, StuckbarError>> + Send {
+        std::future::ready(crate::ProcessRunner::list_processes(self, process_name))
+    }
+
+    fn current_process(&self) -> impl Future<Output = Option<ProcessInfo>> + Send {
+        std::future::ready(crate::ProcessRunner::current_process(self))
+    }
+
+    fn request_exit(
+        &self,
+        process_name: &str,
+    ) -> impl Future<Output = Result<(), StuckbarError>> + Send {
+        std::future::ready(crate::ProcessRunner::request_exit(self, process_name))
+    }
+
+    fn run_blocking<T: Send + 'static>(
+        &self,
+        work: Box<dyn FnOnce() -> T + Send>,
+    ) -> impl Future<Output = T> + Send {
+        blocking(work)
+    }
+}
+
+/// Asynchronous runner of a process backend chosen at runtime
+pub enum BackendRunner {
+    Taskkill(TokioProcessRunner),
+    #[cfg(feature = "native")]
+    Native(crate::native::NativeProcessRunner),
+}
+
+impl AsyncProcessRunner for BackendRunner {
+    async fn kill_process(&self, process_name: &str) -> Result<Outcome, StuckbarError> {
+        match self {
+            Self::Taskkill(runner) => runner.kill_process(process_name).await,
+            #[cfg(feature = "native")]
+            Self::Native(runner) => runner.kill_process(process_name).await,
+        }
+    }
+
+    fn start_process(
+        &self,
+        target: &TargetSpec,
+    ) -> impl Future<Output = Result<Outcome, StuckbarError>> + Send {
+        spawn(target)
+    }
+
+    async fn list_pids(&self, process_name: &str) -> Result<Vec<u32>, StuckbarError> {
+        match self {
+            Self::Taskkill(runner) => runner.list_pids(process_name).await,
+            #[cfg(feature = "native")]
+            Self::Native(runner) => runner.list_pids(process_name).await,
+        }
+    }
+
+    async fn sleep_ms(&self, ms: u64) {
+        tokio::time::sleep(Duration::from_millis(ms)).await
+    }
+
+    async fn kill_pid(&self, pid: u32, process_name: &str) -> Result<(), StuckbarError> {
+        match self {
+            Self::Taskkill(runner) => runner.kill_pid(pid, process_name).await,
+            #[cfg(feature = "native")]
+            Self::Native(runner) => AsyncProcessRunner::kill_pid(runner, pid, process_name).await,
+        }
+    }
+
+    async fn list_processes(&self, process_name: &str) -> Result<Vec<ProcessInfo>, StuckbarError> {
+        match self {
+            Self::Taskkill(runner) => runner.list_processes(process_name).await,
+            #[cfg(feature = "native")]
+            Self::Native(runner) => AsyncProcessRunner::list_processes(runner, process_name).await,
+        }
+    }
+
+    async fn current_process(&self) -> Option<ProcessInfo> {
+        match self {
+            Self::Taskkill(runner) => runner.current_process().await,
+            #[cfg(feature = "native")]
+            Self::Native(runner) => AsyncProcessRunner::current_process(runner).await,
+        }
+    }
+
+    async fn request_exit(&self, process_name: &str) -> Result<(), StuckbarError> {
+        match self {
+            Self::Taskkill(runner) => runner.request_exit(process_name).await,
+            #[cfg(feature = "native")]
+            Self::Native(runner) => AsyncProcessRunner::request_exit(runner, process_name).await,
+        }
+    }
+
+    fn run_blocking<T: Send + 'static>(
+        &self,
+        work: Box<dyn FnOnce() -> T + Send>,
+    ) -> impl Future<Output = T> + Send {
+        blocking(work)
+    }
+}
+
+/// Run `taskkill` with `args` followed by `/IM process_name`
+async fn taskkill(args: &[&str], process_name: &str) -> Result<(), StuckbarError> {
+    let output = Command::from(taskkill_command(args, process_name))
+        .output()
+        .await;
+    taskkill_result(process_name, output)
+}
+
+/// Run `work` on Tokio's blocking threads, passing on its panics
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> T + Send + 'static) -> T {
+    match tokio::task::spawn_blocking(work).await {
+        Ok(output) => output,
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+}
+
+/// Launch `target`; the child is not awaited and keeps running
+async fn spawn(target: &TargetSpec) -> Result<Outcome, StuckbarError> {
+    let (program, command) = launch_command(target);
+    let pid = Command::from(command).spawn().map(|child| child.id());
+    launched(target, program, pid)
+}
+
+/// Ask explorer through the taskbar, or anything else through `taskkill`, to exit
+async fn request_exit(process_name: &str) -> Result<(), StuckbarError> {
+    if posted_taskbar_exit(process_name) {
+        return Ok(());
+    }
+    taskkill(&[], process_name).await
+}
+
+#[cfg(all(test, not(windows)))]
+mod tests {
+    use super::*;
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_missing_tasklist_is_reported() {
+        let err = block_on(TokioProcessRunner.list_pids("explorer.exe")).unwrap_err();
+        assert_eq!(
+            err,
+            StuckbarError::CommandMissing {
+                command: "tasklist".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_missing_launcher_is_reported() {
+        let target = TargetSpec::explorer().with_launch_command("stuckbar-missing-launcher");
+        let err = block_on(TokioProcessRunner.start_process(&target)).unwrap_err();
+        assert_eq!(err.kind(), "command_missing");
+    }
+
+    #[test]
+    fn test_sleep_waits() {
+        let started = std::time::Instant::now();
+        block_on(TokioProcessRunner.sleep_ms(20));
+        assert!(started.elapsed() >= Duration::from_millis(20));
+    }
+}
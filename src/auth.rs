@@ -0,0 +1,356 @@
+//! # HTTP Authentication
+//!
+//! The HTTP MCP server can kill the desktop shell of whoever runs it, so every
+//! request must present one of the configured [`ApiToken`]s, either as
+//! `Authorization: Bearer <token>` or as `X-API-Key: <token>`. Requests without
+//! a valid token are answered with `401 Unauthorized`.
+//!
+//! A token may be limited to some tools. A request made with such a token that
+//! calls any other tool is answered with `403 Forbidden`.
+//!
+//! ```toml
+//! [mcp]
+//! tokens = [
+//!     "full-access-token",
+//!     { token = "read-only-token", tools = ["check_taskbar_health"] },
+//! ]
+//! ```
+//!
+//! Tokens can also be given as `STUCKBAR_MCP_TOKENS`, comma-separated; those
+//! may call every tool.
+//!
+//! Without tokens the server is open to anyone who can reach it, which
+//! [`check_exposure`] only accepts on a loopback address.
+
+use crate::StuckbarError;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+/// Header carrying a token as an alternative to `Authorization: Bearer`
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// A token accepted by the HTTP server
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ApiToken {
+    /// A token that may call every tool
+    Full(String),
+    /// A token that may only call `tools`
+    Limited { token: String, tools: Vec<String> },
+}
+
+impl ApiToken {
+    /// The secret presented by clients
+    pub fn secret(&self) -> &str {
+        match self {
+            ApiToken::Full(token) | ApiToken::Limited { token, .. } => token,
+        }
+    }
+
+    /// Tools this token is limited to, or `None` if it may call every tool
+    pub fn tools(&self) -> Option<&[String]> {
+        match self {
+            ApiToken::Full(_) => None,
+            ApiToken::Limited { tools, .. } => Some(tools),
+        }
+    }
+
+    /// Whether this token may call the tool `name`
+    pub fn allows(&self, name: &str) -> bool {
+        self.tools()
+            .is_none_or(|tools| tools.iter().any(|tool| tool == name))
+    }
+}
+
+/// The tokens the HTTP server accepts; none leaves it unauthenticated
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpAuth {
+    tokens: Vec<ApiToken>,
+}
+
+impl HttpAuth {
+    pub fn new(tokens: Vec<ApiToken>) -> Self {
+        Self { tokens }
+    }
+
+    /// Whether requests must present a token
+    pub fn is_enabled(&self) -> bool {
+        !self.tokens.is_empty()
+    }
+
+    pub fn tokens(&self) -> &[ApiToken] {
+        &self.tokens
+    }
+
+    /// The token whose secret is `presented`
+    ///
+    /// Every token is compared in full, so the time taken does not reveal
+    /// which token matched or how much of one did.
+    pub fn authenticate(&self, presented: &str) -> Option<&ApiToken> {
+        self.tokens.iter().fold(None, |found, token| {
+            let matches = constant_time_eq(token.secret().as_bytes(), presented.as_bytes());
+            if matches && found.is_none() {
+                Some(token)
+            } else {
+                found
+            }
+        })
+    }
+}
+
+/// The token presented in the `Authorization` or [`API_KEY_HEADER`] header
+/// values, preferring a bearer token
+pub fn presented_token<'a>(
+    authorization: Option<&'a str>,
+    api_key: Option<&'a str>,
+) -> Option<&'a str> {
+    let bearer = authorization.and_then(|value| {
+        let (scheme, token) = value.trim().split_once(' ')?;
+        scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+    });
+    bearer
+        .or(api_key.map(str::trim))
+        .filter(|token| !token.is_empty())
+}
+
+/// Whether `host` only accepts connections from this machine
+pub fn is_loopback(host: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host.eq_ignore_ascii_case("localhost")
+        || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Refuse to serve without authentication on an address other machines can
+/// reach, unless `insecure` is set
+///
+/// # Errors
+///
+/// Returns [`StuckbarError::InvalidConfig`] if `host` is not a loopback
+/// address, `auth` has no tokens and `insecure` is not set.
+pub fn check_exposure(host: &str, auth: &HttpAuth, insecure: bool) -> Result<(), StuckbarError> {
+    if auth.is_enabled() || insecure || is_loopback(host) {
+        return Ok(());
+    }
+    Err(StuckbarError::InvalidConfig {
+        details: format!(
+            "refusing to serve on {} without authentication; set mcp.tokens or pass --insecure",
+            host
+        ),
+    })
+}
+
+/// Names of the tools called by the JSON-RPC message or batch in `body`
+///
+/// A call without a tool name is reported as an empty name, so no limited
+/// token allows it.
+///
+/// # Errors
+///
+/// Returns the parse error if `body` is not JSON.
+pub fn called_tools(body: &[u8]) -> Result<Vec<String>, serde_json::Error> {
+    let value: serde_json::Value = serde_json::from_slice(body)?;
+    let messages = match value {
+        serde_json::Value::Array(messages) => messages,
+        message => vec![message],
+    };
+    Ok(messages
+        .iter()
+        .filter(|message| message.get("method").and_then(|m| m.as_str()) == Some("tools/call"))
+        .map(|message| {
+            message
+                .pointer("/params/name")
+                .and_then(|name| name.as_str())
+                .unwrap_or_default()
+                .to_string()
+        })
+        .collect())
+}
+
+/// Compare `a` and `b` in time that depends only on their lengths
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y));
+    std::hint::black_box(diff) == 0
+}
+
+#[cfg(feature = "mcp-http")]
+pub(crate) use layer::reject;
+
+#[cfg(feature = "mcp-http")]
+mod layer {
+    use super::*;
+    use axum::body::{Body, to_bytes};
+    use axum::extract::{Request, State};
+    use axum::http::{StatusCode, header};
+    use axum::middleware::{Next, from_fn_with_state};
+    use axum::response::{IntoResponse, Response};
+    use std::sync::Arc;
+
+    impl HttpAuth {
+        /// Require a valid token for every request to `router`
+        ///
+        /// Bodies of requests made with a limited token are read to find the
+        /// tools they call; the body size limit belongs outside this layer.
+        pub fn protect(self, router: axum::Router) -> axum::Router {
+            router.layer(from_fn_with_state(Arc::new(self), check_request))
+        }
+    }
+
+    async fn check_request(
+        State(auth): State<Arc<HttpAuth>>,
+        request: Request,
+        next: Next,
+    ) -> Response {
+        let headers = request.headers();
+        let presented = presented_token(
+            headers
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok()),
+            headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok()),
+        );
+        let Some(token) = presented.and_then(|presented| auth.authenticate(presented)) else {
+            let mut response = reject(StatusCode::UNAUTHORIZED, "missing or invalid token");
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                header::HeaderValue::from_static("Bearer realm=\"stuckbar\""),
+            );
+            return response;
+        };
+        if token.tools().is_none() {
+            return next.run(request).await;
+        }
+
+        let (parts, body) = request.into_parts();
+        // Already cut off at the server's limit
+        let Ok(body) = to_bytes(body, usize::MAX).await else {
+            return reject(StatusCode::PAYLOAD_TOO_LARGE, "request body too large");
+        };
+        if !body.is_empty() {
+            let Ok(tools) = called_tools(&body) else {
+                return reject(StatusCode::BAD_REQUEST, "request body is not JSON");
+            };
+            if let Some(tool) = tools.iter().find(|tool| !token.allows(tool)) {
+                return reject(
+                    StatusCode::FORBIDDEN,
+                    &format!("this token may not call '{}'", tool),
+                );
+            }
+        }
+        next.run(Request::from_parts(parts, Body::from(body))).await
+    }
+
+    /// JSON error response with `status`
+    pub(crate) fn reject(status: StatusCode, message: &str) -> Response {
+        let error = status
+            .canonical_reason()
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+            .replace(' ', "_");
+        let body = serde_json::json!({ "error": error, "message": message });
+        (status, axum::Json(body)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth() -> HttpAuth {
+        HttpAuth::new(vec![
+            ApiToken::Full("full-secret".to_string()),
+            ApiToken::Limited {
+                token: "limited-secret".to_string(),
+                tools: vec!["check_taskbar_health".to_string()],
+            },
+        ])
+    }
+
+    #[test]
+    fn test_token_deserialize() {
+        let tokens: Vec<ApiToken> = serde_json::from_value(serde_json::json!([
+            "abc",
+            { "token": "def", "tools": ["check_taskbar_health"] }
+        ]))
+        .unwrap();
+
+        assert_eq!(tokens[0], ApiToken::Full("abc".to_string()));
+        assert_eq!(tokens[1].secret(), "def");
+        assert!(tokens[1].allows("check_taskbar_health"));
+        assert!(!tokens[1].allows("restart_explorer"));
+        assert!(tokens[0].allows("restart_explorer"));
+    }
+
+    #[test]
+    fn test_authenticate() {
+        let auth = auth();
+
+        assert_eq!(
+            auth.authenticate("full-secret"),
+            Some(&ApiToken::Full("full-secret".to_string()))
+        );
+        assert_eq!(
+            auth.authenticate("limited-secret").map(ApiToken::secret),
+            Some("limited-secret")
+        );
+        assert_eq!(auth.authenticate("full-secre"), None);
+        assert_eq!(auth.authenticate("full-secreT"), None);
+        assert_eq!(auth.authenticate(""), None);
+        assert!(auth.is_enabled());
+        assert!(!HttpAuth::default().is_enabled());
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secrets"));
+        assert!(constant_time_eq(b"", b""));
+    }
+
+    #[test]
+    fn test_presented_token() {
+        assert_eq!(presented_token(Some("Bearer abc"), None), Some("abc"));
+        assert_eq!(presented_token(Some("bearer  abc "), None), Some("abc"));
+        assert_eq!(presented_token(Some("Basic abc"), None), None);
+        assert_eq!(presented_token(Some("Bearer"), None), None);
+        assert_eq!(presented_token(None, Some("key")), Some("key"));
+        assert_eq!(
+            presented_token(Some("Bearer abc"), Some("key")),
+            Some("abc")
+        );
+        assert_eq!(presented_token(Some("Basic abc"), Some("key")), Some("key"));
+        assert_eq!(presented_token(None, Some(" ")), None);
+        assert_eq!(presented_token(None, None), None);
+    }
+
+    #[test]
+    fn test_check_exposure() {
+        let open = HttpAuth::default();
+
+        for host in ["127.0.0.1", "127.1.2.3", "::1", "[::1]", "localhost"] {
+            assert!(check_exposure(host, &open, false).is_ok(), "{}", host);
+        }
+        let err = check_exposure("0.0.0.0", &open, false).unwrap_err();
+        assert_eq!(err.kind(), "invalid_config");
+        assert!(err.to_string().contains("--insecure"));
+        assert!(check_exposure("192.168.1.5", &open, false).is_err());
+        assert!(check_exposure("desktop.lan", &open, false).is_err());
+        assert!(check_exposure("0.0.0.0", &open, true).is_ok());
+        assert!(check_exposure("0.0.0.0", &auth(), false).is_ok());
+    }
+
+    #[test]
+    fn test_called_tools() {
+        let single = br#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"restart_explorer","arguments":{}}}"#;
+        assert_eq!(called_tools(single).unwrap(), vec!["restart_explorer"]);
+
+        let batch = br#"[{"jsonrpc":"2.0","id":1,"method":"tools/list"},{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{}}]"#;
+        assert_eq!(called_tools(batch).unwrap(), vec![""]);
+
+        let other = br#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#;
+        assert!(called_tools(other).unwrap().is_empty());
+        assert!(called_tools(b"not json").is_err());
+    }
+}
@@ -0,0 +1,1553 @@
+//! # Configuration
+//!
+//! Every tunable can be set in a TOML file, through `STUCKBAR_*` environment
+//! variables or with command-line flags. The layers are merged in that order, each
+//! one overriding the keys it sets:
+//!
+//! 1. built-in defaults ([`Config::default`])
+//! 2. the configuration file: the path given with `--config`, else
+//!    `$STUCKBAR_CONFIG`, else `%APPDATA%\stuckbar\config.toml` if it exists
+//! 3. environment variables named after the key, e.g. `STUCKBAR_MCP_PORT` for
+//!    `mcp.port`; lists are comma-separated
+//! 4. flags given on the command line
+//!
+//! ```toml
+//! [restart]
+//! delay_ms = 500
+//! target = "explorer"
+//! kill_strategy = "graceful"
+//! grace_period_ms = 5000
+//! session = "current"
+//!
+//! [watch]
+//! probes = ["process", "window"]
+//!
+//! [mcp]
+//! transport = "http"
+//! port = 3000
+//! http_transports = ["streamable-http"]
+//! allowed_tools = ["restart_explorer", "check_taskbar_health"]
+//! tokens = ["s3cret", { token = "monitor", tools = ["check_taskbar_health"] }]
+//! base_path = "/stuckbar"
+//!
+//! [mcp.tls]
+//! self_signed = true
+//!
+//! [history]
+//! max_bytes = 1048576
+//!
+//! [profiles.flyouts]
+//! targets = ["shell-experience", "start-menu"]
+//! ```
+//!
+//! Restart profiles are described in the [`profile`](crate::profile) module.
+//!
+//! [`LayeredConfig`] remembers which layer set each key (its [`Source`]), so the
+//! effective configuration can be shown with the origin of every value.
+
+use crate::auth::ApiToken;
+use crate::history::{self, HISTORY_MAX_BYTES, HistoryLog};
+use crate::profile::{BUILTIN_PROFILE_NAMES, Profile};
+use crate::scope::SessionScope;
+use crate::target::PRESET_NAMES;
+use crate::watchdog::{
+    BackoffPolicy, CHECK_INTERVAL_MS, COOLDOWN_MS, CPU_THRESHOLD_PERCENT, FAILURE_THRESHOLD,
+    MAX_RESTARTS_PER_HOUR, MEMORY_GROWTH_MB, PROBE_NAMES, WatchdogConfig,
+};
+use crate::{
+    BACKEND_NAMES, Backend, GRACE_PERIOD_MS, KillStrategy, RESTART_DELAY_MS, StuckbarError,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Prefix of the environment variables that override settings
+pub const ENV_PREFIX: &str = "STUCKBAR_";
+
+/// Environment variable naming the configuration file
+pub const CONFIG_PATH_ENV: &str = "STUCKBAR_CONFIG";
+
+/// Keys whose values are hidden when the configuration is shown
+pub const SECRET_KEYS: &[&str] = &["mcp.tokens"];
+
+/// Shown in place of a secret
+pub const REDACTED: &str = "<redacted>";
+
+/// Effective settings, after every layer has been merged
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub restart: RestartSettings,
+    pub watch: WatchSettings,
+    pub mcp: McpSettings,
+    pub log: LogSettings,
+    pub history: HistorySettings,
+    /// Restart profiles declared in the file, by name; they replace built-in
+    /// profiles of the same name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl Config {
+    /// The declared or built-in profile called `name`
+    pub fn profile(&self, name: &str) -> Option<Profile> {
+        self.profiles
+            .get(name)
+            .cloned()
+            .or_else(|| Profile::builtin(name))
+    }
+
+    /// Every profile that can be run, by name
+    pub fn profiles(&self) -> BTreeMap<String, Profile> {
+        let mut profiles: BTreeMap<String, Profile> = BUILTIN_PROFILE_NAMES
+            .iter()
+            .filter_map(|name| Some((name.to_string(), Profile::builtin(name)?)))
+            .collect();
+        profiles.extend(self.profiles.clone());
+        profiles
+    }
+}
+
+/// How the target is killed and restarted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RestartSettings {
+    /// Delay between the old instances exiting and the new one being started
+    pub delay_ms: u64,
+    /// Target preset to act on (see [`PRESET_NAMES`])
+    pub target: String,
+    /// Process backend (see [`BACKEND_NAMES`])
+    pub backend: String,
+    pub kill_strategy: KillMode,
+    /// Time a graceful exit may take before the process is forced
+    pub grace_period_ms: u64,
+    /// Sessions whose instances are killed: a session ID or one of
+    /// [`SESSION_SCOPE_NAMES`](crate::scope::SESSION_SCOPE_NAMES)
+    pub session: String,
+}
+
+impl Default for RestartSettings {
+    fn default() -> Self {
+        Self {
+            delay_ms: RESTART_DELAY_MS,
+            target: "explorer".to_string(),
+            backend: Backend::default().name().to_string(),
+            kill_strategy: KillMode::Force,
+            grace_period_ms: GRACE_PERIOD_MS,
+            session: SessionScope::Current.to_string(),
+        }
+    }
+}
+
+impl RestartSettings {
+    /// The configured [`KillStrategy`]
+    pub fn strategy(&self) -> KillStrategy {
+        self.kill_strategy.strategy(self.grace_period_ms)
+    }
+
+    /// The configured [`SessionScope`]; the caller's session if it is invalid
+    pub fn session_scope(&self) -> SessionScope {
+        self.session.parse().unwrap_or(SessionScope::Current)
+    }
+}
+
+/// Name of a [`KillStrategy`] in the configuration
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KillMode {
+    #[default]
+    Force,
+    Graceful,
+}
+
+impl KillMode {
+    /// The [`KillStrategy`] of this mode, forcing graceful exits after `grace_ms`
+    pub fn strategy(self, grace_ms: u64) -> KillStrategy {
+        match self {
+            Self::Force => KillStrategy::Force,
+            Self::Graceful => KillStrategy::Graceful { grace_ms },
+        }
+    }
+}
+
+/// Tunables of `stuckbar watch`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WatchSettings {
+    /// Interval between health checks
+    pub interval_ms: u64,
+    /// Consecutive failed checks before restarting
+    pub threshold: u32,
+    /// Minimum time between restarts
+    pub cooldown_ms: u64,
+    pub max_restarts_per_hour: u32,
+    /// Health probes to run (see [`PROBE_NAMES`])
+    pub probes: Vec<String>,
+    /// CPU usage, in percent of one core, considered pegged
+    pub cpu_threshold: u64,
+    /// Working set growth in MB considered a leak
+    pub memory_growth_mb: u64,
+}
+
+impl Default for WatchSettings {
+    fn default() -> Self {
+        Self {
+            interval_ms: CHECK_INTERVAL_MS,
+            threshold: FAILURE_THRESHOLD,
+            cooldown_ms: COOLDOWN_MS,
+            max_restarts_per_hour: MAX_RESTARTS_PER_HOUR,
+            probes: vec!["process".to_string()],
+            cpu_threshold: CPU_THRESHOLD_PERCENT,
+            memory_growth_mb: MEMORY_GROWTH_MB,
+        }
+    }
+}
+
+impl WatchSettings {
+    /// The configured [`WatchdogConfig`]
+    pub fn watchdog_config(&self) -> WatchdogConfig {
+        WatchdogConfig {
+            interval_ms: self.interval_ms,
+            failure_threshold: self.threshold,
+            cooldown_ms: self.cooldown_ms,
+            max_restarts_per_hour: self.max_restarts_per_hour,
+            backoff: BackoffPolicy::default(),
+        }
+    }
+}
+
+/// Tunables of `stuckbar serve`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct McpSettings {
+    pub transport: Transport,
+    /// Host address the HTTP transport binds to
+    pub host: String,
+    /// Port the HTTP transport listens on
+    pub port: u16,
+    /// HTTP transports served, from one listener (see [`HTTP_TRANSPORT_NAMES`])
+    pub http_transports: Vec<HttpTransport>,
+    /// Tools exposed to clients; empty exposes every tool
+    pub allowed_tools: Vec<String>,
+    /// Tokens HTTP clients must present; empty leaves the server open
+    pub tokens: Vec<ApiToken>,
+    /// Serve without tokens on an address other machines can reach
+    pub insecure: bool,
+    /// Prefix of every HTTP path, for serving behind a reverse proxy; empty
+    /// serves from the root
+    pub base_path: String,
+    /// Path of the Streamable HTTP endpoint
+    pub streamable_path: String,
+    /// Path of the SSE event stream
+    pub sse_path: String,
+    /// Path SSE clients POST their requests to
+    pub post_path: String,
+    /// Interval between keep-alive pings on event streams
+    pub sse_keep_alive_ms: u64,
+    /// Origins browsers may call the HTTP server from; `"*"` allows any
+    pub cors_origins: Vec<String>,
+    /// Largest request body accepted
+    pub max_body_bytes: u64,
+    /// Sessions served at once; 0 is unlimited
+    pub max_sessions: u32,
+    /// Time after which an idle Streamable HTTP session is closed; 0 keeps it
+    /// open until the client ends it
+    pub session_timeout_ms: u64,
+    /// Certificate the HTTP transport is served over TLS with
+    pub tls: TlsSettings,
+}
+
+impl Default for McpSettings {
+    fn default() -> Self {
+        Self {
+            transport: Transport::Stdio,
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            http_transports: vec![HttpTransport::StreamableHttp, HttpTransport::Sse],
+            allowed_tools: Vec::new(),
+            tokens: Vec::new(),
+            insecure: false,
+            base_path: String::new(),
+            streamable_path: "/mcp".to_string(),
+            sse_path: "/sse".to_string(),
+            post_path: "/message".to_string(),
+            sse_keep_alive_ms: SSE_KEEP_ALIVE_MS,
+            cors_origins: Vec::new(),
+            max_body_bytes: MAX_BODY_BYTES,
+            max_sessions: 0,
+            session_timeout_ms: 0,
+            tls: TlsSettings::default(),
+        }
+    }
+}
+
+/// MCP transport
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Transport {
+    #[default]
+    Stdio,
+    /// Requires the `mcp-http` feature
+    Http,
+}
+
+/// Names of the [`HttpTransport`]s, as used in the configuration and on the
+/// command line
+pub const HTTP_TRANSPORT_NAMES: &[&str] = &["streamable-http", "sse"];
+
+/// Default interval between keep-alive pings on HTTP event streams
+pub const SSE_KEEP_ALIVE_MS: u64 = 15_000;
+
+/// Default largest HTTP request body accepted
+pub const MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+/// Keys that only the HTTP transport uses
+const HTTP_KEYS: &[&str] = &[
+    "mcp.host",
+    "mcp.port",
+    "mcp.http_transports",
+    "mcp.insecure",
+    "mcp.base_path",
+    "mcp.streamable_path",
+    "mcp.sse_path",
+    "mcp.post_path",
+    "mcp.sse_keep_alive_ms",
+    "mcp.cors_origins",
+    "mcp.max_body_bytes",
+    "mcp.max_sessions",
+    "mcp.session_timeout_ms",
+    "mcp.tls.cert",
+    "mcp.tls.key",
+    "mcp.tls.self_signed",
+];
+
+/// MCP protocol spoken over the HTTP transport
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HttpTransport {
+    /// Streamable HTTP: a single endpoint, `/mcp` by default, taking POSTed
+    /// requests and answering with JSON or an event stream
+    StreamableHttp,
+    /// The deprecated HTTP+SSE transport: an event stream at `/sse` and requests
+    /// POSTed to `/message` by default
+    Sse,
+}
+
+/// Where operation results are recorded
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogSettings {
+    /// File every finished operation is appended to as a line of JSON
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+}
+
+/// Certificate and key of the HTTP transport, in PEM; requires the `mcp-tls`
+/// feature
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cert: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<PathBuf>,
+    /// Generate a self-signed certificate at `cert` and `key` if they do not
+    /// exist [default paths: `%LOCALAPPDATA%\stuckbar\tls\{cert,key}.pem`]
+    pub self_signed: bool,
+}
+
+impl TlsSettings {
+    /// Paths of the certificate and key, or `None` to serve plain HTTP
+    pub fn files(&self) -> Option<(PathBuf, PathBuf)> {
+        match (&self.cert, &self.key) {
+            (Some(cert), Some(key)) => Some((cert.clone(), key.clone())),
+            _ if self.self_signed => {
+                let dir = PathBuf::from(std::env::var_os("LOCALAPPDATA")?)
+                    .join("stuckbar")
+                    .join("tls");
+                Some((dir.join("cert.pem"), dir.join("key.pem")))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Where the [restart history](crate::history) is kept
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistorySettings {
+    /// Record every operation that runs
+    pub enabled: bool,
+    /// History file [default: `%LOCALAPPDATA%\stuckbar\history.jsonl`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// Size the file may reach before it is rotated; 0 never rotates it
+    pub max_bytes: u64,
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            file: None,
+            max_bytes: HISTORY_MAX_BYTES,
+        }
+    }
+}
+
+impl HistorySettings {
+    /// The configured history file, if its path is known
+    ///
+    /// Reading the history does not depend on [`enabled`](Self::enabled).
+    pub fn log(&self) -> Option<HistoryLog> {
+        self.file
+            .clone()
+            .or_else(history::default_path)
+            .map(|path| HistoryLog::new(path).with_max_bytes(self.max_bytes))
+    }
+}
+
+/// Layer that set a configuration key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "source", content = "origin", rename_all = "snake_case")]
+pub enum Source {
+    Default,
+    /// A configuration file
+    File(PathBuf),
+    /// An environment variable
+    Env(String),
+    /// A command-line flag
+    Cli,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::File(path) => write!(f, "file {}", path.display()),
+            Self::Env(name) => write!(f, "env {}", name),
+            Self::Cli => write!(f, "command line"),
+        }
+    }
+}
+
+/// A partial set of settings, keyed like the TOML file
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Layer(Map<String, Value>);
+
+impl Layer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the dotted `key` (e.g. `"mcp.port"`) to `value`
+    pub fn set(&mut self, key: &str, value: impl Into<Value>) {
+        let mut table = &mut self.0;
+        let mut parts = key.split('.').peekable();
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                table.insert(part.to_string(), value.into());
+                return;
+            }
+            let entry = table
+                .entry(part.to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+            if !entry.is_object() {
+                *entry = Value::Object(Map::new());
+            }
+            let Value::Object(next) = entry else {
+                unreachable!("entry was just made a table");
+            };
+            table = next;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Parse the contents of a configuration file
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let table: toml::Table = toml::from_str(text).map_err(|e| e.message().to_string())?;
+        match serde_json::to_value(table) {
+            Ok(Value::Object(map)) => Ok(Self(map)),
+            Ok(_) => Err("expected a table".to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Collect the settings overridden by `STUCKBAR_*` variables
+    ///
+    /// `lookup` returns the value of an environment variable, if set. Values are
+    /// converted to the type of the key they override.
+    pub fn from_env(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, StuckbarError> {
+        let mut keys = Vec::new();
+        leaves(&default_value(), "", &mut |key, value| {
+            keys.push((key.to_string(), value.clone()))
+        });
+
+        let mut layer = Self::new();
+        for (key, default) in keys {
+            let name = env_name(&key);
+            let Some(raw) = lookup(&name) else {
+                continue;
+            };
+            let value = env_value(&default, &raw).ok_or_else(|| StuckbarError::InvalidConfig {
+                details: format!(
+                    "{} = {:?} is not a valid {}",
+                    name,
+                    raw,
+                    type_name(&default)
+                ),
+            })?;
+            layer.set(&key, value);
+        }
+        Ok(layer)
+    }
+}
+
+/// Settings merged from several [`Layer`]s, remembering where each value came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayeredConfig {
+    config: Config,
+    value: Value,
+    sources: BTreeMap<String, Source>,
+    file: Option<PathBuf>,
+}
+
+/// A single effective setting, as shown by `stuckbar config show`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigEntry {
+    /// Dotted key, e.g. `"restart.delay_ms"`
+    pub key: String,
+    pub value: Value,
+    #[serde(flatten)]
+    pub source: Source,
+}
+
+impl Default for LayeredConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LayeredConfig {
+    /// Start from the built-in defaults
+    pub fn new() -> Self {
+        Self {
+            config: Config::default(),
+            value: default_value(),
+            sources: BTreeMap::new(),
+            file: None,
+        }
+    }
+
+    /// Override the keys set in `layer`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StuckbarError::InvalidConfig`] if the layer contains an unknown key
+    /// or a value of the wrong type.
+    pub fn merge(mut self, layer: Layer, source: Source) -> Result<Self, StuckbarError> {
+        let mut value = self.value.clone();
+        merge_value(
+            &mut value,
+            Value::Object(layer.0),
+            "",
+            &source,
+            &mut self.sources,
+        );
+        self.config =
+            serde_json::from_value(value.clone()).map_err(|e| StuckbarError::InvalidConfig {
+                details: format!("{} (from {})", e, source),
+            })?;
+        self.value = value;
+        Ok(self)
+    }
+
+    /// Override the keys set in the TOML file at `path`
+    pub fn merge_file(mut self, path: &Path) -> Result<Self, StuckbarError> {
+        let text = std::fs::read_to_string(path).map_err(|e| StuckbarError::InvalidConfig {
+            details: format!("cannot read {}: {}", path.display(), e),
+        })?;
+        let layer = Layer::from_toml(&text).map_err(|e| StuckbarError::InvalidConfig {
+            details: format!("{}: {}", path.display(), e.trim()),
+        })?;
+        self.file = Some(path.to_path_buf());
+        self.merge(layer, Source::File(path.to_path_buf()))
+    }
+
+    /// Override the keys set by `STUCKBAR_*` variables in the process environment
+    pub fn merge_env(self) -> Result<Self, StuckbarError> {
+        let layer = Layer::from_env(|name| std::env::var(name).ok())?;
+        let mut merged = self;
+        // Each variable is its own source, so they are merged one key at a time
+        let mut keys = Vec::new();
+        leaves(&Value::Object(layer.0), "", &mut |key, value| {
+            keys.push((key.to_string(), value.clone()))
+        });
+        for (key, value) in keys {
+            let mut single = Layer::new();
+            single.set(&key, value);
+            merged = merged.merge(single, Source::Env(env_name(&key)))?;
+        }
+        Ok(merged)
+    }
+
+    /// Check the values that cannot be checked by their type alone
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StuckbarError::InvalidConfig`] naming the key and where it was set.
+    pub fn validate(&self) -> Result<(), StuckbarError> {
+        let restart = &self.config.restart;
+        self.check_name("restart.target", &restart.target, PRESET_NAMES)?;
+        self.check_name("restart.backend", &restart.backend, BACKEND_NAMES)?;
+        if let Err(StuckbarError::InvalidConfig { details }) =
+            restart.session.parse::<SessionScope>()
+        {
+            return Err(StuckbarError::InvalidConfig {
+                details: format!(
+                    "restart.session: {} (from {})",
+                    details,
+                    self.source("restart.session")
+                ),
+            });
+        }
+        for probe in &self.config.watch.probes {
+            self.check_name("watch.probes", probe, PROBE_NAMES)?;
+        }
+        if self.config.mcp.http_transports.is_empty() {
+            return Err(StuckbarError::InvalidConfig {
+                details: format!(
+                    "mcp.http_transports: no transports (from {}), expected some of: {}",
+                    self.source("mcp.http_transports"),
+                    HTTP_TRANSPORT_NAMES.join(", ")
+                ),
+            });
+        }
+        if self
+            .config
+            .mcp
+            .tokens
+            .iter()
+            .any(|token| token.secret().is_empty())
+        {
+            return Err(StuckbarError::InvalidConfig {
+                details: format!(
+                    "mcp.tokens: empty token (from {})",
+                    self.source("mcp.tokens")
+                ),
+            });
+        }
+        self.check_paths()?;
+        let mcp = &self.config.mcp;
+        for (key, value) in [
+            ("mcp.sse_keep_alive_ms", mcp.sse_keep_alive_ms),
+            ("mcp.max_body_bytes", mcp.max_body_bytes),
+        ] {
+            if value == 0 {
+                return Err(StuckbarError::InvalidConfig {
+                    details: format!("{}: must be above 0 (from {})", key, self.source(key)),
+                });
+            }
+        }
+        if mcp.max_sessions > 0 && mcp.session_timeout_ms == 0 {
+            return Err(StuckbarError::InvalidConfig {
+                details: format!(
+                    "mcp.max_sessions: needs mcp.session_timeout_ms, or sessions that clients \
+                     abandon keep their slots forever (from {})",
+                    self.source("mcp.max_sessions")
+                ),
+            });
+        }
+        if let Some(origin) = mcp
+            .cors_origins
+            .iter()
+            .find(|origin| *origin != "*" && !is_origin(origin))
+        {
+            return Err(StuckbarError::InvalidConfig {
+                details: format!(
+                    "mcp.cors_origins: '{}' is not an origin like https://example.com (from {})",
+                    origin,
+                    self.source("mcp.cors_origins")
+                ),
+            });
+        }
+        let tls = &self.config.mcp.tls;
+        if tls.cert.is_some() != tls.key.is_some() {
+            let key = if tls.cert.is_some() {
+                "mcp.tls.cert"
+            } else {
+                "mcp.tls.key"
+            };
+            return Err(StuckbarError::InvalidConfig {
+                details: format!(
+                    "{}: mcp.tls.cert and mcp.tls.key must be set together (from {})",
+                    key,
+                    self.source(key)
+                ),
+            });
+        }
+        for (name, profile) in &self.config.profiles {
+            let key = format!("profiles.{}.targets", name);
+            if profile.targets.is_empty() {
+                return Err(StuckbarError::InvalidConfig {
+                    details: format!("{}: no targets (from {})", key, self.source(&key)),
+                });
+            }
+            for target in &profile.targets {
+                self.check_name(&key, target, PRESET_NAMES)?;
+            }
+        }
+        self.check_unused_flags()
+    }
+
+    /// Reject command-line settings that the effective configuration ignores
+    ///
+    /// They are checked against the merged settings rather than the other flags,
+    /// so `--grace-period` works with `kill_strategy = "graceful"` from a file.
+    fn check_unused_flags(&self) -> Result<(), StuckbarError> {
+        let unused = |key: &str, setting: &str| StuckbarError::InvalidConfig {
+            details: format!("{}: only used with {} (from {})", key, setting, Source::Cli),
+        };
+        let given = |key: &str| *self.source(key) == Source::Cli;
+        if self.config.restart.kill_strategy != KillMode::Graceful
+            && given("restart.grace_period_ms")
+        {
+            return Err(unused(
+                "restart.grace_period_ms",
+                "restart.kill_strategy = \"graceful\"",
+            ));
+        }
+        if self.config.mcp.transport != Transport::Http
+            && let Some(key) = HTTP_KEYS.iter().find(|key| given(key))
+        {
+            return Err(unused(key, "mcp.transport = \"http\""));
+        }
+        Ok(())
+    }
+
+    /// Check that the HTTP paths are well-formed and do not overlap
+    fn check_paths(&self) -> Result<(), StuckbarError> {
+        let mcp = &self.config.mcp;
+        let invalid = |key: &str, reason: &str| StuckbarError::InvalidConfig {
+            details: format!("{}: {} (from {})", key, reason, self.source(key)),
+        };
+        if !mcp.base_path.is_empty() && !is_path(&mcp.base_path) {
+            return Err(invalid(
+                "mcp.base_path",
+                "must be empty or start with '/' and not end with one",
+            ));
+        }
+        let paths = [
+            ("mcp.streamable_path", &mcp.streamable_path),
+            ("mcp.sse_path", &mcp.sse_path),
+            ("mcp.post_path", &mcp.post_path),
+        ];
+        for (i, (key, path)) in paths.iter().enumerate() {
+            if !is_path(path) {
+                return Err(invalid(key, "must start with '/' and not end with one"));
+            }
+            for (other_key, other) in &paths[..i] {
+                let nested = |a: &str, b: &str| a.starts_with(&format!("{}/", b));
+                if path == other || nested(path, other) || nested(other, path) {
+                    return Err(invalid(key, &format!("overlaps {}", other_key)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_name(&self, key: &str, name: &str, allowed: &[&str]) -> Result<(), StuckbarError> {
+        if allowed.contains(&name) {
+            return Ok(());
+        }
+        Err(StuckbarError::InvalidConfig {
+            details: format!(
+                "{}: unknown value '{}' (from {}), expected one of: {}",
+                key,
+                name,
+                self.source(key),
+                allowed.join(", ")
+            ),
+        })
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The configuration file that was loaded, if any
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    /// Layer that set `key`
+    pub fn source(&self, key: &str) -> &Source {
+        self.sources.get(key).unwrap_or(&Source::Default)
+    }
+
+    /// Every effective setting with its source, in key order
+    ///
+    /// Secrets are replaced with [`REDACTED`].
+    pub fn entries(&self) -> Vec<ConfigEntry> {
+        let mut entries = Vec::new();
+        leaves(&self.value, "", &mut |key, value| {
+            entries.push(ConfigEntry {
+                key: key.to_string(),
+                value: if SECRET_KEYS.contains(&key) {
+                    redact(value)
+                } else {
+                    value.clone()
+                },
+                source: self.source(key).clone(),
+            })
+        });
+        entries
+    }
+}
+
+/// Load the configuration: defaults, the configuration file, the environment and
+/// finally the `cli` layer
+///
+/// The file is the one [`resolve_path`] picks for `path`. A file named explicitly
+/// must exist; the default file is optional.
+pub fn load(path: Option<&Path>, cli: Layer) -> Result<LayeredConfig, StuckbarError> {
+    let mut config = LayeredConfig::new();
+    let explicit = path.is_some() || std::env::var_os(CONFIG_PATH_ENV).is_some();
+    if let Some(path) = resolve_path(path).filter(|path| explicit || path.exists()) {
+        config = config.merge_file(&path)?;
+    }
+    let config = config.merge_env()?.merge(cli, Source::Cli)?;
+    config.validate()?;
+    Ok(config)
+}
+
+/// Configuration file to use: `path` if given, else `$STUCKBAR_CONFIG`, else the
+/// [default path](default_path)
+pub fn resolve_path(path: Option<&Path>) -> Option<PathBuf> {
+    path.map(Path::to_path_buf)
+        .or_else(|| std::env::var_os(CONFIG_PATH_ENV).map(PathBuf::from))
+        .or_else(default_path)
+}
+
+/// `%APPDATA%\stuckbar\config.toml`, if `APPDATA` is set
+pub fn default_path() -> Option<PathBuf> {
+    std::env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("stuckbar").join("config.toml"))
+}
+
+/// Write a configuration file holding every default value to `path`
+///
+/// # Errors
+///
+/// Returns [`StuckbarError::InvalidConfig`] if the file exists and `overwrite` is
+/// not set, or if it cannot be written.
+pub fn init(path: &Path, overwrite: bool) -> Result<(), StuckbarError> {
+    if path.exists() && !overwrite {
+        return Err(StuckbarError::InvalidConfig {
+            details: format!(
+                "{} already exists; pass --overwrite to replace it",
+                path.display()
+            ),
+        });
+    }
+    let write = || -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, default_toml())
+    };
+    write().map_err(|e| StuckbarError::InvalidConfig {
+        details: format!("cannot write {}: {}", path.display(), e),
+    })
+}
+
+/// Contents of a configuration file holding every default value
+pub fn default_toml() -> String {
+    let body = toml::to_string_pretty(&Config::default()).unwrap_or_default();
+    format!(
+        "# stuckbar configuration\n\
+         # Every key can also be set with a STUCKBAR_* environment variable,\n\
+         # e.g. STUCKBAR_RESTART_DELAY_MS, and is overridden by command-line flags.\n\n{}",
+        body
+    )
+}
+
+/// Name of the environment variable overriding `key`
+pub fn env_name(key: &str) -> String {
+    format!(
+        "{}{}",
+        ENV_PREFIX,
+        key.replace('.', "_").to_ascii_uppercase()
+    )
+}
+
+/// Whether `path` is a non-root URL path without a trailing slash
+fn is_path(path: &str) -> bool {
+    path.len() > 1
+        && path.starts_with('/')
+        && !path.ends_with('/')
+        && !path.contains(['?', '#', ' '])
+}
+
+/// Whether `origin` is a scheme and host, with an optional port
+fn is_origin(origin: &str) -> bool {
+    let Some((scheme, host)) = origin.split_once("://") else {
+        return false;
+    };
+    matches!(scheme, "http" | "https")
+        && !host.is_empty()
+        && !host.contains(['/', '?', '#'])
+        && origin.bytes().all(|b| b.is_ascii_graphic())
+}
+
+fn default_value() -> Value {
+    let mut value = serde_json::to_value(Config::default()).unwrap_or(Value::Null);
+    // Unset optional keys are still settings that can be overridden
+    if let Some(log) = value.get_mut("log").and_then(Value::as_object_mut) {
+        log.entry("file").or_insert(Value::Null);
+    }
+    if let Some(history) = value.get_mut("history").and_then(Value::as_object_mut) {
+        history.entry("file").or_insert(Value::Null);
+    }
+    if let Some(tls) = value.pointer_mut("/mcp/tls").and_then(Value::as_object_mut) {
+        tls.entry("cert").or_insert(Value::Null);
+        tls.entry("key").or_insert(Value::Null);
+    }
+    value
+}
+
+/// Replace every token in `value` with [`REDACTED`]
+fn redact(value: &Value) -> Value {
+    match value {
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        Value::Object(map) => {
+            let mut map = map.clone();
+            if let Some(token) = map.get_mut("token") {
+                *token = Value::from(REDACTED);
+            }
+            Value::Object(map)
+        }
+        Value::Null => Value::Null,
+        _ => Value::from(REDACTED),
+    }
+}
+
+/// Call `visit` with the dotted key of every non-table value in `value`
+fn leaves(value: &Value, prefix: &str, visit: &mut dyn FnMut(&str, &Value)) {
+    match value {
+        Value::Object(map) => {
+            for (name, child) in map {
+                leaves(child, &join(prefix, name), visit);
+            }
+        }
+        _ => visit(prefix, value),
+    }
+}
+
+/// Merge `layer` into `target`, recording `source` for every value it replaces
+fn merge_value(
+    target: &mut Value,
+    layer: Value,
+    key: &str,
+    source: &Source,
+    sources: &mut BTreeMap<String, Source>,
+) {
+    match (target, layer) {
+        (Value::Object(target), Value::Object(layer)) => {
+            for (name, child) in layer {
+                let slot = target.entry(name.clone()).or_insert(Value::Null);
+                merge_value(slot, child, &join(key, &name), source, sources);
+            }
+        }
+        (target, layer) => {
+            *target = layer;
+            sources.insert(key.to_string(), source.clone());
+        }
+    }
+}
+
+fn join(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", prefix, name)
+    }
+}
+
+/// Convert an environment variable to the type of the `default` it overrides
+fn env_value(default: &Value, raw: &str) -> Option<Value> {
+    let raw = raw.trim();
+    match default {
+        Value::Bool(_) => raw.parse::<bool>().ok().map(Value::Bool),
+        Value::Number(_) => raw.parse::<u64>().ok().map(Value::from),
+        Value::Array(_) => Some(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(Value::from)
+                .collect(),
+        ),
+        _ => Some(Value::from(raw)),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::Array(_) => "list",
+        _ => "string",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    fn file_layer(text: &str) -> Layer {
+        Layer::from_toml(text).unwrap()
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = LayeredConfig::new();
+
+        assert_eq!(config.config(), &Config::default());
+        assert_eq!(config.config().restart.delay_ms, RESTART_DELAY_MS);
+        assert_eq!(config.config().restart.strategy(), KillStrategy::Force);
+        assert_eq!(config.source("restart.delay_ms"), &Source::Default);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_file_overrides_defaults() {
+        let path = PathBuf::from("config.toml");
+        let config = LayeredConfig::new()
+            .merge(
+                file_layer(
+                    "[restart]\nkill_strategy = \"graceful\"\ngrace_period_ms = 8000\n\
+                     [mcp]\nport = 3000\n",
+                ),
+                Source::File(path.clone()),
+            )
+            .unwrap();
+
+        assert_eq!(
+            config.config().restart.strategy(),
+            KillStrategy::Graceful { grace_ms: 8000 }
+        );
+        assert_eq!(config.config().mcp.port, 3000);
+        assert_eq!(config.config().mcp.host, "127.0.0.1");
+        assert_eq!(config.source("mcp.port"), &Source::File(path));
+        assert_eq!(config.source("mcp.host"), &Source::Default);
+    }
+
+    #[test]
+    fn test_later_layers_win() {
+        let mut cli = Layer::new();
+        cli.set("restart.delay_ms", 100);
+        let env = Layer::from_env(env(&[
+            ("STUCKBAR_RESTART_DELAY_MS", "900"),
+            ("STUCKBAR_MCP_HOST", "0.0.0.0"),
+        ]))
+        .unwrap();
+
+        let config = LayeredConfig::new()
+            .merge(
+                file_layer("[restart]\ndelay_ms = 2000"),
+                Source::File("a.toml".into()),
+            )
+            .unwrap()
+            .merge(env, Source::Env("STUCKBAR_*".to_string()))
+            .unwrap()
+            .merge(cli, Source::Cli)
+            .unwrap();
+
+        assert_eq!(config.config().restart.delay_ms, 100);
+        assert_eq!(config.source("restart.delay_ms"), &Source::Cli);
+        assert_eq!(config.config().mcp.host, "0.0.0.0");
+    }
+
+    #[test]
+    fn test_env_values_take_the_type_of_their_key() {
+        let layer = Layer::from_env(env(&[
+            ("STUCKBAR_WATCH_PROBES", "process, window"),
+            ("STUCKBAR_MCP_PORT", "9000"),
+            ("STUCKBAR_LOG_FILE", "C:\\logs\\stuckbar.jsonl"),
+            ("STUCKBAR_UNKNOWN", "ignored"),
+        ]))
+        .unwrap();
+
+        let config = LayeredConfig::new()
+            .merge(layer, Source::Env("STUCKBAR_*".to_string()))
+            .unwrap();
+
+        assert_eq!(config.config().watch.probes, vec!["process", "window"]);
+        assert_eq!(config.config().mcp.port, 9000);
+        assert_eq!(
+            config.config().log.file,
+            Some(PathBuf::from("C:\\logs\\stuckbar.jsonl"))
+        );
+    }
+
+    #[test]
+    fn test_invalid_env_value() {
+        let err = Layer::from_env(env(&[("STUCKBAR_MCP_PORT", "eighty")])).unwrap_err();
+
+        assert_eq!(err.kind(), "invalid_config");
+        assert!(err.to_string().contains("STUCKBAR_MCP_PORT"));
+    }
+
+    #[test]
+    fn test_unknown_key_is_rejected() {
+        let err = LayeredConfig::new()
+            .merge(
+                file_layer("[restart]\ndelay = 500"),
+                Source::File("config.toml".into()),
+            )
+            .unwrap_err();
+
+        assert_eq!(err.kind(), "invalid_config");
+        assert!(err.to_string().contains("unknown field `delay`"));
+        assert!(err.to_string().contains("config.toml"));
+    }
+
+    #[test]
+    fn test_wrong_type_is_rejected() {
+        let result = LayeredConfig::new().merge(
+            file_layer("[mcp]\ntransport = \"websocket\""),
+            Source::File("config.toml".into()),
+        );
+
+        assert_eq!(result.unwrap_err().kind(), "invalid_config");
+    }
+
+    #[test]
+    fn test_validate_names_the_source() {
+        let mut cli = Layer::new();
+        cli.set("watch.probes", vec!["process", "disk"]);
+        let config = LayeredConfig::new().merge(cli, Source::Cli).unwrap();
+
+        let err = config.validate().unwrap_err();
+
+        assert!(err.to_string().contains("watch.probes"));
+        assert!(err.to_string().contains("'disk'"));
+        assert!(err.to_string().contains("command line"));
+    }
+
+    #[test]
+    fn test_flags_are_checked_against_effective_settings() {
+        let mut cli = Layer::new();
+        cli.set("restart.grace_period_ms", 3_000);
+        let config = LayeredConfig::new()
+            .merge(cli.clone(), Source::Cli)
+            .unwrap();
+        let error = config.validate().unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("restart.grace_period_ms: only used")
+        );
+
+        let config = LayeredConfig::new()
+            .merge(
+                file_layer("[restart]\nkill_strategy = \"graceful\"\n"),
+                Source::File("config.toml".into()),
+            )
+            .unwrap()
+            .merge(cli, Source::Cli)
+            .unwrap();
+        assert!(config.validate().is_ok());
+
+        let mut cli = Layer::new();
+        cli.set("mcp.port", 9000);
+        let config = LayeredConfig::new()
+            .merge(cli.clone(), Source::Cli)
+            .unwrap();
+        let error = config.validate().unwrap_err();
+        assert!(error.to_string().contains("mcp.port: only used"));
+
+        let config = LayeredConfig::new()
+            .merge(
+                file_layer("[mcp]\ntransport = \"http\"\n"),
+                Source::File("config.toml".into()),
+            )
+            .unwrap()
+            .merge(cli, Source::Cli)
+            .unwrap();
+        assert!(config.validate().is_ok());
+
+        // Files may hold settings for other modes, as `config init` writes them all
+        let config = LayeredConfig::new()
+            .merge(
+                file_layer("[restart]\ngrace_period_ms = 3000\n[mcp]\nport = 9000\n"),
+                Source::File("config.toml".into()),
+            )
+            .unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_session_scope() {
+        let config = LayeredConfig::new();
+        assert_eq!(
+            config.config().restart.session_scope(),
+            SessionScope::Current
+        );
+
+        let mut cli = Layer::new();
+        cli.set("restart.session", "2");
+        let config = LayeredConfig::new().merge(cli, Source::Cli).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.config().restart.session_scope(), SessionScope::Id(2));
+
+        let mut cli = Layer::new();
+        cli.set("restart.session", "mine");
+        let config = LayeredConfig::new().merge(cli, Source::Cli).unwrap();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("restart.session"));
+        assert!(err.to_string().contains("'mine'"));
+        assert!(err.to_string().contains("command line"));
+    }
+
+    #[test]
+    fn test_declared_profiles() {
+        let config = LayeredConfig::new()
+            .merge(
+                file_layer(
+                    "[profiles.flyouts]\ntargets = [\"shell-experience\", \"start-menu\"]\n\
+                     [profiles.taskbar]\ndelay_ms = 2000\n",
+                ),
+                Source::File("config.toml".into()),
+            )
+            .unwrap();
+        assert!(config.validate().is_ok());
+
+        let settings = config.config();
+        let flyouts = settings.profile("flyouts").unwrap();
+        assert_eq!(flyouts.targets, vec!["shell-experience", "start-menu"]);
+        // A declared profile replaces the built-in one entirely
+        assert_eq!(settings.profile("taskbar").unwrap().delay_ms, 2000);
+        assert!(settings.profile("taskbar").unwrap().post_steps.is_empty());
+        assert_eq!(
+            settings.profile("full-shell"),
+            Profile::builtin("full-shell")
+        );
+
+        let names: Vec<String> = settings.profiles().into_keys().collect();
+        assert_eq!(
+            names,
+            vec!["flyouts", "full-shell", "start-menu", "taskbar"]
+        );
+    }
+
+    #[test]
+    fn test_validate_profile_targets() {
+        let config = LayeredConfig::new()
+            .merge(
+                file_layer("[profiles.broken]\ntargets = [\"explorer\", \"notepad\"]\n"),
+                Source::File("config.toml".into()),
+            )
+            .unwrap();
+
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("profiles.broken.targets"));
+        assert!(err.to_string().contains("'notepad'"));
+
+        let config = LayeredConfig::new()
+            .merge(
+                file_layer("[profiles.empty]\ntargets = []\n"),
+                Source::File("config.toml".into()),
+            )
+            .unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_entries_list_every_key_with_its_source() {
+        let mut cli = Layer::new();
+        cli.set("mcp.transport", "http");
+        let config = LayeredConfig::new().merge(cli, Source::Cli).unwrap();
+
+        let entries = config.entries();
+        let transport = entries.iter().find(|e| e.key == "mcp.transport").unwrap();
+        assert_eq!(transport.value, json!("http"));
+        assert_eq!(transport.source, Source::Cli);
+        assert!(entries.iter().any(|e| e.key == "log.file"));
+        assert!(entries.iter().any(|e| e.key == "watch.interval_ms"));
+    }
+
+    #[test]
+    fn test_entry_serializes_source_and_origin() {
+        let entry = ConfigEntry {
+            key: "mcp.port".to_string(),
+            value: json!(3000),
+            source: Source::Env("STUCKBAR_MCP_PORT".to_string()),
+        };
+        assert_eq!(
+            serde_json::to_value(&entry).unwrap(),
+            json!({
+                "key": "mcp.port",
+                "value": 3000,
+                "source": "env",
+                "origin": "STUCKBAR_MCP_PORT",
+            })
+        );
+    }
+
+    #[test]
+    fn test_merge_file_and_default_toml_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("stuckbar-config-{}.toml", std::process::id()));
+        std::fs::write(&path, default_toml()).unwrap();
+
+        let config = LayeredConfig::new().merge_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.config(), &Config::default());
+        assert_eq!(config.file(), Some(path.as_path()));
+        assert_eq!(config.source("restart.target"), &Source::File(path));
+    }
+
+    #[test]
+    fn test_init_refuses_to_overwrite() {
+        let dir = std::env::temp_dir().join(format!("stuckbar-init-{}", std::process::id()));
+        let path = dir.join("nested").join("config.toml");
+
+        init(&path, false).unwrap();
+        let err = init(&path, false).unwrap_err();
+        assert!(err.to_string().contains("--overwrite"));
+        init(&path, true).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(written.contains("[restart]"));
+    }
+
+    #[test]
+    fn test_http_transports() {
+        assert_eq!(
+            McpSettings::default().http_transports,
+            vec![HttpTransport::StreamableHttp, HttpTransport::Sse]
+        );
+        let names: Vec<Value> = HTTP_TRANSPORT_NAMES
+            .iter()
+            .map(|&name| json!(name))
+            .collect();
+        assert_eq!(
+            serde_json::to_value(McpSettings::default().http_transports).unwrap(),
+            Value::Array(names)
+        );
+
+        let layer = Layer::from_env(env(&[("STUCKBAR_MCP_HTTP_TRANSPORTS", "sse")])).unwrap();
+        let config = LayeredConfig::new()
+            .merge(
+                layer,
+                Source::Env("STUCKBAR_MCP_HTTP_TRANSPORTS".to_string()),
+            )
+            .unwrap();
+        assert_eq!(
+            config.config().mcp.http_transports,
+            vec![HttpTransport::Sse]
+        );
+
+        let result = LayeredConfig::new().merge(
+            file_layer("[mcp]\nhttp_transports = [\"websocket\"]"),
+            Source::File("config.toml".into()),
+        );
+        assert_eq!(result.unwrap_err().kind(), "invalid_config");
+
+        let mut cli = Layer::new();
+        cli.set("mcp.http_transports", Vec::<String>::new());
+        let config = LayeredConfig::new().merge(cli, Source::Cli).unwrap();
+        let error = config.validate().unwrap_err();
+        assert!(error.to_string().contains("mcp.http_transports"));
+    }
+
+    #[test]
+    fn test_mcp_tokens() {
+        let config = LayeredConfig::new()
+            .merge(
+                file_layer(
+                    "[mcp]\ntokens = [\"s3cret\", { token = \"monitor\", tools = [\"check_taskbar_health\"] }]",
+                ),
+                Source::File("config.toml".into()),
+            )
+            .unwrap();
+        assert_eq!(
+            config.config().mcp.tokens,
+            vec![
+                ApiToken::Full("s3cret".to_string()),
+                ApiToken::Limited {
+                    token: "monitor".to_string(),
+                    tools: vec!["check_taskbar_health".to_string()],
+                },
+            ]
+        );
+
+        let shown = config
+            .entries()
+            .into_iter()
+            .find(|entry| entry.key == "mcp.tokens")
+            .unwrap();
+        assert_eq!(
+            shown.value,
+            json!([REDACTED, { "token": REDACTED, "tools": ["check_taskbar_health"] }])
+        );
+
+        let layer = Layer::from_env(env(&[("STUCKBAR_MCP_TOKENS", "a, b")])).unwrap();
+        let config = LayeredConfig::new()
+            .merge(layer, Source::Env("STUCKBAR_MCP_TOKENS".to_string()))
+            .unwrap();
+        assert_eq!(
+            config.config().mcp.tokens,
+            vec![
+                ApiToken::Full("a".to_string()),
+                ApiToken::Full("b".to_string())
+            ]
+        );
+
+        let config = LayeredConfig::new()
+            .merge(
+                file_layer("[mcp]\ntokens = [\"\"]"),
+                Source::File("config.toml".into()),
+            )
+            .unwrap();
+        assert!(
+            config
+                .validate()
+                .unwrap_err()
+                .to_string()
+                .contains("mcp.tokens: empty token")
+        );
+    }
+
+    #[test]
+    fn test_tls_settings() {
+        assert_eq!(TlsSettings::default().files(), None);
+
+        let mut cli = Layer::new();
+        cli.set("mcp.transport", "http");
+        cli.set("mcp.tls.cert", "cert.pem");
+        cli.set("mcp.tls.key", "key.pem");
+        let config = LayeredConfig::new().merge(cli, Source::Cli).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.config().mcp.tls.files(),
+            Some(("cert.pem".into(), "key.pem".into()))
+        );
+
+        let mut cli = Layer::new();
+        cli.set("mcp.tls.cert", "cert.pem");
+        let config = LayeredConfig::new().merge(cli, Source::Cli).unwrap();
+        let error = config.validate().unwrap_err();
+        assert!(error.to_string().contains("must be set together"));
+
+        let config = LayeredConfig::new()
+            .merge(
+                file_layer("[mcp.tls]\nself_signed = true"),
+                Source::File("config.toml".into()),
+            )
+            .unwrap();
+        assert!(config.config().mcp.tls.self_signed);
+        assert_eq!(config.source("mcp.tls.cert"), &Source::Default);
+    }
+
+    #[test]
+    fn test_http_settings() {
+        let config = LayeredConfig::new()
+            .merge(
+                file_layer(
+                    "[mcp]\nbase_path = \"/stuckbar\"\nstreamable_path = \"/rpc\"\n\
+                     cors_origins = [\"https://app.example.com\", \"http://localhost:5173\"]\n\
+                     max_sessions = 4\nsession_timeout_ms = 600000\n",
+                ),
+                Source::File("config.toml".into()),
+            )
+            .unwrap();
+        assert!(config.validate().is_ok());
+        let mcp = &config.config().mcp;
+        assert_eq!(mcp.base_path, "/stuckbar");
+        assert_eq!(mcp.streamable_path, "/rpc");
+        assert_eq!(mcp.sse_path, "/sse");
+        assert_eq!(mcp.sse_keep_alive_ms, SSE_KEEP_ALIVE_MS);
+        assert_eq!(mcp.max_body_bytes, MAX_BODY_BYTES);
+        assert_eq!(mcp.max_sessions, 4);
+        assert_eq!(mcp.session_timeout_ms, 600_000);
+
+        let invalid = [
+            ("mcp.base_path", json!("/")),
+            ("mcp.base_path", json!("stuckbar")),
+            ("mcp.streamable_path", json!("/mcp/")),
+            ("mcp.sse_path", json!("")),
+            ("mcp.sse_path", json!("/mcp")),
+            ("mcp.post_path", json!("/sse/message")),
+            ("mcp.sse_keep_alive_ms", json!(0)),
+            ("mcp.max_body_bytes", json!(0)),
+            ("mcp.max_sessions", json!(4)),
+            ("mcp.cors_origins", json!(["example.com"])),
+            ("mcp.cors_origins", json!(["https://example.com/app"])),
+        ];
+        for (key, value) in invalid {
+            let mut cli = Layer::new();
+            cli.set(key, value.clone());
+            let config = LayeredConfig::new().merge(cli, Source::Cli).unwrap();
+            let error = config.validate().unwrap_err();
+            assert!(
+                error.to_string().contains(&format!(": {}:", key)),
+                "{} = {}: {}",
+                key,
+                value,
+                error
+            );
+        }
+
+        let mut cli = Layer::new();
+        cli.set("mcp.transport", "http");
+        cli.set("mcp.cors_origins", vec!["*"]);
+        let config = LayeredConfig::new().merge(cli, Source::Cli).unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_history_settings() {
+        let mut cli = Layer::new();
+        cli.set("history.file", "C:\\data\\history.jsonl");
+        cli.set("history.max_bytes", 4096);
+        let config = LayeredConfig::new().merge(cli, Source::Cli).unwrap();
+
+        let history = &config.config().history;
+        assert!(history.enabled);
+        assert_eq!(
+            history.log(),
+            Some(HistoryLog::new("C:\\data\\history.jsonl").with_max_bytes(4096))
+        );
+        assert!(config.entries().iter().any(|e| e.key == "history.file"));
+
+        let layer = Layer::from_env(env(&[("STUCKBAR_HISTORY_ENABLED", "false")])).unwrap();
+        let config = LayeredConfig::new()
+            .merge(layer, Source::Env("STUCKBAR_HISTORY_ENABLED".to_string()))
+            .unwrap();
+        assert!(!config.config().history.enabled);
+    }
+
+    #[test]
+    fn test_env_name() {
+        assert_eq!(env_name("restart.delay_ms"), "STUCKBAR_RESTART_DELAY_MS");
+        assert_eq!(env_name("mcp.allowed_tools"), "STUCKBAR_MCP_ALLOWED_TOOLS");
+    }
+}
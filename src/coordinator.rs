@@ -0,0 +1,395 @@
+//! # Operation Coordination
+//!
+//! Managers that share an [`OperationCoordinator`] never run two operations on the
+//! same target at once. A restart requested while another restart is running joins
+//! it and receives its result instead of killing the freshly started shell; any
+//! other overlapping request is rejected with [`StuckbarError::Busy`].
+//!
+//! [`OperationCoordinator::shared`] returns the process-wide coordinator of a
+//! target. It also holds a lock file while an operation runs, so operations from
+//! other stuckbar processes (the CLI, a watchdog, another MCP server) are rejected
+//! too. Joining only works within a process.
+//!
+//! ```no_run
+//! use stuckbar::coordinator::OperationCoordinator;
+//! use stuckbar::{ExplorerManager, SystemProcessRunner, TargetSpec};
+//!
+//! let coordinator = OperationCoordinator::shared(&TargetSpec::explorer());
+//! let manager = ExplorerManager::new(SystemProcessRunner).with_coordinator(coordinator);
+//! manager.restart().ok();
+//! ```
+
+use crate::events::Action;
+use crate::{Outcome, StuckbarError, TargetSpec};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+use std::task::{Context, Poll, Waker};
+
+/// Serializes the operations of every manager it is shared with
+pub struct OperationCoordinator {
+    process_name: String,
+    lock_path: Option<PathBuf>,
+    current: Mutex<Option<InFlight>>,
+}
+
+/// The operation that currently holds a coordinator
+struct InFlight {
+    action: Action,
+    result: Arc<SharedResult>,
+}
+
+/// Permission to proceed, returned by [`OperationCoordinator::begin`]
+pub enum Admission {
+    /// Run the operation and hand its result to the guard
+    Lead(OperationGuard),
+    /// An identical operation is already running; wait for its result
+    Join(Joined),
+}
+
+impl OperationCoordinator {
+    /// Create a coordinator that only serializes operations within this process
+    pub fn new(target: &TargetSpec) -> Self {
+        Self {
+            process_name: target.process_name.clone(),
+            lock_path: None,
+            current: Mutex::new(None),
+        }
+    }
+
+    /// Also hold the lock file at `path` while an operation runs
+    pub fn with_lock_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.lock_path = Some(path.into());
+        self
+    }
+
+    /// The process-wide coordinator for `target`
+    ///
+    /// Every call with the same process name returns the same coordinator. Its
+    /// lock file lives in the temporary directory.
+    pub fn shared(target: &TargetSpec) -> Arc<Self> {
+        static SHARED: OnceLock<Mutex<HashMap<String, Arc<OperationCoordinator>>>> =
+            OnceLock::new();
+
+        let key = target.process_name.to_ascii_lowercase();
+        let mut shared = lock(SHARED.get_or_init(Default::default));
+        shared
+            .entry(key.clone())
+            .or_insert_with(|| {
+                let path = std::env::temp_dir().join(format!("stuckbar-{}.lock", key));
+                Arc::new(Self::new(target).with_lock_file(path))
+            })
+            .clone()
+    }
+
+    /// Ask to run `action`
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StuckbarError::Busy`] if a different operation is running in this
+    /// process, or any operation in another process.
+    pub fn begin(self: &Arc<Self>, action: Action) -> Result<Admission, StuckbarError> {
+        let mut current = lock(&self.current);
+        if let Some(in_flight) = current.as_ref() {
+            if in_flight.action == Action::Restart && action == Action::Restart {
+                return Ok(Admission::Join(Joined {
+                    result: in_flight.result.clone(),
+                }));
+            }
+            return Err(StuckbarError::Busy {
+                operation: self.describe(in_flight.action),
+            });
+        }
+
+        let lock_file = self.lock_file()?;
+        let result = Arc::new(SharedResult::default());
+        *current = Some(InFlight {
+            action,
+            result: result.clone(),
+        });
+        Ok(Admission::Lead(OperationGuard {
+            coordinator: self.clone(),
+            action,
+            result,
+            lock_file,
+            finished: false,
+        }))
+    }
+
+    /// Whether an operation is running in this process
+    pub fn is_busy(&self) -> bool {
+        lock(&self.current).is_some()
+    }
+
+    /// Take the cross-process lock, if one is configured
+    fn lock_file(&self) -> Result<Option<File>, StuckbarError> {
+        let Some(path) = &self.lock_path else {
+            return Ok(None);
+        };
+        let file = open_lock_file(path).map_err(|e| StuckbarError::AccessDenied {
+            process: self.process_name.clone(),
+            details: format!("cannot open lock file {}: {}", path.display(), e),
+        })?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(file)),
+            Err(TryLockError::WouldBlock) => Err(StuckbarError::Busy {
+                operation: format!("operation on {} in another process", self.process_name),
+            }),
+            Err(TryLockError::Error(e)) => Err(StuckbarError::AccessDenied {
+                process: self.process_name.clone(),
+                details: format!("cannot lock {}: {}", path.display(), e),
+            }),
+        }
+    }
+
+    fn describe(&self, action: Action) -> String {
+        let verb = match action {
+            Action::Kill => "kill",
+            Action::Start => "start",
+            Action::Restart => "restart",
+        };
+        format!("{} of {}", verb, self.process_name)
+    }
+}
+
+/// Held by the caller that runs an operation
+///
+/// Dropping the guard without calling [`finish`](Self::finish), for example because
+/// an async operation was cancelled, fails every joined caller with
+/// [`StuckbarError::Interrupted`].
+pub struct OperationGuard {
+    coordinator: Arc<OperationCoordinator>,
+    action: Action,
+    result: Arc<SharedResult>,
+    lock_file: Option<File>,
+    finished: bool,
+}
+
+impl OperationGuard {
+    /// Release the coordinator and hand `result` to every joined caller
+    pub fn finish(mut self, result: &Result<Outcome, StuckbarError>) {
+        self.release();
+        self.result.publish(result.clone());
+        self.finished = true;
+    }
+
+    fn release(&mut self) {
+        // The file lock goes first, so a caller admitted next can take it
+        self.lock_file.take();
+        lock(&self.coordinator.current).take();
+    }
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.release();
+            self.result.publish(Err(StuckbarError::Interrupted {
+                operation: self.coordinator.describe(self.action),
+            }));
+        }
+    }
+}
+
+/// Result of an operation joined through [`Admission::Join`]
+///
+/// Wait for it with [`wait`](Self::wait) or `.await` it.
+pub struct Joined {
+    result: Arc<SharedResult>,
+}
+
+impl Joined {
+    /// Block the current thread until the joined operation finishes
+    pub fn wait(self) -> Result<Outcome, StuckbarError> {
+        let mut state = lock(&self.result.state);
+        loop {
+            if let Some(result) = &state.result {
+                return result.clone();
+            }
+            state = self
+                .result
+                .ready
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+impl Future for Joined {
+    type Output = Result<Outcome, StuckbarError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = lock(&self.result.state);
+        match &state.result {
+            Some(result) => Poll::Ready(result.clone()),
+            None => {
+                state.wakers.push(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Result slot shared between the leading caller and the joined ones
+#[derive(Default)]
+struct SharedResult {
+    state: Mutex<ResultState>,
+    ready: Condvar,
+}
+
+#[derive(Default)]
+struct ResultState {
+    result: Option<Result<Outcome, StuckbarError>>,
+    wakers: Vec<Waker>,
+}
+
+impl SharedResult {
+    fn publish(&self, result: Result<Outcome, StuckbarError>) {
+        let wakers = {
+            let mut state = lock(&self.state);
+            state.result = Some(result);
+            std::mem::take(&mut state.wakers)
+        };
+        self.ready.notify_all();
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+/// Open (creating it if needed) a file that is only used for its lock
+pub(crate) fn open_lock_file(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+}
+
+/// Lock a mutex, ignoring poisoning; the protected state stays consistent
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn coordinator() -> Arc<OperationCoordinator> {
+        Arc::new(OperationCoordinator::new(&TargetSpec::explorer()))
+    }
+
+    fn lead(admission: Admission) -> OperationGuard {
+        match admission {
+            Admission::Lead(guard) => guard,
+            Admission::Join(_) => panic!("expected to lead"),
+        }
+    }
+
+    fn join(admission: Admission) -> Joined {
+        match admission {
+            Admission::Join(joined) => joined,
+            Admission::Lead(_) => panic!("expected to join"),
+        }
+    }
+
+    #[test]
+    fn test_restart_joins_running_restart() {
+        let coordinator = coordinator();
+        let guard = lead(coordinator.begin(Action::Restart).unwrap());
+        let joined = join(coordinator.begin(Action::Restart).unwrap());
+
+        let waiter = thread::spawn(move || joined.wait());
+        guard.finish(&Ok(Outcome::new("restarted").with_new_pid(7)));
+
+        assert_eq!(waiter.join().unwrap().unwrap().new_pid, Some(7));
+        assert!(!coordinator.is_busy());
+    }
+
+    #[test]
+    fn test_kill_during_restart_is_rejected() {
+        let coordinator = coordinator();
+        let _guard = lead(coordinator.begin(Action::Restart).unwrap());
+
+        let err = coordinator.begin(Action::Kill).err().unwrap();
+
+        assert_eq!(
+            err,
+            StuckbarError::Busy {
+                operation: "restart of explorer.exe".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_restart_during_kill_is_rejected() {
+        let coordinator = coordinator();
+        let _guard = lead(coordinator.begin(Action::Kill).unwrap());
+
+        assert_eq!(
+            coordinator.begin(Action::Restart).err().unwrap().kind(),
+            "busy"
+        );
+    }
+
+    #[test]
+    fn test_finished_operation_frees_coordinator() {
+        let coordinator = coordinator();
+        lead(coordinator.begin(Action::Kill).unwrap()).finish(&Ok(Outcome::new("killed")));
+
+        assert!(matches!(
+            coordinator.begin(Action::Kill),
+            Ok(Admission::Lead(_))
+        ));
+    }
+
+    #[test]
+    fn test_dropped_guard_interrupts_joined_callers() {
+        let coordinator = coordinator();
+        let guard = lead(coordinator.begin(Action::Restart).unwrap());
+        let joined = join(coordinator.begin(Action::Restart).unwrap());
+
+        drop(guard);
+
+        assert_eq!(joined.wait().unwrap_err().kind(), "interrupted");
+        assert!(!coordinator.is_busy());
+    }
+
+    #[test]
+    fn test_lock_file_rejects_other_coordinators() {
+        let path = std::env::temp_dir().join(format!(
+            "stuckbar-test-{}-{:?}.lock",
+            std::process::id(),
+            thread::current().id()
+        ));
+        let first =
+            Arc::new(OperationCoordinator::new(&TargetSpec::explorer()).with_lock_file(&path));
+        let second =
+            Arc::new(OperationCoordinator::new(&TargetSpec::explorer()).with_lock_file(&path));
+
+        let guard = lead(first.begin(Action::Restart).unwrap());
+        let err = second.begin(Action::Restart).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Another operation is in progress: operation on explorer.exe in another process"
+        );
+
+        guard.finish(&Ok(Outcome::new("restarted")));
+        assert!(matches!(
+            second.begin(Action::Restart),
+            Ok(Admission::Lead(_))
+        ));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_shared_coordinator_is_per_process_name() {
+        let explorer = OperationCoordinator::shared(&TargetSpec::explorer());
+        let again = OperationCoordinator::shared(&TargetSpec::new("EXPLORER.EXE"));
+        let search = OperationCoordinator::shared(&TargetSpec::new("SearchHost.exe"));
+
+        assert!(Arc::ptr_eq(&explorer, &again));
+        assert!(!Arc::ptr_eq(&explorer, &search));
+    }
+}
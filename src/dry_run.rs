@@ -0,0 +1,686 @@
+//! # Dry Run
+//!
+//! Shows what an operation would do without doing it. [`ProcessManager::dry_run`]
+//! swaps a manager's runner for a [`DryRunRunner`], which passes queries such as
+//! listing processes through to the real runner but only records terminations,
+//! exit requests and launches in a [`DryRunPlan`]. Their effect is simulated:
+//! terminated instances disappear, launched ones appear, and instances asked to
+//! exit do so at once. Waits advance a virtual clock, so the whole pipeline runs to
+//! completion without pausing.
+//!
+//! Reopening File Explorer windows, clearing the icon cache and the commands of
+//! profile post-steps (see [`DryRunSteps`]) are recorded in the same plan.
+
+use crate::events::{EventSink, ManagerEvent};
+use crate::profile::StepRunner;
+use crate::session::{ExplorerWindow, ShellWindowEnumerator};
+use crate::window::TaskbarHealth;
+use crate::{
+    Outcome, ProcessInfo, ProcessManager, ProcessRunner, ProcessUsage, StuckbarError, TargetSpec,
+};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// PID given to the first simulated instance; real PIDs stay far below it
+const SIMULATED_PID_BASE: u32 = 0xF000_0000;
+
+/// Something an operation would have done
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum PlannedStep {
+    /// These instances would be asked to exit
+    RequestExit {
+        process: String,
+        instances: Vec<ProcessInfo>,
+    },
+    /// These instances would be terminated
+    Terminate {
+        process: String,
+        instances: Vec<ProcessInfo>,
+    },
+    /// The operation would wait; consecutive waits are merged
+    Wait { ms: u64 },
+    /// These icon cache files would be removed
+    ClearIconCache { files: Vec<PathBuf> },
+    /// The target would be launched with this command line
+    Launch {
+        process: String,
+        command: String,
+        args: Vec<String>,
+        working_dir: Option<PathBuf>,
+    },
+    /// A File Explorer window would be reopened
+    ReopenWindow { path: String },
+    /// A profile post-step would run this command
+    Run { command: String, args: Vec<String> },
+}
+
+impl PlannedStep {
+    /// One-line, human-readable description of the step
+    pub fn describe(&self) -> String {
+        match self {
+            Self::RequestExit { process, instances } => {
+                format!("Ask {} to exit: {}", process, describe_instances(instances))
+            }
+            Self::Terminate { process, instances } => {
+                format!("Terminate {}: {}", process, describe_instances(instances))
+            }
+            Self::Wait { ms } => format!("Wait {} ms", ms),
+            Self::ClearIconCache { files } => format!(
+                "Remove {} icon cache file{}",
+                files.len(),
+                if files.len() == 1 { "" } else { "s" }
+            ),
+            Self::Launch {
+                command,
+                args,
+                working_dir,
+                ..
+            } => {
+                let mut line = format!("Launch {}", command_line(command, args));
+                if let Some(dir) = working_dir {
+                    line = format!("{} in {}", line, dir.display());
+                }
+                line
+            }
+            Self::ReopenWindow { path } => format!("Reopen File Explorer window on {}", path),
+            Self::Run { command, args } => format!("Run {}", command_line(command, args)),
+        }
+    }
+}
+
+/// List instances as "PID 4312 (session 1), PID 9876"
+fn describe_instances(instances: &[ProcessInfo]) -> String {
+    instances
+        .iter()
+        .map(|instance| match instance.session_id {
+            Some(session) => format!("PID {} (session {})", instance.pid, session),
+            None => format!("PID {}", instance.pid),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn command_line(command: &str, args: &[String]) -> String {
+    std::iter::once(command)
+        .chain(args.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Steps recorded during a dry run
+///
+/// Clones share the steps, so one plan can collect the steps of several managers
+/// (as for a profile) and still be read once they are done.
+#[derive(Clone, Default)]
+pub struct DryRunPlan {
+    steps: Arc<Mutex<Vec<PlannedStep>>>,
+}
+
+impl DryRunPlan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `step`, merging it into the previous step if both are waits or both
+    /// terminate instances of the same process
+    pub fn record(&self, step: PlannedStep) {
+        let mut steps = self.steps.lock().unwrap();
+        match (steps.last_mut(), step) {
+            (Some(PlannedStep::Wait { ms: total }), PlannedStep::Wait { ms }) => *total += ms,
+            (
+                Some(PlannedStep::Terminate {
+                    process: previous,
+                    instances: terminated,
+                }),
+                PlannedStep::Terminate { process, instances },
+            ) if *previous == process => terminated.extend(instances),
+            (_, step) => steps.push(step),
+        }
+    }
+
+    pub fn steps(&self) -> Vec<PlannedStep> {
+        self.steps.lock().unwrap().clone()
+    }
+}
+
+/// Records the icon cache files a restart would remove
+impl EventSink for DryRunPlan {
+    fn on_event(&self, _target: &TargetSpec, event: &ManagerEvent) {
+        if let ManagerEvent::IconCacheCleared { report } = event {
+            self.record(PlannedStep::ClearIconCache {
+                files: report.removed.clone(),
+            });
+        }
+    }
+}
+
+/// Simulated changes to the process table
+#[derive(Default)]
+struct Simulation {
+    /// PIDs of real instances that were terminated or asked to exit
+    exited: Vec<u32>,
+    /// Simulated instances as `(process name, PID)`
+    started: Vec<(String, u32)>,
+    next_pid: u32,
+    clock_ms: u64,
+}
+
+impl Simulation {
+    fn spawn(&mut self, process_name: &str) -> u32 {
+        let pid = SIMULATED_PID_BASE + self.next_pid;
+        self.next_pid += 1;
+        self.started.push((process_name.to_string(), pid));
+        pid
+    }
+
+    /// Remove the instances of `process_name` with these PIDs from the table
+    fn exit(&mut self, process_name: &str, pids: &[u32]) {
+        self.exited.extend_from_slice(pids);
+        self.started
+            .retain(|(name, pid)| !(name.eq_ignore_ascii_case(process_name) && pids.contains(pid)));
+    }
+}
+
+/// [`ProcessRunner`] decorator that records actions instead of performing them
+pub struct DryRunRunner<R> {
+    inner: R,
+    plan: DryRunPlan,
+    /// Whether Windows relaunches the process by itself once it is terminated
+    system_relaunch: bool,
+    simulation: Mutex<Simulation>,
+    epoch: Instant,
+}
+
+impl<R: ProcessRunner> DryRunRunner<R> {
+    /// Record the actions that would be taken through `inner` in `plan`
+    pub fn new(inner: R, plan: DryRunPlan) -> Self {
+        Self {
+            inner,
+            plan,
+            system_relaunch: false,
+            simulation: Mutex::new(Simulation::default()),
+            epoch: Instant::now(),
+        }
+    }
+
+    /// Simulate Windows relaunching terminated instances, as it does for shell hosts
+    pub fn with_system_relaunch(mut self, relaunch: bool) -> Self {
+        self.system_relaunch = relaunch;
+        self
+    }
+
+    pub fn plan(&self) -> &DryRunPlan {
+        &self.plan
+    }
+
+    /// Record `step` for the running instances `pid` selects (every instance if
+    /// `None`) and simulate them exiting
+    fn stop(
+        &self,
+        process_name: &str,
+        pid: Option<u32>,
+        step: impl FnOnce(String, Vec<ProcessInfo>) -> PlannedStep,
+    ) -> Result<Vec<u32>, StuckbarError> {
+        let mut instances = self.list_processes(process_name)?;
+        instances.retain(|instance| pid.is_none_or(|pid| instance.pid == pid));
+        if instances.is_empty() {
+            return Err(StuckbarError::NotRunning {
+                process: process_name.to_string(),
+            });
+        }
+        let pids: Vec<u32> = instances.iter().map(|instance| instance.pid).collect();
+        self.plan.record(step(process_name.to_string(), instances));
+
+        let mut simulation = self.simulation.lock().unwrap();
+        simulation.exit(process_name, &pids);
+        if self.system_relaunch {
+            simulation.spawn(process_name);
+        }
+        Ok(pids)
+    }
+}
+
+impl<R: ProcessRunner> ProcessRunner for DryRunRunner<R> {
+    fn kill_process(&self, process_name: &str) -> Result<Outcome, StuckbarError> {
+        let pids = self.stop(process_name, None, |process, instances| {
+            PlannedStep::Terminate { process, instances }
+        })?;
+        Ok(Outcome::new(format!("Would terminate {}", process_name)).with_killed_pids(pids))
+    }
+
+    fn request_exit(&self, process_name: &str) -> Result<(), StuckbarError> {
+        self.stop(process_name, None, |process, instances| {
+            PlannedStep::RequestExit { process, instances }
+        })
+        .map(|_| ())
+    }
+
+    fn kill_pid(&self, pid: u32, process_name: &str) -> Result<(), StuckbarError> {
+        self.stop(process_name, Some(pid), |process, instances| {
+            PlannedStep::Terminate { process, instances }
+        })
+        .map(|_| ())
+    }
+
+    fn start_process(&self, target: &TargetSpec) -> Result<Outcome, StuckbarError> {
+        let command = target
+            .launch_command
+            .as_deref()
+            .unwrap_or(&target.process_name);
+        self.plan.record(PlannedStep::Launch {
+            process: target.process_name.clone(),
+            command: command.to_string(),
+            args: target.args.clone(),
+            working_dir: target.working_dir.clone(),
+        });
+        let pid = self.simulation.lock().unwrap().spawn(&target.process_name);
+        Ok(Outcome::new(format!("Would launch {}", target.process_name)).with_new_pid(pid))
+    }
+
+    fn list_pids(&self, process_name: &str) -> Result<Vec<u32>, StuckbarError> {
+        Ok(self
+            .list_processes(process_name)?
+            .into_iter()
+            .map(|instance| instance.pid)
+            .collect())
+    }
+
+    fn list_processes(&self, process_name: &str) -> Result<Vec<ProcessInfo>, StuckbarError> {
+        let mut instances = self.inner.list_processes(process_name)?;
+        let simulation = self.simulation.lock().unwrap();
+        instances.retain(|instance| !simulation.exited.contains(&instance.pid));
+        instances.extend(
+            simulation
+                .started
+                .iter()
+                .filter(|(name, _)| name.eq_ignore_ascii_case(process_name))
+                .map(|(_, pid)| ProcessInfo {
+                    pid: *pid,
+                    session_id: None,
+                    owner: None,
+                }),
+        );
+        Ok(instances)
+    }
+
+    fn current_process(&self) -> Option<ProcessInfo> {
+        self.inner.current_process()
+    }
+
+    fn process_usage(&self, process_name: &str) -> Result<Vec<ProcessUsage>, StuckbarError> {
+        let mut usage = self.inner.process_usage(process_name)?;
+        let simulation = self.simulation.lock().unwrap();
+        usage.retain(|sample| !simulation.exited.contains(&sample.pid));
+        Ok(usage)
+    }
+
+    fn sleep_ms(&self, ms: u64) {
+        self.plan.record(PlannedStep::Wait { ms });
+        self.simulation.lock().unwrap().clock_ms += ms;
+    }
+
+    fn now(&self) -> Instant {
+        self.epoch + Duration::from_millis(self.simulation.lock().unwrap().clock_ms)
+    }
+}
+
+/// Lists the real windows but only records reopening them
+struct DryRunShellWindows {
+    inner: Arc<dyn ShellWindowEnumerator + Send + Sync>,
+    plan: DryRunPlan,
+}
+
+impl ShellWindowEnumerator for DryRunShellWindows {
+    fn open_windows(&self) -> Result<Vec<ExplorerWindow>, StuckbarError> {
+        self.inner.open_windows()
+    }
+
+    fn open_window(&self, window: &ExplorerWindow) -> Result<(), StuckbarError> {
+        self.plan.record(PlannedStep::ReopenWindow {
+            path: window.path.clone(),
+        });
+        Ok(())
+    }
+}
+
+/// [`StepRunner`] decorator that records waits and commands instead of running them
+///
+/// Taskbar checks only observe the system and are passed through.
+pub struct DryRunSteps<S> {
+    inner: S,
+    plan: DryRunPlan,
+}
+
+impl<S: StepRunner> DryRunSteps<S> {
+    pub fn new(inner: S, plan: DryRunPlan) -> Self {
+        Self { inner, plan }
+    }
+}
+
+impl<S: StepRunner> StepRunner for DryRunSteps<S> {
+    fn sleep_ms(&self, ms: u64) {
+        self.plan.record(PlannedStep::Wait { ms });
+    }
+
+    fn check_taskbar(&self) -> TaskbarHealth {
+        self.inner.check_taskbar()
+    }
+
+    fn run_command(&self, command: &str, args: &[String]) -> Result<(), StuckbarError> {
+        self.plan.record(PlannedStep::Run {
+            command: command.to_string(),
+            args: args.to_vec(),
+        });
+        Ok(())
+    }
+}
+
+impl<R: ProcessRunner> ProcessManager<R> {
+    /// Turn this manager into one that only records what its operations would do
+    ///
+    /// Steps are recorded in `plan`. The coordinator and restart limiter are
+    /// dropped, so a dry run neither waits for other operations nor counts towards
+    /// the restart limits. Call this last: builders applied afterwards may replace
+    /// the recording decorators.
+    pub fn dry_run(self, plan: &DryRunPlan) -> ProcessManager<DryRunRunner<R>> {
+        let system_relaunch = self.target.launch_command.is_none();
+        let mut sinks = self.sinks;
+        sinks.push(Box::new(plan.clone()));
+
+        ProcessManager {
+            runner: DryRunRunner::new(self.runner, plan.clone())
+                .with_system_relaunch(system_relaunch),
+            target: self.target,
+            restart_delay_ms: self.restart_delay_ms,
+            liveness: self.liveness,
+            kill_strategy: self.kill_strategy,
+            session_scope: self.session_scope,
+            restore_windows: self.restore_windows,
+            shell_windows: Arc::new(DryRunShellWindows {
+                inner: self.shell_windows,
+                plan: plan.clone(),
+            }),
+            rebuild_icon_cache: self.rebuild_icon_cache,
+            icon_cache: self.icon_cache.with_dry_run(true),
+            sinks,
+            coordinator: None,
+            limiter: None,
+        }
+    }
+}
+
+/// Result of a dry run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DryRunReport {
+    /// Operation that was simulated, such as "restart"
+    pub operation: String,
+    /// What the operation would do, in order
+    pub steps: Vec<PlannedStep>,
+    /// Whether the operation would succeed
+    pub success: bool,
+    pub message: String,
+    pub error: Option<StuckbarError>,
+}
+
+impl DryRunReport {
+    /// Report the steps recorded in `plan` for an operation that ended with `result`
+    pub fn new(
+        operation: impl Into<String>,
+        plan: &DryRunPlan,
+        result: Result<(), StuckbarError>,
+    ) -> Self {
+        let operation = operation.into();
+        let steps = plan.steps();
+        let (message, error) = match result {
+            Ok(()) => (
+                format!(
+                    "{} would take {} step{}; nothing was changed",
+                    operation,
+                    steps.len(),
+                    if steps.len() == 1 { "" } else { "s" }
+                ),
+                None,
+            ),
+            Err(error) => (format!("{} would fail: {}", operation, error), Some(error)),
+        };
+        Self {
+            operation,
+            steps,
+            success: error.is_none(),
+            message,
+            error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::icon_cache::IconCache;
+    use crate::mock::{MOCK_OWNER, MockCacheFiles, MockProcessRunner, MockShellWindows};
+    use crate::scope::SessionScope;
+    use crate::{KillStrategy, TargetSpec};
+
+    fn explorer(pid: u32, session: u32) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            session_id: Some(session),
+            owner: Some(MOCK_OWNER.to_string()),
+        }
+    }
+
+    /// Manager for every session whose runner reports explorer running as PIDs
+    /// 100 and 200
+    fn running_explorer() -> ProcessManager<MockProcessRunner> {
+        ProcessManager::new(
+            MockProcessRunner::new()
+                .with_running(&[100, 200])
+                .with_session(100, 1)
+                .with_session(200, 2),
+        )
+        .with_session_scope(SessionScope::All)
+    }
+
+    #[test]
+    fn test_restart_records_plan_without_side_effects() {
+        let plan = DryRunPlan::new();
+        let manager = running_explorer().dry_run(&plan);
+
+        manager.restart().unwrap();
+
+        assert!(manager.runner.inner.get_killed().is_empty());
+        assert!(manager.runner.inner.get_started().is_empty());
+        assert!(manager.runner.inner.get_sleep_calls().is_empty());
+        let steps = plan.steps();
+        assert_eq!(
+            steps[0],
+            PlannedStep::Terminate {
+                process: "explorer.exe".to_string(),
+                instances: vec![explorer(100, 1), explorer(200, 2)],
+            }
+        );
+        assert_eq!(steps[1], PlannedStep::Wait { ms: 500 });
+        assert!(matches!(
+            &steps[2],
+            PlannedStep::Launch { command, .. } if command == "explorer.exe"
+        ));
+        assert!(matches!(steps[3], PlannedStep::Wait { .. }));
+        assert_eq!(steps.len(), 4);
+    }
+
+    #[test]
+    fn test_scoped_kill_records_instances_in_scope() {
+        let plan = DryRunPlan::new();
+        let manager = ProcessManager::new(
+            MockProcessRunner::new()
+                .with_instance(100, 1, "S-1-5-21-1")
+                .with_instance(200, 2, "S-1-5-21-2")
+                .with_instance(300, 1, "S-1-5-21-1")
+                .with_caller(1, "S-1-5-21-1"),
+        )
+        .with_session_scope(SessionScope::Current)
+        .dry_run(&plan);
+
+        let outcome = manager.kill().unwrap();
+
+        assert_eq!(outcome.killed_pids, vec![100, 300]);
+        assert!(manager.runner.inner.get_killed_pids().is_empty());
+        let owned = |pid| ProcessInfo {
+            owner: Some("S-1-5-21-1".to_string()),
+            ..explorer(pid, 1)
+        };
+        assert_eq!(
+            plan.steps()[0],
+            PlannedStep::Terminate {
+                process: "explorer.exe".to_string(),
+                instances: vec![owned(100), owned(300)],
+            }
+        );
+    }
+
+    #[test]
+    fn test_graceful_kill_records_exit_request() {
+        let plan = DryRunPlan::new();
+        let manager = running_explorer()
+            .with_kill_strategy(KillStrategy::graceful())
+            .dry_run(&plan);
+
+        let outcome = manager.kill().unwrap();
+
+        assert_eq!(outcome.killed_pids, vec![100, 200]);
+        assert!(manager.runner.inner.get_exit_requests().is_empty());
+        assert!(matches!(plan.steps()[0], PlannedStep::RequestExit { .. }));
+        assert!(
+            !plan
+                .steps()
+                .iter()
+                .any(|step| matches!(step, PlannedStep::Terminate { .. }))
+        );
+    }
+
+    #[test]
+    fn test_kill_when_not_running_fails_like_real_kill() {
+        let plan = DryRunPlan::new();
+        let manager = ProcessManager::new(MockProcessRunner::new()).dry_run(&plan);
+
+        let error = manager.kill().unwrap_err();
+
+        assert_eq!(error.kind(), "not_running");
+        let report = DryRunReport::new("kill", &plan, Err(error));
+        assert!(!report.success);
+        assert!(report.message.starts_with("kill would fail"));
+    }
+
+    #[test]
+    fn test_system_relaunched_target_restarts() {
+        let plan = DryRunPlan::new();
+        let target = TargetSpec::preset("start-menu").unwrap();
+        let manager =
+            ProcessManager::for_target(MockProcessRunner::new().with_running(&[300]), target)
+                .dry_run(&plan);
+
+        manager.restart().unwrap();
+
+        assert!(
+            !plan
+                .steps()
+                .iter()
+                .any(|step| matches!(step, PlannedStep::Launch { .. }))
+        );
+    }
+
+    #[test]
+    fn test_restart_records_windows_and_icon_cache() {
+        let plan = DryRunPlan::new();
+        let windows = MockShellWindows::new(vec![ExplorerWindow::new("C:\\Users")]);
+        let files = MockCacheFiles::new(&["cache/iconcache_32.db"]);
+        let manager = running_explorer()
+            .with_restore_windows(true)
+            .with_shell_windows(windows.clone())
+            .with_rebuild_icon_cache(true)
+            .with_icon_cache(
+                IconCache::new(Some(PathBuf::from("cache"))).with_file_system(files.clone()),
+            )
+            .dry_run(&plan);
+
+        manager.restart().unwrap();
+
+        assert!(windows.get_opened().is_empty());
+        assert_eq!(files.get_files().len(), 1);
+        let steps = plan.steps();
+        assert!(steps.contains(&PlannedStep::ClearIconCache {
+            files: vec![PathBuf::from("cache/iconcache_32.db")],
+        }));
+        assert_eq!(
+            steps.last(),
+            Some(&PlannedStep::ReopenWindow {
+                path: "C:\\Users".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_consecutive_waits_are_merged() {
+        let plan = DryRunPlan::new();
+        plan.record(PlannedStep::Wait { ms: 100 });
+        plan.record(PlannedStep::Wait { ms: 200 });
+        plan.record(PlannedStep::ReopenWindow {
+            path: "C:\\".to_string(),
+        });
+        plan.record(PlannedStep::Wait { ms: 50 });
+
+        assert_eq!(
+            plan.steps(),
+            vec![
+                PlannedStep::Wait { ms: 300 },
+                PlannedStep::ReopenWindow {
+                    path: "C:\\".to_string(),
+                },
+                PlannedStep::Wait { ms: 50 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_describe() {
+        let terminate = PlannedStep::Terminate {
+            process: "explorer.exe".to_string(),
+            instances: vec![
+                explorer(4312, 1),
+                ProcessInfo {
+                    pid: 9876,
+                    session_id: None,
+                    owner: None,
+                },
+            ],
+        };
+        assert_eq!(
+            terminate.describe(),
+            "Terminate explorer.exe: PID 4312 (session 1), PID 9876"
+        );
+        let run = PlannedStep::Run {
+            command: "ie4uinit.exe".to_string(),
+            args: vec!["-show".to_string()],
+        };
+        assert_eq!(run.describe(), "Run ie4uinit.exe -show");
+    }
+
+    #[test]
+    fn test_report_json() {
+        let plan = DryRunPlan::new();
+        plan.record(PlannedStep::Wait { ms: 500 });
+        let report = DryRunReport::new("restart", &plan, Ok(()));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["steps"][0]["step"], "wait");
+        assert_eq!(json["steps"][0]["ms"], 500);
+        assert_eq!(
+            json["message"],
+            "restart would take 1 step; nothing was changed"
+        );
+        assert_eq!(json["success"], true);
+    }
+}
@@ -0,0 +1,218 @@
+//! # Lifecycle Events
+//!
+//! [`ProcessManager`](crate::ProcessManager) does not print anything itself. Each
+//! operation emits a sequence of [`ManagerEvent`]s to the [`EventSink`]s registered
+//! with [`with_sink`](crate::ProcessManager::with_sink), which decide how progress
+//! and results are presented or recorded.
+//!
+//! A forced restart that succeeds emits:
+//!
+//! ```text
+//! RestartStarted
+//! KillStarted
+//! KillCompleted { pids }
+//! WaitingForExit { pids }
+//! StartStarted
+//! WaitingForStart
+//! StartCompleted { pid }
+//! OperationCompleted { action: Restart, outcome }
+//! ```
+//!
+//! Every operation ends with exactly one [`OperationCompleted`](ManagerEvent::OperationCompleted)
+//! or [`OperationFailed`](ManagerEvent::OperationFailed). A failed restart also
+//! emits [`RestartFailed`](ManagerEvent::RestartFailed) naming the stage that failed,
+//! and a restart that joins one already in progress emits only
+//! [`OperationJoined`](ManagerEvent::OperationJoined) before its result.
+
+use crate::icon_cache::CacheReport;
+use crate::{Outcome, StuckbarError, TargetSpec};
+use serde::{Deserialize, Serialize};
+
+/// An operation performed by a manager
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Kill,
+    Start,
+    Restart,
+}
+
+impl Action {
+    /// Stable snake_case name of the action, as used in its JSON form
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Kill => "kill",
+            Action::Start => "start",
+            Action::Restart => "restart",
+        }
+    }
+}
+
+/// Stage of a restart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Kill,
+    Start,
+}
+
+/// Something that happened while a manager was running an operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ManagerEvent {
+    /// A restart is starting
+    RestartStarted,
+    /// The running instances are about to be terminated
+    KillStarted,
+    /// The process was asked to exit and has `grace_ms` to do so
+    ExitRequested { grace_ms: u64 },
+    /// The process did not exit within the grace period and will be forced
+    GraceExpired { grace_ms: u64 },
+    /// These instances were terminated or exited on their own
+    KillCompleted { pids: Vec<u32> },
+    /// Waiting for the terminated instances to disappear
+    WaitingForExit { pids: Vec<u32> },
+    /// Explorer's icon cache was cleared while it was down
+    IconCacheCleared { report: CacheReport },
+    /// A new instance is about to be launched
+    StartStarted,
+    /// Waiting for a new instance to appear and settle
+    WaitingForStart,
+    /// A new instance is up and survived the settle window
+    StartCompleted { pid: u32 },
+    /// A restart failed at `stage`
+    RestartFailed { stage: Stage, error: StuckbarError },
+    /// The requested operation joined an identical one already in progress
+    OperationJoined { action: Action },
+    /// The requested operation succeeded
+    OperationCompleted { action: Action, outcome: Outcome },
+    /// The requested operation failed
+    OperationFailed {
+        action: Action,
+        error: StuckbarError,
+    },
+}
+
+impl ManagerEvent {
+    /// Stable snake_case name of the event, as used in its JSON form
+    pub fn name(&self) -> &'static str {
+        match self {
+            ManagerEvent::RestartStarted => "restart_started",
+            ManagerEvent::KillStarted => "kill_started",
+            ManagerEvent::ExitRequested { .. } => "exit_requested",
+            ManagerEvent::GraceExpired { .. } => "grace_expired",
+            ManagerEvent::KillCompleted { .. } => "kill_completed",
+            ManagerEvent::WaitingForExit { .. } => "waiting_for_exit",
+            ManagerEvent::IconCacheCleared { .. } => "icon_cache_cleared",
+            ManagerEvent::StartStarted => "start_started",
+            ManagerEvent::WaitingForStart => "waiting_for_start",
+            ManagerEvent::StartCompleted { .. } => "start_completed",
+            ManagerEvent::RestartFailed { .. } => "restart_failed",
+            ManagerEvent::OperationJoined { .. } => "operation_joined",
+            ManagerEvent::OperationCompleted { .. } => "operation_completed",
+            ManagerEvent::OperationFailed { .. } => "operation_failed",
+        }
+    }
+
+    /// Event that ends an operation with `result`
+    pub fn finished(action: Action, result: &Result<Outcome, StuckbarError>) -> Self {
+        match result {
+            Ok(outcome) => ManagerEvent::OperationCompleted {
+                action,
+                outcome: outcome.clone(),
+            },
+            Err(error) => ManagerEvent::OperationFailed {
+                action,
+                error: error.clone(),
+            },
+        }
+    }
+}
+
+/// Receiver of the events emitted by a manager
+///
+/// Sinks are called synchronously from the operation, so they should return
+/// quickly. Closures taking `(&TargetSpec, &ManagerEvent)` are sinks too.
+pub trait EventSink {
+    fn on_event(&self, target: &TargetSpec, event: &ManagerEvent);
+}
+
+impl<F: Fn(&TargetSpec, &ManagerEvent)> EventSink for F {
+    fn on_event(&self, target: &TargetSpec, event: &ManagerEvent) {
+        self(target, event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_event_names_match_json_tag() {
+        let events = vec![
+            ManagerEvent::RestartStarted,
+            ManagerEvent::KillStarted,
+            ManagerEvent::ExitRequested { grace_ms: 3000 },
+            ManagerEvent::GraceExpired { grace_ms: 3000 },
+            ManagerEvent::KillCompleted { pids: vec![1] },
+            ManagerEvent::WaitingForExit { pids: vec![1] },
+            ManagerEvent::StartStarted,
+            ManagerEvent::WaitingForStart,
+            ManagerEvent::StartCompleted { pid: 7 },
+            ManagerEvent::RestartFailed {
+                stage: Stage::Kill,
+                error: StuckbarError::NotRunning {
+                    process: "explorer.exe".to_string(),
+                },
+            },
+            ManagerEvent::OperationJoined {
+                action: Action::Restart,
+            },
+            ManagerEvent::finished(Action::Kill, &Ok(Outcome::new("Killed"))),
+            ManagerEvent::finished(
+                Action::Kill,
+                &Err(StuckbarError::NotRunning {
+                    process: "explorer.exe".to_string(),
+                }),
+            ),
+        ];
+
+        for event in events {
+            let value = serde_json::to_value(&event).unwrap();
+            assert_eq!(value["event"], event.name());
+        }
+    }
+
+    #[test]
+    fn test_restart_failed_serializes_stage_and_error() {
+        let event = ManagerEvent::RestartFailed {
+            stage: Stage::Start,
+            error: StuckbarError::Crashed {
+                process: "explorer.exe".to_string(),
+                pid: 7,
+            },
+        };
+
+        let value = serde_json::to_value(&event).unwrap();
+
+        assert_eq!(value["stage"], "start");
+        assert_eq!(value["error"]["kind"], "crashed");
+    }
+
+    #[test]
+    fn test_closure_is_a_sink() {
+        let seen = std::cell::RefCell::new(Vec::new());
+        let sink = |target: &TargetSpec, event: &ManagerEvent| {
+            seen.borrow_mut()
+                .push(json!([target.process_name, event.name()]));
+        };
+
+        sink.on_event(&TargetSpec::explorer(), &ManagerEvent::KillStarted);
+
+        assert_eq!(
+            seen.into_inner(),
+            vec![json!(["explorer.exe", "kill_started"])]
+        );
+    }
+}
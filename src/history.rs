@@ -0,0 +1,530 @@
+//! # Restart History
+//!
+//! Every kill, start and restart that really ran is appended to a [`HistoryLog`],
+//! one [`HistoryEntry`] per line of JSON, so machines whose shell keeps needing
+//! restarts can be spotted. The log lives in
+//! `%LOCALAPPDATA%\stuckbar\history.jsonl` unless `history.file` says otherwise.
+//!
+//! Entries are written by a [`HistorySink`] registered with the manager; it tags
+//! each one with its [`Trigger`] and, for profile runs, the profile name. Dry runs
+//! and callers that joined an operation already in progress are not recorded.
+//!
+//! Once appending an entry would grow the file beyond its size limit, the file is
+//! renamed to `history.jsonl.1`, replacing the previous one, and a new file is
+//! started. Reading covers both files.
+
+use crate::events::{Action, EventSink, ManagerEvent};
+use crate::{StuckbarError, TargetSpec, Timings};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default size in bytes the history file may reach before it is rotated
+pub const HISTORY_MAX_BYTES: u64 = 1024 * 1024;
+
+/// Number of entries served as recent history
+pub const RECENT_ENTRIES: usize = 50;
+
+/// What asked for an operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    /// A `stuckbar` command
+    Cli,
+    /// A tool call to the MCP server
+    Mcp,
+    /// `stuckbar watch` after its health checks failed
+    Watchdog,
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cli => write!(f, "cli"),
+            Self::Mcp => write!(f, "mcp"),
+            Self::Watchdog => write!(f, "watchdog"),
+        }
+    }
+}
+
+/// A finished operation, as recorded in the history
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// When the operation finished, in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub trigger: Trigger,
+    /// Profile the operation was run for, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    pub action: Action,
+    pub target: String,
+    pub success: bool,
+    pub message: String,
+    #[serde(default)]
+    pub killed_pids: Vec<u32>,
+    #[serde(default)]
+    pub new_pid: Option<u32>,
+    #[serde(default)]
+    pub timings: Timings,
+    /// [Kind](StuckbarError::kind) of the error the operation failed with
+    #[serde(default)]
+    pub error_kind: Option<String>,
+}
+
+impl HistoryEntry {
+    /// Entry for `event` if it ends an operation that was not joined
+    pub fn from_event(
+        timestamp_ms: u64,
+        trigger: Trigger,
+        target: &TargetSpec,
+        event: &ManagerEvent,
+    ) -> Option<Self> {
+        let entry = |action, success, message: String| Self {
+            timestamp_ms,
+            trigger,
+            profile: None,
+            action,
+            target: target.process_name.clone(),
+            success,
+            message,
+            killed_pids: Vec::new(),
+            new_pid: None,
+            timings: Timings::default(),
+            error_kind: None,
+        };
+        match event {
+            ManagerEvent::OperationCompleted { action, outcome } if !outcome.joined => Some(Self {
+                killed_pids: outcome.killed_pids.clone(),
+                new_pid: outcome.new_pid,
+                timings: outcome.timings,
+                ..entry(*action, true, outcome.message.clone())
+            }),
+            ManagerEvent::OperationFailed { action, error } => Some(Self {
+                error_kind: Some(error.kind().to_string()),
+                ..entry(*action, false, error.to_string())
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Append-only history file, rotated by size
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryLog {
+    path: PathBuf,
+    max_bytes: u64,
+}
+
+impl HistoryLog {
+    /// History kept in the file at `path`, rotated at [`HISTORY_MAX_BYTES`]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            max_bytes: HISTORY_MAX_BYTES,
+        }
+    }
+
+    /// Rotate the file once it would grow beyond `max_bytes`; 0 never rotates it
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// File the previous entries were moved to by the last rotation
+    pub fn rotated_path(&self) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(".1");
+        PathBuf::from(name)
+    }
+
+    /// Append `entry`, rotating the file first if it would grow too large
+    pub fn append(&self, entry: &HistoryEntry) -> io::Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let size = fs::metadata(&self.path).map_or(0, |metadata| metadata.len());
+        if self.max_bytes > 0 && size > 0 && size + line.len() as u64 > self.max_bytes {
+            fs::rename(&self.path, self.rotated_path())?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        // One write per entry, so concurrent writers do not interleave lines
+        file.write_all(line.as_bytes())
+    }
+
+    /// Every entry, oldest first
+    ///
+    /// Missing files hold no entries, and lines that are not entries are skipped.
+    pub fn read(&self) -> io::Result<Vec<HistoryEntry>> {
+        let mut entries = Vec::new();
+        for path in [self.rotated_path(), self.path.clone()] {
+            let text = match fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            entries.extend(
+                text.lines()
+                    .filter_map(|line| serde_json::from_str::<HistoryEntry>(line).ok()),
+            );
+        }
+        Ok(entries)
+    }
+
+    /// Entries of operations that finished at or after `start_ms`, oldest first
+    pub fn since(&self, start_ms: u64) -> io::Result<Vec<HistoryEntry>> {
+        let mut entries = self.read()?;
+        entries.retain(|entry| entry.timestamp_ms >= start_ms);
+        Ok(entries)
+    }
+
+    /// The last `count` entries, oldest first
+    pub fn recent(&self, count: usize) -> io::Result<Vec<HistoryEntry>> {
+        let mut entries = self.read()?;
+        entries.drain(..entries.len().saturating_sub(count));
+        Ok(entries)
+    }
+}
+
+/// `%LOCALAPPDATA%\stuckbar\history.jsonl`, if `LOCALAPPDATA` is set
+pub fn default_path() -> Option<PathBuf> {
+    std::env::var_os("LOCALAPPDATA")
+        .map(|dir| PathBuf::from(dir).join("stuckbar").join("history.jsonl"))
+}
+
+/// Sink that appends every finished operation to a [`HistoryLog`]
+#[derive(Debug, Clone)]
+pub struct HistorySink {
+    log: HistoryLog,
+    trigger: Trigger,
+    profile: Option<String>,
+}
+
+impl HistorySink {
+    pub fn new(log: HistoryLog, trigger: Trigger) -> Self {
+        Self {
+            log,
+            trigger,
+            profile: None,
+        }
+    }
+
+    /// Record the operations as part of the profile called `name`
+    pub fn with_profile(mut self, name: impl Into<String>) -> Self {
+        self.profile = Some(name.into());
+        self
+    }
+}
+
+impl EventSink for HistorySink {
+    fn on_event(&self, target: &TargetSpec, event: &ManagerEvent) {
+        let Some(mut entry) =
+            HistoryEntry::from_event(unix_ms(SystemTime::now()), self.trigger, target, event)
+        else {
+            return;
+        };
+        entry.profile = self.profile.clone();
+        // The history is a record, not part of the operation, so a failed write is ignored
+        let _ = self.log.append(&entry);
+    }
+}
+
+/// Start of the period shown by `stuckbar history --since`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Since {
+    /// This many milliseconds ago, written as a number and a unit: `90s`, `30m`,
+    /// `12h`, `7d` or `2w`
+    Ago(u64),
+    /// Midnight UTC of a date written as `YYYY-MM-DD`, in milliseconds since the
+    /// Unix epoch
+    Date(u64),
+}
+
+impl Since {
+    /// Start of the period in milliseconds since the Unix epoch, at `now`
+    pub fn start_ms(&self, now: SystemTime) -> u64 {
+        match self {
+            Self::Ago(ms) => unix_ms(now).saturating_sub(*ms),
+            Self::Date(ms) => *ms,
+        }
+    }
+}
+
+impl FromStr for Since {
+    type Err = StuckbarError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || StuckbarError::InvalidConfig {
+            details: format!(
+                "invalid --since '{}', expected a duration such as 30m, 12h or 7d, or a date such as 2026-10-01",
+                value
+            ),
+        };
+        if let Some(ms) = parse_date(value) {
+            return Ok(Self::Date(ms));
+        }
+        let split = value.len().checked_sub(1).ok_or_else(invalid)?;
+        let (count, unit) = value.split_at_checked(split).ok_or_else(invalid)?;
+        let unit_ms: u64 = match unit {
+            "s" => 1000,
+            "m" => 60 * 1000,
+            "h" => 60 * 60 * 1000,
+            "d" => 24 * 60 * 60 * 1000,
+            "w" => 7 * 24 * 60 * 60 * 1000,
+            _ => return Err(invalid()),
+        };
+        let count: u64 = count.parse().map_err(|_| invalid())?;
+        count
+            .checked_mul(unit_ms)
+            .map(Self::Ago)
+            .ok_or_else(invalid)
+    }
+}
+
+/// Midnight UTC of `YYYY-MM-DD` in milliseconds since the Unix epoch
+fn parse_date(value: &str) -> Option<u64> {
+    let mut parts = value.splitn(3, '-');
+    let year: i64 = parts.next().filter(|part| part.len() == 4)?.parse().ok()?;
+    let month: i64 = parts.next().filter(|part| part.len() == 2)?.parse().ok()?;
+    let day: i64 = parts.next().filter(|part| part.len() == 2)?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    u64::try_from(days * 86_400_000).ok()
+}
+
+/// "2026-10-16 09:12:03 UTC" for a time in milliseconds since the Unix epoch
+pub fn format_timestamp(timestamp_ms: u64) -> String {
+    let seconds = timestamp_ms / 1000;
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3600 % 24,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Proleptic Gregorian date of a number of days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Outcome;
+    use std::time::Duration;
+
+    fn temp_log(name: &str) -> HistoryLog {
+        let dir =
+            std::env::temp_dir().join(format!("stuckbar-history-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        HistoryLog::new(dir.join("history.jsonl"))
+    }
+
+    fn entry(timestamp_ms: u64) -> HistoryEntry {
+        let outcome = Outcome::new("Restarted").with_new_pid(7);
+        let event = ManagerEvent::finished(Action::Restart, &Ok(outcome));
+        HistoryEntry::from_event(timestamp_ms, Trigger::Cli, &TargetSpec::explorer(), &event)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_entry_from_events() {
+        let target = TargetSpec::explorer();
+        let mut outcome = Outcome::new("Restarted").with_killed_pids(vec![4]);
+        outcome.timings.total_ms = 1200;
+        let completed = ManagerEvent::finished(Action::Restart, &Ok(outcome.clone()));
+
+        let entry = HistoryEntry::from_event(5, Trigger::Mcp, &target, &completed).unwrap();
+        assert!(entry.success);
+        assert_eq!(entry.killed_pids, vec![4]);
+        assert_eq!(entry.timings.total_ms, 1200);
+        assert_eq!(entry.error_kind, None);
+
+        let error = StuckbarError::NotRunning {
+            process: "explorer.exe".to_string(),
+        };
+        let failed = ManagerEvent::finished(Action::Kill, &Err(error));
+        let entry = HistoryEntry::from_event(5, Trigger::Watchdog, &target, &failed).unwrap();
+        assert!(!entry.success);
+        assert_eq!(entry.error_kind.as_deref(), Some("not_running"));
+        assert_eq!(entry.message, "explorer.exe is not running");
+
+        outcome.joined = true;
+        let joined = ManagerEvent::finished(Action::Restart, &Ok(outcome));
+        assert_eq!(
+            HistoryEntry::from_event(5, Trigger::Cli, &target, &joined),
+            None
+        );
+        assert_eq!(
+            HistoryEntry::from_event(5, Trigger::Cli, &target, &ManagerEvent::KillStarted),
+            None
+        );
+    }
+
+    #[test]
+    fn test_entry_json() {
+        let value = serde_json::to_value(entry(1000)).unwrap();
+
+        assert_eq!(value["timestamp_ms"], 1000);
+        assert_eq!(value["trigger"], "cli");
+        assert_eq!(value["action"], "restart");
+        assert_eq!(value["target"], "explorer.exe");
+        assert_eq!(value["new_pid"], 7);
+        assert!(value.get("profile").is_none());
+        assert_eq!(
+            serde_json::from_value::<HistoryEntry>(value).unwrap(),
+            entry(1000)
+        );
+    }
+
+    #[test]
+    fn test_append_and_read() {
+        let log = temp_log("read");
+        log.append(&entry(1)).unwrap();
+        log.append(&entry(2)).unwrap();
+        log.append(&entry(3)).unwrap();
+
+        let stamps = |entries: Vec<HistoryEntry>| -> Vec<u64> {
+            entries.iter().map(|entry| entry.timestamp_ms).collect()
+        };
+        assert_eq!(stamps(log.read().unwrap()), vec![1, 2, 3]);
+        assert_eq!(stamps(log.since(2).unwrap()), vec![2, 3]);
+        assert_eq!(stamps(log.recent(2).unwrap()), vec![2, 3]);
+        assert_eq!(stamps(log.recent(10).unwrap()), vec![1, 2, 3]);
+        let _ = fs::remove_dir_all(log.path().parent().unwrap());
+    }
+
+    #[test]
+    fn test_read_missing_and_malformed() {
+        let log = temp_log("malformed");
+        assert_eq!(log.read().unwrap(), Vec::new());
+
+        log.append(&entry(1)).unwrap();
+        let mut file = OpenOptions::new().append(true).open(log.path()).unwrap();
+        writeln!(file, "not json").unwrap();
+        drop(file);
+        log.append(&entry(2)).unwrap();
+
+        assert_eq!(log.read().unwrap().len(), 2);
+        let _ = fs::remove_dir_all(log.path().parent().unwrap());
+    }
+
+    #[test]
+    fn test_rotates_by_size() {
+        let line = serde_json::to_string(&entry(1)).unwrap().len() as u64 + 1;
+        let log = temp_log("rotate").with_max_bytes(line * 2);
+
+        for timestamp in 1..=5 {
+            log.append(&entry(timestamp)).unwrap();
+        }
+
+        assert!(log.rotated_path().ends_with("history.jsonl.1"));
+        let current = fs::read_to_string(log.path()).unwrap();
+        assert_eq!(current.lines().count(), 1);
+        let stamps: Vec<u64> = log
+            .read()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.timestamp_ms)
+            .collect();
+        assert_eq!(stamps, vec![3, 4, 5]);
+        let _ = fs::remove_dir_all(log.path().parent().unwrap());
+    }
+
+    #[test]
+    fn test_sink_records_trigger_and_profile() {
+        let log = temp_log("sink");
+        let sink = HistorySink::new(log.clone(), Trigger::Watchdog).with_profile("taskbar");
+        let target = TargetSpec::explorer();
+
+        sink.on_event(&target, &ManagerEvent::RestartStarted);
+        sink.on_event(
+            &target,
+            &ManagerEvent::finished(Action::Restart, &Ok(Outcome::new("Restarted"))),
+        );
+
+        let entries = log.read().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].trigger, Trigger::Watchdog);
+        assert_eq!(entries[0].profile.as_deref(), Some("taskbar"));
+        assert!(entries[0].timestamp_ms > 0);
+        let _ = fs::remove_dir_all(log.path().parent().unwrap());
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = UNIX_EPOCH + Duration::from_millis(10_000_000_000);
+        assert_eq!("30m".parse::<Since>(), Ok(Since::Ago(30 * 60 * 1000)));
+        assert_eq!("2w".parse::<Since>(), Ok(Since::Ago(14 * 86_400_000)));
+        assert_eq!(
+            "12h".parse::<Since>().unwrap().start_ms(now),
+            10_000_000_000 - 12 * 3_600_000
+        );
+        assert_eq!(
+            "2026-10-16".parse::<Since>(),
+            Ok(Since::Date(1_792_108_800_000))
+        );
+        for invalid in ["", "h", "12", "12y", "-1h", "2026-13-01", "26-10-16"] {
+            let error = invalid.parse::<Since>().unwrap_err();
+            assert_eq!(error.kind(), "invalid_config", "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(
+            format_timestamp(1_792_108_800_000 + 9 * 3_600_000 + 12 * 60_000 + 3_000),
+            "2026-10-16 09:12:03 UTC"
+        );
+        assert_eq!(format_timestamp(951_782_400_000), "2000-02-29 00:00:00 UTC");
+    }
+}
@@ -0,0 +1,314 @@
+//! # Icon Cache Rebuild
+//!
+//! Explorer keeps icons and thumbnails in `iconcache_*.db` and `thumbcache_*.db`
+//! files under `%LOCALAPPDATA%\Microsoft\Windows\Explorer`. A corrupt cache
+//! survives a restart and keeps the taskbar drawing blank or wrong icons. The
+//! files are only unlocked while explorer is down, so [`IconCache::clear`] runs
+//! between kill and start of a restart (see
+//! [`ProcessManager::with_rebuild_icon_cache`](crate::ProcessManager::with_rebuild_icon_cache));
+//! explorer rebuilds them when it starts.
+//!
+//! File access sits behind [`CacheFileSystem`], so clearing can be tested against
+//! a temporary directory or a mock.
+
+use serde::Serialize;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Name prefixes of the cache files, which all end in `.db`
+pub const CACHE_FILE_PREFIXES: &[&str] = &["iconcache_", "thumbcache_"];
+
+/// Trait for abstracting file access (enables testing)
+pub trait CacheFileSystem {
+    /// List the files directly inside `dir`
+    fn list_files(&self, dir: &Path) -> io::Result<Vec<PathBuf>>;
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+}
+
+/// [`CacheFileSystem`] backed by `std::fs`
+pub struct SystemFileSystem;
+
+impl CacheFileSystem for SystemFileSystem {
+    fn list_files(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                files.push(entry.path());
+            }
+        }
+        Ok(files)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        std::fs::remove_file(path)
+    }
+}
+
+/// `%LOCALAPPDATA%\Microsoft\Windows\Explorer`, if `LOCALAPPDATA` is set
+pub fn default_cache_dir() -> Option<PathBuf> {
+    std::env::var_os("LOCALAPPDATA").map(|dir| {
+        PathBuf::from(dir)
+            .join("Microsoft")
+            .join("Windows")
+            .join("Explorer")
+    })
+}
+
+/// Whether `name` is an icon or thumbnail cache file name
+pub fn is_cache_file(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.ends_with(".db")
+        && CACHE_FILE_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+}
+
+/// Explorer's icon and thumbnail caches
+#[derive(Clone)]
+pub struct IconCache {
+    /// Directory holding the cache files; `None` if it could not be determined
+    pub dir: Option<PathBuf>,
+    pub fs: Arc<dyn CacheFileSystem + Send + Sync>,
+    /// Only report the files that would be removed
+    pub dry_run: bool,
+}
+
+impl Default for IconCache {
+    fn default() -> Self {
+        Self::new(default_cache_dir())
+    }
+}
+
+impl IconCache {
+    /// Caches in `dir`, accessed through the real file system
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self {
+            dir,
+            fs: Arc::new(SystemFileSystem),
+            dry_run: false,
+        }
+    }
+
+    pub fn with_file_system(mut self, fs: impl CacheFileSystem + Send + Sync + 'static) -> Self {
+        self.fs = Arc::new(fs);
+        self
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Remove every cache file, continuing past individual failures
+    ///
+    /// Explorer must not be running, or the files are locked.
+    pub fn clear(&self) -> CacheReport {
+        let mut report = CacheReport {
+            dir: self.dir.clone(),
+            dry_run: self.dry_run,
+            ..Default::default()
+        };
+        let Some(dir) = &self.dir else {
+            report.error = Some("the Explorer cache directory is unknown".to_string());
+            return report;
+        };
+
+        let mut files = match self.fs.list_files(dir) {
+            Ok(files) => files,
+            Err(e) => {
+                report.error = Some(format!("cannot read {}: {}", dir.display(), e));
+                return report;
+            }
+        };
+        files.retain(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(is_cache_file)
+        });
+        files.sort();
+
+        for path in files {
+            if self.dry_run {
+                report.removed.push(path);
+                continue;
+            }
+            match self.fs.remove_file(&path) {
+                Ok(()) => report.removed.push(path),
+                Err(e) => report.failed.push(CacheFailure {
+                    path,
+                    error: e.to_string(),
+                }),
+            }
+        }
+        report
+    }
+}
+
+/// A cache file that could not be removed
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct CacheFailure {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// Result of [`IconCache::clear`]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize)]
+pub struct CacheReport {
+    pub dir: Option<PathBuf>,
+    /// Files removed, or that would be removed in a dry run
+    pub removed: Vec<PathBuf>,
+    pub failed: Vec<CacheFailure>,
+    pub dry_run: bool,
+    /// Why the cache directory could not be read, if it could not
+    pub error: Option<String>,
+}
+
+impl CacheReport {
+    /// Short summary such as "removed 4 icon cache files"
+    pub fn summary(&self) -> String {
+        if let Some(error) = &self.error {
+            return format!("icon cache not cleared: {}", error);
+        }
+        let count = self.removed.len();
+        let files = format!(
+            "{} icon cache file{}",
+            count,
+            if count == 1 { "" } else { "s" }
+        );
+        let mut summary = if self.dry_run {
+            format!("would remove {}", files)
+        } else {
+            format!("removed {}", files)
+        };
+        if !self.failed.is_empty() {
+            summary = format!("{} ({} in use)", summary, self.failed.len());
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Empty directory unique to this test
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "stuckbar-icon-cache-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn touch(dir: &Path, names: &[&str]) {
+        for name in names {
+            fs::write(dir.join(name), b"cache").unwrap();
+        }
+    }
+
+    fn remaining(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// File system whose removals fail for one file name
+    struct LockedFile(&'static str);
+
+    impl CacheFileSystem for LockedFile {
+        fn list_files(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+            SystemFileSystem.list_files(dir)
+        }
+
+        fn remove_file(&self, path: &Path) -> io::Result<()> {
+            if path.ends_with(self.0) {
+                return Err(io::Error::new(io::ErrorKind::PermissionDenied, "in use"));
+            }
+            SystemFileSystem.remove_file(path)
+        }
+    }
+
+    #[test]
+    fn test_is_cache_file() {
+        assert!(is_cache_file("iconcache_32.db"));
+        assert!(is_cache_file("IconCache_idx.db"));
+        assert!(is_cache_file("thumbcache_1280.db"));
+        assert!(!is_cache_file("iconcache_32.db-journal"));
+        assert!(!is_cache_file("ExplorerStartupLog.etl"));
+        assert!(!is_cache_file("thumbcache.db"));
+    }
+
+    #[test]
+    fn test_clear_removes_only_cache_files() {
+        let dir = scratch_dir("clear");
+        touch(
+            &dir,
+            &[
+                "iconcache_16.db",
+                "iconcache_idx.db",
+                "thumbcache_256.db",
+                "ExplorerStartupLog.etl",
+            ],
+        );
+
+        let report = IconCache::new(Some(dir.clone())).clear();
+
+        assert_eq!(report.removed.len(), 3);
+        assert!(report.failed.is_empty());
+        assert_eq!(remaining(&dir), vec!["ExplorerStartupLog.etl"]);
+        assert_eq!(report.summary(), "removed 3 icon cache files");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_dry_run_keeps_files() {
+        let dir = scratch_dir("dry-run");
+        touch(&dir, &["iconcache_16.db", "thumbcache_32.db"]);
+
+        let report = IconCache::new(Some(dir.clone())).with_dry_run(true).clear();
+
+        assert_eq!(
+            report.removed,
+            vec![dir.join("iconcache_16.db"), dir.join("thumbcache_32.db")]
+        );
+        assert_eq!(remaining(&dir).len(), 2);
+        assert_eq!(report.summary(), "would remove 2 icon cache files");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_locked_file_is_reported() {
+        let dir = scratch_dir("locked");
+        touch(&dir, &["iconcache_16.db", "iconcache_32.db"]);
+
+        let report = IconCache::new(Some(dir.clone()))
+            .with_file_system(LockedFile("iconcache_32.db"))
+            .clear();
+
+        assert_eq!(report.removed, vec![dir.join("iconcache_16.db")]);
+        assert_eq!(report.failed[0].path, dir.join("iconcache_32.db"));
+        assert_eq!(report.summary(), "removed 1 icon cache file (1 in use)");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_missing_directory_is_reported() {
+        let dir = std::env::temp_dir().join("stuckbar-icon-cache-does-not-exist");
+
+        let report = IconCache::new(Some(dir)).clear();
+        assert!(report.error.is_some());
+        assert!(report.summary().starts_with("icon cache not cleared"));
+
+        let report = IconCache::new(None).clear();
+        assert!(report.error.unwrap().contains("unknown"));
+    }
+}
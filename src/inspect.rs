@@ -0,0 +1,332 @@
+//! # Process Inspection
+//!
+//! Reports what the shell processes are doing without acting on them: for every
+//! running instance of the [preset targets](crate::target) its session, start
+//! time, uptime, working set, handle count, CPU time and whether its main window
+//! responds.
+//!
+//! Process queries sit behind [`ProcessInspector`], so [`ShellStatus`] can be
+//! tested without Windows. [`PowerShellInspector`] implements it with
+//! `Get-Process`.
+
+use crate::session::{encode_command, quote_powershell};
+use crate::target::{PRESET_NAMES, TargetSpec};
+use crate::window::{TaskbarHealth, WindowInspector, check_taskbar};
+use crate::{StuckbarError, map_spawn_error};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+/// A running process instance and what it is doing
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct ProcessDetails {
+    /// Image name, such as "explorer.exe"
+    pub process: String,
+    pub pid: u32,
+    pub session_id: Option<u32>,
+    /// Start time in ISO 8601 with the local UTC offset
+    pub started_at: Option<String>,
+    pub uptime_ms: Option<u64>,
+    /// Working set size in bytes
+    pub working_set_bytes: Option<u64>,
+    pub handle_count: Option<u32>,
+    /// Total CPU time consumed so far in milliseconds
+    pub cpu_time_ms: Option<u64>,
+    /// Whether the main window processes messages; `None` if it cannot be told
+    pub responding: Option<bool>,
+}
+
+/// Trait for abstracting process queries (enables testing)
+///
+/// Values the caller may not read, such as the start time of another user's
+/// process, are reported as unknown rather than failing the query.
+pub trait ProcessInspector {
+    /// Details of every running instance of each of `process_names`
+    fn inspect(&self, process_names: &[&str]) -> Result<Vec<ProcessDetails>, StuckbarError>;
+}
+
+impl<I: ProcessInspector + ?Sized> ProcessInspector for Box<I> {
+    fn inspect(&self, process_names: &[&str]) -> Result<Vec<ProcessDetails>, StuckbarError> {
+        (**self).inspect(process_names)
+    }
+}
+
+/// Image names of the preset targets, in preset order
+pub fn shell_process_names() -> Vec<String> {
+    PRESET_NAMES
+        .iter()
+        .filter_map(|name| TargetSpec::preset(name))
+        .map(|target| target.process_name)
+        .collect()
+}
+
+/// Instances of the preset targets, grouped by process in preset order
+///
+/// # Errors
+///
+/// Returns the error of `inspector` if the processes cannot be queried.
+pub fn inspect_shell(
+    inspector: &dyn ProcessInspector,
+) -> Result<Vec<ProcessDetails>, StuckbarError> {
+    let names = shell_process_names();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let mut processes = inspector.inspect(&names)?;
+    processes.sort_by_key(|details| {
+        let rank = names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(&details.process));
+        (rank, details.pid)
+    });
+    Ok(processes)
+}
+
+/// Taskbar health and the shell processes, as reported by `status`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ShellStatus {
+    #[serde(flatten)]
+    pub taskbar: TaskbarHealth,
+    /// Instances of the preset targets, grouped by process in preset order
+    pub processes: Vec<ProcessDetails>,
+    /// Why the processes could not be inspected, if they could not
+    pub processes_error: Option<StuckbarError>,
+}
+
+impl ShellStatus {
+    /// Check the taskbar through `windows` and inspect the shell processes
+    pub fn collect(
+        windows: &dyn WindowInspector,
+        inspector: &dyn ProcessInspector,
+        timeout_ms: u32,
+    ) -> Self {
+        let (processes, processes_error) = match inspect_shell(inspector) {
+            Ok(processes) => (processes, None),
+            Err(error) => (Vec::new(), Some(error)),
+        };
+        Self {
+            taskbar: check_taskbar(windows, timeout_ms),
+            processes,
+            processes_error,
+        }
+    }
+}
+
+/// Real implementation that queries `Get-Process` through PowerShell
+pub struct PowerShellInspector;
+
+impl ProcessInspector for PowerShellInspector {
+    fn inspect(&self, process_names: &[&str]) -> Result<Vec<ProcessDetails>, StuckbarError> {
+        let output = Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-NonInteractive",
+                "-EncodedCommand",
+                &encode_command(&inspect_script(process_names)),
+            ])
+            .output()
+            .map_err(|e| map_spawn_error("powershell", "Get-Process", e))?;
+        if !output.status.success() {
+            return Err(StuckbarError::SpawnFailed {
+                process: "powershell".to_string(),
+                details: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        parse_processes(&String::from_utf8_lossy(&output.stdout), process_names)
+    }
+}
+
+/// Script printing every instance of `process_names` as a JSON array
+///
+/// `Get-Process` takes names without the `.exe` extension. Properties that cannot
+/// be read are left `null`.
+fn inspect_script(process_names: &[&str]) -> String {
+    let names: Vec<String> = process_names
+        .iter()
+        .map(|name| quote_powershell(image_stem(name)))
+        .collect();
+    format!(
+        r#"[Console]::OutputEncoding = [Text.Encoding]::UTF8
+$ErrorActionPreference = 'SilentlyContinue'
+$now = Get-Date
+$processes = @(Get-Process -Name {} | ForEach-Object {{
+    $started = $_.StartTime
+    $cpu = $_.TotalProcessorTime
+    [pscustomobject]@{{
+        name = $_.ProcessName
+        pid = $_.Id
+        session_id = $_.SessionId
+        started_at = if ($started) {{ $started.ToString('o') }} else {{ $null }}
+        uptime_ms = if ($started) {{ [long]($now - $started).TotalMilliseconds }} else {{ $null }}
+        working_set_bytes = $_.WorkingSet64
+        handle_count = $_.HandleCount
+        cpu_time_ms = if ($cpu) {{ [long]$cpu.TotalMilliseconds }} else {{ $null }}
+        responding = $_.Responding
+    }}
+}})
+ConvertTo-Json -InputObject $processes -Compress"#,
+        names.join(",")
+    )
+}
+
+/// "explorer" for "explorer.exe"
+fn image_stem(process_name: &str) -> &str {
+    let len = process_name.len();
+    if len > 4 && process_name[len - 4..].eq_ignore_ascii_case(".exe") {
+        &process_name[..len - 4]
+    } else {
+        process_name
+    }
+}
+
+/// An instance as printed by [`inspect_script`]
+#[derive(Deserialize)]
+struct ScriptProcess {
+    name: String,
+    pid: u32,
+    session_id: Option<u32>,
+    started_at: Option<String>,
+    uptime_ms: Option<u64>,
+    working_set_bytes: Option<u64>,
+    handle_count: Option<u32>,
+    cpu_time_ms: Option<u64>,
+    responding: Option<bool>,
+}
+
+/// Parse the output of [`inspect_script`], naming each instance after the
+/// requested image name it matches
+fn parse_processes(
+    output: &str,
+    process_names: &[&str],
+) -> Result<Vec<ProcessDetails>, StuckbarError> {
+    let output = output.trim();
+    if output.is_empty() {
+        return Ok(Vec::new());
+    }
+    let processes: Vec<ScriptProcess> =
+        serde_json::from_str(output).map_err(|e| StuckbarError::SpawnFailed {
+            process: "powershell".to_string(),
+            details: format!("unexpected Get-Process output: {}", e),
+        })?;
+    Ok(processes
+        .into_iter()
+        .map(|p| ProcessDetails {
+            process: process_names
+                .iter()
+                .find(|name| image_stem(name).eq_ignore_ascii_case(&p.name))
+                .map_or_else(|| format!("{}.exe", p.name), |name| name.to_string()),
+            pid: p.pid,
+            session_id: p.session_id,
+            started_at: p.started_at,
+            uptime_ms: p.uptime_ms,
+            working_set_bytes: p.working_set_bytes,
+            handle_count: p.handle_count,
+            cpu_time_ms: p.cpu_time_ms,
+            responding: p.responding,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockProcessInspector, MockWindowInspector};
+
+    fn details(process: &str, pid: u32) -> ProcessDetails {
+        ProcessDetails {
+            process: process.to_string(),
+            pid,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_shell_process_names() {
+        assert_eq!(
+            shell_process_names(),
+            vec![
+                "explorer.exe",
+                "StartMenuExperienceHost.exe",
+                "ShellExperienceHost.exe",
+                "SearchHost.exe",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_image_stem() {
+        assert_eq!(image_stem("explorer.exe"), "explorer");
+        assert_eq!(image_stem("SearchHost.EXE"), "SearchHost");
+        assert_eq!(image_stem("explorer"), "explorer");
+        assert_eq!(image_stem(".exe"), ".exe");
+    }
+
+    #[test]
+    fn test_inspect_script_quotes_names() {
+        let script = inspect_script(&["explorer.exe", "Search'Host.exe"]);
+        assert!(script.contains("Get-Process -Name 'explorer','Search''Host'"));
+    }
+
+    #[test]
+    fn test_parse_processes() {
+        let output = r#"[{"name":"explorer","pid":4312,"session_id":1,"started_at":"2026-10-16T09:12:03.0000000+02:00","uptime_ms":8040000,"working_set_bytes":149159936,"handle_count":3120,"cpu_time_ms":112500,"responding":true},{"name":"SearchHost","pid":7000,"session_id":2,"started_at":null,"uptime_ms":null,"working_set_bytes":1024,"handle_count":null,"cpu_time_ms":null,"responding":false}]"#;
+
+        let processes = parse_processes(output, &["explorer.exe", "SearchHost.exe"]).unwrap();
+
+        assert_eq!(
+            processes[0],
+            ProcessDetails {
+                process: "explorer.exe".to_string(),
+                pid: 4312,
+                session_id: Some(1),
+                started_at: Some("2026-10-16T09:12:03.0000000+02:00".to_string()),
+                uptime_ms: Some(8_040_000),
+                working_set_bytes: Some(149_159_936),
+                handle_count: Some(3120),
+                cpu_time_ms: Some(112_500),
+                responding: Some(true),
+            }
+        );
+        assert_eq!(processes[1].process, "SearchHost.exe");
+        assert_eq!(processes[1].started_at, None);
+        assert_eq!(processes[1].responding, Some(false));
+    }
+
+    #[test]
+    fn test_parse_processes_empty_and_malformed() {
+        assert_eq!(parse_processes("[]\r\n", &["explorer.exe"]), Ok(Vec::new()));
+        assert_eq!(parse_processes("", &["explorer.exe"]), Ok(Vec::new()));
+        let err = parse_processes("Get-Process : denied", &["explorer.exe"]).unwrap_err();
+        assert_eq!(err.kind(), "spawn_failed");
+    }
+
+    #[test]
+    fn test_collect_orders_processes_by_preset() {
+        let inspector = MockProcessInspector::new(vec![
+            details("SearchHost.exe", 30),
+            details("explorer.exe", 20),
+            details("notepad.exe", 5),
+            details("explorer.exe", 10),
+        ]);
+
+        let status = ShellStatus::collect(&MockWindowInspector::responsive(), &inspector, 100);
+
+        let order: Vec<u32> = status.processes.iter().map(|p| p.pid).collect();
+        assert_eq!(order, vec![10, 20, 30]);
+        assert_eq!(status.taskbar, TaskbarHealth::Responsive);
+        assert_eq!(status.processes_error, None);
+    }
+
+    #[test]
+    fn test_collect_reports_inspection_failure() {
+        let error = StuckbarError::UnsupportedPlatform {
+            os: "linux".to_string(),
+        };
+        let inspector = MockProcessInspector::failing(error.clone());
+
+        let status = ShellStatus::collect(&MockWindowInspector::missing(), &inspector, 100);
+
+        assert!(status.processes.is_empty());
+        assert_eq!(status.processes_error, Some(error));
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["status"], "missing");
+        assert_eq!(json["processes_error"]["kind"], "unsupported_platform");
+    }
+}
@@ -3,483 +3,3154 @@
 //! A CLI tool for restarting Windows Explorer when the taskbar gets stuck.
 //!
 //! This crate provides functionality to kill, start, and restart the Windows Explorer
-//! process, which is useful when the Windows taskbar becomes unresponsive.
+//! process, which is useful when the Windows taskbar becomes unresponsive. Other shell
+//! components (Start menu, search, shell flyouts) can be targeted through [`TargetSpec`].
 //!
 //! ## Features
 //!
 //! - `mcp` - Enable Model Context Protocol (MCP) server support for AI agent integration
+//! - `async` - Enable the Tokio-based asynchronous process runner (implied by `mcp`)
+//! - `native` - Enable the native backend, which uses the Win32 API instead of
+//!   `taskkill`/`tasklist`
+//!
+//! ## Events
+//!
+//! [`ProcessManager`] operations emit lifecycle events to registered sinks (see
+//! the [`events`] module); the [`report`] module provides console sinks.
+//!
+//! Managers that share a [`coordinator::OperationCoordinator`] never overlap:
+//! concurrent restarts are coalesced into one and other requests are rejected.
+//! A [`limiter::RestartLimiter`] refuses restarts that come too often or keep
+//! crashing the shell; killing a target that Windows relaunches counts as a
+//! restart.
+//!
+//! ## Watchdog
+//!
+//! The [`watchdog`] module can monitor the shell with pluggable health probes and
+//! restart it automatically when it stays unhealthy. The [`window`] module detects
+//! a taskbar that is running but no longer responding.
+//!
+//! ## Window Restore
+//!
+//! The [`session`] module records open File Explorer windows before a restart so
+//! they can be reopened afterwards (see [`ProcessManager::with_restore_windows`]).
+//!
+//! ## History
+//!
+//! A [`history::HistorySink`] appends every finished operation to a size-rotated
+//! JSON-lines file, so machines that keep needing restarts can be found.
+//!
+//! ## Sessions
+//!
+//! A [`scope::SessionScope`] limits kills to the instances of one session, such
+//! as the caller's own (see [`ProcessManager::with_session_scope`]).
+//!
+//! ## Dry Run
+//!
+//! [`ProcessManager::dry_run`] runs an operation against a recording runner (see
+//! the [`dry_run`] module) and reports what it would do without doing it.
+//!
+//! ## Configuration
+//!
+//! The [`config`] module merges the configuration file, `STUCKBAR_*` environment
+//! variables and command-line flags into the effective settings, remembering
+//! where each value came from.
 //!
 //! ## Platform Support
 //!
 //! This tool is Windows-only. Running on other platforms will result in an error.
 
-use colored::Colorize;
-use std::process::Command;
+use serde::{Deserialize, Serialize, Serializer, ser::SerializeStruct};
+use std::fmt;
+use std::io;
+use std::pin::pin;
+use std::process::{Command, Output};
+use std::sync::{Arc, OnceLock};
+use std::task::{Context, Poll, Waker};
+use std::time::{Instant, SystemTime};
+
+use coordinator::{Admission, OperationCoordinator, OperationGuard};
+use events::{Action, EventSink, ManagerEvent, Stage};
+use icon_cache::{CacheReport, IconCache};
+use limiter::RestartLimiter;
+use scope::SessionScope;
+use session::{PowerShellShellWindows, RestoreReport, SessionSnapshot, ShellWindowEnumerator};
+
+#[cfg(feature = "async")]
+pub mod async_runner;
+pub mod auth;
+pub mod config;
+pub mod coordinator;
+pub mod dry_run;
+pub mod events;
+pub mod history;
+pub mod icon_cache;
+pub mod inspect;
+pub mod limiter;
+#[cfg(feature = "native")]
+pub mod native;
+pub mod profile;
+pub mod report;
+pub mod scope;
+pub mod session;
+pub mod target;
+mod tasklist;
+#[cfg(feature = "mcp-tls")]
+pub mod tls;
+pub mod watchdog;
+pub mod window;
+
+pub use target::TargetSpec;
+
+/// Delay in milliseconds before starting the target process after termination
+pub const RESTART_DELAY_MS: u64 = 500;
+
+/// Default time in milliseconds a graceful exit may take before the process is forced
+pub const GRACE_PERIOD_MS: u64 = 3_000;
+
+/// Interval in milliseconds between process liveness checks
+pub const POLL_INTERVAL_MS: u64 = 100;
+
+/// Maximum time in milliseconds to wait for terminated processes to exit
+pub const EXIT_TIMEOUT_MS: u64 = 5_000;
+
+/// Maximum time in milliseconds to wait for a new instance to appear
+pub const START_TIMEOUT_MS: u64 = 10_000;
+
+/// Time in milliseconds a new instance must stay alive to count as started
+pub const SETTLE_MS: u64 = 1_000;
+
+/// Exit code reported by `taskkill` when no matching process was found
+const TASKKILL_NOT_FOUND_EXIT_CODE: i32 = 128;
+
+/// Errors that can occur while managing a process
+///
+/// Each variant maps to a stable [`kind`](StuckbarError::kind) string and a
+/// distinct process [`exit_code`](StuckbarError::exit_code), so callers can
+/// react to specific failures without parsing messages.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum StuckbarError {
+    /// The target process is not running
+    NotRunning { process: String },
+    /// The caller lacks the rights to act on the target process
+    AccessDenied { process: String, details: String },
+    /// A required executable could not be found
+    CommandMissing { command: String },
+    /// The target process could not be launched
+    SpawnFailed { process: String, details: String },
+    /// The target process could not be terminated
+    KillFailed { process: String, details: String },
+    /// An operation did not complete within the allotted time
+    Timeout { operation: String, waited_ms: u64 },
+    /// A newly started instance exited before it settled
+    Crashed { process: String, pid: u32 },
+    /// The current platform is not supported
+    UnsupportedPlatform { os: String },
+    /// Another operation on the target is in progress
+    Busy { operation: String },
+    /// A joined operation was abandoned before it finished
+    Interrupted { operation: String },
+    /// A [`RestartPolicy`](limiter::RestartPolicy) refused the restart
+    RateLimited { reason: String, retry_after_ms: u64 },
+    /// A configuration file, environment variable or setting is invalid
+    InvalidConfig { details: String },
+    /// The taskbar is hung or missing
+    Unhealthy { details: String },
+}
+
+impl StuckbarError {
+    /// Stable, machine-readable identifier for the error variant
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::NotRunning { .. } => "not_running",
+            Self::AccessDenied { .. } => "access_denied",
+            Self::CommandMissing { .. } => "command_missing",
+            Self::SpawnFailed { .. } => "spawn_failed",
+            Self::KillFailed { .. } => "kill_failed",
+            Self::Timeout { .. } => "timeout",
+            Self::Crashed { .. } => "crashed",
+            Self::UnsupportedPlatform { .. } => "unsupported_platform",
+            Self::Busy { .. } => "busy",
+            Self::Interrupted { .. } => "interrupted",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::InvalidConfig { .. } => "invalid_config",
+            Self::Unhealthy { .. } => "unhealthy",
+        }
+    }
+
+    /// Process exit code used by the CLI for this error
+    ///
+    /// Exit code 2 is left to clap for usage errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::KillFailed { .. } => 1,
+            Self::NotRunning { .. } => 3,
+            Self::AccessDenied { .. } => 4,
+            Self::CommandMissing { .. } => 5,
+            Self::SpawnFailed { .. } => 6,
+            Self::Timeout { .. } => 7,
+            Self::UnsupportedPlatform { .. } => 8,
+            Self::Crashed { .. } => 9,
+            Self::Busy { .. } => 10,
+            Self::Interrupted { .. } => 11,
+            Self::RateLimited { .. } => 12,
+            Self::InvalidConfig { .. } => 13,
+            Self::Unhealthy { .. } => 14,
+        }
+    }
+}
+
+impl fmt::Display for StuckbarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotRunning { process } => write!(f, "{} is not running", process),
+            Self::AccessDenied { process, details } => {
+                write!(f, "Access denied while managing {}: {}", process, details)
+            }
+            Self::CommandMissing { command } => write!(f, "Command not found: {}", command),
+            Self::SpawnFailed { process, details } => {
+                write!(f, "Error starting {}: {}", process, details)
+            }
+            Self::KillFailed { process, details } => {
+                write!(f, "Failed to terminate {}: {}", process, details)
+            }
+            Self::Timeout {
+                operation,
+                waited_ms,
+            } => write!(f, "Timed out after {}ms while {}", waited_ms, operation),
+            Self::Crashed { process, pid } => {
+                write!(f, "{} (PID {}) exited right after starting", process, pid)
+            }
+            Self::UnsupportedPlatform { os } => write!(
+                f,
+                "stuckbar is a Windows-only tool.\n\
+                Current platform '{}' is not supported.\n\
+                This tool restarts explorer.exe which only exists on Windows.",
+                os
+            ),
+            Self::Busy { operation } => {
+                write!(f, "Another operation is in progress: {}", operation)
+            }
+            Self::Interrupted { operation } => {
+                write!(f, "The {} was interrupted before it finished", operation)
+            }
+            Self::RateLimited {
+                reason,
+                retry_after_ms,
+            } => write!(
+                f,
+                "Restart refused: {}; retry after {}ms",
+                reason, retry_after_ms
+            ),
+            Self::InvalidConfig { details } => write!(f, "Invalid configuration: {}", details),
+            Self::Unhealthy { details } => write!(f, "{}", details),
+        }
+    }
+}
+
+impl std::error::Error for StuckbarError {}
+
+/// Errors serialize as `{"kind": ..., "message": ...}`
+///
+/// [`RateLimited`](StuckbarError::RateLimited) also carries `retry_after_ms`.
+impl Serialize for StuckbarError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let retry_after_ms = match self {
+            Self::RateLimited { retry_after_ms, .. } => Some(*retry_after_ms),
+            _ => None,
+        };
+        let len = 2 + retry_after_ms.is_some() as usize;
+        let mut error = serializer.serialize_struct("StuckbarError", len)?;
+        error.serialize_field("kind", self.kind())?;
+        error.serialize_field("message", &self.to_string())?;
+        if let Some(retry_after_ms) = retry_after_ms {
+            error.serialize_field("retry_after_ms", &retry_after_ms)?;
+        }
+        error.end()
+    }
+}
+
+/// Successful outcome of a process operation
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize)]
+pub struct Outcome {
+    pub message: String,
+    /// PIDs of the instances that were terminated
+    pub killed_pids: Vec<u32>,
+    /// PID of the newly started instance, if known
+    pub new_pid: Option<u32>,
+    /// Instances that could not be acted on, when others succeeded
+    pub failures: Vec<PidFailure>,
+    /// Explorer windows reopened after a restart, if restoring was requested
+    pub windows: Option<RestoreReport>,
+    /// Icon cache files removed during a restart, if rebuilding was requested
+    pub icon_cache: Option<Box<CacheReport>>,
+    /// How long each phase of the operation took
+    pub timings: Timings,
+    /// Whether this caller joined an operation that was already in progress
+    pub joined: bool,
+}
+
+/// A single process instance that an operation failed on
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct PidFailure {
+    pub pid: u32,
+    pub error: StuckbarError,
+}
+
+impl Outcome {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_killed_pids(mut self, pids: Vec<u32>) -> Self {
+        self.killed_pids = pids;
+        self
+    }
+
+    pub fn with_new_pid(mut self, pid: u32) -> Self {
+        self.new_pid = Some(pid);
+        self
+    }
+}
+
+/// Elapsed milliseconds for each phase of an operation
+///
+/// Phases that did not run are left at zero.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct Timings {
+    /// Time spent waiting for the old instances to exit on request
+    pub grace_ms: u64,
+    /// Time spent terminating the old instances
+    pub kill_ms: u64,
+    /// Time spent waiting for the old instances to exit
+    pub exit_wait_ms: u64,
+    /// Time spent launching and waiting for a new instance to appear
+    pub start_ms: u64,
+    /// Time the new instance was observed to stay alive
+    pub settle_ms: u64,
+    /// Total time of the operation
+    pub total_ms: u64,
+}
+
+/// How the target process is terminated
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum KillStrategy {
+    /// Terminate immediately
+    #[default]
+    Force,
+    /// Ask the process to exit cleanly and force-terminate it only if it is still
+    /// running after `grace_ms`
+    Graceful { grace_ms: u64 },
+}
+
+impl KillStrategy {
+    /// Graceful strategy with the default [`GRACE_PERIOD_MS`]
+    pub fn graceful() -> Self {
+        Self::Graceful {
+            grace_ms: GRACE_PERIOD_MS,
+        }
+    }
+}
+
+/// Per-call options for a restart
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct RestartOptions {
+    pub kill_strategy: KillStrategy,
+    /// Reopen the File Explorer windows that were open before the restart
+    pub restore_windows: bool,
+    /// Remove Explorer's icon and thumbnail caches while it is down
+    pub rebuild_icon_cache: bool,
+}
+
+/// Timeouts and intervals used to verify that processes exited and started
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct LivenessPolicy {
+    /// Interval between liveness checks
+    pub poll_interval_ms: u64,
+    /// Maximum time to wait for terminated processes to exit
+    pub exit_timeout_ms: u64,
+    /// Maximum time to wait for a new instance to appear
+    pub start_timeout_ms: u64,
+    /// Time a new instance must stay alive to count as started
+    pub settle_ms: u64,
+}
+
+impl Default for LivenessPolicy {
+    fn default() -> Self {
+        Self {
+            poll_interval_ms: POLL_INTERVAL_MS,
+            exit_timeout_ms: EXIT_TIMEOUT_MS,
+            start_timeout_ms: START_TIMEOUT_MS,
+            settle_ms: SETTLE_MS,
+        }
+    }
+}
+
+/// Resource usage of a single process instance
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ProcessUsage {
+    pub pid: u32,
+    /// Working set size in bytes
+    pub working_set_bytes: u64,
+    /// Total CPU time consumed so far in milliseconds
+    pub cpu_time_ms: u64,
+}
+
+/// A running process instance, the session it belongs to and its owner
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    /// Terminal Services session ID, if the runner can tell
+    pub session_id: Option<u32>,
+    /// Account running the process, if the caller may read it: a SID such as
+    /// `S-1-5-21-…` for the native backend, `DOMAIN\user` for `tasklist`
+    pub owner: Option<String>,
+}
+
+/// Trait for abstracting process operations (enables testing)
+pub trait ProcessRunner {
+    fn kill_process(&self, process_name: &str) -> Result<Outcome, StuckbarError>;
+    fn start_process(&self, target: &TargetSpec) -> Result<Outcome, StuckbarError>;
+    fn list_pids(&self, process_name: &str) -> Result<Vec<u32>, StuckbarError>;
+    fn sleep_ms(&self, ms: u64);
+    /// Terminate the single instance `pid` of `process_name`
+    fn kill_pid(&self, pid: u32, process_name: &str) -> Result<(), StuckbarError>;
+
+    /// Ask every instance to exit cleanly without waiting for it
+    ///
+    /// Runners that cannot do this leave the processes alone, so a graceful kill
+    /// falls back to terminating them once the grace period is over.
+    fn request_exit(&self, _process_name: &str) -> Result<(), StuckbarError> {
+        Ok(())
+    }
+
+    fn is_running(&self, process_name: &str) -> Result<bool, StuckbarError> {
+        Ok(!self.list_pids(process_name)?.is_empty())
+    }
+
+    /// List every running instance together with its session and owner
+    ///
+    /// Runners that cannot tell sessions apart report them as unknown.
+    fn list_processes(&self, process_name: &str) -> Result<Vec<ProcessInfo>, StuckbarError> {
+        Ok(unknown_sessions(self.list_pids(process_name)?))
+    }
+
+    /// The calling process, whose session and owner [`SessionScope::Current`]
+    /// refers to; `None` if the runner cannot tell
+    fn current_process(&self) -> Option<ProcessInfo> {
+        None
+    }
+
+    /// Sample the resource usage of every running instance
+    ///
+    /// Runners that cannot sample usage return an empty list.
+    fn process_usage(&self, _process_name: &str) -> Result<Vec<ProcessUsage>, StuckbarError> {
+        Ok(Vec::new())
+    }
+
+    /// Current time, used to measure timeouts and phase durations
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+impl<R: ProcessRunner + ?Sized> ProcessRunner for Box<R> {
+    fn kill_process(&self, process_name: &str) -> Result<Outcome, StuckbarError> {
+        (**self).kill_process(process_name)
+    }
+
+    fn start_process(&self, target: &TargetSpec) -> Result<Outcome, StuckbarError> {
+        (**self).start_process(target)
+    }
+
+    fn list_pids(&self, process_name: &str) -> Result<Vec<u32>, StuckbarError> {
+        (**self).list_pids(process_name)
+    }
+
+    fn sleep_ms(&self, ms: u64) {
+        (**self).sleep_ms(ms)
+    }
+
+    fn request_exit(&self, process_name: &str) -> Result<(), StuckbarError> {
+        (**self).request_exit(process_name)
+    }
+
+    fn is_running(&self, process_name: &str) -> Result<bool, StuckbarError> {
+        (**self).is_running(process_name)
+    }
+
+    fn kill_pid(&self, pid: u32, process_name: &str) -> Result<(), StuckbarError> {
+        (**self).kill_pid(pid, process_name)
+    }
+
+    fn list_processes(&self, process_name: &str) -> Result<Vec<ProcessInfo>, StuckbarError> {
+        (**self).list_processes(process_name)
+    }
+
+    fn current_process(&self) -> Option<ProcessInfo> {
+        (**self).current_process()
+    }
+
+    fn process_usage(&self, process_name: &str) -> Result<Vec<ProcessUsage>, StuckbarError> {
+        (**self).process_usage(process_name)
+    }
+
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+}
+
+/// Asynchronous counterpart of [`ProcessRunner`]
+///
+/// Used by the `*_async` operations of [`ProcessManager`], so waiting for
+/// processes to exit or settle never blocks an async runtime's worker thread.
+/// The futures must be `Send` so operations can run inside spawned tasks.
+pub trait AsyncProcessRunner {
+    fn kill_process(
+        &self,
+        process_name: &str,
+    ) -> impl Future<Output = Result<Outcome, StuckbarError>> + Send;
+    fn start_process(
+        &self,
+        target: &TargetSpec,
+    ) -> impl Future<Output = Result<Outcome, StuckbarError>> + Send;
+    fn list_pids(
+        &self,
+        process_name: &str,
+    ) -> impl Future<Output = Result<Vec<u32>, StuckbarError>> + Send;
+    fn sleep_ms(&self, ms: u64) -> impl Future<Output = ()> + Send;
+    /// Terminate the single instance `pid` of `process_name`
+    fn kill_pid(
+        &self,
+        pid: u32,
+        process_name: &str,
+    ) -> impl Future<Output = Result<(), StuckbarError>> + Send;
+
+    /// List every running instance together with its session and owner
+    ///
+    /// See [`ProcessRunner::list_processes`].
+    fn list_processes(
+        &self,
+        process_name: &str,
+    ) -> impl Future<Output = Result<Vec<ProcessInfo>, StuckbarError>> + Send {
+        let pids = self.list_pids(process_name);
+        async move { Ok(unknown_sessions(pids.await?)) }
+    }
+
+    /// The calling process; see [`ProcessRunner::current_process`]
+    fn current_process(&self) -> impl Future<Output = Option<ProcessInfo>> + Send {
+        std::future::ready(None)
+    }
+
+    /// Ask every instance to exit cleanly without waiting for it
+    ///
+    /// See [`ProcessRunner::request_exit`].
+    fn request_exit(
+        &self,
+        _process_name: &str,
+    ) -> impl Future<Output = Result<(), StuckbarError>> + Send {
+        std::future::ready(Ok(()))
+    }
+
+    /// Run `work`, which blocks, without stalling the runner's other futures
+    ///
+    /// Used for recording and reopening windows and clearing the icon cache.
+    /// Runners on an async runtime move it to a blocking thread; by default it
+    /// runs in place.
+    fn run_blocking<T: Send + 'static>(
+        &self,
+        work: Box<dyn FnOnce() -> T + Send>,
+    ) -> impl Future<Output = T> + Send {
+        std::future::ready(work())
+    }
+
+    /// Current time, used to measure timeouts and phase durations
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Presents a blocking [`ProcessRunner`] as an [`AsyncProcessRunner`]
+///
+/// Every call completes before its future is returned, so the futures are always
+/// ready and can be driven by [`block_on`].
+struct Blocking<'a, R>(&'a R);
+
+impl<R: ProcessRunner> AsyncProcessRunner for Blocking<'_, R> {
+    fn kill_process(
+        &self,
+        process_name: &str,
+    ) -> impl Future<Output = Result<Outcome, StuckbarError>> + Send {
+        std::future::ready(self.0.kill_process(process_name))
+    }
+
+    fn start_process(
+        &self,
+        target: &TargetSpec,
+    ) -> impl Future<Output = Result<Outcome, StuckbarError>> + Send {
+        std::future::ready(self.0.start_process(target))
+    }
+
+    fn list_pids(
+        &self,
+        process_name: &str,
+    ) -> impl Future<Output = Result<Vec<u32>, StuckbarError>> + Send {
+        std::future::ready(self.0.list_pids(process_name))
+    }
+
+    fn sleep_ms(&self, ms: u64) -> impl Future<Output = ()> + Send {
+        self.0.sleep_ms(ms);
+        std::future::ready(())
+    }
+
+    fn kill_pid(
+        &self,
+        pid: u32,
+        process_name: &str,
+    ) -> impl Future<Output = Result<(), StuckbarError>> + Send {
+        std::future::ready(self.0.kill_pid(pid, process_name))
+    }
+
+    fn list_processes(
+        &self,
+        process_name: &str,
+    ) -> impl Future<Output = Result<Vec<ProcessInfo>, StuckbarError>> + Send {
+        std::future::ready(self.0.list_processes(process_name))
+    }
+
+    fn current_process(&self) -> impl Future<Output = Option<ProcessInfo>> + Send {
+        std::future::ready(self.0.current_process())
+    }
+
+    fn request_exit(
+        &self,
+        process_name: &str,
+    ) -> impl Future<Output = Result<(), StuckbarError>> + Send {
+        std::future::ready(self.0.request_exit(process_name))
+    }
+
+    fn now(&self) -> Instant {
+        self.0.now()
+    }
+}
+
+/// Result of terminating a single process instance
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PidResult {
+    pub pid: u32,
+    pub result: Result<(), StuckbarError>,
+}
+
+/// Fold per-PID termination results into a single kill outcome
+///
+/// The kill succeeds if at least one instance was terminated; instances that could
+/// not be terminated are listed in [`Outcome::failures`]. If every instance failed,
+/// the first error is returned.
+pub(crate) fn summarize_kill(
+    process_name: &str,
+    results: Vec<PidResult>,
+) -> Result<Outcome, StuckbarError> {
+    if results.is_empty() {
+        return Err(StuckbarError::NotRunning {
+            process: process_name.to_string(),
+        });
+    }
+
+    let mut killed_pids = Vec::new();
+    let mut failures = Vec::new();
+    for PidResult { pid, result } in results {
+        match result {
+            Ok(()) => killed_pids.push(pid),
+            Err(error) => failures.push(PidFailure { pid, error }),
+        }
+    }
+    if killed_pids.is_empty() {
+        return Err(failures.swap_remove(0).error);
+    }
+
+    let message = match failures.len() {
+        0 => format!("Successfully terminated {}", process_name),
+        n => format!(
+            "Terminated {} of {} instances of {}",
+            killed_pids.len(),
+            killed_pids.len() + n,
+            process_name
+        ),
+    };
+
+    let mut outcome = Outcome::new(message).with_killed_pids(killed_pids);
+    outcome.failures = failures;
+    Ok(outcome)
+}
+
+/// Instances listed by PID only, with unknown sessions and owners
+fn unknown_sessions(pids: Vec<u32>) -> Vec<ProcessInfo> {
+    pids.into_iter()
+        .map(|pid| ProcessInfo {
+            pid,
+            session_id: None,
+            owner: None,
+        })
+        .collect()
+}
+
+/// Run a future built on [`Blocking`] to completion on the current thread
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut context = Context::from_waker(Waker::noop());
+    match pin!(future).poll(&mut context) {
+        Poll::Ready(output) => output,
+        Poll::Pending => unreachable!("blocking runners never suspend"),
+    }
+}
+
+/// A process runner chosen at runtime
+pub type BoxedRunner = Box<dyn ProcessRunner + Send + Sync>;
+
+/// Names of the available process backends, in display order
+#[cfg(feature = "native")]
+pub const BACKEND_NAMES: &[&str] = &["native", "taskkill"];
+
+/// Names of the available process backends, in display order
+#[cfg(not(feature = "native"))]
+pub const BACKEND_NAMES: &[&str] = &["taskkill"];
+
+/// Mechanism used to find and terminate processes
+///
+/// The native backend is the default when the `native` feature is enabled;
+/// `taskkill` stays available as a fallback.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Backend {
+    /// `taskkill` and `tasklist` ([`SystemProcessRunner`])
+    #[cfg_attr(not(feature = "native"), default)]
+    Taskkill,
+    /// Toolhelp snapshots and `TerminateProcess` ([`native::NativeProcessRunner`])
+    #[cfg(feature = "native")]
+    #[default]
+    Native,
+}
+
+impl Backend {
+    /// Look up a backend by name (see [`BACKEND_NAMES`])
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "taskkill" => Some(Self::Taskkill),
+            #[cfg(feature = "native")]
+            "native" => Some(Self::Native),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Taskkill => "taskkill",
+            #[cfg(feature = "native")]
+            Self::Native => "native",
+        }
+    }
+
+    /// Create the process runner for this backend
+    pub fn runner(&self) -> BoxedRunner {
+        match self {
+            Self::Taskkill => Box::new(SystemProcessRunner),
+            #[cfg(feature = "native")]
+            Self::Native => Box::new(native::NativeProcessRunner),
+        }
+    }
+
+    /// Create the asynchronous process runner for this backend
+    #[cfg(feature = "async")]
+    pub fn async_runner(&self) -> async_runner::BackendRunner {
+        match self {
+            Self::Taskkill => {
+                async_runner::BackendRunner::Taskkill(async_runner::TokioProcessRunner)
+            }
+            #[cfg(feature = "native")]
+            Self::Native => async_runner::BackendRunner::Native(native::NativeProcessRunner),
+        }
+    }
+}
+
+/// Real implementation that interacts with the system
+pub struct SystemProcessRunner;
+
+impl SystemProcessRunner {
+    /// Run `taskkill` with `args` followed by `/IM process_name`
+    fn taskkill(&self, args: &[&str], process_name: &str) -> Result<(), StuckbarError> {
+        taskkill_result(process_name, taskkill_command(args, process_name).output())
+    }
+}
+
+// Command construction and output interpretation are shared with the
+// asynchronous runner, which only differs in how the commands are run.
+
+/// `taskkill` with `args` followed by `/IM process_name`
+fn taskkill_command(args: &[&str], process_name: &str) -> Command {
+    let mut command = Command::new("taskkill");
+    command.args(args).args(["/IM", process_name]);
+    command
+}
+
+/// Interpret the result of running a [`taskkill_command`]
+fn taskkill_result(process_name: &str, output: io::Result<Output>) -> Result<(), StuckbarError> {
+    let output = output.map_err(|e| map_spawn_error("taskkill", process_name, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(classify_taskkill_failure(
+            process_name,
+            output.status.code(),
+            &stderr,
+        ))
+    }
+}
+
+/// `taskkill /F /PID pid`
+fn taskkill_pid_command(pid: u32) -> Command {
+    let mut command = Command::new("taskkill");
+    command.args(["/F", "/PID", &pid.to_string()]);
+    command
+}
+
+/// `tasklist` filtered to `process_name`, with verbose columns if requested
+fn tasklist_command(process_name: &str, verbose: bool) -> Command {
+    let filter = format!("IMAGENAME eq {}", process_name);
+    let mut command = Command::new("tasklist");
+    if verbose {
+        command.arg("/V");
+    }
+    command.args(["/FI", &filter, "/FO", "CSV", "/NH"]);
+    command
+}
+
+/// Interpret the result of running a verbose [`tasklist_command`]
+fn tasklist_processes(
+    process_name: &str,
+    output: io::Result<Output>,
+) -> Result<Vec<ProcessInfo>, StuckbarError> {
+    let output = output.map_err(|e| map_spawn_error("tasklist", process_name, e))?;
+    Ok(tasklist::parse_processes(
+        &String::from_utf8_lossy(&output.stdout),
+        process_name,
+    ))
+}
+
+/// This process as reported by `tasklist`, looked up once
+fn tasklist_current_process() -> Option<ProcessInfo> {
+    static CURRENT: OnceLock<Option<ProcessInfo>> = OnceLock::new();
+    CURRENT
+        .get_or_init(|| {
+            let filter = format!("PID eq {}", std::process::id());
+            let output = Command::new("tasklist")
+                .args(["/V", "/FI", &filter, "/FO", "CSV", "/NH"])
+                .output()
+                .ok()?;
+            tasklist::parse_first_process(&String::from_utf8_lossy(&output.stdout))
+        })
+        .clone()
+}
+
+/// Interpret the result of running a non-verbose [`tasklist_command`]
+fn tasklist_pids(
+    process_name: &str,
+    output: io::Result<Output>,
+) -> Result<Vec<u32>, StuckbarError> {
+    let output = output.map_err(|e| map_spawn_error("tasklist", process_name, e))?;
+    Ok(tasklist::parse_pids(
+        &String::from_utf8_lossy(&output.stdout),
+        process_name,
+    ))
+}
+
+/// Program that launches `target` and the command that runs it
+fn launch_command(target: &TargetSpec) -> (&str, Command) {
+    let program = target
+        .launch_command
+        .as_deref()
+        .unwrap_or(&target.process_name);
+
+    let mut command = Command::new(program);
+    command.args(&target.args).envs(target.env.iter().cloned());
+    if let Some(dir) = &target.working_dir {
+        command.current_dir(dir);
+    }
+    (program, command)
+}
+
+/// Interpret the result of spawning a [`launch_command`]
+fn launched(
+    target: &TargetSpec,
+    program: &str,
+    pid: io::Result<Option<u32>>,
+) -> Result<Outcome, StuckbarError> {
+    let pid = pid.map_err(|e| map_spawn_error(program, &target.process_name, e))?;
+    let outcome = Outcome::new(format!("Successfully started {}", target.process_name));
+    Ok(match pid {
+        Some(pid) => outcome.with_new_pid(pid),
+        None => outcome,
+    })
+}
+
+/// Whether an exit request for `process_name` is delivered as a taskbar message
+///
+/// If the message cannot be posted, `taskkill` without `/F` is used instead.
+fn posted_taskbar_exit(process_name: &str) -> bool {
+    process_name.eq_ignore_ascii_case(&TargetSpec::explorer().process_name)
+        && window::request_taskbar_exit(&window::SystemWindowInspector)
+}
+
+impl ProcessRunner for SystemProcessRunner {
+    fn kill_process(&self, process_name: &str) -> Result<Outcome, StuckbarError> {
+        self.taskkill(&["/F"], process_name)?;
+        Ok(Outcome::new(format!(
+            "Successfully terminated {}",
+            process_name
+        )))
+    }
+
+    /// Explorer is sent the taskbar's "Exit Explorer" message; other processes
+    /// get `taskkill` without `/F`, which asks their windows to close
+    fn request_exit(&self, process_name: &str) -> Result<(), StuckbarError> {
+        if posted_taskbar_exit(process_name) {
+            return Ok(());
+        }
+        self.taskkill(&[], process_name)
+    }
+
+    fn start_process(&self, target: &TargetSpec) -> Result<Outcome, StuckbarError> {
+        let (program, mut command) = launch_command(target);
+        launched(
+            target,
+            program,
+            command.spawn().map(|child| Some(child.id())),
+        )
+    }
+
+    fn list_pids(&self, process_name: &str) -> Result<Vec<u32>, StuckbarError> {
+        tasklist_pids(process_name, tasklist_command(process_name, false).output())
+    }
+
+    /// Uses verbose `tasklist` output, which names the owner of each instance
+    fn list_processes(&self, process_name: &str) -> Result<Vec<ProcessInfo>, StuckbarError> {
+        tasklist_processes(process_name, tasklist_command(process_name, true).output())
+    }
+
+    fn current_process(&self) -> Option<ProcessInfo> {
+        tasklist_current_process()
+    }
+
+    fn kill_pid(&self, pid: u32, process_name: &str) -> Result<(), StuckbarError> {
+        taskkill_result(process_name, taskkill_pid_command(pid).output())
+    }
+
+    fn process_usage(&self, process_name: &str) -> Result<Vec<ProcessUsage>, StuckbarError> {
+        let output = tasklist_command(process_name, true)
+            .output()
+            .map_err(|e| map_spawn_error("tasklist", process_name, e))?;
+
+        Ok(tasklist::parse_usage(
+            &String::from_utf8_lossy(&output.stdout),
+            process_name,
+        ))
+    }
+
+    fn sleep_ms(&self, ms: u64) {
+        std::thread::sleep(std::time::Duration::from_millis(ms));
+    }
+}
+
+/// Map an I/O error from spawning `command` into a [`StuckbarError`]
+fn map_spawn_error(command: &str, process_name: &str, error: io::Error) -> StuckbarError {
+    match error.kind() {
+        io::ErrorKind::NotFound => StuckbarError::CommandMissing {
+            command: command.to_string(),
+        },
+        io::ErrorKind::PermissionDenied => StuckbarError::AccessDenied {
+            process: process_name.to_string(),
+            details: error.to_string(),
+        },
+        _ => StuckbarError::SpawnFailed {
+            process: command.to_string(),
+            details: error.to_string(),
+        },
+    }
+}
+
+/// Classify a failed `taskkill` invocation from its exit code and stderr
+fn classify_taskkill_failure(
+    process_name: &str,
+    exit_code: Option<i32>,
+    stderr: &str,
+) -> StuckbarError {
+    let details = stderr.trim().to_string();
+
+    if exit_code == Some(TASKKILL_NOT_FOUND_EXIT_CODE) {
+        StuckbarError::NotRunning {
+            process: process_name.to_string(),
+        }
+    } else if details.to_lowercase().contains("access is denied") {
+        StuckbarError::AccessDenied {
+            process: process_name.to_string(),
+            details,
+        }
+    } else {
+        StuckbarError::KillFailed {
+            process: process_name.to_string(),
+            details,
+        }
+    }
+}
+
+/// Manager that kills, starts and restarts a [`TargetSpec`] through a [`ProcessRunner`]
+///
+/// Instead of assuming a fixed delay is enough, every operation polls the runner
+/// until the old instances are gone and a new one has stayed alive for the
+/// [`LivenessPolicy::settle_ms`] window.
+pub struct ProcessManager<R> {
+    pub runner: R,
+    pub target: TargetSpec,
+    pub restart_delay_ms: u64,
+    pub liveness: LivenessPolicy,
+    /// Strategy used by [`kill`](Self::kill) and [`restart`](Self::restart)
+    pub kill_strategy: KillStrategy,
+    /// Sessions whose instances are killed; the caller's by default
+    pub session_scope: SessionScope,
+    /// Whether [`restart`](Self::restart) reopens the File Explorer windows that
+    /// were open before; only applies when the target is explorer.exe
+    pub restore_windows: bool,
+    /// Used to record and reopen File Explorer windows
+    pub shell_windows: Arc<dyn ShellWindowEnumerator + Send + Sync>,
+    /// Whether [`restart`](Self::restart) clears the icon cache between kill and
+    /// start; only applies when the target is explorer.exe
+    pub rebuild_icon_cache: bool,
+    /// Cleared when the icon cache is rebuilt
+    pub icon_cache: IconCache,
+    /// Receive the [`ManagerEvent`]s emitted by every operation
+    pub sinks: Vec<Box<dyn EventSink + Send + Sync>>,
+    /// Keeps operations of managers that share it from overlapping
+    pub coordinator: Option<Arc<OperationCoordinator>>,
+    /// Refuses restarts that its policy does not allow, and kills of targets
+    /// that Windows [relaunches](TargetSpec::restarts_when_killed)
+    pub limiter: Option<Arc<RestartLimiter>>,
+}
+
+/// Explorer manager that handles explorer.exe operations
+///
+/// This is a [`ProcessManager`] whose default target is [`TargetSpec::explorer`].
+pub type ExplorerManager<R> = ProcessManager<R>;
+
+impl<R> ProcessManager<R> {
+    /// Create a manager for explorer.exe
+    pub fn new(runner: R) -> Self {
+        Self::for_target(runner, TargetSpec::explorer())
+    }
+
+    /// Create a manager for an arbitrary target process
+    pub fn for_target(runner: R, target: TargetSpec) -> Self {
+        Self {
+            runner,
+            target,
+            restart_delay_ms: RESTART_DELAY_MS,
+            liveness: LivenessPolicy::default(),
+            kill_strategy: KillStrategy::default(),
+            session_scope: SessionScope::default(),
+            restore_windows: false,
+            shell_windows: Arc::new(PowerShellShellWindows),
+            rebuild_icon_cache: false,
+            icon_cache: IconCache::default(),
+            sinks: Vec::new(),
+            coordinator: None,
+            limiter: None,
+        }
+    }
+
+    pub fn with_target(mut self, target: TargetSpec) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn with_restart_delay(mut self, delay_ms: u64) -> Self {
+        self.restart_delay_ms = delay_ms;
+        self
+    }
+
+    pub fn with_liveness(mut self, liveness: LivenessPolicy) -> Self {
+        self.liveness = liveness;
+        self
+    }
+
+    pub fn with_kill_strategy(mut self, strategy: KillStrategy) -> Self {
+        self.kill_strategy = strategy;
+        self
+    }
+
+    /// Only kill instances in `scope`; see [`scope`] for how it is applied
+    pub fn with_session_scope(mut self, scope: SessionScope) -> Self {
+        self.session_scope = scope;
+        self
+    }
+
+    pub fn with_restore_windows(mut self, restore: bool) -> Self {
+        self.restore_windows = restore;
+        self
+    }
+
+    pub fn with_shell_windows(
+        mut self,
+        shell_windows: impl ShellWindowEnumerator + Send + Sync + 'static,
+    ) -> Self {
+        self.shell_windows = Arc::new(shell_windows);
+        self
+    }
+
+    pub fn with_rebuild_icon_cache(mut self, rebuild: bool) -> Self {
+        self.rebuild_icon_cache = rebuild;
+        self
+    }
+
+    pub fn with_icon_cache(mut self, icon_cache: IconCache) -> Self {
+        self.icon_cache = icon_cache;
+        self
+    }
+
+    /// Register a sink; every registered sink receives every event
+    pub fn with_sink(mut self, sink: impl EventSink + Send + Sync + 'static) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    /// Coordinate every operation with other managers sharing `coordinator`
+    pub fn with_coordinator(mut self, coordinator: Arc<OperationCoordinator>) -> Self {
+        self.coordinator = Some(coordinator);
+        self
+    }
+
+    /// Enforce `limiter`'s policy on every restart, including kills that Windows
+    /// follows with a relaunch
+    pub fn with_restart_limiter(mut self, limiter: Arc<RestartLimiter>) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Options used by [`restart`](Self::restart)
+    pub fn restart_options(&self) -> RestartOptions {
+        RestartOptions {
+            kill_strategy: self.kill_strategy,
+            restore_windows: self.restore_windows,
+            rebuild_icon_cache: self.rebuild_icon_cache,
+        }
+    }
+
+    /// Send `event` to every registered sink
+    fn emit(&self, event: ManagerEvent) {
+        for sink in &self.sinks {
+            sink.on_event(&self.target, &event);
+        }
+    }
+
+    /// Emit the event that ends an operation and pass its result through
+    fn finish(
+        &self,
+        action: Action,
+        result: Result<Outcome, StuckbarError>,
+    ) -> Result<Outcome, StuckbarError> {
+        self.emit(ManagerEvent::finished(action, &result));
+        result
+    }
+
+    /// Ask the coordinator, if any, to run `action`
+    ///
+    /// `Ok(None)` means the operation runs without coordination.
+    fn admit(&self, action: Action) -> Result<Option<Admission>, StuckbarError> {
+        self.coordinator
+            .as_ref()
+            .map(|coordinator| coordinator.begin(action))
+            .transpose()
+    }
+
+    /// End an operation that joined one already in progress (only restarts join)
+    fn joined(
+        &self,
+        action: Action,
+        result: Result<Outcome, StuckbarError>,
+    ) -> Result<Outcome, StuckbarError> {
+        self.emit(ManagerEvent::OperationJoined { action });
+        let result = result.map(|outcome| Outcome {
+            message: format!("Restart already in progress, joined: {}", outcome.message),
+            joined: true,
+            ..outcome
+        });
+        self.finish(action, result)
+    }
+
+    fn restart_failed(&self, stage: Stage, error: StuckbarError) -> StuckbarError {
+        self.emit(ManagerEvent::RestartFailed {
+            stage,
+            error: error.clone(),
+        });
+        error
+    }
+
+    // The operations below are written once against `AsyncProcessRunner`. The
+    // blocking API drives them through `Blocking`, whose futures are always ready.
+
+    async fn kill_operation(
+        &self,
+        runner: &impl AsyncProcessRunner,
+        strategy: KillStrategy,
+    ) -> Result<Outcome, StuckbarError> {
+        // Windows relaunches such a target, so the kill is limited like a restart
+        let limited = self.target.restarts_when_killed();
+        if limited && let Err(e) = self.limit_restart(runner).await {
+            return self.finish(Action::Kill, Err(e));
+        }
+        let result = self.kill_stage(runner, strategy).await;
+        if limited && let Some(limiter) = &self.limiter {
+            limiter.record(SystemTime::now(), &result);
+        }
+        self.finish(Action::Kill, result)
+    }
+
+    async fn start_operation(
+        &self,
+        runner: &impl AsyncProcessRunner,
+    ) -> Result<Outcome, StuckbarError> {
+        let result = self.start_stage(runner).await;
+        self.finish(Action::Start, result)
+    }
+
+    async fn restart_operation(
+        &self,
+        runner: &impl AsyncProcessRunner,
+        options: RestartOptions,
+    ) -> Result<Outcome, StuckbarError> {
+        if let Err(e) = self.limit_restart(runner).await {
+            return self.finish(Action::Restart, Err(e));
+        }
+        self.emit(ManagerEvent::RestartStarted);
+        let result = self.restart_stages(runner, options).await;
+        if let Some(limiter) = &self.limiter {
+            limiter.record(SystemTime::now(), &result);
+        }
+        self.finish(Action::Restart, result)
+    }
+
+    /// Ask the limiter, if any, whether a restart may start now
+    async fn limit_restart(&self, runner: &impl AsyncProcessRunner) -> Result<(), StuckbarError> {
+        let Some(limiter) = &self.limiter else {
+            return Ok(());
+        };
+        let running = runner.list_pids(&self.target.process_name).await?;
+        limiter.acquire(SystemTime::now(), &running)
+    }
+
+    async fn restart_stages(
+        &self,
+        runner: &impl AsyncProcessRunner,
+        options: RestartOptions,
+    ) -> Result<Outcome, StuckbarError> {
+        let started_at = runner.now();
+        let snapshot = self.capture_windows(runner, options.restore_windows).await;
+        let killed = self
+            .kill_stage(runner, options.kill_strategy)
+            .await
+            .map_err(|e| self.restart_failed(Stage::Kill, e))?;
+
+        // The cache files are only unlocked while explorer is down
+        let icon_cache = self
+            .clear_icon_cache(runner, options.rebuild_icon_cache)
+            .await;
+
+        // Small delay to let the shell release its resources
+        runner.sleep_ms(self.restart_delay_ms).await;
+
+        let started = self
+            .start_stage(runner)
+            .await
+            .map_err(|e| self.restart_failed(Stage::Start, e))?;
+
+        let windows = match snapshot {
+            Some(snapshot) => Some(self.reopen_windows(runner, snapshot).await),
+            None => None,
+        };
+        let mut outcome = self.restarted(killed, started, windows, elapsed_ms(runner, started_at));
+        if let Some(report) = &icon_cache {
+            outcome.message = format!("{}, {}", outcome.message, report.summary());
+        }
+        outcome.icon_cache = icon_cache.map(Box::new);
+        Ok(outcome)
+    }
+
+    /// Terminate the target and wait until every terminated instance has exited
+    ///
+    /// Runners that report which PIDs they terminated are trusted; otherwise every
+    /// instance that was running beforehand is awaited.
+    async fn kill_stage(
+        &self,
+        runner: &impl AsyncProcessRunner,
+        strategy: KillStrategy,
+    ) -> Result<Outcome, StuckbarError> {
+        self.emit(ManagerEvent::KillStarted);
+        let started_at = runner.now();
+        let process_name = &self.target.process_name;
+        let (old_pids, whole) = self.scoped_pids(runner).await?;
+
+        let mut escalated = None;
+        if let KillStrategy::Graceful { grace_ms } = strategy {
+            if old_pids.is_empty() {
+                return Err(StuckbarError::NotRunning {
+                    process: process_name.clone(),
+                });
+            }
+
+            // A refused request is not fatal; the process is simply forced right away.
+            // Requests reach every instance, or for explorer the caller's taskbar, so
+            // they are skipped when that would touch instances out of scope.
+            let may_request = whole
+                || (self.target.is_explorer() && {
+                    let caller = runner.current_process().await;
+                    self.session_scope.includes_caller_session(caller.as_ref())
+                });
+            if may_request && runner.request_exit(process_name).await.is_ok() {
+                self.emit(ManagerEvent::ExitRequested { grace_ms });
+                if self.exited_within(runner, &old_pids, grace_ms).await? {
+                    self.emit(ManagerEvent::KillCompleted {
+                        pids: old_pids.clone(),
+                    });
+                    let mut outcome = Outcome::new(format!("{} exited gracefully", process_name))
+                        .with_killed_pids(old_pids);
+                    outcome.timings.grace_ms = elapsed_ms(runner, started_at);
+                    outcome.timings.total_ms = outcome.timings.grace_ms;
+                    return Ok(outcome);
+                }
+                self.emit(ManagerEvent::GraceExpired { grace_ms });
+                escalated = Some(grace_ms);
+            }
+        }
+
+        let grace_elapsed_ms = elapsed_ms(runner, started_at);
+        let killed = if whole {
+            runner.kill_process(process_name).await
+        } else {
+            self.kill_pids(runner, &old_pids).await
+        };
+        let mut outcome = match (killed, escalated) {
+            (Ok(outcome), None) => outcome,
+            (Ok(outcome), Some(grace_ms)) => Outcome {
+                message: format!(
+                    "{} did not exit within {}ms; {}",
+                    process_name, grace_ms, outcome.message
+                ),
+                ..outcome
+            },
+            // The last instances finished exiting on their own just before the force kill
+            (Err(StuckbarError::NotRunning { .. }), Some(_)) => {
+                Outcome::new(format!("{} exited gracefully", process_name))
+            }
+            (Err(e), _) => return Err(e),
+        };
+        let kill_ms = elapsed_ms(runner, started_at) - grace_elapsed_ms;
+
+        if outcome.killed_pids.is_empty() {
+            outcome.killed_pids = old_pids;
+        }
+        self.emit(ManagerEvent::KillCompleted {
+            pids: outcome.killed_pids.clone(),
+        });
+        self.emit(ManagerEvent::WaitingForExit {
+            pids: outcome.killed_pids.clone(),
+        });
+        self.wait_for_exit(runner, &outcome.killed_pids).await?;
+
+        outcome.timings.grace_ms = grace_elapsed_ms;
+        outcome.timings.kill_ms = kill_ms;
+        outcome.timings.exit_wait_ms = elapsed_ms(runner, started_at) - grace_elapsed_ms - kill_ms;
+        outcome.timings.total_ms = elapsed_ms(runner, started_at);
+        Ok(outcome)
+    }
+
+    /// Launch the target and wait until a new instance has settled
+    async fn start_stage(
+        &self,
+        runner: &impl AsyncProcessRunner,
+    ) -> Result<Outcome, StuckbarError> {
+        self.emit(ManagerEvent::StartStarted);
+        let started_at = runner.now();
+
+        // A relaunched-by-system target counts as started as soon as any instance runs
+        let (outcome, existing) = match self.target.launch_command {
+            Some(_) => {
+                let existing = runner.list_pids(&self.target.process_name).await?;
+                (runner.start_process(&self.target).await?, existing)
+            }
+            None => (
+                Outcome::new(format!(
+                    "{} is relaunched automatically by Windows",
+                    self.target.process_name
+                )),
+                Vec::new(),
+            ),
+        };
+
+        self.emit(ManagerEvent::WaitingForStart);
+        let pid = self
+            .wait_for_new_instance(runner, &existing, outcome.new_pid)
+            .await?;
+        let start_ms = elapsed_ms(runner, started_at);
+
+        self.wait_for_settle(runner, pid).await?;
+        self.emit(ManagerEvent::StartCompleted { pid });
+
+        let mut outcome = outcome.with_new_pid(pid);
+        outcome.timings.start_ms = start_ms;
+        outcome.timings.settle_ms = elapsed_ms(runner, started_at) - start_ms;
+        outcome.timings.total_ms = elapsed_ms(runner, started_at);
+        Ok(outcome)
+    }
+
+    /// Record the open File Explorer windows if they are to be restored
+    ///
+    /// Only explorer.exe owns folder windows, so other targets record nothing.
+    async fn capture_windows(
+        &self,
+        runner: &impl AsyncProcessRunner,
+        restore: bool,
+    ) -> Option<Result<SessionSnapshot, StuckbarError>> {
+        if !(restore && self.target.is_explorer()) {
+            return None;
+        }
+        let shell_windows = self.shell_windows.clone();
+        Some(
+            runner
+                .run_blocking(Box::new(move || {
+                    SessionSnapshot::capture(shell_windows.as_ref())
+                }))
+                .await,
+        )
+    }
+
+    /// Clear the icon cache if it is to be rebuilt
+    ///
+    /// Only explorer.exe locks the cache files, so other targets leave them alone.
+    async fn clear_icon_cache(
+        &self,
+        runner: &impl AsyncProcessRunner,
+        rebuild: bool,
+    ) -> Option<CacheReport> {
+        if !(rebuild && self.target.is_explorer()) {
+            return None;
+        }
+        let icon_cache = self.icon_cache.clone();
+        let report = runner
+            .run_blocking(Box::new(move || icon_cache.clear()))
+            .await;
+        self.emit(ManagerEvent::IconCacheCleared {
+            report: report.clone(),
+        });
+        Some(report)
+    }
+
+    /// Reopen the windows recorded before the restart
+    ///
+    /// A failed snapshot does not fail the restart; it is reported instead.
+    async fn reopen_windows(
+        &self,
+        runner: &impl AsyncProcessRunner,
+        snapshot: Result<SessionSnapshot, StuckbarError>,
+    ) -> RestoreReport {
+        match snapshot {
+            Ok(snapshot) => {
+                let shell_windows = self.shell_windows.clone();
+                runner
+                    .run_blocking(Box::new(move || snapshot.restore(shell_windows.as_ref())))
+                    .await
+            }
+            Err(error) => RestoreReport {
+                snapshot_error: Some(error),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Combine the kill and start outcomes of a restart with the reopened windows
+    fn restarted(
+        &self,
+        killed: Outcome,
+        started: Outcome,
+        windows: Option<RestoreReport>,
+        total_ms: u64,
+    ) -> Outcome {
+        let mut message = match started.new_pid {
+            Some(pid) => format!(
+                "{} restarted successfully (PID {})",
+                self.target.display_name(),
+                pid
+            ),
+            None => format!("{} restarted successfully", self.target.display_name()),
+        };
+        if let Some(report) = &windows {
+            message = format!("{}, {}", message, report.summary());
+        }
+
+        Outcome {
+            message,
+            killed_pids: killed.killed_pids,
+            new_pid: started.new_pid,
+            failures: killed.failures,
+            windows,
+            icon_cache: None,
+            timings: Timings {
+                grace_ms: killed.timings.grace_ms,
+                kill_ms: killed.timings.kill_ms,
+                exit_wait_ms: killed.timings.exit_wait_ms,
+                start_ms: started.timings.start_ms,
+                settle_ms: started.timings.settle_ms,
+                total_ms,
+            },
+            joined: false,
+        }
+    }
+
+    /// PIDs of the instances in [`session_scope`](Self::session_scope), and
+    /// whether they are every running instance
+    async fn scoped_pids(
+        &self,
+        runner: &impl AsyncProcessRunner,
+    ) -> Result<(Vec<u32>, bool), StuckbarError> {
+        let process_name = &self.target.process_name;
+        if self.session_scope == SessionScope::All {
+            return Ok((runner.list_pids(process_name).await?, true));
+        }
+
+        let processes = runner.list_processes(process_name).await?;
+        let caller = runner.current_process().await;
+        let total = processes.len();
+        let pids: Vec<u32> = processes
+            .into_iter()
+            .filter(|process| self.session_scope.includes(process, caller.as_ref()))
+            .map(|process| process.pid)
+            .collect();
+        let whole = pids.len() == total;
+        Ok((pids, whole))
+    }
+
+    /// Terminate `pids` one at a time; instances that already exited are skipped
+    async fn kill_pids(
+        &self,
+        runner: &impl AsyncProcessRunner,
+        pids: &[u32],
+    ) -> Result<Outcome, StuckbarError> {
+        let process_name = &self.target.process_name;
+        let mut results = Vec::new();
+        for &pid in pids {
+            match runner.kill_pid(pid, process_name).await {
+                Err(StuckbarError::NotRunning { .. }) => {}
+                result => results.push(PidResult { pid, result }),
+            }
+        }
+        summarize_kill(process_name, results)
+    }
+
+    /// Poll until none of `old_pids` is running any more
+    async fn wait_for_exit(
+        &self,
+        runner: &impl AsyncProcessRunner,
+        old_pids: &[u32],
+    ) -> Result<(), StuckbarError> {
+        let started_at = runner.now();
+        if self
+            .exited_within(runner, old_pids, self.liveness.exit_timeout_ms)
+            .await?
+        {
+            return Ok(());
+        }
+        Err(StuckbarError::Timeout {
+            operation: format!("waiting for {} to exit", self.target.process_name),
+            waited_ms: elapsed_ms(runner, started_at),
+        })
+    }
+
+    /// Poll for up to `timeout_ms` until none of `old_pids` is running any more
+    async fn exited_within(
+        &self,
+        runner: &impl AsyncProcessRunner,
+        old_pids: &[u32],
+        timeout_ms: u64,
+    ) -> Result<bool, StuckbarError> {
+        let started_at = runner.now();
+        loop {
+            let running = runner.list_pids(&self.target.process_name).await?;
+            if !running.iter().any(|pid| old_pids.contains(pid)) {
+                return Ok(true);
+            }
+
+            if elapsed_ms(runner, started_at) >= timeout_ms {
+                return Ok(false);
+            }
+            runner.sleep_ms(self.liveness.poll_interval_ms).await;
+        }
+    }
+
+    /// Poll until an instance that is not in `existing` appears
+    ///
+    /// The spawned PID is preferred when it is alive; otherwise the first new
+    /// instance wins (the launcher may hand off to another process).
+    async fn wait_for_new_instance(
+        &self,
+        runner: &impl AsyncProcessRunner,
+        existing: &[u32],
+        spawned: Option<u32>,
+    ) -> Result<u32, StuckbarError> {
+        let started_at = runner.now();
+        loop {
+            let running = runner.list_pids(&self.target.process_name).await?;
+            let new_pid = spawned
+                .filter(|pid| running.contains(pid))
+                .or_else(|| running.iter().copied().find(|pid| !existing.contains(pid)));
+            if let Some(pid) = new_pid {
+                return Ok(pid);
+            }
+
+            let waited_ms = elapsed_ms(runner, started_at);
+            if waited_ms >= self.liveness.start_timeout_ms {
+                return Err(StuckbarError::Timeout {
+                    operation: format!("waiting for {} to start", self.target.process_name),
+                    waited_ms,
+                });
+            }
+            runner.sleep_ms(self.liveness.poll_interval_ms).await;
+        }
+    }
+
+    /// Poll until `pid` has stayed alive for the settle window
+    async fn wait_for_settle(
+        &self,
+        runner: &impl AsyncProcessRunner,
+        pid: u32,
+    ) -> Result<(), StuckbarError> {
+        let started_at = runner.now();
+        while elapsed_ms(runner, started_at) < self.liveness.settle_ms {
+            runner.sleep_ms(self.liveness.poll_interval_ms).await;
+
+            if !runner
+                .list_pids(&self.target.process_name)
+                .await?
+                .contains(&pid)
+            {
+                return Err(StuckbarError::Crashed {
+                    process: self.target.process_name.clone(),
+                    pid,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: ProcessRunner> ProcessManager<R> {
+    /// Kill the target process
+    ///
+    /// Uses the manager's [`kill_strategy`](Self::kill_strategy).
+    pub fn kill(&self) -> Result<Outcome, StuckbarError> {
+        self.kill_with(self.kill_strategy)
+    }
+
+    /// Kill the target process with the given strategy
+    pub fn kill_with(&self, strategy: KillStrategy) -> Result<Outcome, StuckbarError> {
+        self.coordinated(Action::Kill, || {
+            block_on(self.kill_operation(&Blocking(&self.runner), strategy))
+        })
+    }
+
+    /// Start the target process
+    ///
+    /// Targets without a launch command are relaunched by Windows, so nothing is
+    /// spawned and only their reappearance is awaited.
+    pub fn start(&self) -> Result<Outcome, StuckbarError> {
+        self.coordinated(Action::Start, || {
+            block_on(self.start_operation(&Blocking(&self.runner)))
+        })
+    }
+
+    /// Restart the target process (kill then start)
+    ///
+    /// Uses the manager's [`restart_options`](Self::restart_options).
+    pub fn restart(&self) -> Result<Outcome, StuckbarError> {
+        self.restart_with(self.restart_options())
+    }
+
+    /// Restart the target process with the given options
+    ///
+    /// With a [`coordinator`](Self::coordinator), a restart that is already in
+    /// progress is joined: this call waits for it and returns its outcome.
+    pub fn restart_with(&self, options: RestartOptions) -> Result<Outcome, StuckbarError> {
+        self.coordinated(Action::Restart, || {
+            block_on(self.restart_operation(&Blocking(&self.runner), options))
+        })
+    }
+
+    /// Run `operation` once the coordinator admits `action`
+    fn coordinated(
+        &self,
+        action: Action,
+        operation: impl FnOnce() -> Result<Outcome, StuckbarError>,
+    ) -> Result<Outcome, StuckbarError> {
+        match self.admit(action) {
+            Ok(None) => operation(),
+            Ok(Some(Admission::Lead(guard))) => led(guard, operation()),
+            Ok(Some(Admission::Join(joined))) => self.joined(action, joined.wait()),
+            Err(e) => self.finish(action, Err(e)),
+        }
+    }
+}
+
+/// Asynchronous counterparts of the blocking operations
+///
+/// They emit the same events and produce the same outcomes, but wait through the
+/// runner's futures instead of blocking the thread. Recording and reopening File
+/// Explorer windows and clearing the icon cache go through
+/// [`AsyncProcessRunner::run_blocking`].
+impl<R: AsyncProcessRunner> ProcessManager<R> {
+    /// Kill the target process with the manager's kill strategy
+    pub async fn kill_async(&self) -> Result<Outcome, StuckbarError> {
+        self.kill_with_async(self.kill_strategy).await
+    }
+
+    /// Kill the target process with the given strategy
+    pub async fn kill_with_async(&self, strategy: KillStrategy) -> Result<Outcome, StuckbarError> {
+        let operation = self.kill_operation(&self.runner, strategy);
+        self.coordinated_async(Action::Kill, operation).await
+    }
+
+    /// Start the target process
+    pub async fn start_async(&self) -> Result<Outcome, StuckbarError> {
+        let operation = self.start_operation(&self.runner);
+        self.coordinated_async(Action::Start, operation).await
+    }
+
+    /// Restart the target process with the manager's restart options
+    pub async fn restart_async(&self) -> Result<Outcome, StuckbarError> {
+        self.restart_with_async(self.restart_options()).await
+    }
+
+    /// Restart the target process with the given options
+    pub async fn restart_with_async(
+        &self,
+        options: RestartOptions,
+    ) -> Result<Outcome, StuckbarError> {
+        let operation = self.restart_operation(&self.runner, options);
+        self.coordinated_async(Action::Restart, operation).await
+    }
+
+    /// Run `operation` once the coordinator admits `action`
+    async fn coordinated_async(
+        &self,
+        action: Action,
+        operation: impl Future<Output = Result<Outcome, StuckbarError>>,
+    ) -> Result<Outcome, StuckbarError> {
+        match self.admit(action) {
+            Ok(None) => operation.await,
+            Ok(Some(Admission::Lead(guard))) => led(guard, operation.await),
+            Ok(Some(Admission::Join(joined))) => self.joined(action, joined.await),
+            Err(e) => self.finish(action, Err(e)),
+        }
+    }
+}
+
+/// Hand the result of a coordinated operation to the callers that joined it
+fn led(
+    guard: OperationGuard,
+    result: Result<Outcome, StuckbarError>,
+) -> Result<Outcome, StuckbarError> {
+    guard.finish(&result);
+    result
+}
+
+fn elapsed_ms(runner: &impl AsyncProcessRunner, since: Instant) -> u64 {
+    runner.now().saturating_duration_since(since).as_millis() as u64
+}
+
+/// Check if the current platform is Windows
+pub fn is_windows() -> bool {
+    cfg!(target_os = "windows")
+}
+
+/// Check platform and return an error if not Windows
+pub fn check_platform() -> Result<(), StuckbarError> {
+    if !is_windows() {
+        Err(StuckbarError::UnsupportedPlatform {
+            os: std::env::consts::OS.to_string(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "mcp")]
+pub mod mcp;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limiter::RestartPolicy;
+    use crate::mock::{
+        MockCacheFiles, MockProcessRunner, MockShellWindows, RecordingSink, kill_failed,
+        spawn_failed,
+    };
+    use crate::session::ExplorerWindow;
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    // Outcome tests
+    #[test]
+    fn test_outcome_new() {
+        let outcome = Outcome::new("test message");
+        assert_eq!(outcome.message, "test message");
+    }
+
+    #[test]
+    fn test_outcome_clone() {
+        let outcome = Outcome::new("test");
+        let cloned = outcome.clone();
+        assert_eq!(outcome, cloned);
+    }
+
+    // StuckbarError tests
+    #[test]
+    fn test_error_kinds_are_distinct() {
+        let errors = [
+            StuckbarError::NotRunning {
+                process: "explorer.exe".to_string(),
+            },
+            StuckbarError::AccessDenied {
+                process: "explorer.exe".to_string(),
+                details: String::new(),
+            },
+            StuckbarError::CommandMissing {
+                command: "taskkill".to_string(),
+            },
+            spawn_failed(""),
+            kill_failed(""),
+            StuckbarError::Timeout {
+                operation: "waiting".to_string(),
+                waited_ms: 10,
+            },
+            StuckbarError::UnsupportedPlatform {
+                os: "linux".to_string(),
+            },
+            StuckbarError::Crashed {
+                process: "explorer.exe".to_string(),
+                pid: 1,
+            },
+            StuckbarError::Busy {
+                operation: "restart of explorer.exe".to_string(),
+            },
+            StuckbarError::Interrupted {
+                operation: "restart of explorer.exe".to_string(),
+            },
+            StuckbarError::RateLimited {
+                reason: "too many restarts".to_string(),
+                retry_after_ms: 1000,
+            },
+            StuckbarError::InvalidConfig {
+                details: "unknown field".to_string(),
+            },
+            StuckbarError::Unhealthy {
+                details: "Taskbar window not found".to_string(),
+            },
+        ];
+
+        let mut kinds: Vec<_> = errors.iter().map(|e| e.kind()).collect();
+        let mut codes: Vec<_> = errors.iter().map(|e| e.exit_code()).collect();
+        kinds.sort();
+        kinds.dedup();
+        codes.sort();
+        codes.dedup();
+
+        assert_eq!(kinds.len(), errors.len());
+        assert_eq!(codes.len(), errors.len());
+        assert!(!codes.contains(&0));
+        assert!(!codes.contains(&2));
+    }
+
+    #[test]
+    fn test_error_display() {
+        let err = StuckbarError::NotRunning {
+            process: "explorer.exe".to_string(),
+        };
+        assert_eq!(err.to_string(), "explorer.exe is not running");
+
+        let err = StuckbarError::CommandMissing {
+            command: "taskkill".to_string(),
+        };
+        assert_eq!(err.to_string(), "Command not found: taskkill");
+    }
+
+    // taskkill classification tests
+    #[test]
+    fn test_classify_taskkill_not_found() {
+        let err = classify_taskkill_failure(
+            "explorer.exe",
+            Some(128),
+            "ERROR: The process \"explorer.exe\" not found.",
+        );
+        assert_eq!(
+            err,
+            StuckbarError::NotRunning {
+                process: "explorer.exe".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_classify_taskkill_access_denied() {
+        let err = classify_taskkill_failure(
+            "explorer.exe",
+            Some(1),
+            "ERROR: The process with PID 1234 could not be terminated.\r\nReason: Access is denied.\r\n",
+        );
+        assert_eq!(err.kind(), "access_denied");
+    }
+
+    #[test]
+    fn test_classify_taskkill_other_failure() {
+        let err = classify_taskkill_failure("explorer.exe", Some(1), "  something else  ");
+        assert_eq!(err, kill_failed("something else"));
+    }
+
+    #[test]
+    fn test_map_spawn_error_not_found() {
+        let err = map_spawn_error(
+            "taskkill",
+            "explorer.exe",
+            io::Error::from(io::ErrorKind::NotFound),
+        );
+        assert_eq!(
+            err,
+            StuckbarError::CommandMissing {
+                command: "taskkill".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_map_spawn_error_permission_denied() {
+        let err = map_spawn_error(
+            "explorer.exe",
+            "explorer.exe",
+            io::Error::from(io::ErrorKind::PermissionDenied),
+        );
+        assert_eq!(err.kind(), "access_denied");
+    }
+
+    #[test]
+    fn test_map_spawn_error_other() {
+        let err = map_spawn_error("explorer.exe", "explorer.exe", io::Error::other("boom"));
+        assert_eq!(err, spawn_failed("boom"));
+    }
+
+    // ExplorerManager::kill tests
+    #[test]
+    fn test_kill_success() {
+        let runner = MockProcessRunner::new().with_kill_result(Ok(Outcome::new("Killed")));
+        let manager = ExplorerManager::new(runner);
+
+        assert!(manager.kill().is_ok());
+    }
+
+    #[test]
+    fn test_kill_failure() {
+        let runner = MockProcessRunner::new().with_kill_result(Err(kill_failed("Failed to kill")));
+        let manager = ExplorerManager::new(runner);
+
+        assert!(manager.kill().is_err());
+    }
+
+    // ExplorerManager::start tests
+    #[test]
+    fn test_start_success() {
+        let runner = MockProcessRunner::new().with_start_result(Ok(Outcome::new("Started")));
+        let manager = ExplorerManager::new(runner);
+
+        assert!(manager.start().is_ok());
+    }
+
+    #[test]
+    fn test_start_failure() {
+        let runner =
+            MockProcessRunner::new().with_start_result(Err(spawn_failed("Failed to start")));
+        let manager = ExplorerManager::new(runner);
+
+        assert!(manager.start().is_err());
+    }
+
+    // ExplorerManager::restart tests
+    #[test]
+    fn test_restart_success() {
+        let runner = MockProcessRunner::new()
+            .with_kill_result(Ok(Outcome::new("Killed")))
+            .with_start_result(Ok(Outcome::new("Started")));
+        let manager = ExplorerManager::new(runner).with_restart_delay(100);
+
+        assert!(manager.restart().is_ok());
+    }
+
+    #[test]
+    fn test_restart_kill_fails() {
+        let runner = MockProcessRunner::new().with_kill_result(Err(kill_failed("Failed to kill")));
+        let manager = ExplorerManager::new(runner);
+
+        assert_eq!(manager.restart(), Err(kill_failed("Failed to kill")));
+    }
+
+    #[test]
+    fn test_restart_start_fails() {
+        let runner = MockProcessRunner::new()
+            .with_kill_result(Ok(Outcome::new("Killed")))
+            .with_start_result(Err(spawn_failed("Failed to start")));
+        let manager = ExplorerManager::new(runner);
+
+        assert_eq!(manager.restart(), Err(spawn_failed("Failed to start")));
+    }
+
+    #[test]
+    fn test_restart_sleeps_between_operations() {
+        let runner = MockProcessRunner::new()
+            .with_kill_result(Ok(Outcome::new("Killed")))
+            .with_start_result(Ok(Outcome::new("Started")));
+        let manager = ExplorerManager::new(runner).with_restart_delay(250);
+
+        manager.restart().unwrap();
+
+        // The old instance is gone immediately, so the first sleep is the restart delay;
+        // the remaining ones poll the new instance through the settle window
+        let sleep_calls = &manager.runner.get_sleep_calls();
+        assert_eq!(sleep_calls[0], 250);
+        assert!(sleep_calls[1..].iter().all(|ms| *ms == POLL_INTERVAL_MS));
+    }
+
+    #[test]
+    fn test_restart_uses_default_delay() {
+        let runner = MockProcessRunner::new()
+            .with_kill_result(Ok(Outcome::new("Killed")))
+            .with_start_result(Ok(Outcome::new("Started")));
+        let manager = ExplorerManager::new(runner);
+
+        assert_eq!(manager.restart_delay_ms, RESTART_DELAY_MS);
+    }
+
+    // ExplorerManager builder pattern test
+    #[test]
+    fn test_explorer_manager_with_restart_delay() {
+        let runner = MockProcessRunner::new();
+        let manager = ExplorerManager::new(runner).with_restart_delay(1000);
+        assert_eq!(manager.restart_delay_ms, 1000);
+    }
+
+    #[test]
+    fn test_kill_not_running() {
+        let not_running = StuckbarError::NotRunning {
+            process: "explorer.exe".to_string(),
+        };
+        let runner = MockProcessRunner::new().with_kill_result(Err(not_running.clone()));
+        let manager = ExplorerManager::new(runner);
+
+        assert_eq!(manager.kill(), Err(not_running));
+    }
+
+    #[test]
+    fn test_restart_message() {
+        let runner = MockProcessRunner::new()
+            .with_kill_result(Ok(Outcome::new("Killed")))
+            .with_start_result(Ok(Outcome::new("Started")));
+        let manager = ExplorerManager::new(runner);
+
+        let result = manager.restart().unwrap();
+        assert_eq!(
+            result.message,
+            "Explorer.exe restarted successfully (PID 1000)"
+        );
+    }
+
+    // Target selection tests
+    #[test]
+    fn test_manager_defaults_to_explorer() {
+        let manager = ExplorerManager::new(MockProcessRunner::new());
+        assert_eq!(manager.target, TargetSpec::explorer());
+    }
+
+    #[test]
+    fn test_restart_uses_configured_target() {
+        let target = TargetSpec::new("custom.exe").with_args(["--shell"]);
+        let runner = MockProcessRunner::new()
+            .with_kill_result(Ok(Outcome::new("Killed")))
+            .with_start_result(Ok(Outcome::new("Started")));
+        let manager = ProcessManager::for_target(runner, target.clone());
+
+        let result = manager.restart().unwrap();
+
+        assert_eq!(
+            result.message,
+            "Custom.exe restarted successfully (PID 1000)"
+        );
+        assert_eq!(manager.runner.get_killed(), vec!["custom.exe"]);
+        assert_eq!(manager.runner.get_started(), vec![target]);
+    }
+
+    #[test]
+    fn test_start_skips_targets_relaunched_by_system() {
+        let manager = ProcessManager::new(MockProcessRunner::new().with_running(&[7]))
+            .with_target(TargetSpec::start_menu());
+
+        let result = manager.start().unwrap();
+
+        assert!(result.message.contains("relaunched automatically"));
+        assert_eq!(result.new_pid, Some(7));
+        assert!(manager.runner.get_started().is_empty());
+    }
+
+    #[test]
+    fn test_restart_system_relaunched_target_only_kills() {
+        let runner = MockProcessRunner::new()
+            .with_running(&[7])
+            .with_respawn()
+            .with_kill_result(Ok(Outcome::new("Killed")));
+        let manager = ProcessManager::for_target(runner, TargetSpec::search_host());
+
+        let result = manager.restart().unwrap();
+        assert_eq!(result.killed_pids, vec![7]);
+        assert_eq!(result.new_pid, Some(1000));
+        assert_eq!(manager.runner.get_killed(), vec!["SearchHost.exe"]);
+        assert!(manager.runner.get_started().is_empty());
+    }
+
+    // Liveness verification tests
+    #[test]
+    fn test_restart_reports_pids_and_timings() {
+        let runner = MockProcessRunner::new()
+            .with_running(&[42, 43])
+            .with_kill_result(Ok(Outcome::new("Killed")))
+            .with_start_result(Ok(Outcome::new("Started")));
+        let manager = ExplorerManager::new(runner).with_restart_delay(200);
+
+        let result = manager.restart().unwrap();
+
+        assert_eq!(result.killed_pids, vec![42, 43]);
+        assert_eq!(result.new_pid, Some(1000));
+        assert_eq!(result.timings.exit_wait_ms, 0);
+        assert_eq!(result.timings.settle_ms, SETTLE_MS);
+        assert_eq!(result.timings.total_ms, 200 + SETTLE_MS);
+    }
+
+    #[test]
+    fn test_kill_keeps_pids_reported_by_runner() {
+        let failure = PidFailure {
+            pid: 43,
+            error: StuckbarError::AccessDenied {
+                process: "explorer.exe".to_string(),
+                details: "PID 43".to_string(),
+            },
+        };
+        let mut killed = Outcome::new("Killed").with_killed_pids(vec![42]);
+        killed.failures = vec![failure.clone()];
+        let runner = MockProcessRunner::new()
+            .with_running(&[42, 43])
+            .with_kill_result(Ok(killed));
+        let manager = ExplorerManager::new(runner);
+
+        let result = manager.kill().unwrap();
+
+        assert_eq!(result.killed_pids, vec![42]);
+        assert_eq!(result.failures, vec![failure]);
+    }
+
+    #[test]
+    fn test_backend_names_resolve() {
+        for name in BACKEND_NAMES {
+            let backend = Backend::from_name(name).unwrap();
+            assert_eq!(backend.name(), *name);
+        }
+        assert!(Backend::from_name("wmic").is_none());
+        assert!(BACKEND_NAMES.contains(&Backend::default().name()));
+    }
+
+    #[test]
+    fn test_graceful_kill_exits_without_force() {
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_graceful_exit();
+        let manager = ExplorerManager::new(runner);
+
+        let result = manager
+            .kill_with(KillStrategy::Graceful { grace_ms: 1000 })
+            .unwrap();
 
-/// Delay in milliseconds before starting explorer.exe after termination
-pub const RESTART_DELAY_MS: u64 = 500;
+        assert_eq!(result.message, "explorer.exe exited gracefully");
+        assert_eq!(result.killed_pids, vec![42]);
+        assert_eq!(manager.runner.get_exit_requests(), vec!["explorer.exe"]);
+        assert!(manager.runner.get_killed().is_empty());
+    }
 
-/// Result of a process operation
-#[derive(Debug, PartialEq, Clone)]
-pub struct ProcessResult {
-    pub success: bool,
-    pub message: String,
-}
+    #[test]
+    fn test_graceful_kill_forces_after_grace_period() {
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_kill_result(Ok(Outcome::new("Successfully terminated explorer.exe")));
+        let manager = ExplorerManager::new(runner);
 
-impl ProcessResult {
-    pub fn success(message: impl Into<String>) -> Self {
-        Self {
-            success: true,
-            message: message.into(),
-        }
+        let result = manager
+            .kill_with(KillStrategy::Graceful { grace_ms: 1000 })
+            .unwrap();
+
+        assert_eq!(
+            result.message,
+            "explorer.exe did not exit within 1000ms; Successfully terminated explorer.exe"
+        );
+        assert_eq!(manager.runner.get_killed(), vec!["explorer.exe"]);
+        assert_eq!(result.timings.grace_ms, 1000);
     }
 
-    pub fn failure(message: impl Into<String>) -> Self {
-        Self {
-            success: false,
-            message: message.into(),
-        }
+    #[test]
+    fn test_graceful_kill_forces_immediately_when_refused() {
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_exit_refused(kill_failed(
+                "This process can only be terminated forcefully",
+            ))
+            .with_kill_result(Ok(Outcome::new("Killed")));
+        let manager = ExplorerManager::new(runner).with_kill_strategy(KillStrategy::graceful());
+
+        let result = manager.kill().unwrap();
+
+        assert_eq!(result.message, "Killed");
+        assert_eq!(manager.runner.get_killed(), vec!["explorer.exe"]);
+        assert_eq!(result.timings.grace_ms, 0);
     }
-}
 
-/// Trait for abstracting process operations (enables testing)
-pub trait ProcessRunner {
-    fn kill_process(&self, process_name: &str) -> ProcessResult;
-    fn start_process(&self, process_name: &str) -> ProcessResult;
-    fn sleep_ms(&self, ms: u64);
-}
+    #[test]
+    fn test_graceful_kill_not_running() {
+        let manager = ExplorerManager::new(MockProcessRunner::new());
 
-/// Real implementation that interacts with the system
-pub struct SystemProcessRunner;
+        let err = manager.kill_with(KillStrategy::graceful()).unwrap_err();
 
-impl ProcessRunner for SystemProcessRunner {
-    fn kill_process(&self, process_name: &str) -> ProcessResult {
-        let result = Command::new("taskkill")
-            .args(["/F", "/IM", process_name])
-            .output();
+        assert_eq!(err.kind(), "not_running");
+        assert!(manager.runner.get_exit_requests().is_empty());
+    }
 
-        match result {
-            Ok(output) => {
-                if output.status.success() {
-                    ProcessResult::success(format!("Successfully terminated {}", process_name))
-                } else {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-                    ProcessResult::failure(format!(
-                        "Failed to terminate {}: {}",
-                        process_name, stderr
-                    ))
-                }
-            }
-            Err(e) => ProcessResult::failure(format!("Error executing taskkill: {}", e)),
-        }
+    #[test]
+    fn test_force_kill_does_not_request_exit() {
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_graceful_exit()
+            .with_kill_result(Ok(Outcome::new("Killed")));
+        let manager = ExplorerManager::new(runner);
+
+        manager.kill_with(KillStrategy::Force).unwrap();
+
+        assert!(manager.runner.get_exit_requests().is_empty());
+        assert_eq!(manager.runner.get_killed(), vec!["explorer.exe"]);
     }
 
-    fn start_process(&self, process_name: &str) -> ProcessResult {
-        let result = Command::new(process_name).spawn();
+    #[test]
+    fn test_restart_with_graceful_strategy() {
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_graceful_exit()
+            .with_start_result(Ok(Outcome::new("Started")));
+        let manager = ExplorerManager::new(runner).with_kill_strategy(KillStrategy::graceful());
 
-        match result {
-            Ok(_) => ProcessResult::success(format!("Successfully started {}", process_name)),
-            Err(e) => ProcessResult::failure(format!("Error starting {}: {}", process_name, e)),
-        }
+        let result = manager.restart().unwrap();
+
+        assert_eq!(result.killed_pids, vec![42]);
+        assert_eq!(result.new_pid, Some(1000));
+        assert!(manager.runner.get_killed().is_empty());
     }
 
-    fn sleep_ms(&self, ms: u64) {
-        std::thread::sleep(std::time::Duration::from_millis(ms));
+    #[test]
+    fn test_restart_emits_events_in_order() {
+        let sink = RecordingSink::default();
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_kill_result(Ok(Outcome::new("Killed")))
+            .with_start_result(Ok(Outcome::new("Started")));
+        let manager = ExplorerManager::new(runner).with_sink(sink.clone());
+
+        manager.restart().unwrap();
+
+        assert_eq!(
+            sink.get_names(),
+            vec![
+                "restart_started",
+                "kill_started",
+                "kill_completed",
+                "waiting_for_exit",
+                "start_started",
+                "waiting_for_start",
+                "start_completed",
+                "operation_completed",
+            ]
+        );
+        let events = sink.get_events();
+        assert_eq!(events[2], ManagerEvent::KillCompleted { pids: vec![42] });
+        assert_eq!(events[6], ManagerEvent::StartCompleted { pid: 1000 });
     }
-}
 
-/// Explorer manager that handles explorer.exe operations
-pub struct ExplorerManager<R: ProcessRunner> {
-    pub runner: R,
-    pub restart_delay_ms: u64,
-}
+    #[test]
+    fn test_failed_kill_emits_operation_failed() {
+        let sink = RecordingSink::default();
+        let runner = MockProcessRunner::new().with_kill_result(Err(kill_failed("denied")));
+        let manager = ExplorerManager::new(runner).with_sink(sink.clone());
+
+        assert!(manager.kill().is_err());
+        assert_eq!(
+            sink.get_events(),
+            vec![
+                ManagerEvent::KillStarted,
+                ManagerEvent::OperationFailed {
+                    action: Action::Kill,
+                    error: kill_failed("denied"),
+                },
+            ]
+        );
+    }
 
-impl<R: ProcessRunner> ExplorerManager<R> {
-    pub fn new(runner: R) -> Self {
-        Self {
-            runner,
-            restart_delay_ms: RESTART_DELAY_MS,
-        }
+    #[test]
+    fn test_failed_restart_names_the_stage() {
+        let sink = RecordingSink::default();
+        let runner = MockProcessRunner::new()
+            .with_kill_result(Ok(Outcome::new("Killed")))
+            .with_start_result(Err(spawn_failed("Start failed")));
+        let manager = ExplorerManager::new(runner).with_sink(sink.clone());
+
+        assert!(manager.restart().is_err());
+
+        let events = sink.get_events();
+        assert_eq!(
+            events[events.len() - 2..],
+            [
+                ManagerEvent::RestartFailed {
+                    stage: Stage::Start,
+                    error: spawn_failed("Start failed"),
+                },
+                ManagerEvent::OperationFailed {
+                    action: Action::Restart,
+                    error: spawn_failed("Start failed"),
+                },
+            ]
+        );
+        assert!(!sink.get_names().contains(&"start_completed"));
     }
 
-    pub fn with_restart_delay(mut self, delay_ms: u64) -> Self {
-        self.restart_delay_ms = delay_ms;
-        self
+    #[test]
+    fn test_graceful_kill_emits_exit_request() {
+        let sink = RecordingSink::default();
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_kill_result(Ok(Outcome::new("Killed")));
+        let manager = ExplorerManager::new(runner).with_sink(sink.clone());
+
+        manager
+            .kill_with(KillStrategy::Graceful { grace_ms: 1000 })
+            .unwrap();
+
+        assert_eq!(
+            sink.get_names(),
+            vec![
+                "kill_started",
+                "exit_requested",
+                "grace_expired",
+                "kill_completed",
+                "waiting_for_exit",
+                "operation_completed",
+            ]
+        );
     }
 
-    /// Kill explorer.exe process
-    pub fn kill(&self) -> bool {
-        println!("{}", "Terminating explorer.exe...".yellow());
-        let result = self.runner.kill_process("explorer.exe");
+    #[test]
+    fn test_every_sink_receives_events() {
+        let first = RecordingSink::default();
+        let second = RecordingSink::default();
+        let runner = MockProcessRunner::new().with_start_result(Ok(Outcome::new("Started")));
+        let manager = ExplorerManager::new(runner)
+            .with_sink(first.clone())
+            .with_sink(second.clone());
+
+        manager.start().unwrap();
+
+        assert_eq!(first.get_events(), second.get_events());
+        assert_eq!(first.get_names().len(), 4);
+    }
 
-        if result.success {
-            println!("{}", result.message.green());
-        } else {
-            eprintln!("{}", result.message.red());
-        }
+    #[test]
+    fn test_async_restart_matches_blocking_restart() {
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_kill_result(Ok(Outcome::new("Killed")))
+            .with_start_result(Ok(Outcome::new("Started")));
+        let sink = RecordingSink::default();
+        let manager = ExplorerManager::new(Blocking(&runner)).with_sink(sink.clone());
+
+        let result = block_on(manager.restart_async()).unwrap();
+
+        assert_eq!(
+            result.message,
+            "Explorer.exe restarted successfully (PID 1000)"
+        );
+        assert_eq!(result.killed_pids, vec![42]);
+        assert_eq!(runner.get_killed(), vec!["explorer.exe"]);
+        assert_eq!(sink.get_names().len(), 8);
+    }
+
+    #[test]
+    fn test_async_kill_uses_strategy() {
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_graceful_exit();
+        let manager = ExplorerManager::new(Blocking(&runner));
 
-        result.success
+        let result = block_on(manager.kill_with_async(KillStrategy::graceful())).unwrap();
+
+        assert_eq!(result.message, "explorer.exe exited gracefully");
+        assert!(runner.get_killed().is_empty());
     }
 
-    /// Start explorer.exe process
-    pub fn start(&self) -> bool {
-        println!("{}", "Starting explorer.exe...".yellow());
-        let result = self.runner.start_process("explorer.exe");
+    #[test]
+    fn test_restart_joins_restart_in_progress() {
+        let coordinator = Arc::new(OperationCoordinator::new(&TargetSpec::explorer()));
+        let Ok(Admission::Lead(leader)) = coordinator.begin(Action::Restart) else {
+            panic!("expected to lead");
+        };
+        let runner = MockProcessRunner::new().with_running(&[42]);
+        let sink = RecordingSink::default();
+        let manager = ExplorerManager::new(Blocking(&runner))
+            .with_coordinator(coordinator.clone())
+            .with_sink(sink.clone());
+
+        let mut restart = pin!(manager.restart_async());
+        let mut context = Context::from_waker(Waker::noop());
+        assert!(restart.as_mut().poll(&mut context).is_pending());
+
+        leader.finish(&Ok(
+            Outcome::new("Explorer.exe restarted successfully").with_new_pid(7)
+        ));
+        let Poll::Ready(result) = restart.as_mut().poll(&mut context) else {
+            panic!("joined restart should have finished");
+        };
+
+        let outcome = result.unwrap();
+        assert_eq!(
+            outcome.message,
+            "Restart already in progress, joined: Explorer.exe restarted successfully"
+        );
+        assert!(outcome.joined);
+        assert_eq!(outcome.new_pid, Some(7));
+        assert!(runner.get_killed().is_empty());
+        assert_eq!(
+            sink.get_names(),
+            vec!["operation_joined", "operation_completed"]
+        );
+    }
 
-        if result.success {
-            println!("{}", result.message.green());
-        } else {
-            eprintln!("{}", result.message.red());
-        }
+    #[test]
+    fn test_kill_rejected_during_restart() {
+        let coordinator = Arc::new(OperationCoordinator::new(&TargetSpec::explorer()));
+        let _leader = coordinator.begin(Action::Restart).unwrap();
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_kill_result(Ok(Outcome::new("Killed")));
+        let manager = ExplorerManager::new(runner).with_coordinator(coordinator);
+
+        let err = manager.kill().unwrap_err();
+
+        assert_eq!(err.kind(), "busy");
+        assert!(manager.runner.get_killed().is_empty());
+    }
 
-        result.success
+    #[test]
+    fn test_coordinated_operation_releases_coordinator() {
+        let coordinator = Arc::new(OperationCoordinator::new(&TargetSpec::explorer()));
+        let runner = MockProcessRunner::new().with_kill_result(Err(kill_failed("denied")));
+        let manager = ExplorerManager::new(runner).with_coordinator(coordinator.clone());
+
+        assert!(manager.kill().is_err());
+        assert!(!coordinator.is_busy());
     }
 
-    /// Restart explorer.exe (kill then start)
-    pub fn restart(&self) -> bool {
-        println!("{}", "Restarting explorer.exe...".cyan().bold());
+    #[test]
+    fn test_restart_refused_by_limiter() {
+        let limiter = Arc::new(RestartLimiter::new(RestartPolicy {
+            min_interval_ms: 60_000,
+            ..RestartPolicy::unlimited()
+        }));
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_kill_result(Ok(Outcome::new("Killed")))
+            .with_start_result(Ok(Outcome::new("Started")));
+        let sink = RecordingSink::default();
+        let manager = ExplorerManager::new(runner)
+            .with_restart_delay(0)
+            .with_restart_limiter(limiter.clone())
+            .with_sink(sink.clone());
+
+        assert!(manager.restart().is_ok());
+        let second = ExplorerManager::new(MockProcessRunner::new().with_running(&[1000]))
+            .with_restart_limiter(limiter)
+            .with_sink(sink.clone());
+        let err = second.restart().unwrap_err();
+
+        assert_eq!(err.kind(), "rate_limited");
+        assert!(second.runner.get_killed().is_empty());
+        assert_eq!(sink.get_names().last(), Some(&"operation_failed"));
+    }
 
-        if !self.kill() {
-            return false;
+    #[test]
+    fn test_kill_loop_refused_by_limiter() {
+        let limiter = Arc::new(RestartLimiter::new(RestartPolicy {
+            max_restarts: 3,
+            window_ms: 60_000,
+            ..RestartPolicy::unlimited()
+        }));
+        let mut runner = MockProcessRunner::new().with_running(&[42]).with_respawn();
+        for _ in 0..3 {
+            runner = runner.with_kill_result(Ok(Outcome::new("Killed")));
         }
+        let manager = ExplorerManager::new(runner).with_restart_limiter(limiter);
 
-        // Small delay to ensure explorer is fully terminated
-        self.runner.sleep_ms(self.restart_delay_ms);
+        let results: Vec<_> = (0..5).map(|_| manager.kill()).collect();
 
-        if !self.start() {
-            return false;
+        assert!(results[..3].iter().all(Result::is_ok));
+        for result in &results[3..] {
+            assert_eq!(result.as_ref().unwrap_err().kind(), "rate_limited");
         }
+        assert_eq!(manager.runner.get_killed().len(), 3);
+    }
+
+    #[test]
+    fn test_kill_of_launched_target_is_not_limited() {
+        let limiter = Arc::new(RestartLimiter::new(RestartPolicy {
+            max_restarts: 1,
+            window_ms: 60_000,
+            ..RestartPolicy::unlimited()
+        }));
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_kill_result(Ok(Outcome::new("Killed")))
+            .with_kill_result(Ok(Outcome::new("Killed")));
+        let manager = ProcessManager::for_target(runner, TargetSpec::new("app.exe"))
+            .with_restart_limiter(limiter);
+
+        assert!(manager.kill().is_ok());
+        assert!(manager.kill().is_ok());
+    }
+
+    #[test]
+    fn test_crashed_restart_opens_breaker() {
+        let limiter = Arc::new(RestartLimiter::new(RestartPolicy {
+            breaker_threshold: 1,
+            breaker_cooldown_ms: 60_000,
+            ..RestartPolicy::unlimited()
+        }));
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_kill_result(Ok(Outcome::new("Killed")))
+            .with_kill_result(Ok(Outcome::new("Killed")))
+            .with_start_result(Ok(Outcome::new("Started")))
+            .with_start_result(Ok(Outcome::new("Started")))
+            .with_crash_after_start();
+        let manager = ExplorerManager::new(runner)
+            .with_restart_delay(0)
+            .with_restart_limiter(limiter);
+
+        assert_eq!(manager.restart().unwrap_err().kind(), "crashed");
+        let err = manager.restart().unwrap_err();
+
+        assert_eq!(err.kind(), "rate_limited");
+        assert!(err.to_string().contains("circuit breaker"));
+        assert_eq!(manager.runner.get_killed().len(), 1);
+    }
 
-        println!("{}", "Explorer.exe restarted successfully!".green().bold());
-        true
+    #[test]
+    fn test_rate_limited_error_serializes_retry_after() {
+        let error = StuckbarError::RateLimited {
+            reason: "restarts must be at least 5000ms apart".to_string(),
+            retry_after_ms: 4000,
+        };
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "kind": "rate_limited",
+                "message": "Restart refused: restarts must be at least 5000ms apart; retry after 4000ms",
+                "retry_after_ms": 4000,
+            })
+        );
     }
 
-    /// Kill explorer.exe without printing (for MCP/programmatic use)
-    pub fn kill_silent(&self) -> ProcessResult {
-        self.runner.kill_process("explorer.exe")
+    #[test]
+    fn test_error_serializes_kind_and_message() {
+        let error = StuckbarError::Timeout {
+            operation: "waiting for explorer.exe to exit".to_string(),
+            waited_ms: 5000,
+        };
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "kind": "timeout",
+                "message": "Timed out after 5000ms while waiting for explorer.exe to exit",
+            })
+        );
     }
 
-    /// Start explorer.exe without printing (for MCP/programmatic use)
-    pub fn start_silent(&self) -> ProcessResult {
-        self.runner.start_process("explorer.exe")
+    fn restore_manager(shell: MockShellWindows) -> ExplorerManager<MockProcessRunner> {
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_kill_result(Ok(Outcome::new("Killed")))
+            .with_start_result(Ok(Outcome::new("Started")));
+        ExplorerManager::new(runner).with_shell_windows(shell)
     }
 
-    /// Restart explorer.exe without printing (for MCP/programmatic use)
-    pub fn restart_silent(&self) -> ProcessResult {
-        let kill_result = self.runner.kill_process("explorer.exe");
-        if !kill_result.success {
-            return kill_result;
-        }
+    #[test]
+    fn test_restart_restores_windows() {
+        let windows = vec![
+            ExplorerWindow::new("C:\\Users\\me\\Documents"),
+            ExplorerWindow::new("D:\\Projects"),
+        ];
+        let shell = MockShellWindows::new(windows.clone());
+        let manager = restore_manager(shell.clone()).with_restore_windows(true);
+
+        let result = manager.restart().unwrap();
+
+        assert_eq!(shell.get_opened(), windows);
+        assert_eq!(result.windows.unwrap().reopened, windows);
+        assert_eq!(
+            result.message,
+            "Explorer.exe restarted successfully (PID 1000), reopened 2 of 2 windows"
+        );
+    }
 
-        self.runner.sleep_ms(self.restart_delay_ms);
+    #[test]
+    fn test_restart_without_restore_leaves_windows_alone() {
+        let shell = MockShellWindows::new(vec![ExplorerWindow::new("D:\\Projects")]);
+        let manager = restore_manager(shell.clone());
 
-        let start_result = self.runner.start_process("explorer.exe");
-        if !start_result.success {
-            return start_result;
-        }
+        let result = manager.restart().unwrap();
 
-        ProcessResult::success("Explorer.exe restarted successfully")
+        assert!(shell.get_opened().is_empty());
+        assert!(result.windows.is_none());
     }
-}
 
-/// Check if the current platform is Windows
-pub fn is_windows() -> bool {
-    cfg!(target_os = "windows")
-}
+    #[test]
+    fn test_restart_restore_per_call() {
+        let shell = MockShellWindows::new(vec![ExplorerWindow::new("D:\\Projects")]);
+        let manager = restore_manager(shell.clone());
+
+        let result = manager
+            .restart_with(RestartOptions {
+                restore_windows: true,
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(shell.get_opened().len(), 1);
+        assert!(result.windows.is_some());
+    }
 
-/// Check platform and return an error message if not Windows
-pub fn check_platform() -> Result<(), String> {
-    if !is_windows() {
-        Err(format!(
-            "stuckbar is a Windows-only tool.\n\
-            Current platform '{}' is not supported.\n\
-            This tool restarts explorer.exe which only exists on Windows.",
-            std::env::consts::OS
-        ))
-    } else {
-        Ok(())
+    #[test]
+    fn test_restart_survives_failed_snapshot() {
+        let shell =
+            MockShellWindows::new(Vec::new()).with_list_error(StuckbarError::CommandMissing {
+                command: "powershell".to_string(),
+            });
+        let manager = restore_manager(shell).with_restore_windows(true);
+
+        let result = manager.restart().unwrap();
+
+        let report = result.windows.unwrap();
+        assert_eq!(report.snapshot_error.unwrap().kind(), "command_missing");
+        assert_eq!(result.new_pid, Some(1000));
     }
-}
 
-#[cfg(feature = "mcp")]
-pub mod mcp;
+    #[test]
+    fn test_restore_ignored_for_other_targets() {
+        let shell = MockShellWindows::new(vec![ExplorerWindow::new("D:\\Projects")]);
+        let manager = restore_manager(shell.clone())
+            .with_target(TargetSpec::new("app.exe"))
+            .with_restore_windows(true);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::cell::RefCell;
+        let result = manager.restart().unwrap();
 
-    /// Mock process runner for testing
-    pub struct MockProcessRunner {
-        kill_results: RefCell<Vec<ProcessResult>>,
-        start_results: RefCell<Vec<ProcessResult>>,
-        sleep_calls: RefCell<Vec<u64>>,
+        assert!(shell.get_opened().is_empty());
+        assert!(result.windows.is_none());
     }
 
-    impl MockProcessRunner {
-        pub fn new() -> Self {
-            Self {
-                kill_results: RefCell::new(Vec::new()),
-                start_results: RefCell::new(Vec::new()),
-                sleep_calls: RefCell::new(Vec::new()),
-            }
+    fn icon_cache(files: &MockCacheFiles) -> IconCache {
+        IconCache::new(Some(PathBuf::from("cache"))).with_file_system(files.clone())
+    }
+
+    #[test]
+    fn test_restart_rebuilds_icon_cache_while_explorer_is_down() {
+        let files = MockCacheFiles::new(&["cache/iconcache_32.db", "cache/desktop.ini"]);
+        let sink = RecordingSink::default();
+        let manager = restore_manager(MockShellWindows::default())
+            .with_icon_cache(icon_cache(&files))
+            .with_rebuild_icon_cache(true)
+            .with_sink(sink.clone());
+
+        let result = manager.restart().unwrap();
+
+        assert_eq!(files.get_files(), vec![PathBuf::from("cache/desktop.ini")]);
+        assert_eq!(result.icon_cache.unwrap().removed.len(), 1);
+        assert!(result.message.ends_with(", removed 1 icon cache file"));
+        let names = sink.get_names();
+        let cleared = names.iter().position(|n| *n == "icon_cache_cleared");
+        assert!(cleared > names.iter().position(|n| *n == "waiting_for_exit"));
+        assert!(cleared < names.iter().position(|n| *n == "start_started"));
+    }
+
+    #[test]
+    fn test_restart_keeps_icon_cache_unless_asked() {
+        let files = MockCacheFiles::new(&["cache/iconcache_32.db"]);
+        let manager =
+            restore_manager(MockShellWindows::default()).with_icon_cache(icon_cache(&files));
+
+        let result = manager.restart().unwrap();
+        assert!(result.icon_cache.is_none());
+        assert_eq!(files.get_files().len(), 1);
+
+        let manager =
+            restore_manager(MockShellWindows::default()).with_icon_cache(icon_cache(&files));
+        let result = manager
+            .restart_with(RestartOptions {
+                rebuild_icon_cache: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(result.icon_cache.is_some());
+        assert!(files.get_files().is_empty());
+    }
+
+    #[test]
+    fn test_icon_cache_ignored_for_other_targets() {
+        let files = MockCacheFiles::new(&["cache/iconcache_32.db"]);
+        let manager = restore_manager(MockShellWindows::default())
+            .with_target(TargetSpec::new("app.exe"))
+            .with_icon_cache(icon_cache(&files))
+            .with_rebuild_icon_cache(true);
+
+        let result = manager.restart().unwrap();
+
+        assert_eq!(files.get_files().len(), 1);
+        assert!(result.icon_cache.is_none());
+    }
+
+    /// Runs blocking work on a thread of its own and records which thread
+    struct Offloading<'a> {
+        inner: Blocking<'a, MockProcessRunner>,
+        threads: Arc<Mutex<Vec<std::thread::ThreadId>>>,
+    }
+
+    impl AsyncProcessRunner for Offloading<'_> {
+        fn kill_process(
+            &self,
+            process_name: &str,
+        ) -> impl Future<Output = Result<Outcome, StuckbarError>> + Send {
+            self.inner.kill_process(process_name)
         }
 
-        pub fn with_kill_result(self, result: ProcessResult) -> Self {
-            self.kill_results.borrow_mut().push(result);
-            self
+        fn start_process(
+            &self,
+            target: &TargetSpec,
+        ) -> impl Future<Output = Result<Outcome, StuckbarError>> + Send {
+            self.inner.start_process(target)
         }
 
-        pub fn with_start_result(self, result: ProcessResult) -> Self {
-            self.start_results.borrow_mut().push(result);
-            self
+        fn list_pids(
+            &self,
+            process_name: &str,
+        ) -> impl Future<Output = Result<Vec<u32>, StuckbarError>> + Send {
+            self.inner.list_pids(process_name)
         }
 
-        pub fn get_sleep_calls(&self) -> Vec<u64> {
-            self.sleep_calls.borrow().clone()
+        fn sleep_ms(&self, ms: u64) -> impl Future<Output = ()> + Send {
+            self.inner.sleep_ms(ms)
         }
-    }
 
-    impl Default for MockProcessRunner {
-        fn default() -> Self {
-            Self::new()
+        fn kill_pid(
+            &self,
+            pid: u32,
+            process_name: &str,
+        ) -> impl Future<Output = Result<(), StuckbarError>> + Send {
+            self.inner.kill_pid(pid, process_name)
         }
-    }
 
-    impl ProcessRunner for MockProcessRunner {
-        fn kill_process(&self, _process_name: &str) -> ProcessResult {
-            self.kill_results
-                .borrow_mut()
-                .pop()
-                .unwrap_or_else(|| ProcessResult::failure("No mock result configured"))
+        fn list_processes(
+            &self,
+            process_name: &str,
+        ) -> impl Future<Output = Result<Vec<ProcessInfo>, StuckbarError>> + Send {
+            self.inner.list_processes(process_name)
         }
 
-        fn start_process(&self, _process_name: &str) -> ProcessResult {
-            self.start_results
-                .borrow_mut()
-                .pop()
-                .unwrap_or_else(|| ProcessResult::failure("No mock result configured"))
+        fn current_process(&self) -> impl Future<Output = Option<ProcessInfo>> + Send {
+            self.inner.current_process()
         }
 
-        fn sleep_ms(&self, ms: u64) {
-            self.sleep_calls.borrow_mut().push(ms);
+        fn now(&self) -> Instant {
+            self.inner.now()
         }
-    }
 
-    // ProcessResult tests
-    #[test]
-    fn test_process_result_success() {
-        let result = ProcessResult::success("test message");
-        assert!(result.success);
-        assert_eq!(result.message, "test message");
+        fn run_blocking<T: Send + 'static>(
+            &self,
+            work: Box<dyn FnOnce() -> T + Send>,
+        ) -> impl Future<Output = T> + Send {
+            let threads = self.threads.clone();
+            let mut handle = Some(std::thread::spawn(move || {
+                threads.lock().unwrap().push(std::thread::current().id());
+                work()
+            }));
+            std::future::poll_fn(move |_| match handle.take_if(|h| h.is_finished()) {
+                Some(handle) => Poll::Ready(handle.join().unwrap()),
+                None => Poll::Pending,
+            })
+        }
     }
 
     #[test]
-    fn test_process_result_failure() {
-        let result = ProcessResult::failure("error message");
-        assert!(!result.success);
-        assert_eq!(result.message, "error message");
+    fn test_async_restart_awaits_blocking_stages() {
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_kill_result(Ok(Outcome::new("Killed")))
+            .with_start_result(Ok(Outcome::new("Started")));
+        let threads = Arc::new(Mutex::new(Vec::new()));
+        let windows = vec![ExplorerWindow::new("D:\\Projects")];
+        let shell = MockShellWindows::new(windows.clone());
+        let files = MockCacheFiles::new(&["cache/iconcache_32.db"]);
+        let manager = ExplorerManager::new(Offloading {
+            inner: Blocking(&runner),
+            threads: threads.clone(),
+        })
+        .with_shell_windows(shell.clone())
+        .with_restore_windows(true)
+        .with_icon_cache(icon_cache(&files))
+        .with_rebuild_icon_cache(true);
+
+        let mut restart = pin!(manager.restart_async());
+        let mut context = Context::from_waker(Waker::noop());
+        let mut suspended = 0;
+        let result = loop {
+            match restart.as_mut().poll(&mut context) {
+                Poll::Ready(result) => break result,
+                Poll::Pending => {
+                    suspended += 1;
+                    std::thread::yield_now();
+                }
+            }
+        };
+
+        let outcome = result.unwrap();
+        assert_eq!(outcome.windows.unwrap().reopened, windows);
+        assert_eq!(outcome.icon_cache.unwrap().removed.len(), 1);
+        assert_eq!(shell.get_opened(), windows);
+        assert!(suspended > 0);
+        // Recording, clearing the cache and reopening each left the polling thread
+        let threads = threads.lock().unwrap();
+        assert_eq!(threads.len(), 3);
+        assert!(!threads.contains(&std::thread::current().id()));
     }
 
     #[test]
-    fn test_process_result_clone() {
-        let result = ProcessResult::success("test");
-        let cloned = result.clone();
-        assert_eq!(result, cloned);
+    fn test_kill_times_out_when_process_does_not_exit() {
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_stuck_processes()
+            .with_kill_result(Ok(Outcome::new("Killed")));
+        let manager = ExplorerManager::new(runner);
+
+        let err = manager.kill().unwrap_err();
+
+        assert_eq!(err.kind(), "timeout");
+        assert!(manager.runner.elapsed_ms() >= EXIT_TIMEOUT_MS);
     }
 
-    // ExplorerManager::kill tests
     #[test]
-    fn test_kill_success() {
-        let runner = MockProcessRunner::new().with_kill_result(ProcessResult::success("Killed"));
+    fn test_restart_does_not_start_when_exit_times_out() {
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_stuck_processes()
+            .with_kill_result(Ok(Outcome::new("Killed")))
+            .with_start_result(Ok(Outcome::new("Started")));
         let manager = ExplorerManager::new(runner);
 
-        assert!(manager.kill());
+        assert!(manager.restart().is_err());
+        assert!(manager.runner.get_started().is_empty());
     }
 
     #[test]
-    fn test_kill_failure() {
-        let runner =
-            MockProcessRunner::new().with_kill_result(ProcessResult::failure("Failed to kill"));
-        let manager = ExplorerManager::new(runner);
-
-        assert!(!manager.kill());
+    fn test_start_times_out_when_no_instance_appears() {
+        let runner = MockProcessRunner::new()
+            .with_spawn_vanishing()
+            .with_start_result(Ok(Outcome::new("Started")));
+        let liveness = LivenessPolicy {
+            start_timeout_ms: 500,
+            ..Default::default()
+        };
+        let manager = ExplorerManager::new(runner).with_liveness(liveness);
+
+        match manager.start() {
+            Err(StuckbarError::Timeout { waited_ms, .. }) => assert_eq!(waited_ms, 500),
+            other => panic!("expected timeout, got {:?}", other),
+        }
     }
 
-    // ExplorerManager::start tests
     #[test]
-    fn test_start_success() {
-        let runner = MockProcessRunner::new().with_start_result(ProcessResult::success("Started"));
+    fn test_start_detects_crash_during_settle() {
+        let runner = MockProcessRunner::new()
+            .with_crash_after_start()
+            .with_start_result(Ok(Outcome::new("Started")));
         let manager = ExplorerManager::new(runner);
 
-        assert!(manager.start());
+        assert_eq!(
+            manager.start(),
+            Err(StuckbarError::Crashed {
+                process: "explorer.exe".to_string(),
+                pid: 1000,
+            })
+        );
     }
 
     #[test]
-    fn test_start_failure() {
-        let runner =
-            MockProcessRunner::new().with_start_result(ProcessResult::failure("Failed to start"));
+    fn test_start_ignores_instances_that_were_already_running() {
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_start_result(Ok(Outcome::new("Started")));
         let manager = ExplorerManager::new(runner);
 
-        assert!(!manager.start());
+        assert_eq!(manager.start().unwrap().new_pid, Some(1000));
     }
 
-    // ExplorerManager::restart tests
     #[test]
-    fn test_restart_success() {
-        let runner = MockProcessRunner::new()
-            .with_kill_result(ProcessResult::success("Killed"))
-            .with_start_result(ProcessResult::success("Started"));
-        let manager = ExplorerManager::new(runner).with_restart_delay(100);
+    fn test_start_without_settle_window() {
+        let runner = MockProcessRunner::new().with_start_result(Ok(Outcome::new("Started")));
+        let liveness = LivenessPolicy {
+            settle_ms: 0,
+            ..Default::default()
+        };
+        let manager = ExplorerManager::new(runner).with_liveness(liveness);
+
+        assert!(manager.start().is_ok());
+        assert!(manager.runner.get_sleep_calls().is_empty());
+    }
 
-        assert!(manager.restart());
+    // Platform check tests
+    #[test]
+    fn test_is_windows() {
+        // This test verifies the function works, actual result depends on platform
+        let result = is_windows();
+        #[cfg(target_os = "windows")]
+        assert!(result);
+        #[cfg(not(target_os = "windows"))]
+        assert!(!result);
     }
 
     #[test]
-    fn test_restart_kill_fails() {
-        let runner =
-            MockProcessRunner::new().with_kill_result(ProcessResult::failure("Failed to kill"));
-        let manager = ExplorerManager::new(runner);
+    fn test_check_platform() {
+        let result = check_platform();
+        #[cfg(target_os = "windows")]
+        assert!(result.is_ok());
+        #[cfg(not(target_os = "windows"))]
+        {
+            assert!(result.is_err());
+            let err = result.unwrap_err();
+            assert_eq!(err.kind(), "unsupported_platform");
+            assert!(err.to_string().contains("Windows-only"));
+            assert!(err.to_string().contains(std::env::consts::OS));
+        }
+    }
 
-        assert!(!manager.restart());
+    // Session scope tests
+    /// Caller's explorer in session 1 and another user's in session 2
+    fn two_sessions() -> MockProcessRunner {
+        MockProcessRunner::new()
+            .with_instance(100, 1, "S-1-5-21-1")
+            .with_instance(200, 2, "S-1-5-21-2")
+            .with_caller(1, "S-1-5-21-1")
     }
 
     #[test]
-    fn test_restart_start_fails() {
-        let runner = MockProcessRunner::new()
-            .with_kill_result(ProcessResult::success("Killed"))
-            .with_start_result(ProcessResult::failure("Failed to start"));
-        let manager = ExplorerManager::new(runner);
+    fn test_default_scope_is_callers_session() {
+        let manager = ExplorerManager::new(two_sessions());
+
+        let result = manager.kill().unwrap();
 
-        assert!(!manager.restart());
+        assert_eq!(manager.session_scope, SessionScope::Current);
+        assert_eq!(result.killed_pids, vec![100]);
+        assert_eq!(manager.runner.list_pids("explorer.exe").unwrap(), vec![200]);
     }
 
     #[test]
-    fn test_restart_sleeps_between_operations() {
-        let runner = MockProcessRunner::new()
-            .with_kill_result(ProcessResult::success("Killed"))
-            .with_start_result(ProcessResult::success("Started"));
-        let manager = ExplorerManager::new(runner).with_restart_delay(250);
+    fn test_all_scope_kills_every_session() {
+        let runner = two_sessions().with_kill_result(Ok(Outcome::new("Killed")));
+        let manager = ExplorerManager::new(runner).with_session_scope(SessionScope::All);
 
-        manager.restart();
+        let result = manager.kill().unwrap();
 
-        let sleep_calls = &manager.runner.get_sleep_calls();
-        assert_eq!(sleep_calls.len(), 1);
-        assert_eq!(sleep_calls[0], 250);
+        assert_eq!(result.killed_pids, vec![100, 200]);
+        assert_eq!(manager.runner.get_killed(), vec!["explorer.exe"]);
+        assert!(manager.runner.get_killed_pids().is_empty());
     }
 
     #[test]
-    fn test_restart_uses_default_delay() {
-        let runner = MockProcessRunner::new()
-            .with_kill_result(ProcessResult::success("Killed"))
-            .with_start_result(ProcessResult::success("Started"));
-        let manager = ExplorerManager::new(runner);
+    fn test_current_scope_kills_only_callers_instances() {
+        let manager =
+            ExplorerManager::new(two_sessions()).with_session_scope(SessionScope::Current);
 
-        assert_eq!(manager.restart_delay_ms, RESTART_DELAY_MS);
+        let result = manager.kill().unwrap();
+
+        assert_eq!(result.message, "Successfully terminated explorer.exe");
+        assert_eq!(result.killed_pids, vec![100]);
+        assert_eq!(manager.runner.get_killed_pids(), vec![100]);
+        assert!(manager.runner.get_killed().is_empty());
+        assert_eq!(manager.runner.list_pids("explorer.exe").unwrap(), vec![200]);
     }
 
-    // ExplorerManager builder pattern test
     #[test]
-    fn test_explorer_manager_with_restart_delay() {
-        let runner = MockProcessRunner::new();
-        let manager = ExplorerManager::new(runner).with_restart_delay(1000);
-        assert_eq!(manager.restart_delay_ms, 1000);
+    fn test_scope_covering_every_instance_kills_by_name() {
+        let runner = MockProcessRunner::new()
+            .with_instance(100, 1, "S-1-5-21-1")
+            .with_caller(1, "S-1-5-21-1")
+            .with_kill_result(Ok(Outcome::new("Killed")));
+        let manager = ExplorerManager::new(runner).with_session_scope(SessionScope::Current);
+
+        manager.kill().unwrap();
+
+        assert_eq!(manager.runner.get_killed(), vec!["explorer.exe"]);
+        assert!(manager.runner.get_killed_pids().is_empty());
     }
 
-    // Silent method tests
     #[test]
-    fn test_kill_silent_success() {
-        let runner = MockProcessRunner::new().with_kill_result(ProcessResult::success("Killed"));
-        let manager = ExplorerManager::new(runner);
+    fn test_scoped_kill_with_nothing_in_scope() {
+        let manager = ExplorerManager::new(two_sessions()).with_session_scope(SessionScope::Id(3));
+
+        let err = manager.kill().unwrap_err();
 
-        let result = manager.kill_silent();
-        assert!(result.success);
+        assert_eq!(err.kind(), "not_running");
+        assert!(manager.runner.get_killed_pids().is_empty());
+        assert!(manager.runner.get_killed().is_empty());
     }
 
     #[test]
-    fn test_kill_silent_failure() {
-        let runner = MockProcessRunner::new().with_kill_result(ProcessResult::failure("Error"));
-        let manager = ExplorerManager::new(runner);
+    fn test_current_scope_with_unknown_caller_kills_nothing() {
+        let runner = MockProcessRunner::new()
+            .with_instance(100, 1, "S-1-5-21-1")
+            .with_unknown_caller();
+        let manager = ExplorerManager::new(runner).with_session_scope(SessionScope::Current);
 
-        let result = manager.kill_silent();
-        assert!(!result.success);
+        assert_eq!(manager.kill().unwrap_err().kind(), "not_running");
     }
 
     #[test]
-    fn test_start_silent_success() {
-        let runner = MockProcessRunner::new().with_start_result(ProcessResult::success("Started"));
-        let manager = ExplorerManager::new(runner);
+    fn test_graceful_kill_of_other_session_is_forced() {
+        let manager = ExplorerManager::new(two_sessions())
+            .with_session_scope(SessionScope::Id(2))
+            .with_kill_strategy(KillStrategy::graceful());
+
+        let result = manager.kill().unwrap();
 
-        let result = manager.start_silent();
-        assert!(result.success);
+        assert!(manager.runner.get_exit_requests().is_empty());
+        assert_eq!(result.killed_pids, vec![200]);
+        assert_eq!(result.timings.grace_ms, 0);
     }
 
     #[test]
-    fn test_start_silent_failure() {
-        let runner = MockProcessRunner::new().with_start_result(ProcessResult::failure("Error"));
-        let manager = ExplorerManager::new(runner);
-
-        let result = manager.start_silent();
-        assert!(!result.success);
+    fn test_graceful_kill_of_callers_session_requests_exit() {
+        let manager = ExplorerManager::new(two_sessions())
+            .with_session_scope(SessionScope::Current)
+            .with_kill_strategy(KillStrategy::Graceful { grace_ms: 500 });
+
+        let result = manager.kill().unwrap();
+
+        assert_eq!(manager.runner.get_exit_requests(), vec!["explorer.exe"]);
+        assert_eq!(manager.runner.get_killed_pids(), vec![100]);
+        assert_eq!(
+            result.message,
+            "explorer.exe did not exit within 500ms; Successfully terminated explorer.exe"
+        );
     }
 
     #[test]
-    fn test_restart_silent_success() {
+    fn test_graceful_scoped_kill_skips_requests_for_other_targets() {
         let runner = MockProcessRunner::new()
-            .with_kill_result(ProcessResult::success("Killed"))
-            .with_start_result(ProcessResult::success("Started"));
-        let manager = ExplorerManager::new(runner);
+            .with_instance(100, 1, "S-1-5-21-1")
+            .with_instance(200, 2, "S-1-5-21-2")
+            .with_caller(1, "S-1-5-21-1");
+        let manager = ProcessManager::for_target(runner, TargetSpec::new("notepad.exe"))
+            .with_session_scope(SessionScope::Current)
+            .with_kill_strategy(KillStrategy::graceful());
+
+        manager.kill().unwrap();
 
-        let result = manager.restart_silent();
-        assert!(result.success);
-        assert_eq!(result.message, "Explorer.exe restarted successfully");
+        assert!(manager.runner.get_exit_requests().is_empty());
+        assert_eq!(manager.runner.get_killed_pids(), vec![100]);
     }
 
-    #[test]
-    fn test_restart_silent_kill_fails() {
-        let runner =
-            MockProcessRunner::new().with_kill_result(ProcessResult::failure("Kill failed"));
-        let manager = ExplorerManager::new(runner);
+    // Per-PID kill summary tests
+    fn ok(pid: u32) -> PidResult {
+        PidResult {
+            pid,
+            result: Ok(()),
+        }
+    }
 
-        let result = manager.restart_silent();
-        assert!(!result.success);
-        assert_eq!(result.message, "Kill failed");
+    fn denied(pid: u32) -> PidResult {
+        PidResult {
+            pid,
+            result: Err(StuckbarError::AccessDenied {
+                process: "explorer.exe".to_string(),
+                details: format!("PID {}", pid),
+            }),
+        }
     }
 
     #[test]
-    fn test_restart_silent_start_fails() {
-        let runner = MockProcessRunner::new()
-            .with_kill_result(ProcessResult::success("Killed"))
-            .with_start_result(ProcessResult::failure("Start failed"));
-        let manager = ExplorerManager::new(runner);
+    fn test_summarize_kill_all_succeeded() {
+        let outcome = summarize_kill("explorer.exe", vec![ok(1), ok(2)]).unwrap();
 
-        let result = manager.restart_silent();
-        assert!(!result.success);
-        assert_eq!(result.message, "Start failed");
+        assert_eq!(outcome.message, "Successfully terminated explorer.exe");
+        assert_eq!(outcome.killed_pids, vec![1, 2]);
+        assert!(outcome.failures.is_empty());
     }
 
-    // Platform check tests
     #[test]
-    fn test_is_windows() {
-        // This test verifies the function works, actual result depends on platform
-        let result = is_windows();
-        #[cfg(target_os = "windows")]
-        assert!(result);
-        #[cfg(not(target_os = "windows"))]
-        assert!(!result);
+    fn test_summarize_kill_partial_failure() {
+        let outcome = summarize_kill("explorer.exe", vec![ok(1), denied(2)]).unwrap();
+
+        assert_eq!(
+            outcome.message,
+            "Terminated 1 of 2 instances of explorer.exe"
+        );
+        assert_eq!(outcome.killed_pids, vec![1]);
+        assert_eq!(
+            outcome.failures,
+            vec![PidFailure {
+                pid: 2,
+                error: denied(2).result.unwrap_err(),
+            }]
+        );
     }
 
     #[test]
-    fn test_check_platform() {
-        let result = check_platform();
-        #[cfg(target_os = "windows")]
-        assert!(result.is_ok());
-        #[cfg(not(target_os = "windows"))]
-        {
-            assert!(result.is_err());
-            let err = result.unwrap_err();
-            assert!(err.contains("Windows-only"));
-            assert!(err.contains(std::env::consts::OS));
-        }
+    fn test_summarize_kill_all_failed() {
+        let err = summarize_kill("explorer.exe", vec![denied(1), denied(2)]).unwrap_err();
+        assert_eq!(err, denied(1).result.unwrap_err());
+    }
+
+    #[test]
+    fn test_summarize_kill_nothing_running() {
+        let err = summarize_kill("explorer.exe", Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), "not_running");
     }
 }
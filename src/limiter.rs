@@ -0,0 +1,495 @@
+//! # Restart Limits
+//!
+//! A [`RestartLimiter`] keeps a caller, typically an agent stuck in a loop, from
+//! restarting the shell over and over. Before a restart runs, the limiter checks
+//! its [`RestartPolicy`]:
+//!
+//! - at most [`max_restarts`](RestartPolicy::max_restarts) restarts per
+//!   [`window_ms`](RestartPolicy::window_ms)
+//! - at least [`min_interval_ms`](RestartPolicy::min_interval_ms) between two restarts
+//! - a circuit breaker that opens after
+//!   [`breaker_threshold`](RestartPolicy::breaker_threshold) consecutive restarts
+//!   whose new instance crashed, and stays open for
+//!   [`breaker_cooldown_ms`](RestartPolicy::breaker_cooldown_ms)
+//!
+//! A restart counts as crashed when its new instance exits during the settle
+//! window, or when it is gone by the time the next restart is requested within
+//! [`crash_window_ms`](RestartPolicy::crash_window_ms). Refused restarts fail with
+//! [`StuckbarError::RateLimited`].
+//!
+//! [`RestartLimiter::for_target`] keeps its state in a file in the temporary
+//! directory, so every stuckbar process (the CLI, a watchdog, an MCP server)
+//! counts the same restarts. Each change reads and rewrites that file while
+//! holding a lock file next to it, and the new state is renamed into place, so
+//! no process reads a half-written state or overwrites another one's change.
+
+use crate::coordinator::open_lock_file;
+use crate::{Outcome, StuckbarError, TargetSpec};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Default maximum number of restarts per window
+pub const MAX_RESTARTS: u32 = 5;
+
+/// Default length in milliseconds of the window restarts are counted in
+pub const RESTART_WINDOW_MS: u64 = 10 * 60 * 1000;
+
+/// Default minimum time in milliseconds between two restarts
+pub const MIN_RESTART_INTERVAL_MS: u64 = 5_000;
+
+/// Default time in milliseconds a restarted instance must survive to not count as crashed
+pub const CRASH_WINDOW_MS: u64 = 30_000;
+
+/// Default number of consecutive crashed restarts that opens the circuit breaker
+pub const BREAKER_THRESHOLD: u32 = 3;
+
+/// Default time in milliseconds the circuit breaker stays open
+pub const BREAKER_COOLDOWN_MS: u64 = 5 * 60 * 1000;
+
+/// Limits applied to restarts
+///
+/// A zero count or interval disables the corresponding limit.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RestartPolicy {
+    /// Maximum number of restarts within any [`window_ms`](Self::window_ms)
+    pub max_restarts: u32,
+    pub window_ms: u64,
+    /// Minimum time between two restarts
+    pub min_interval_ms: u64,
+    /// Time a restarted instance must survive to not count as crashed
+    pub crash_window_ms: u64,
+    /// Consecutive crashed restarts that open the circuit breaker
+    pub breaker_threshold: u32,
+    /// Time the circuit breaker stays open before one more restart is allowed
+    pub breaker_cooldown_ms: u64,
+}
+
+impl RestartPolicy {
+    /// Policy that never refuses a restart
+    pub fn unlimited() -> Self {
+        Self {
+            max_restarts: 0,
+            window_ms: 0,
+            min_interval_ms: 0,
+            crash_window_ms: 0,
+            breaker_threshold: 0,
+            breaker_cooldown_ms: 0,
+        }
+    }
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: MAX_RESTARTS,
+            window_ms: RESTART_WINDOW_MS,
+            min_interval_ms: MIN_RESTART_INTERVAL_MS,
+            crash_window_ms: CRASH_WINDOW_MS,
+            breaker_threshold: BREAKER_THRESHOLD,
+            breaker_cooldown_ms: BREAKER_COOLDOWN_MS,
+        }
+    }
+}
+
+/// Enforces a [`RestartPolicy`] across every manager it is shared with
+pub struct RestartLimiter {
+    policy: RestartPolicy,
+    state_path: Option<PathBuf>,
+    state: Mutex<LimiterState>,
+}
+
+/// What the limiter remembers, in milliseconds since the Unix epoch
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+struct LimiterState {
+    /// Start times of the restarts within the window, oldest first
+    restarts: VecDeque<u64>,
+    /// Consecutive restarts whose new instance crashed
+    crashes: u32,
+    /// The circuit breaker refuses restarts until then
+    open_until: Option<u64>,
+    /// Most recent successful restart, checked for a crash on the next request
+    last: Option<LastRestart>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+struct LastRestart {
+    at: u64,
+    pid: u32,
+}
+
+impl RestartLimiter {
+    /// Create a limiter that only counts restarts within this process
+    pub fn new(policy: RestartPolicy) -> Self {
+        Self {
+            policy,
+            state_path: None,
+            state: Mutex::new(LimiterState::default()),
+        }
+    }
+
+    /// Also keep the state in the file at `path`
+    ///
+    /// Limiters sharing a state file count each other's restarts. A file that
+    /// cannot be read or written is ignored and the state is kept in memory.
+    pub fn with_state_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.state_path = Some(path.into());
+        self
+    }
+
+    /// Limiter for `target` whose state file lives in the temporary directory
+    pub fn for_target(target: &TargetSpec, policy: RestartPolicy) -> Self {
+        let name = target.process_name.to_ascii_lowercase();
+        let path = std::env::temp_dir().join(format!("stuckbar-{}-restarts.json", name));
+        Self::new(policy).with_state_file(path)
+    }
+
+    pub fn policy(&self) -> RestartPolicy {
+        self.policy
+    }
+
+    /// Ask to start a restart at `now`
+    ///
+    /// `running` lists the instances of the target that are running now; it tells
+    /// whether the previous restart crashed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StuckbarError::RateLimited`] if the policy refuses the restart.
+    pub fn acquire(&self, now: SystemTime, running: &[u32]) -> Result<(), StuckbarError> {
+        let now = unix_ms(now);
+        let policy = self.policy;
+        self.update(|state| {
+            if let Some(last) = state.last.take() {
+                let survived = now.saturating_sub(last.at) >= policy.crash_window_ms
+                    || running.contains(&last.pid);
+                if survived {
+                    state.crashes = 0;
+                } else {
+                    state.crashed(now, &policy);
+                }
+            }
+
+            if let Some(until) = state.open_until {
+                if now < until {
+                    return Err(StuckbarError::RateLimited {
+                        reason: format!(
+                            "circuit breaker is open after {} crashed restarts",
+                            state.crashes
+                        ),
+                        retry_after_ms: until - now,
+                    });
+                }
+                // Half-open: one more restart may try, and reopens it if it crashes
+                state.open_until = None;
+            }
+
+            // The newest restart is kept for the interval check even outside the window
+            let keep_ms = policy.window_ms.max(policy.min_interval_ms);
+            while state
+                .restarts
+                .front()
+                .is_some_and(|at| now.saturating_sub(*at) >= keep_ms)
+            {
+                state.restarts.pop_front();
+            }
+
+            if let Some(last) = state.restarts.back() {
+                let since_last = now.saturating_sub(*last);
+                if since_last < policy.min_interval_ms {
+                    return Err(StuckbarError::RateLimited {
+                        reason: format!(
+                            "restarts must be at least {}ms apart",
+                            policy.min_interval_ms
+                        ),
+                        retry_after_ms: policy.min_interval_ms - since_last,
+                    });
+                }
+            }
+
+            let in_window: Vec<u64> = state
+                .restarts
+                .iter()
+                .copied()
+                .filter(|at| now.saturating_sub(*at) < policy.window_ms)
+                .collect();
+            if policy.max_restarts > 0 && in_window.len() >= policy.max_restarts as usize {
+                let oldest = in_window[0];
+                return Err(StuckbarError::RateLimited {
+                    reason: format!(
+                        "at most {} restarts are allowed per {}ms",
+                        policy.max_restarts, policy.window_ms
+                    ),
+                    retry_after_ms: (oldest + policy.window_ms).saturating_sub(now),
+                });
+            }
+
+            state.restarts.push_back(now);
+            Ok(())
+        })
+    }
+
+    /// Record the result of a restart admitted by [`acquire`](Self::acquire)
+    pub fn record(&self, now: SystemTime, result: &Result<Outcome, StuckbarError>) {
+        let now = unix_ms(now);
+        let policy = self.policy;
+        self.update(|state| match result {
+            Ok(outcome) => {
+                state.last = outcome.new_pid.map(|pid| LastRestart { at: now, pid });
+            }
+            Err(StuckbarError::Crashed { .. }) => state.crashed(now, &policy),
+            Err(_) => {}
+        });
+    }
+
+    /// Whether the circuit breaker refuses restarts at `now`
+    pub fn is_open(&self, now: SystemTime) -> bool {
+        let now = unix_ms(now);
+        self.update(|state| state.open_until.is_some_and(|until| now < until))
+    }
+
+    /// Apply `change` to the latest state and save it
+    fn update<T>(&self, change: impl FnOnce(&mut LimiterState) -> T) -> T {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let _lock = self.lock_state_file();
+        if let Some(saved) = self.load() {
+            *state = saved;
+        }
+        let result = change(&mut state);
+        self.save(&state);
+        result
+    }
+
+    fn load(&self) -> Option<LimiterState> {
+        let json = std::fs::read_to_string(self.state_path.as_ref()?).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    /// Wait for the lock file that guards the state file, if there is one
+    ///
+    /// The lock is released when the returned file is dropped.
+    fn lock_state_file(&self) -> Option<File> {
+        let path = self.state_path.as_ref()?.with_extension("lock");
+        let file = open_lock_file(&path).ok()?;
+        file.lock().ok()?;
+        Some(file)
+    }
+
+    fn save(&self, state: &LimiterState) {
+        // Limits keep working in memory when the file cannot be written
+        let Some(path) = &self.state_path else {
+            return;
+        };
+        let Ok(json) = serde_json::to_string(state) else {
+            return;
+        };
+        // Readers see either the old state or the new one, never a partial write
+        let temp = path.with_extension(format!("{}.tmp", std::process::id()));
+        if std::fs::write(&temp, json).is_err() || std::fs::rename(&temp, path).is_err() {
+            let _ = std::fs::remove_file(&temp);
+        }
+    }
+}
+
+impl LimiterState {
+    fn crashed(&mut self, now: u64, policy: &RestartPolicy) {
+        self.crashes += 1;
+        if policy.breaker_threshold > 0 && self.crashes >= policy.breaker_threshold {
+            self.open_until = Some(now + policy.breaker_cooldown_ms);
+        }
+    }
+}
+
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(ms: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(1_000_000 + ms)
+    }
+
+    fn crashed() -> Result<Outcome, StuckbarError> {
+        Err(StuckbarError::Crashed {
+            process: "explorer.exe".to_string(),
+            pid: 7,
+        })
+    }
+
+    fn retry_after(result: Result<(), StuckbarError>) -> u64 {
+        match result {
+            Err(StuckbarError::RateLimited { retry_after_ms, .. }) => retry_after_ms,
+            other => panic!("expected rate limit, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_min_interval_between_restarts() {
+        let limiter = RestartLimiter::new(RestartPolicy {
+            min_interval_ms: 5_000,
+            ..RestartPolicy::unlimited()
+        });
+
+        limiter.acquire(at(0), &[]).unwrap();
+
+        assert_eq!(retry_after(limiter.acquire(at(1_000), &[])), 4_000);
+        assert!(limiter.acquire(at(5_000), &[]).is_ok());
+    }
+
+    #[test]
+    fn test_max_restarts_per_window() {
+        let limiter = RestartLimiter::new(RestartPolicy {
+            max_restarts: 2,
+            window_ms: 60_000,
+            ..RestartPolicy::unlimited()
+        });
+
+        limiter.acquire(at(0), &[]).unwrap();
+        limiter.acquire(at(10_000), &[]).unwrap();
+
+        assert_eq!(retry_after(limiter.acquire(at(20_000), &[])), 40_000);
+        assert!(limiter.acquire(at(60_000), &[]).is_ok());
+    }
+
+    #[test]
+    fn test_breaker_opens_after_crashed_restarts() {
+        let limiter = RestartLimiter::new(RestartPolicy {
+            breaker_threshold: 2,
+            breaker_cooldown_ms: 60_000,
+            ..RestartPolicy::unlimited()
+        });
+
+        limiter.acquire(at(0), &[]).unwrap();
+        limiter.record(at(1_000), &crashed());
+        limiter.acquire(at(2_000), &[]).unwrap();
+        limiter.record(at(3_000), &crashed());
+
+        assert!(limiter.is_open(at(3_000)));
+        assert_eq!(retry_after(limiter.acquire(at(4_000), &[])), 59_000);
+    }
+
+    #[test]
+    fn test_breaker_half_opens_after_cooldown() {
+        let limiter = RestartLimiter::new(RestartPolicy {
+            breaker_threshold: 1,
+            breaker_cooldown_ms: 60_000,
+            ..RestartPolicy::unlimited()
+        });
+        limiter.acquire(at(0), &[]).unwrap();
+        limiter.record(at(0), &crashed());
+
+        limiter.acquire(at(60_000), &[]).unwrap();
+        limiter.record(at(61_000), &crashed());
+
+        assert!(limiter.is_open(at(61_000)));
+    }
+
+    #[test]
+    fn test_vanished_instance_counts_as_crash() {
+        let limiter = RestartLimiter::new(RestartPolicy {
+            crash_window_ms: 30_000,
+            breaker_threshold: 2,
+            breaker_cooldown_ms: 60_000,
+            ..RestartPolicy::unlimited()
+        });
+
+        limiter.acquire(at(0), &[]).unwrap();
+        limiter.record(at(1_000), &Ok(Outcome::new("restarted").with_new_pid(7)));
+        limiter.acquire(at(10_000), &[]).unwrap();
+        limiter.record(at(11_000), &Ok(Outcome::new("restarted").with_new_pid(8)));
+
+        assert_eq!(
+            limiter.acquire(at(20_000), &[]).unwrap_err().kind(),
+            "rate_limited"
+        );
+    }
+
+    #[test]
+    fn test_surviving_instance_resets_crashes() {
+        let limiter = RestartLimiter::new(RestartPolicy {
+            crash_window_ms: 30_000,
+            breaker_threshold: 2,
+            ..RestartPolicy::unlimited()
+        });
+
+        limiter.acquire(at(0), &[]).unwrap();
+        limiter.record(at(0), &crashed());
+        limiter.acquire(at(1_000), &[]).unwrap();
+        limiter.record(at(2_000), &Ok(Outcome::new("restarted").with_new_pid(7)));
+        limiter.acquire(at(3_000), &[7]).unwrap();
+        limiter.record(at(4_000), &crashed());
+
+        assert!(!limiter.is_open(at(4_000)));
+    }
+
+    #[test]
+    fn test_state_file_is_shared() {
+        let path = std::env::temp_dir().join(format!(
+            "stuckbar-test-{}-{:?}-restarts.json",
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        let policy = RestartPolicy {
+            min_interval_ms: 5_000,
+            ..RestartPolicy::unlimited()
+        };
+        let first = RestartLimiter::new(policy).with_state_file(&path);
+        let second = RestartLimiter::new(policy).with_state_file(&path);
+
+        first.acquire(at(0), &[]).unwrap();
+
+        assert_eq!(retry_after(second.acquire(at(1_000), &[])), 4_000);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_concurrent_updates_are_not_lost() {
+        let path = std::env::temp_dir().join(format!(
+            "stuckbar-test-{}-concurrent-restarts.json",
+            std::process::id()
+        ));
+        let policy = RestartPolicy {
+            max_restarts: 1_000,
+            window_ms: 60_000,
+            ..RestartPolicy::unlimited()
+        };
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let limiter = RestartLimiter::new(policy).with_state_file(&path);
+                std::thread::spawn(move || {
+                    for _ in 0..25 {
+                        limiter.acquire(at(0), &[]).unwrap();
+                    }
+                })
+            })
+            .collect();
+        threads
+            .into_iter()
+            .for_each(|thread| thread.join().unwrap());
+
+        let saved = RestartLimiter::new(policy).with_state_file(&path).load();
+        assert_eq!(saved.unwrap().restarts.len(), 100);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("lock"));
+    }
+
+    #[test]
+    fn test_unlimited_policy_never_refuses() {
+        let limiter = RestartLimiter::new(RestartPolicy::unlimited());
+
+        for _ in 0..100 {
+            limiter.acquire(at(0), &[]).unwrap();
+            limiter.record(at(0), &crashed());
+        }
+    }
+}
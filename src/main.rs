@@ -1,8 +1,37 @@
-#[cfg(feature = "mcp")]
-use clap::Args;
-use clap::{Parser, Subcommand};
+use clap::builder::PossibleValuesParser;
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use colored::Colorize;
-use stuckbar::{ExplorerManager, SystemProcessRunner, check_platform};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+use stuckbar::config::{
+    self, Config, HistorySettings, Layer, LayeredConfig, LogSettings, WatchSettings,
+};
+use stuckbar::coordinator::OperationCoordinator;
+use stuckbar::dry_run::{DryRunPlan, DryRunReport, DryRunRunner, DryRunSteps};
+use stuckbar::events::Action;
+use stuckbar::history::{HistoryEntry, HistorySink, Since, Trigger, format_timestamp};
+use stuckbar::inspect::{PowerShellInspector, ProcessDetails, ShellStatus};
+use stuckbar::limiter::{
+    MAX_RESTARTS, MIN_RESTART_INTERVAL_MS, RESTART_WINDOW_MS, RestartLimiter, RestartPolicy,
+};
+use stuckbar::profile::{Profile, ProfileOutcome, SystemStepRunner};
+use stuckbar::report::{JsonSink, TextSink};
+use stuckbar::scope::{ScopedProcess, SessionScope};
+use stuckbar::target::PRESET_NAMES;
+use stuckbar::watchdog::{
+    CHECK_INTERVAL_MS, COOLDOWN_MS, CPU_THRESHOLD_PERCENT, CpuPeggedProbe, FAILURE_THRESHOLD,
+    MAX_RESTARTS_PER_HOUR, MEMORY_GROWTH_MB, MemoryGrowthProbe, ProcessPresentProbe,
+    SuppressReason, Watchdog, WatchdogEvent,
+};
+use stuckbar::window::{RESPONSE_TIMEOUT_MS, SystemWindowInspector, TaskbarProbe};
+use stuckbar::{
+    BACKEND_NAMES, Backend, BoxedRunner, GRACE_PERIOD_MS, ProcessManager, ProcessRunner,
+    StuckbarError, TargetSpec, check_platform,
+};
 
 #[derive(Parser)]
 #[command(
@@ -10,27 +39,261 @@ use stuckbar::{ExplorerManager, SystemProcessRunner, check_platform};
     about = "A CLI tool for restarting Windows Explorer when the taskbar gets stuck",
     long_about = "A CLI tool for restarting Windows Explorer when the taskbar gets stuck.\n\n\
                   This tool is Windows-only and provides commands to kill, start, or restart \
-                  explorer.exe. It also supports running as an MCP (Model Context Protocol) \
+                  explorer.exe or another shell component chosen with --target. It also supports running as an MCP (Model Context Protocol) \
                   server for AI agent integration.",
     version,
     author
 )]
 struct Cli {
+    /// Configuration file [default: %APPDATA%\stuckbar\config.toml]
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Shell component to act on
+    #[arg(
+        long,
+        global = true,
+        default_value = "explorer",
+        value_parser = PossibleValuesParser::new(PRESET_NAMES)
+    )]
+    target: String,
+
+    /// How processes are found and terminated
+    #[arg(
+        long,
+        global = true,
+        default_value = Backend::default().name(),
+        value_parser = PossibleValuesParser::new(BACKEND_NAMES)
+    )]
+    backend: String,
+
+    /// Ask the process to exit cleanly before force-terminating it
+    #[arg(long, global = true, conflicts_with = "force")]
+    graceful: bool,
+
+    /// Terminate the process immediately (default)
+    #[arg(long, global = true)]
+    force: bool,
+
+    /// Seconds to wait for a graceful exit before forcing it (only used with the
+    /// graceful kill strategy)
+    #[arg(long, global = true, default_value_t = GRACE_PERIOD_MS / 1000)]
+    grace_period: u64,
+
+    /// Output format
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// Maximum number of restarts within the restart window (0 for no limit)
+    #[arg(long, global = true, default_value_t = MAX_RESTARTS)]
+    max_restarts: u32,
+
+    /// Seconds in which --max-restarts restarts are counted
+    #[arg(long, global = true, default_value_t = RESTART_WINDOW_MS / 1000)]
+    restart_window: u64,
+
+    /// Minimum seconds between two restarts
+    #[arg(long, global = true, default_value_t = MIN_RESTART_INTERVAL_MS / 1000)]
+    min_restart_interval: u64,
+
+    /// Do not limit how often restarts may run
+    #[arg(long, global = true)]
+    no_restart_limit: bool,
+
+    /// Show what kill, start or restart would do without doing it
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Sessions whose instances are killed: a session ID, current or all
+    #[arg(
+        long,
+        global = true,
+        default_value = "current",
+        value_name = "ID|current|all"
+    )]
+    session: String,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
 #[derive(Subcommand, Debug, Clone, PartialEq)]
 pub enum Commands {
-    /// Terminate explorer.exe process
+    /// Terminate the target process (see --target; explorer.exe by default)
     Kill,
-    /// Start explorer.exe process
+    /// Start the target process
     Start,
-    /// Restart explorer.exe (kill then start)
-    Restart,
+    /// Restart the target process (kill then start)
+    Restart(RestartArgs),
+    /// Watch the target process and restart it automatically when it gets stuck
+    Watch(WatchArgs),
+    /// Check whether the taskbar is responding and show the shell processes
+    Status,
+    /// List the running instances of the target with their session and owner
+    List,
+    /// Show the kills, starts and restarts recorded in the history
+    History(HistoryArgs),
+    /// Show, check or create the configuration file
+    Config(ConfigArgs),
     /// Start an MCP server for AI agent integration
     #[cfg(feature = "mcp")]
-    Serve(ServeArgs),
+    Serve(Box<ServeArgs>),
+}
+
+impl Commands {
+    /// Whether the command kills, starts or restarts a process
+    fn is_operation(&self) -> bool {
+        matches!(self, Self::Kill | Self::Start | Self::Restart(_))
+    }
+}
+
+/// Arguments for the restart command
+#[derive(Args, Debug, Clone, Default, PartialEq)]
+pub struct RestartArgs {
+    /// Reopen the File Explorer windows that were open before the restart
+    #[arg(long)]
+    pub restore_windows: bool,
+
+    /// Delete explorer's icon and thumbnail caches while it is down, so they are rebuilt
+    #[arg(long)]
+    pub rebuild_icon_cache: bool,
+
+    /// Run a restart profile instead (built-in: taskbar, full-shell, start-menu)
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+}
+
+/// Arguments for the history command
+#[derive(Args, Debug, Clone, Default, PartialEq)]
+pub struct HistoryArgs {
+    /// Only show operations since a duration ago (30m, 12h, 7d, 2w) or a date
+    /// (2026-10-01, UTC)
+    #[arg(long, value_name = "WHEN")]
+    pub since: Option<String>,
+
+    /// Print JSON, as --output json does
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Arguments for the config command
+#[derive(Args, Debug, Clone, PartialEq)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+/// Subcommands of the config command
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+pub enum ConfigCommand {
+    /// Print the effective configuration and where each value comes from
+    Show,
+    /// Check the configuration file, environment and flags for errors
+    Validate,
+    /// Write a configuration file holding the default values
+    Init(InitArgs),
+}
+
+/// Arguments for `config init`
+#[derive(Args, Debug, Clone, Default, PartialEq)]
+pub struct InitArgs {
+    /// Replace an existing configuration file
+    #[arg(long)]
+    pub overwrite: bool,
+}
+
+/// Arguments for the watch command
+#[derive(Args, Debug, Clone, PartialEq)]
+pub struct WatchArgs {
+    /// Seconds between health checks
+    #[arg(long, default_value_t = CHECK_INTERVAL_MS / 1000)]
+    pub interval: u64,
+
+    /// Consecutive failed checks before restarting
+    #[arg(long, default_value_t = FAILURE_THRESHOLD)]
+    pub threshold: u32,
+
+    /// Minimum seconds between restarts (grows while restarts keep being needed)
+    #[arg(long, default_value_t = COOLDOWN_MS / 1000)]
+    pub cooldown: u64,
+
+    /// Maximum number of restarts within one hour
+    #[arg(long, default_value_t = MAX_RESTARTS_PER_HOUR)]
+    pub max_restarts_per_hour: u32,
+
+    /// Health probes to run
+    #[arg(long = "probe", value_enum, value_delimiter = ',', default_values_t = [ProbeKind::Process])]
+    pub probes: Vec<ProbeKind>,
+
+    /// CPU usage, in percent of one core, considered pegged (cpu probe)
+    #[arg(long, default_value_t = CPU_THRESHOLD_PERCENT)]
+    pub cpu_threshold: u64,
+
+    /// Working set growth in MB considered a leak (memory probe)
+    #[arg(long, default_value_t = MEMORY_GROWTH_MB)]
+    pub memory_growth_mb: u64,
+}
+
+impl WatchArgs {
+    /// Add the flags given on the command line to `layer`
+    fn overrides(&self, matches: &ArgMatches, layer: &mut Layer) {
+        let given = |id: &str| given(matches, id);
+        if given("interval") {
+            layer.set("watch.interval_ms", secs_to_ms(self.interval));
+        }
+        if given("threshold") {
+            layer.set("watch.threshold", self.threshold);
+        }
+        if given("cooldown") {
+            layer.set("watch.cooldown_ms", secs_to_ms(self.cooldown));
+        }
+        if given("max_restarts_per_hour") {
+            layer.set("watch.max_restarts_per_hour", self.max_restarts_per_hour);
+        }
+        if given("probes") {
+            let names: Vec<&str> = self.probes.iter().map(ProbeKind::name).collect();
+            layer.set("watch.probes", names);
+        }
+        if given("cpu_threshold") {
+            layer.set("watch.cpu_threshold", self.cpu_threshold);
+        }
+        if given("memory_growth_mb") {
+            layer.set("watch.memory_growth_mb", self.memory_growth_mb);
+        }
+    }
+}
+
+/// How command results are printed
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Colored, human-readable text
+    Text,
+    /// One JSON document per operation or event, one per line
+    Json,
+}
+
+/// Health probes available to the watch command
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeKind {
+    /// The process is running
+    Process,
+    /// The process is not pegging a CPU core
+    Cpu,
+    /// The process working set is not growing without bound
+    Memory,
+    /// The taskbar window is responding to messages
+    Window,
+}
+
+impl ProbeKind {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Process => "process",
+            Self::Cpu => "cpu",
+            Self::Memory => "memory",
+            Self::Window => "window",
+        }
+    }
 }
 
 /// Arguments for the serve command
@@ -48,77 +311,1148 @@ pub struct ServeArgs {
 
     /// Host address to bind to (only used with --http)
     #[cfg(feature = "mcp-http")]
-    #[arg(long, default_value = "127.0.0.1", requires = "http")]
+    #[arg(long, default_value = "127.0.0.1")]
     pub host: String,
 
     /// Port number to listen on (only used with --http)
     #[cfg(feature = "mcp-http")]
-    #[arg(long, default_value = "8080", requires = "http")]
+    #[arg(long, default_value = "8080")]
     pub port: u16,
+
+    /// HTTP transports to serve from the port: streamable-http at --mcp-path,
+    /// the legacy sse at --sse-path, or both (only used with --http)
+    #[cfg(feature = "mcp-http")]
+    #[arg(
+        long = "transport",
+        value_delimiter = ',',
+        default_values = config::HTTP_TRANSPORT_NAMES,
+        value_parser = PossibleValuesParser::new(config::HTTP_TRANSPORT_NAMES)
+    )]
+    pub transports: Vec<String>,
+
+    /// Serve without tokens on an address other machines can reach (only used
+    /// with --http)
+    #[cfg(feature = "mcp-http")]
+    #[arg(long)]
+    pub insecure: bool,
+
+    /// Prefix of every HTTP path, e.g. /stuckbar behind a reverse proxy (only
+    /// used with --http)
+    #[cfg(feature = "mcp-http")]
+    #[arg(long, value_name = "PATH")]
+    pub base_path: Option<String>,
+
+    /// Path of the Streamable HTTP endpoint [default: /mcp]
+    #[cfg(feature = "mcp-http")]
+    #[arg(long, value_name = "PATH")]
+    pub mcp_path: Option<String>,
+
+    /// Path of the SSE event stream [default: /sse]
+    #[cfg(feature = "mcp-http")]
+    #[arg(long, value_name = "PATH")]
+    pub sse_path: Option<String>,
+
+    /// Path SSE clients POST requests to [default: /message]
+    #[cfg(feature = "mcp-http")]
+    #[arg(long, value_name = "PATH")]
+    pub post_path: Option<String>,
+
+    /// Seconds between keep-alive pings on event streams [default: 15]
+    #[cfg(feature = "mcp-http")]
+    #[arg(long, value_name = "SECS")]
+    pub sse_keep_alive: Option<u64>,
+
+    /// Origins browsers may call the server from, or * for any (only used with
+    /// --http)
+    #[cfg(feature = "mcp-http")]
+    #[arg(long = "cors-origin", value_name = "ORIGIN", value_delimiter = ',')]
+    pub cors_origins: Vec<String>,
+
+    /// Largest request body accepted [default: 1048576]
+    #[cfg(feature = "mcp-http")]
+    #[arg(long, value_name = "BYTES")]
+    pub max_body_bytes: Option<u64>,
+
+    /// Sessions served at once; 0 is unlimited, otherwise needs
+    /// --session-timeout [default: 0]
+    #[cfg(feature = "mcp-http")]
+    #[arg(long, value_name = "COUNT")]
+    pub max_sessions: Option<u32>,
+
+    /// Seconds after which an idle Streamable HTTP session is closed; 0 never
+    /// closes it [default: 0]
+    #[cfg(feature = "mcp-http")]
+    #[arg(long, value_name = "SECS")]
+    pub session_timeout: Option<u64>,
+
+    /// PEM certificate chain to serve TLS with (only used with --http)
+    #[cfg(feature = "mcp-tls")]
+    #[arg(long, value_name = "PEM")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key of --tls-cert
+    #[cfg(feature = "mcp-tls")]
+    #[arg(long, value_name = "PEM")]
+    pub tls_key: Option<PathBuf>,
+
+    /// Generate a self-signed certificate on first run, at --tls-cert and
+    /// --tls-key or in %LOCALAPPDATA%\stuckbar\tls (only used with --http)
+    #[cfg(feature = "mcp-tls")]
+    #[arg(long)]
+    pub tls_self_signed: bool,
+}
+
+#[cfg(feature = "mcp")]
+impl ServeArgs {
+    /// Add the flags given on the command line to `layer`
+    fn overrides(&self, matches: &ArgMatches, layer: &mut Layer) {
+        if self.stdio {
+            layer.set("mcp.transport", "stdio");
+        }
+        #[cfg(feature = "mcp-http")]
+        {
+            if self.http {
+                layer.set("mcp.transport", "http");
+            }
+            if given(matches, "host") {
+                layer.set("mcp.host", self.host.as_str());
+            }
+            if given(matches, "port") {
+                layer.set("mcp.port", self.port);
+            }
+            if given(matches, "transports") {
+                layer.set("mcp.http_transports", self.transports.clone());
+            }
+            if self.insecure {
+                layer.set("mcp.insecure", true);
+            }
+            let paths = [
+                ("mcp.base_path", &self.base_path),
+                ("mcp.streamable_path", &self.mcp_path),
+                ("mcp.sse_path", &self.sse_path),
+                ("mcp.post_path", &self.post_path),
+            ];
+            for (key, path) in paths {
+                if let Some(path) = path {
+                    layer.set(key, path.as_str());
+                }
+            }
+            if let Some(keep_alive) = self.sse_keep_alive {
+                layer.set("mcp.sse_keep_alive_ms", secs_to_ms(keep_alive));
+            }
+            if !self.cors_origins.is_empty() {
+                layer.set("mcp.cors_origins", self.cors_origins.clone());
+            }
+            if let Some(max_body_bytes) = self.max_body_bytes {
+                layer.set("mcp.max_body_bytes", max_body_bytes);
+            }
+            if let Some(max_sessions) = self.max_sessions {
+                layer.set("mcp.max_sessions", max_sessions);
+            }
+            if let Some(timeout) = self.session_timeout {
+                layer.set("mcp.session_timeout_ms", secs_to_ms(timeout));
+            }
+        }
+        #[cfg(feature = "mcp-tls")]
+        {
+            if let Some(cert) = &self.tls_cert {
+                layer.set("mcp.tls.cert", cert.display().to_string());
+            }
+            if let Some(key) = &self.tls_key {
+                layer.set("mcp.tls.key", key.display().to_string());
+            }
+            if self.tls_self_signed {
+                layer.set("mcp.tls.self_signed", true);
+            }
+        }
+        #[cfg(not(feature = "mcp-http"))]
+        let _ = matches;
+    }
+}
+
+/// Milliseconds in `secs` seconds, capped instead of overflowing
+fn secs_to_ms(secs: u64) -> u64 {
+    secs.saturating_mul(1000)
+}
+
+/// Bytes in `mb` megabytes, capped instead of overflowing
+fn mb_to_bytes(mb: u64) -> u64 {
+    mb.saturating_mul(1024 * 1024)
+}
+
+/// Whether the argument `id` was given on the command line rather than defaulted
+fn given(matches: &ArgMatches, id: &str) -> bool {
+    matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+impl Cli {
+    /// Settings given explicitly on the command line, as a configuration layer
+    ///
+    /// Flags left at their default do not override the configuration file.
+    fn overrides(&self, matches: &ArgMatches) -> Layer {
+        let mut layer = Layer::new();
+        if given(matches, "target") {
+            layer.set("restart.target", self.target.as_str());
+        }
+        if given(matches, "backend") {
+            layer.set("restart.backend", self.backend.as_str());
+        }
+        if self.graceful {
+            layer.set("restart.kill_strategy", "graceful");
+        }
+        if self.force {
+            layer.set("restart.kill_strategy", "force");
+        }
+        if given(matches, "grace_period") {
+            layer.set("restart.grace_period_ms", secs_to_ms(self.grace_period));
+        }
+        if given(matches, "session") {
+            layer.set("restart.session", self.session.as_str());
+        }
+
+        match (&self.command, matches.subcommand()) {
+            (Some(Commands::Watch(args)), Some((_, matches))) => {
+                args.overrides(matches, &mut layer)
+            }
+            #[cfg(feature = "mcp")]
+            (Some(Commands::Serve(args)), Some((_, matches))) => {
+                args.overrides(matches, &mut layer)
+            }
+            _ => {}
+        }
+        layer
+    }
+
+    /// Reject flag combinations that clap cannot express
+    ///
+    /// A dry run of the watchdog would still have to act on what it observes.
+    fn check(&self) -> Result<(), clap::Error> {
+        if self.dry_run && matches!(self.command, Some(Commands::Watch(_))) {
+            return Err(Cli::command().error(
+                ErrorKind::ArgumentConflict,
+                "--dry-run cannot be used with watch",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Restart limits selected by the rate limit flags
+    fn restart_policy(&self) -> RestartPolicy {
+        if self.no_restart_limit {
+            return RestartPolicy::unlimited();
+        }
+        RestartPolicy {
+            max_restarts: self.max_restarts,
+            window_ms: secs_to_ms(self.restart_window),
+            min_interval_ms: secs_to_ms(self.min_restart_interval),
+            ..RestartPolicy::default()
+        }
+    }
+}
+
+/// Present the manager's events in the requested output format
+///
+/// The watchdog reports its own events, so only one-shot commands use this.
+fn with_output_sink(
+    manager: ProcessManager<BoxedRunner>,
+    output: OutputFormat,
+) -> ProcessManager<BoxedRunner> {
+    match output {
+        OutputFormat::Text => manager.with_sink(TextSink),
+        OutputFormat::Json => manager.with_sink(JsonSink::stdout()),
+    }
+}
+
+/// Also append the manager's operations to the configured log file
+fn with_log_sink(
+    manager: ProcessManager<BoxedRunner>,
+    log: &LogSettings,
+) -> Result<ProcessManager<BoxedRunner>, StuckbarError> {
+    let Some(path) = &log.file else {
+        return Ok(manager);
+    };
+    let sink = JsonSink::append(path).map_err(|e| StuckbarError::InvalidConfig {
+        details: format!("cannot open log file {}: {}", path.display(), e),
+    })?;
+    Ok(manager.with_sink(sink))
+}
+
+/// Print an error that no manager has reported
+fn report_error(error: &StuckbarError, output: OutputFormat) {
+    match output {
+        OutputFormat::Text => eprintln!("{}", error.to_string().red().bold()),
+        OutputFormat::Json => print_json(&serde_json::json!({
+            "success": false,
+            "error": error,
+        })),
+    }
+}
+
+/// Sink recording operations asked for by `trigger` in the history, unless
+/// recording is disabled
+fn history_sink(settings: &HistorySettings, trigger: Trigger) -> Option<HistorySink> {
+    if !settings.enabled {
+        return None;
+    }
+    settings.log().map(|log| HistorySink::new(log, trigger))
+}
+
+/// Manager for `target` with the configured backend and session scope, enough
+/// to look at the running instances
+fn inspecting_manager(target: TargetSpec, settings: &Config) -> ProcessManager<BoxedRunner> {
+    // Names were validated when the configuration was loaded, so the lookup cannot fail
+    let backend = Backend::from_name(&settings.restart.backend).unwrap_or_default();
+    ProcessManager::for_target(backend.runner(), target)
+        .with_session_scope(settings.restart.session_scope())
+}
+
+/// Manager for `target` with the configured backend, delay, kill strategy, session
+/// scope, restart limits and log file, recording its operations with `history`
+fn configured_manager(
+    target: TargetSpec,
+    settings: &Config,
+    policy: RestartPolicy,
+    history: Option<HistorySink>,
+) -> Result<ProcessManager<BoxedRunner>, StuckbarError> {
+    let coordinator = OperationCoordinator::shared(&target);
+    let limiter = Arc::new(RestartLimiter::for_target(&target, policy));
+    let manager = inspecting_manager(target, settings)
+        .with_restart_delay(settings.restart.delay_ms)
+        .with_kill_strategy(settings.restart.strategy())
+        .with_coordinator(coordinator)
+        .with_restart_limiter(limiter);
+    let manager = match history {
+        Some(sink) => manager.with_sink(sink),
+        None => manager,
+    };
+    with_log_sink(manager, &settings.log)
 }
 
-/// Execute the CLI command
-fn run_command(command: Option<Commands>) -> bool {
-    let manager = ExplorerManager::new(SystemProcessRunner);
+/// Execute the CLI command with the effective configuration
+fn run_command(cli: Cli, config: &LayeredConfig) -> Result<(), StuckbarError> {
+    let settings = config.config();
+    // Names were validated when the configuration was loaded, so the lookup cannot fail
+    let target = TargetSpec::preset(&settings.restart.target).unwrap_or_default();
+    let output = cli.output;
+    let policy = cli.restart_policy();
+
+    if cli.dry_run && cli.command.as_ref().is_none_or(Commands::is_operation) {
+        return run_dry_run(cli.command.as_ref(), target, settings, output);
+    }
+
+    if let Some(Commands::Restart(args)) = &cli.command
+        && let Some(name) = &args.profile
+    {
+        return run_profile(name, args, settings, policy, output);
+    }
+
+    let trigger = match cli.command {
+        Some(Commands::Watch(_)) => Trigger::Watchdog,
+        _ => Trigger::Cli,
+    };
+    // Only the commands that act on the target open the log file and limiter
+    let manager = || {
+        let history = history_sink(&settings.history, trigger);
+        configured_manager(target.clone(), settings, policy, history)
+            .inspect_err(|e| report_error(e, output))
+    };
 
-    match command {
-        Some(Commands::Kill) => manager.kill(),
-        Some(Commands::Start) => manager.start(),
-        Some(Commands::Restart) => manager.restart(),
+    match cli.command {
+        Some(Commands::Kill) => with_output_sink(manager()?, output).kill().map(|_| ()),
+        Some(Commands::Start) => with_output_sink(manager()?, output).start().map(|_| ()),
+        Some(Commands::Restart(args)) => with_output_sink(manager()?, output)
+            .with_restore_windows(args.restore_windows)
+            .with_rebuild_icon_cache(args.rebuild_icon_cache)
+            .restart()
+            .map(|_| ()),
+        Some(Commands::Watch(_)) => {
+            run_watch(manager()?, &settings.watch, output);
+            Ok(())
+        }
+        Some(Commands::Status) => {
+            let status = ShellStatus::collect(
+                &SystemWindowInspector,
+                &PowerShellInspector,
+                RESPONSE_TIMEOUT_MS,
+            );
+            print_status(&status, output)
+        }
+        Some(Commands::List) => print_instances(&inspecting_manager(target, settings), output)
+            .inspect_err(|e| report_error(e, output)),
+        Some(Commands::History(args)) => {
+            let output = if args.json {
+                OutputFormat::Json
+            } else {
+                output
+            };
+            print_history(&settings.history, args.since.as_deref(), output)
+                .inspect_err(|e| report_error(e, output))
+        }
+        Some(Commands::Config(args)) => {
+            match args.command {
+                ConfigCommand::Show => print_config(config, output),
+                ConfigCommand::Validate => print_valid(config, output),
+                // Handled before the configuration is loaded
+                ConfigCommand::Init(_) => {}
+            }
+            Ok(())
+        }
         #[cfg(feature = "mcp")]
-        Some(Commands::Serve(args)) => {
-            run_mcp_server(args);
-            true
+        Some(Commands::Serve(_)) => {
+            let backend = Backend::from_name(&settings.restart.backend).unwrap_or_default();
+            let server = stuckbar::mcp::StuckbarMcpServer::with_backend(backend)
+                .with_restart_policy(policy)
+                .with_restart_delay(settings.restart.delay_ms)
+                .with_profiles(&settings.profiles)
+                .with_dry_run(cli.dry_run)
+                .with_session_scope(settings.restart.session_scope())
+                .with_effective_config(config)
+                .with_allowed_tools(&settings.mcp.allowed_tools)
+                .inspect_err(|e| report_error(e, output))?;
+            let server = match &settings.log.file {
+                Some(path) => server.with_log_file(path),
+                None => server,
+            };
+            let server = match settings.history.log() {
+                Some(log) => server
+                    .with_history(log)
+                    .with_history_recording(settings.history.enabled),
+                None => server,
+            };
+            run_mcp_server(&settings.mcp, server).inspect_err(|e| report_error(e, output))
+        }
+        None => with_output_sink(manager()?, output).restart().map(|_| ()),
+    }
+}
+
+/// Run the profile called `name` and print its outcome
+fn run_profile(
+    name: &str,
+    args: &RestartArgs,
+    settings: &Config,
+    policy: RestartPolicy,
+    output: OutputFormat,
+) -> Result<(), StuckbarError> {
+    let profile = find_profile(name, args, settings).inspect_err(|e| report_error(e, output))?;
+
+    // Failed operations are reported by the managers' sinks
+    let history = history_sink(&settings.history, Trigger::Cli).map(|sink| sink.with_profile(name));
+    let outcome = profile.run(
+        name,
+        |target| {
+            configured_manager(target, settings, policy, history.clone())
+                .map(|manager| with_output_sink(manager, output))
+                .inspect_err(|e| report_error(e, output))
+        },
+        &SystemStepRunner,
+    )?;
+    print_profile(&outcome, output);
+    Ok(())
+}
+
+/// The profile called `name`, with the additions requested by `args`
+///
+/// `--restore-windows` and `--rebuild-icon-cache` add to what the profile does.
+fn find_profile(
+    name: &str,
+    args: &RestartArgs,
+    settings: &Config,
+) -> Result<Profile, StuckbarError> {
+    let mut profile = settings.profile(name).ok_or_else(|| {
+        let names: Vec<String> = settings.profiles().into_keys().collect();
+        StuckbarError::InvalidConfig {
+            details: format!(
+                "unknown profile '{}', expected one of: {}",
+                name,
+                names.join(", ")
+            ),
+        }
+    })?;
+    profile.restore_windows |= args.restore_windows;
+    profile.rebuild_icon_cache |= args.rebuild_icon_cache;
+    Ok(profile)
+}
+
+/// Simulate `command` and print what it would do
+///
+/// Nothing is terminated, launched, removed or logged, and the restart limits
+/// neither apply nor count the simulated restart.
+fn run_dry_run(
+    command: Option<&Commands>,
+    target: TargetSpec,
+    settings: &Config,
+    output: OutputFormat,
+) -> Result<(), StuckbarError> {
+    let plan = DryRunPlan::new();
+    let manager = |target| dry_run_manager(target, settings, &plan);
+
+    let (operation, result) = match command {
+        Some(Commands::Kill) => ("kill".to_string(), manager(target).kill().map(|_| ())),
+        Some(Commands::Start) => ("start".to_string(), manager(target).start().map(|_| ())),
+        Some(Commands::Restart(
+            args @ RestartArgs {
+                profile: Some(name),
+                ..
+            },
+        )) => {
+            let profile =
+                find_profile(name, args, settings).inspect_err(|e| report_error(e, output))?;
+            let steps = DryRunSteps::new(SystemStepRunner, plan.clone());
+            let result = profile.run(name, |target| Ok(manager(target)), &steps);
+            (format!("profile {}", name), result.map(|_| ()))
+        }
+        Some(Commands::Restart(args)) => (
+            "restart".to_string(),
+            manager(target)
+                .with_restore_windows(args.restore_windows)
+                .with_rebuild_icon_cache(args.rebuild_icon_cache)
+                .restart()
+                .map(|_| ()),
+        ),
+        _ => ("restart".to_string(), manager(target).restart().map(|_| ())),
+    };
+
+    let report = DryRunReport::new(operation, &plan, result.clone());
+    print_dry_run(&report, output);
+    result
+}
+
+/// Manager for `target` with the configured backend, delay, kill strategy and
+/// session scope that records what it would do in `plan`
+fn dry_run_manager(
+    target: TargetSpec,
+    settings: &Config,
+    plan: &DryRunPlan,
+) -> ProcessManager<DryRunRunner<BoxedRunner>> {
+    // Names were validated when the configuration was loaded, so the lookup cannot fail
+    let backend = Backend::from_name(&settings.restart.backend).unwrap_or_default();
+    ProcessManager::for_target(backend.runner(), target)
+        .with_restart_delay(settings.restart.delay_ms)
+        .with_kill_strategy(settings.restart.strategy())
+        .with_session_scope(settings.restart.session_scope())
+        .dry_run(plan)
+}
+
+/// JSON document printed after a dry run
+#[derive(Serialize)]
+struct DryRunJson<'a> {
+    action: &'static str,
+    #[serde(flatten)]
+    report: &'a DryRunReport,
+}
+
+/// Print the steps of a dry run and whether the operation would succeed
+fn print_dry_run(report: &DryRunReport, output: OutputFormat) {
+    if output == OutputFormat::Json {
+        return print_json(&DryRunJson {
+            action: "dry_run",
+            report,
+        });
+    }
+    println!("{}", format!("Dry run: {}", report.operation).cyan().bold());
+    for (number, step) in report.steps.iter().enumerate() {
+        println!("  {}. {}", number + 1, step.describe());
+    }
+    if report.success {
+        println!("{}", report.message.green().bold());
+    } else {
+        eprintln!("{}", report.message.red().bold());
+    }
+}
+
+/// JSON document printed once a profile has run
+#[derive(Serialize)]
+struct ProfileReport<'a> {
+    action: &'static str,
+    #[serde(flatten)]
+    outcome: &'a ProfileOutcome,
+}
+
+/// Print the post-steps and summary of a profile
+fn print_profile(outcome: &ProfileOutcome, output: OutputFormat) {
+    if output == OutputFormat::Json {
+        return print_json(&ProfileReport {
+            action: "profile",
+            outcome,
+        });
+    }
+    for step in &outcome.steps {
+        if step.success {
+            println!("{}", step.message.green());
+        } else {
+            println!("{}", step.message.yellow());
         }
-        None => manager.restart(),
     }
+    println!("{}", outcome.message.green().bold());
+}
+
+/// JSON document printed by the status command
+#[derive(Serialize)]
+struct StatusReport<'a> {
+    action: &'static str,
+    #[serde(flatten)]
+    status: &'a ShellStatus,
 }
 
-/// Run the MCP server with the specified transport
+/// Print the taskbar health and a table of the shell processes
+///
+/// # Errors
+///
+/// Returns [`StuckbarError::Unhealthy`] if the taskbar is hung or missing, once
+/// the status has been printed, so scripts can check the exit code.
+fn print_status(status: &ShellStatus, output: OutputFormat) -> Result<(), StuckbarError> {
+    let health = &status.taskbar;
+    if output == OutputFormat::Json {
+        print_json(&StatusReport {
+            action: "status",
+            status,
+        });
+        return health.ensure_healthy();
+    }
+
+    if health.is_healthy() {
+        println!("{}", health.describe().green());
+    } else {
+        println!("{}", health.describe().red());
+    }
+    match &status.processes_error {
+        Some(error) => println!(
+            "{}",
+            format!("Shell processes could not be inspected: {}", error).yellow()
+        ),
+        None => {
+            println!();
+            for line in status_table(&status.processes) {
+                println!("{}", line);
+            }
+        }
+    }
+    health.ensure_healthy()
+}
+
+/// Lines of the process table printed by the status command
+fn status_table(processes: &[ProcessDetails]) -> Vec<String> {
+    let unknown = || "?".to_string();
+    let mut lines = vec![format!(
+        "{:<28} {:>7} {:>7}  {:<19} {:>8} {:>11} {:>7} {:>8}  RESPONDING",
+        "PROCESS", "PID", "SESSION", "STARTED", "UPTIME", "WORKING SET", "HANDLES", "CPU TIME"
+    )];
+    for details in processes {
+        lines.push(format!(
+            "{:<28} {:>7} {:>7}  {:<19} {:>8} {:>11} {:>7} {:>8}  {}",
+            details.process,
+            details.pid,
+            details.session_id.map_or_else(unknown, |id| id.to_string()),
+            details
+                .started_at
+                .as_deref()
+                .map_or_else(unknown, describe_start_time),
+            details.uptime_ms.map_or_else(unknown, describe_duration),
+            details
+                .working_set_bytes
+                .map_or_else(unknown, describe_bytes),
+            details.handle_count.map_or_else(unknown, |n| n.to_string()),
+            details.cpu_time_ms.map_or_else(unknown, describe_duration),
+            match details.responding {
+                Some(true) => "yes",
+                Some(false) => "no",
+                None => "?",
+            }
+        ));
+    }
+    if processes.is_empty() {
+        lines.push("No shell processes are running".to_string());
+    }
+    lines
+}
+
+/// "2026-10-16 09:12:03" for an ISO 8601 timestamp
+fn describe_start_time(timestamp: &str) -> String {
+    timestamp
+        .get(..19)
+        .unwrap_or(timestamp)
+        .replacen('T', " ", 1)
+}
+
+/// Largest two units of a duration, such as "2h 14m" or "52s"
+fn describe_duration(ms: u64) -> String {
+    let seconds = ms / 1000;
+    let (days, hours, minutes) = (seconds / 86_400, seconds / 3600 % 24, seconds / 60 % 60);
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{}s", seconds),
+        (0, 0, _) => format!("{}m {}s", minutes, seconds % 60),
+        (0, _, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h", days, hours),
+    }
+}
+
+/// Size in MB with one decimal, such as "142.3 MB"
+fn describe_bytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// JSON document printed by the list command
+#[derive(Serialize)]
+struct ListReport<'a> {
+    action: &'static str,
+    process: &'a str,
+    session: SessionScope,
+    instances: &'a [ScopedProcess],
+}
+
+/// Print the instances of the manager's target and which of them it would kill
+fn print_instances(
+    manager: &ProcessManager<BoxedRunner>,
+    output: OutputFormat,
+) -> Result<(), StuckbarError> {
+    let process = &manager.target.process_name;
+    let scope = manager.session_scope;
+    let caller = manager.runner.current_process();
+    let instances = scope.classify(manager.runner.list_processes(process)?, caller.as_ref());
+
+    if output == OutputFormat::Json {
+        print_json(&ListReport {
+            action: "list",
+            process,
+            session: scope,
+            instances: &instances,
+        });
+        return Ok(());
+    }
+    if instances.is_empty() {
+        println!("{}", format!("{} is not running", process).yellow());
+        return Ok(());
+    }
+    println!("{:>8}  {:>7}  OWNER", "PID", "SESSION");
+    for ScopedProcess { process, in_scope } in &instances {
+        let unknown = || "?".to_string();
+        let line = format!(
+            "{:>8}  {:>7}  {}",
+            process.pid,
+            process.session_id.map_or_else(unknown, |id| id.to_string()),
+            process.owner.clone().unwrap_or_else(unknown)
+        );
+        if *in_scope {
+            println!("{}", line.green());
+        } else {
+            println!("{}", line.dimmed());
+        }
+    }
+    let in_scope = instances
+        .iter()
+        .filter(|instance| instance.in_scope)
+        .count();
+    println!(
+        "{} instance{} of {}, {} in session scope '{}'",
+        instances.len(),
+        if instances.len() == 1 { "" } else { "s" },
+        process,
+        in_scope,
+        scope
+    );
+    Ok(())
+}
+
+/// JSON document printed by the history command
+#[derive(Serialize)]
+struct HistoryReport<'a> {
+    action: &'static str,
+    file: &'a Path,
+    /// Start of the period shown, in milliseconds since the Unix epoch
+    since_ms: Option<u64>,
+    entries: &'a [HistoryEntry],
+}
+
+/// Print the recorded operations, since `since` if given
+fn print_history(
+    settings: &HistorySettings,
+    since: Option<&str>,
+    output: OutputFormat,
+) -> Result<(), StuckbarError> {
+    let log = settings.log().ok_or_else(|| StuckbarError::InvalidConfig {
+        details: "no history file; set history.file or LOCALAPPDATA".to_string(),
+    })?;
+    let since_ms = since
+        .map(str::parse::<Since>)
+        .transpose()?
+        .map(|since| since.start_ms(SystemTime::now()));
+    let entries = match since_ms {
+        Some(start_ms) => log.since(start_ms),
+        None => log.read(),
+    }
+    .map_err(|e| StuckbarError::InvalidConfig {
+        details: format!("cannot read history file {}: {}", log.path().display(), e),
+    })?;
+
+    if output == OutputFormat::Json {
+        print_json(&HistoryReport {
+            action: "history",
+            file: log.path(),
+            since_ms,
+            entries: &entries,
+        });
+        return Ok(());
+    }
+    if entries.is_empty() {
+        println!("{}", "No operations recorded".yellow());
+        return Ok(());
+    }
+    let lines = history_table(&entries);
+    println!("{}", lines[0]);
+    for (line, entry) in lines[1..].iter().zip(&entries) {
+        if entry.success {
+            println!("{}", line);
+        } else {
+            println!("{}", line.red());
+        }
+    }
+    println!("{}", history_summary(&entries));
+    Ok(())
+}
+
+/// Lines of the table printed by the history command, header first
+fn history_table(entries: &[HistoryEntry]) -> Vec<String> {
+    let mut lines = vec![format!(
+        "{:<23}  {:<8}  {:<7}  {:<28}  {:<16}  {:>8}  PROFILE",
+        "TIME", "TRIGGER", "ACTION", "TARGET", "RESULT", "DURATION"
+    )];
+    for entry in entries {
+        lines.push(format!(
+            "{:<23}  {:<8}  {:<7}  {:<28}  {:<16}  {:>8}  {}",
+            format_timestamp(entry.timestamp_ms),
+            entry.trigger.to_string(),
+            entry.action.name(),
+            entry.target,
+            entry.error_kind.as_deref().unwrap_or("ok"),
+            describe_duration(entry.timings.total_ms),
+            entry.profile.as_deref().unwrap_or("-"),
+        ));
+    }
+    lines
+}
+
+/// "12 operations (9 restarts), 2 failed"
+fn history_summary(entries: &[HistoryEntry]) -> String {
+    let restarts = entries
+        .iter()
+        .filter(|entry| entry.action == Action::Restart)
+        .count();
+    let failed = entries.iter().filter(|entry| !entry.success).count();
+    format!(
+        "{} operation{} ({} restart{}), {} failed",
+        entries.len(),
+        if entries.len() == 1 { "" } else { "s" },
+        restarts,
+        if restarts == 1 { "" } else { "s" },
+        failed
+    )
+}
+
+/// JSON document printed by `config show`
+#[derive(Serialize)]
+struct ConfigReport<'a> {
+    action: &'static str,
+    file: Option<&'a Path>,
+    settings: Vec<config::ConfigEntry>,
+}
+
+/// Print every effective setting with its source
+fn print_config(config: &LayeredConfig, output: OutputFormat) {
+    if output == OutputFormat::Json {
+        return print_json(&ConfigReport {
+            action: "config_show",
+            file: config.file(),
+            settings: config.entries(),
+        });
+    }
+
+    match config.file() {
+        Some(path) => println!(
+            "{}",
+            format!("# Configuration file: {}", path.display()).cyan()
+        ),
+        None => println!("{}", "# No configuration file".cyan()),
+    }
+    for entry in config.entries() {
+        println!(
+            "{} = {}  {}",
+            entry.key,
+            entry.value,
+            format!("# {}", entry.source).dimmed()
+        );
+    }
+}
+
+/// Report that the configuration loaded without errors
+fn print_valid(config: &LayeredConfig, output: OutputFormat) {
+    match output {
+        OutputFormat::Json => print_json(&serde_json::json!({
+            "action": "config_validate",
+            "valid": true,
+            "file": config.file(),
+        })),
+        OutputFormat::Text => match config.file() {
+            Some(path) => println!(
+                "{}",
+                format!("Configuration is valid ({})", path.display()).green()
+            ),
+            None => println!(
+                "{}",
+                "Configuration is valid (no configuration file)".green()
+            ),
+        },
+    }
+}
+
+/// Write a configuration file holding the default values
+fn init_config(
+    path: Option<&Path>,
+    args: &InitArgs,
+    output: OutputFormat,
+) -> Result<(), StuckbarError> {
+    let path = config::resolve_path(path).ok_or_else(|| StuckbarError::InvalidConfig {
+        details: "no configuration path; pass --config or set APPDATA".to_string(),
+    })?;
+    config::init(&path, args.overwrite)?;
+    match output {
+        OutputFormat::Json => print_json(&serde_json::json!({
+            "action": "config_init",
+            "file": path,
+        })),
+        OutputFormat::Text => println!(
+            "{}",
+            format!("Wrote default configuration to {}", path.display()).green()
+        ),
+    }
+    Ok(())
+}
+
+/// Print a value as a single line of JSON
+fn print_json(value: &impl Serialize) {
+    match serde_json::to_string(value) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("{}", format!("Failed to serialize output: {}", e).red()),
+    }
+}
+
+/// Run the watchdog loop until the process is interrupted
+fn run_watch(manager: ProcessManager<BoxedRunner>, settings: &WatchSettings, output: OutputFormat) {
+    let process_name = manager.target.process_name.clone();
+    let mut watchdog = Watchdog::new(manager, settings.watchdog_config());
+    // Probe names were validated when the configuration was loaded
+    for probe in settings
+        .probes
+        .iter()
+        .filter_map(|name| ProbeKind::from_str(name, false).ok())
+    {
+        watchdog = match probe {
+            ProbeKind::Process => watchdog.with_probe(ProcessPresentProbe),
+            ProbeKind::Cpu => watchdog.with_probe(CpuPeggedProbe::new(settings.cpu_threshold)),
+            ProbeKind::Memory => watchdog.with_probe(MemoryGrowthProbe::new(mb_to_bytes(
+                settings.memory_growth_mb,
+            ))),
+            ProbeKind::Window => watchdog.with_probe(TaskbarProbe::new(SystemWindowInspector)),
+        };
+    }
+
+    if output == OutputFormat::Text {
+        println!(
+            "{}",
+            format!(
+                "Watching {} every {}s (press Ctrl+C to stop)...",
+                process_name,
+                settings.interval_ms / 1000
+            )
+            .cyan()
+            .bold()
+        );
+    }
+
+    watchdog.run(|event| {
+        match output {
+            OutputFormat::Text => print_watch_event(event, settings.threshold),
+            OutputFormat::Json => print_json(event),
+        }
+        true
+    });
+}
+
+/// Print a watchdog event; healthy checks are silent
+fn print_watch_event(event: &WatchdogEvent, threshold: u32) {
+    match event {
+        WatchdogEvent::Healthy => {}
+        WatchdogEvent::Unhealthy { failures, reasons } => println!(
+            "{}",
+            format!(
+                "Health check failed ({}/{}): {}",
+                failures,
+                threshold,
+                reasons.join("; ")
+            )
+            .yellow()
+        ),
+        WatchdogEvent::Suppressed { reason, .. } => {
+            let why = match reason {
+                SuppressReason::Cooldown { remaining_ms } => {
+                    format!("cooldown, {}s remaining", remaining_ms.div_ceil(1000))
+                }
+                SuppressReason::HourlyLimit { retry_after_ms } => format!(
+                    "hourly restart limit reached, retry in {}s",
+                    retry_after_ms.div_ceil(1000)
+                ),
+            };
+            println!(
+                "{}",
+                format!("Restart due but suppressed: {}", why).yellow()
+            );
+        }
+        WatchdogEvent::Restarted { outcome, reasons } => {
+            println!("{}", format!("Unhealthy: {}", reasons.join("; ")).yellow());
+            println!("{}", outcome.message.green().bold());
+        }
+        WatchdogEvent::RestartFailed { error, reasons } => {
+            println!("{}", format!("Unhealthy: {}", reasons.join("; ")).yellow());
+            eprintln!("{}", format!("Restart failed: {}", error).red());
+        }
+    }
+}
+
+/// Run the MCP server with the configured transport
 #[cfg(feature = "mcp")]
-#[allow(unused_variables)]
-fn run_mcp_server(args: ServeArgs) {
+fn run_mcp_server(
+    settings: &config::McpSettings,
+    server: stuckbar::mcp::StuckbarMcpServer,
+) -> Result<(), StuckbarError> {
     use tokio::runtime::Runtime;
 
+    let http = settings.transport == config::Transport::Http;
+    if http && !cfg!(feature = "mcp-http") {
+        return Err(StuckbarError::InvalidConfig {
+            details: "mcp.transport = \"http\" requires the mcp-http feature".to_string(),
+        });
+    }
+
+    let tls = settings.tls.files();
+    if http && settings.tls.self_signed && tls.is_none() {
+        return Err(StuckbarError::InvalidConfig {
+            details:
+                "mcp.tls.self_signed: LOCALAPPDATA is not set; set mcp.tls.cert and mcp.tls.key"
+                    .to_string(),
+        });
+    }
+    if http && tls.is_some() && !cfg!(feature = "mcp-tls") {
+        return Err(StuckbarError::InvalidConfig {
+            details: "mcp.tls requires the mcp-tls feature".to_string(),
+        });
+    }
+    #[cfg(feature = "mcp-http")]
+    let config = stuckbar::mcp::HttpServerConfig {
+        host: settings.host.clone(),
+        port: settings.port,
+        transports: settings.http_transports.clone(),
+        auth: stuckbar::auth::HttpAuth::new(settings.tokens.clone()),
+        base_path: settings.base_path.clone(),
+        streamable_path: settings.streamable_path.clone(),
+        sse_path: settings.sse_path.clone(),
+        post_path: settings.post_path.clone(),
+        sse_keep_alive: std::time::Duration::from_millis(settings.sse_keep_alive_ms),
+        cors_origins: settings.cors_origins.clone(),
+        max_body_bytes: usize::try_from(settings.max_body_bytes).unwrap_or(usize::MAX),
+        max_sessions: (settings.max_sessions > 0).then_some(settings.max_sessions as usize),
+        session_timeout: (settings.session_timeout_ms > 0)
+            .then(|| std::time::Duration::from_millis(settings.session_timeout_ms)),
+        #[cfg(feature = "mcp-tls")]
+        tls: tls.map(|(cert, key)| stuckbar::tls::TlsFiles {
+            cert,
+            key,
+            self_signed: settings.tls.self_signed,
+        }),
+    };
+    #[cfg(feature = "mcp-http")]
+    if http {
+        stuckbar::auth::check_exposure(&config.host, &config.auth, settings.insecure)?;
+        for token in config.auth.tokens() {
+            server.check_tools("mcp.tokens", token.tools().unwrap_or_default())?;
+        }
+    }
+
     let rt = Runtime::new().expect("Failed to create Tokio runtime");
 
     rt.block_on(async {
         #[cfg(feature = "mcp-http")]
-        if args.http {
-            if let Err(e) = stuckbar::mcp::run_http_server(&args.host, args.port).await {
+        if http {
+            if let Err(e) = stuckbar::mcp::run_http_server(config, server).await {
                 eprintln!("{} {}", "MCP HTTP server error:".red(), e);
                 std::process::exit(1);
             }
             return;
         }
 
-        // Default to STDIO if no transport specified or --stdio flag used
-        if let Err(e) = stuckbar::mcp::run_stdio_server().await {
+        if let Err(e) = stuckbar::mcp::run_stdio_server(server).await {
             eprintln!("{} {}", "MCP STDIO server error:".red(), e);
             std::process::exit(1);
         }
     });
+    Ok(())
 }
 
 fn main() {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    cli.check().unwrap_or_else(|e| e.exit());
+    let output = cli.output;
+
     // Check platform before doing anything
     if let Err(e) = check_platform() {
-        eprintln!("{}", e.red().bold());
-        std::process::exit(1);
+        report_error(&e, output);
+        std::process::exit(e.exit_code());
     }
 
-    let cli = Cli::parse();
+    // A broken configuration file must not keep it from being recreated
+    if let Some(Commands::Config(ConfigArgs {
+        command: ConfigCommand::Init(args),
+    })) = &cli.command
+    {
+        if let Err(e) = init_config(cli.config.as_deref(), args, output) {
+            report_error(&e, output);
+            std::process::exit(e.exit_code());
+        }
+        return;
+    }
 
-    let success = run_command(cli.command);
+    let config = match config::load(cli.config.as_deref(), cli.overrides(&matches)) {
+        Ok(config) => config,
+        Err(e) => {
+            report_error(&e, output);
+            std::process::exit(e.exit_code());
+        }
+    };
 
-    if !success {
-        std::process::exit(1);
+    // Errors are already reported; only the exit code is left to set
+    if let Err(e) = run_command(cli, &config) {
+        std::process::exit(e.exit_code());
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use stuckbar::config::{Config, Source};
+    use stuckbar::window::TaskbarHealth;
+    use stuckbar::{KillStrategy, ProcessInfo};
+
+    /// Configuration made of the defaults and the flags in `args`
+    fn effective(args: &[&str]) -> LayeredConfig {
+        let matches = Cli::command().try_get_matches_from(args).unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+        LayeredConfig::new()
+            .merge(cli.overrides(&matches), Source::Cli)
+            .unwrap()
+    }
+
+    fn kill_strategy(args: &[&str]) -> KillStrategy {
+        effective(args).config().restart.strategy()
+    }
 
     // CLI parsing tests
     #[test]
@@ -142,7 +1476,527 @@ mod tests {
     #[test]
     fn test_cli_parse_restart() {
         let cli = Cli::parse_from(["stuckbar", "restart"]);
-        assert_eq!(cli.command, Some(Commands::Restart));
+        assert_eq!(cli.command, Some(Commands::Restart(RestartArgs::default())));
+    }
+
+    #[test]
+    fn test_cli_parse_restart_restore_windows() {
+        let cli = Cli::parse_from(["stuckbar", "restart", "--restore-windows"]);
+        assert_eq!(
+            cli.command,
+            Some(Commands::Restart(RestartArgs {
+                restore_windows: true,
+                ..RestartArgs::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn test_cli_parse_restart_profile() {
+        let cli = Cli::parse_from(["stuckbar", "restart", "--profile", "full-shell"]);
+        assert_eq!(
+            cli.command,
+            Some(Commands::Restart(RestartArgs {
+                profile: Some("full-shell".to_string()),
+                ..RestartArgs::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn test_cli_parse_restart_rebuild_icon_cache() {
+        let cli = Cli::parse_from(["stuckbar", "restart", "--rebuild-icon-cache"]);
+        assert_eq!(
+            cli.command,
+            Some(Commands::Restart(RestartArgs {
+                rebuild_icon_cache: true,
+                ..RestartArgs::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn test_cli_profile_only_for_restart() {
+        let result = Cli::try_parse_from(["stuckbar", "kill", "--profile", "taskbar"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cli_restore_windows_only_for_restart() {
+        let result = Cli::try_parse_from(["stuckbar", "kill", "--restore-windows"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cli_default_target() {
+        let cli = Cli::parse_from(["stuckbar"]);
+        assert_eq!(cli.target, "explorer");
+    }
+
+    #[test]
+    fn test_cli_parse_target() {
+        let cli = Cli::parse_from(["stuckbar", "restart", "--target", "start-menu"]);
+        assert_eq!(cli.command, Some(Commands::Restart(RestartArgs::default())));
+        assert_eq!(cli.target, "start-menu");
+    }
+
+    #[test]
+    fn test_cli_invalid_target() {
+        let result = Cli::try_parse_from(["stuckbar", "--target", "notepad"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cli_default_backend() {
+        let cli = Cli::parse_from(["stuckbar"]);
+        assert_eq!(cli.backend, Backend::default().name());
+    }
+
+    #[test]
+    fn test_cli_parse_backend() {
+        let cli = Cli::parse_from(["stuckbar", "kill", "--backend", "taskkill"]);
+        assert_eq!(cli.command, Some(Commands::Kill));
+        assert_eq!(cli.backend, "taskkill");
+    }
+
+    #[test]
+    fn test_cli_invalid_backend() {
+        let result = Cli::try_parse_from(["stuckbar", "--backend", "wmic"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cli_default_kill_strategy_is_force() {
+        assert_eq!(kill_strategy(&["stuckbar", "restart"]), KillStrategy::Force);
+    }
+
+    #[test]
+    fn test_cli_parse_graceful() {
+        assert_eq!(
+            kill_strategy(&["stuckbar", "restart", "--graceful"]),
+            KillStrategy::Graceful {
+                grace_ms: GRACE_PERIOD_MS
+            }
+        );
+    }
+
+    #[test]
+    fn test_cli_parse_grace_period() {
+        assert_eq!(
+            kill_strategy(&["stuckbar", "kill", "--graceful", "--grace-period", "10"]),
+            KillStrategy::Graceful { grace_ms: 10_000 }
+        );
+    }
+
+    #[test]
+    fn test_cli_defaults_do_not_override_config() {
+        let config = effective(&["stuckbar", "watch"]);
+
+        assert_eq!(config.config(), &Config::default());
+        assert!(config.entries().iter().all(|e| e.source == Source::Default));
+    }
+
+    #[test]
+    fn test_cli_flags_override_config() {
+        let config = effective(&[
+            "stuckbar",
+            "--target",
+            "search",
+            "watch",
+            "--interval",
+            "10",
+            "--probe",
+            "cpu,window",
+        ]);
+
+        let settings = config.config();
+        assert_eq!(settings.restart.target, "search");
+        assert_eq!(settings.watch.interval_ms, 10_000);
+        assert_eq!(settings.watch.probes, vec!["cpu", "window"]);
+        assert_eq!(settings.watch.threshold, FAILURE_THRESHOLD);
+        assert_eq!(config.source("watch.interval_ms"), &Source::Cli);
+        assert_eq!(config.source("watch.threshold"), &Source::Default);
+    }
+
+    #[test]
+    fn test_cli_force_overrides_graceful_config() {
+        let mut file = Layer::new();
+        file.set("restart.kill_strategy", "graceful");
+        let matches = Cli::command()
+            .try_get_matches_from(["stuckbar", "kill", "--force"])
+            .unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+
+        let config = LayeredConfig::new()
+            .merge(file, Source::File("config.toml".into()))
+            .unwrap()
+            .merge(cli.overrides(&matches), Source::Cli)
+            .unwrap();
+
+        assert_eq!(config.config().restart.strategy(), KillStrategy::Force);
+    }
+
+    #[test]
+    fn test_cli_parse_config_commands() {
+        let cli = Cli::parse_from(["stuckbar", "config", "show"]);
+        assert_eq!(
+            cli.command,
+            Some(Commands::Config(ConfigArgs {
+                command: ConfigCommand::Show
+            }))
+        );
+
+        let cli = Cli::parse_from([
+            "stuckbar",
+            "--config",
+            "C:\\stuckbar.toml",
+            "config",
+            "init",
+            "--overwrite",
+        ]);
+        assert_eq!(cli.config, Some(PathBuf::from("C:\\stuckbar.toml")));
+        assert_eq!(
+            cli.command,
+            Some(Commands::Config(ConfigArgs {
+                command: ConfigCommand::Init(InitArgs { overwrite: true })
+            }))
+        );
+    }
+
+    #[test]
+    fn test_cli_graceful_conflicts_with_force() {
+        let result = Cli::try_parse_from(["stuckbar", "kill", "--graceful", "--force"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cli_grace_period_requires_graceful() {
+        let config = effective(&["stuckbar", "kill", "--grace-period", "10"]);
+        assert!(config.validate().is_err());
+
+        let config = effective(&["stuckbar", "kill", "--graceful", "--grace-period", "10"]);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_cli_grace_period_does_not_overflow() {
+        let max = u64::MAX.to_string();
+        let config = effective(&["stuckbar", "kill", "--graceful", "--grace-period", &max]);
+        assert_eq!(config.config().restart.grace_period_ms, u64::MAX);
+    }
+
+    #[test]
+    fn test_only_operations_open_the_log_file() {
+        let cli = |args: &[&str]| {
+            let matches = Cli::command().try_get_matches_from(args).unwrap();
+            Cli::from_arg_matches(&matches).unwrap()
+        };
+        let mut env = Layer::new();
+        let log = std::env::temp_dir().join("stuckbar-missing-dir/stuckbar.log");
+        env.set("log.file", log.display().to_string());
+        let config = LayeredConfig::new()
+            .merge(env, Source::Env("STUCKBAR_LOG_FILE".to_string()))
+            .unwrap();
+
+        assert!(run_command(cli(&["stuckbar", "config", "validate"]), &config).is_ok());
+        let error = run_command(cli(&["stuckbar", "kill"]), &config).unwrap_err();
+        assert_eq!(error.kind(), "invalid_config");
+    }
+
+    #[test]
+    fn test_memory_growth_does_not_overflow() {
+        assert_eq!(mb_to_bytes(300), 300 * 1024 * 1024);
+        assert_eq!(mb_to_bytes(u64::MAX), u64::MAX);
+
+        let max = u64::MAX.to_string();
+        let config = effective(&["stuckbar", "watch", "--memory-growth-mb", &max]);
+        assert_eq!(
+            mb_to_bytes(config.config().watch.memory_growth_mb),
+            u64::MAX
+        );
+    }
+
+    #[test]
+    fn test_cli_default_output_is_text() {
+        let cli = Cli::parse_from(["stuckbar"]);
+        assert_eq!(cli.output, OutputFormat::Text);
+    }
+
+    #[test]
+    fn test_cli_parse_output_json() {
+        let cli = Cli::parse_from(["stuckbar", "status", "--output", "json"]);
+        assert_eq!(cli.command, Some(Commands::Status));
+        assert_eq!(cli.output, OutputFormat::Json);
+    }
+
+    #[test]
+    fn test_cli_invalid_output() {
+        let result = Cli::try_parse_from(["stuckbar", "--output", "yaml"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_status_report_json() {
+        let status = ShellStatus {
+            taskbar: TaskbarHealth::Missing,
+            processes: vec![ProcessDetails {
+                process: "explorer.exe".to_string(),
+                pid: 4312,
+                session_id: Some(1),
+                responding: Some(false),
+                ..Default::default()
+            }],
+            processes_error: None,
+        };
+        let report = StatusReport {
+            action: "status",
+            status: &status,
+        };
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "action": "status",
+                "status": "missing",
+                "healthy": false,
+                "message": "Taskbar window not found",
+                "processes": [{
+                    "process": "explorer.exe",
+                    "pid": 4312,
+                    "session_id": 1,
+                    "started_at": null,
+                    "uptime_ms": null,
+                    "working_set_bytes": null,
+                    "handle_count": null,
+                    "cpu_time_ms": null,
+                    "responding": false,
+                }],
+                "processes_error": null,
+            })
+        );
+    }
+
+    #[test]
+    fn test_status_fails_when_taskbar_is_unhealthy() {
+        let mut status = ShellStatus {
+            taskbar: TaskbarHealth::Responsive,
+            processes: Vec::new(),
+            processes_error: None,
+        };
+        for output in [OutputFormat::Text, OutputFormat::Json] {
+            assert!(print_status(&status, output).is_ok());
+        }
+
+        status.taskbar = TaskbarHealth::Hung {
+            reason: "no reply within 2000ms".to_string(),
+        };
+        for output in [OutputFormat::Text, OutputFormat::Json] {
+            let error = print_status(&status, output).unwrap_err();
+            assert_eq!(error.kind(), "unhealthy");
+            assert_eq!(error.exit_code(), 14);
+        }
+    }
+
+    #[test]
+    fn test_status_table() {
+        let lines = status_table(&[ProcessDetails {
+            process: "explorer.exe".to_string(),
+            pid: 4312,
+            session_id: Some(1),
+            started_at: Some("2026-10-16T09:12:03.0000000+02:00".to_string()),
+            uptime_ms: Some(8_040_000),
+            working_set_bytes: Some(149_159_936),
+            handle_count: Some(3120),
+            cpu_time_ms: Some(112_500),
+            responding: Some(true),
+        }]);
+
+        assert!(lines[0].starts_with("PROCESS"));
+        assert!(lines[0].ends_with("RESPONDING"));
+        let row = &lines[1];
+        for column in [
+            "explorer.exe",
+            "4312",
+            "2026-10-16 09:12:03",
+            "2h 14m",
+            "142.2 MB",
+            "3120",
+            "1m 52s",
+            "yes",
+        ] {
+            assert!(row.contains(column), "{} missing from {}", column, row);
+        }
+        assert_eq!(status_table(&[])[1], "No shell processes are running");
+    }
+
+    #[test]
+    fn test_describe_duration() {
+        assert_eq!(describe_duration(999), "0s");
+        assert_eq!(describe_duration(52_000), "52s");
+        assert_eq!(describe_duration(112_500), "1m 52s");
+        assert_eq!(describe_duration(8_040_000), "2h 14m");
+        assert_eq!(describe_duration(90_000_000), "1d 1h");
+    }
+
+    #[test]
+    fn test_cli_parse_status() {
+        let cli = Cli::parse_from(["stuckbar", "status"]);
+        assert_eq!(cli.command, Some(Commands::Status));
+    }
+
+    #[test]
+    fn test_cli_parse_list() {
+        let cli = Cli::parse_from(["stuckbar", "list"]);
+        assert_eq!(cli.command, Some(Commands::List));
+        assert!(!Commands::List.is_operation());
+    }
+
+    #[test]
+    fn test_cli_parse_history() {
+        let cli = Cli::parse_from(["stuckbar", "history"]);
+        assert_eq!(cli.command, Some(Commands::History(HistoryArgs::default())));
+
+        let cli = Cli::parse_from(["stuckbar", "history", "--since", "7d", "--json"]);
+        assert_eq!(
+            cli.command,
+            Some(Commands::History(HistoryArgs {
+                since: Some("7d".to_string()),
+                json: true,
+            }))
+        );
+        assert!(!Commands::History(HistoryArgs::default()).is_operation());
+    }
+
+    fn history_entry(success: bool) -> HistoryEntry {
+        HistoryEntry {
+            timestamp_ms: 1_792_141_923_000,
+            trigger: Trigger::Watchdog,
+            profile: Some("taskbar".to_string()),
+            action: Action::Restart,
+            target: "explorer.exe".to_string(),
+            success,
+            message: "Explorer.exe restarted successfully".to_string(),
+            killed_pids: vec![4312],
+            new_pid: Some(7000),
+            timings: stuckbar::Timings {
+                total_ms: 2_500,
+                ..Default::default()
+            },
+            error_kind: (!success).then(|| "start_failed".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_history_table() {
+        let lines = history_table(&[history_entry(true), history_entry(false)]);
+
+        assert!(lines[0].starts_with("TIME"));
+        assert!(lines[0].ends_with("PROFILE"));
+        for column in [
+            "2026-10-16 09:12:03 UTC",
+            "watchdog",
+            "restart",
+            "explorer.exe",
+            "ok",
+            "2s",
+            "taskbar",
+        ] {
+            assert!(
+                lines[1].contains(column),
+                "{} missing from {}",
+                column,
+                lines[1]
+            );
+        }
+        assert!(lines[2].contains("start_failed"));
+    }
+
+    #[test]
+    fn test_history_summary() {
+        let mut kill = history_entry(true);
+        kill.action = Action::Kill;
+        assert_eq!(
+            history_summary(&[history_entry(true), history_entry(false), kill]),
+            "3 operations (2 restarts), 1 failed"
+        );
+        assert_eq!(
+            history_summary(&[history_entry(true)]),
+            "1 operation (1 restart), 0 failed"
+        );
+    }
+
+    #[test]
+    fn test_history_report_json() {
+        let entries = [history_entry(false)];
+        let report = HistoryReport {
+            action: "history",
+            file: Path::new("history.jsonl"),
+            since_ms: Some(1_792_108_800_000),
+            entries: &entries,
+        };
+
+        let value = serde_json::to_value(&report).unwrap();
+        assert_eq!(value["action"], "history");
+        assert_eq!(value["file"], "history.jsonl");
+        assert_eq!(value["since_ms"], 1_792_108_800_000_u64);
+        assert_eq!(value["entries"][0]["trigger"], "watchdog");
+        assert_eq!(value["entries"][0]["profile"], "taskbar");
+        assert_eq!(value["entries"][0]["error_kind"], "start_failed");
+        assert_eq!(value["entries"][0]["timings"]["total_ms"], 2_500);
+    }
+
+    #[test]
+    fn test_cli_session_defaults_to_current() {
+        let config = effective(&["stuckbar", "kill"]);
+        assert_eq!(
+            config.config().restart.session_scope(),
+            SessionScope::Current
+        );
+        assert_eq!(config.source("restart.session"), &Source::Default);
+    }
+
+    #[test]
+    fn test_cli_parse_session() {
+        let config = effective(&["stuckbar", "restart", "--session", "2"]);
+        assert_eq!(config.config().restart.session_scope(), SessionScope::Id(2));
+        assert_eq!(config.source("restart.session"), &Source::Cli);
+
+        let config = effective(&["stuckbar", "--session", "all", "list"]);
+        assert_eq!(config.config().restart.session_scope(), SessionScope::All);
+
+        let config = effective(&["stuckbar", "kill", "--session", "mine"]);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_list_report_json() {
+        let instances = SessionScope::All.classify(
+            vec![ProcessInfo {
+                pid: 4312,
+                session_id: Some(1),
+                owner: Some("PC\\alice".to_string()),
+            }],
+            None,
+        );
+        let report = ListReport {
+            action: "list",
+            process: "explorer.exe",
+            session: SessionScope::All,
+            instances: &instances,
+        };
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "action": "list",
+                "process": "explorer.exe",
+                "session": "all",
+                "instances": [{
+                    "pid": 4312,
+                    "session_id": 1,
+                    "owner": "PC\\alice",
+                    "in_scope": true,
+                }],
+            })
+        );
     }
 
     #[test]
@@ -163,22 +2017,134 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_cli_default_restart_policy() {
+        let cli = Cli::parse_from(["stuckbar"]);
+        assert_eq!(cli.restart_policy(), RestartPolicy::default());
+    }
+
+    #[test]
+    fn test_cli_parse_restart_limits() {
+        let cli = Cli::parse_from([
+            "stuckbar",
+            "restart",
+            "--max-restarts",
+            "2",
+            "--restart-window",
+            "60",
+            "--min-restart-interval",
+            "30",
+        ]);
+        let policy = cli.restart_policy();
+        assert_eq!(policy.max_restarts, 2);
+        assert_eq!(policy.window_ms, 60_000);
+        assert_eq!(policy.min_interval_ms, 30_000);
+    }
+
+    #[test]
+    fn test_cli_no_restart_limit() {
+        let cli = Cli::parse_from(["stuckbar", "--no-restart-limit"]);
+        assert_eq!(cli.restart_policy(), RestartPolicy::unlimited());
+    }
+
+    #[test]
+    fn test_cli_parse_dry_run() {
+        let cli = Cli::parse_from(["stuckbar", "restart", "--dry-run"]);
+        assert!(cli.dry_run);
+        assert!(cli.check().is_ok());
+
+        let cli = Cli::parse_from(["stuckbar", "--dry-run"]);
+        assert!(cli.dry_run);
+        assert_eq!(cli.command, None);
+    }
+
+    #[test]
+    fn test_cli_dry_run_rejected_for_watch() {
+        let cli = Cli::parse_from(["stuckbar", "watch", "--dry-run"]);
+        let error = cli.check().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_commands_is_operation() {
+        assert!(Commands::Kill.is_operation());
+        assert!(Commands::Restart(RestartArgs::default()).is_operation());
+        assert!(!Commands::Status.is_operation());
+    }
+
     // Commands enum tests
     #[test]
     fn test_commands_equality() {
         assert_eq!(Commands::Kill, Commands::Kill);
         assert_eq!(Commands::Start, Commands::Start);
-        assert_eq!(Commands::Restart, Commands::Restart);
+        assert_eq!(
+            Commands::Restart(RestartArgs::default()),
+            Commands::Restart(RestartArgs::default())
+        );
         assert_ne!(Commands::Kill, Commands::Start);
     }
 
     #[test]
     fn test_commands_clone() {
-        let cmd = Commands::Restart;
+        let cmd = Commands::Restart(RestartArgs::default());
         let cloned = cmd.clone();
         assert_eq!(cmd, cloned);
     }
 
+    // Watch command tests
+    #[test]
+    fn test_cli_parse_watch_defaults() {
+        let cli = Cli::parse_from(["stuckbar", "watch"]);
+        match cli.command {
+            Some(Commands::Watch(args)) => {
+                assert_eq!(args.interval, 5);
+                assert_eq!(args.threshold, 3);
+                assert_eq!(args.cooldown, 60);
+                assert_eq!(args.max_restarts_per_hour, 4);
+                assert_eq!(args.probes, vec![ProbeKind::Process]);
+            }
+            _ => panic!("Expected Watch command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_watch_custom() {
+        let cli = Cli::parse_from([
+            "stuckbar",
+            "watch",
+            "--interval",
+            "10",
+            "--threshold",
+            "2",
+            "--probe",
+            "process,cpu",
+            "--probe",
+            "memory,window",
+        ]);
+        match cli.command {
+            Some(Commands::Watch(args)) => {
+                assert_eq!(args.interval, 10);
+                assert_eq!(args.threshold, 2);
+                assert_eq!(
+                    args.probes,
+                    vec![
+                        ProbeKind::Process,
+                        ProbeKind::Cpu,
+                        ProbeKind::Memory,
+                        ProbeKind::Window
+                    ]
+                );
+            }
+            _ => panic!("Expected Watch command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_watch_invalid_probe() {
+        let result = Cli::try_parse_from(["stuckbar", "watch", "--probe", "disk"]);
+        assert!(result.is_err());
+    }
+
     // MCP serve command tests
     #[cfg(feature = "mcp")]
     #[test]
@@ -201,6 +2167,7 @@ mod tests {
                 assert!(args.http);
                 assert_eq!(args.host, "127.0.0.1");
                 assert_eq!(args.port, 8080);
+                assert_eq!(args.transports, vec!["streamable-http", "sse"]);
             }
             _ => panic!("Expected Serve command"),
         }
@@ -219,6 +2186,152 @@ mod tests {
         }
     }
 
+    #[cfg(all(feature = "mcp", feature = "mcp-http"))]
+    #[test]
+    fn test_cli_serve_flags_override_config() {
+        let config = effective(&["stuckbar", "serve", "--http", "--port", "3000"]);
+
+        let mcp = &config.config().mcp;
+        assert_eq!(mcp.transport, config::Transport::Http);
+        assert_eq!(mcp.port, 3000);
+        assert_eq!(config.source("mcp.host"), &Source::Default);
+    }
+
+    #[cfg(all(feature = "mcp", feature = "mcp-http"))]
+    #[test]
+    fn test_cli_serve_transport() {
+        let config = effective(&["stuckbar", "serve", "--http", "--transport", "sse"]);
+        assert_eq!(
+            config.config().mcp.http_transports,
+            vec![config::HttpTransport::Sse]
+        );
+        assert_eq!(config.source("mcp.http_transports"), &Source::Cli);
+
+        let config = effective(&["stuckbar", "serve", "--http"]);
+        assert_eq!(config.source("mcp.http_transports"), &Source::Default);
+
+        assert!(
+            Cli::try_parse_from(["stuckbar", "serve", "--http", "--transport", "websocket"])
+                .is_err()
+        );
+        let config = effective(&["stuckbar", "serve", "--transport", "sse"]);
+        assert!(config.validate().is_err());
+    }
+
+    #[cfg(all(feature = "mcp", feature = "mcp-http"))]
+    #[test]
+    fn test_cli_serve_insecure() {
+        let config = effective(&["stuckbar", "serve", "--http", "--insecure"]);
+        assert!(config.config().mcp.insecure);
+        assert_eq!(config.source("mcp.insecure"), &Source::Cli);
+
+        let config = effective(&["stuckbar", "serve", "--http"]);
+        assert!(!config.config().mcp.insecure);
+
+        let config = effective(&["stuckbar", "serve", "--insecure"]);
+        assert!(config.validate().is_err());
+    }
+
+    #[cfg(feature = "mcp-tls")]
+    #[test]
+    fn test_cli_serve_tls() {
+        let config = effective(&[
+            "stuckbar",
+            "serve",
+            "--http",
+            "--tls-cert",
+            "cert.pem",
+            "--tls-key",
+            "key.pem",
+        ]);
+        let tls = &config.config().mcp.tls;
+        assert_eq!(tls.files(), Some(("cert.pem".into(), "key.pem".into())));
+        assert!(!tls.self_signed);
+        assert_eq!(config.source("mcp.tls.cert"), &Source::Cli);
+
+        let config = effective(&["stuckbar", "serve", "--http", "--tls-self-signed"]);
+        assert!(config.config().mcp.tls.self_signed);
+
+        for args in [
+            &["stuckbar", "serve", "--http", "--tls-cert", "cert.pem"][..],
+            &["stuckbar", "serve", "--http", "--tls-key", "key.pem"],
+            &["stuckbar", "serve", "--tls-self-signed"],
+        ] {
+            assert!(effective(args).validate().is_err(), "{:?}", args);
+        }
+    }
+
+    #[cfg(feature = "mcp-http")]
+    #[test]
+    fn test_cli_serve_http_options() {
+        let config = effective(&[
+            "stuckbar",
+            "serve",
+            "--http",
+            "--base-path",
+            "/stuckbar",
+            "--mcp-path",
+            "/rpc",
+            "--sse-keep-alive",
+            "30",
+            "--cors-origin",
+            "https://app.example.com,http://localhost:5173",
+            "--max-body-bytes",
+            "65536",
+            "--max-sessions",
+            "8",
+            "--session-timeout",
+            "600",
+        ]);
+        let mcp = &config.config().mcp;
+        assert_eq!(mcp.base_path, "/stuckbar");
+        assert_eq!(mcp.streamable_path, "/rpc");
+        assert_eq!(mcp.sse_path, "/sse");
+        assert_eq!(mcp.sse_keep_alive_ms, 30_000);
+        assert_eq!(
+            mcp.cors_origins,
+            vec!["https://app.example.com", "http://localhost:5173"]
+        );
+        assert_eq!(mcp.max_body_bytes, 65536);
+        assert_eq!(mcp.max_sessions, 8);
+        assert_eq!(mcp.session_timeout_ms, 600_000);
+        assert_eq!(config.source("mcp.base_path"), &Source::Cli);
+        assert_eq!(config.source("mcp.post_path"), &Source::Default);
+
+        let config = effective(&["stuckbar", "serve", "--http", "--sse-path", "/mcp"]);
+        assert!(config.validate().is_err());
+        let config = effective(&["stuckbar", "serve", "--max-sessions", "2"]);
+        assert!(config.validate().is_err());
+    }
+
+    #[cfg(all(feature = "mcp", feature = "mcp-http"))]
+    #[test]
+    fn test_run_mcp_server_refuses_exposed_unauthenticated_http() {
+        let settings = config::McpSettings {
+            transport: config::Transport::Http,
+            host: "0.0.0.0".to_string(),
+            ..Default::default()
+        };
+        let server = stuckbar::mcp::StuckbarMcpServer::new();
+        let error = run_mcp_server(&settings, server.clone()).unwrap_err();
+        assert_eq!(error.kind(), "invalid_config");
+        assert!(error.to_string().contains("--insecure"));
+
+        let settings = config::McpSettings {
+            tokens: vec![stuckbar::auth::ApiToken::Limited {
+                token: "s3cret".to_string(),
+                tools: vec!["reboot".to_string()],
+            }],
+            ..settings
+        };
+        let error = run_mcp_server(&settings, server).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("mcp.tokens: unknown tool 'reboot'")
+        );
+    }
+
     #[cfg(all(feature = "mcp", feature = "mcp-http"))]
     #[test]
     fn test_cli_parse_serve_http_custom_host_port() {
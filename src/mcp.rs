@@ -5,16 +5,54 @@
 //!
 //! ## Features
 //!
-//! The MCP server exposes three tools:
+//! The MCP server exposes six tools:
 //! - `kill_explorer` - Terminate the explorer.exe process
 //! - `start_explorer` - Start the explorer.exe process
 //! - `restart_explorer` - Restart explorer.exe (kill then start)
+//! - `run_profile` - Run a named restart [profile](crate::profile); the profile
+//!   names are enumerated in the tool's input schema
+//! - `check_taskbar_health` - Check whether the taskbar is responding
+//! - `get_explorer_status` - Report the taskbar health and every shell process
+//!   (see [`crate::inspect`])
+//!
+//! It also offers these resources, as JSON:
+//! - `stuckbar://explorer/processes` - Every shell process, as
+//!   `get_explorer_status` reports them
+//! - `stuckbar://taskbar/health` - Whether the taskbar responds
+//! - `stuckbar://history/recent` - The last [`RECENT_ENTRIES`] operations in the
+//!   [restart history](crate::history), once a history file is set with
+//!   [`with_history`](StuckbarMcpServer::with_history)
+//! - `stuckbar://config/effective` - The settings the server runs with, once set
+//!   with [`with_effective_config`](StuckbarMcpServer::with_effective_config)
+//!
+//! Clients can subscribe to them. While any client is subscribed, the shell
+//! processes are checked every [`RESOURCE_POLL_MS`], and right after each
+//! operation of the server; when they changed, because explorer crashed or was
+//! restarted, subscribers of the first three resources get `resources/updated`.
+//!
+//! Clients that send a progress token with a tool call receive the manager's
+//! lifecycle events (see [`ManagerEvent`]) as progress notifications.
+//!
+//! Every tool except `check_taskbar_health` and `get_explorer_status` accepts
+//! `dry_run`, which reports the steps the call would take (see
+//! [`crate::dry_run`]) without taking them. The tools that terminate explorer
+//! accept `session` (a session ID, `current` or `all`; see [`crate::scope`]),
+//! which defaults to the server's session scope.
 //!
 //! ## Transport Options
 //!
 //! Two transport modes are supported:
 //! - **STDIO** - Standard input/output transport for direct process communication
-//! - **HTTP** - SSE (Server-Sent Events) HTTP transport for network-based communication
+//! - **HTTP** - for network-based communication, speaking Streamable HTTP at
+//!   `/mcp`, the deprecated HTTP+SSE transport at `/sse` and `/message`, or both
+//!   from the same port (see [`http_router`]); the paths, CORS origins and
+//!   body and session limits are set in [`HttpServerConfig`]
+//!
+//! The HTTP transport can require a token with every request (see
+//! [`crate::auth`]); without tokens it only serves on a loopback address unless
+//! told otherwise.
+//! With the `mcp-tls` feature it can be served over TLS (see `crate::tls` and
+//! [`HttpServerConfig`]).
 //!
 //! ## Usage
 //!
@@ -28,17 +66,56 @@
 //! # Start MCP server with HTTP transport on custom port
 //! stuckbar serve --http --port 3000
 //!
-//! # Start MCP server with HTTP on custom host and port
-//! stuckbar serve --http --host 0.0.0.0 --port 8080
+//! # Start MCP server with HTTP on custom host and port, requiring a token
+//! STUCKBAR_MCP_TOKENS=s3cret stuckbar serve --http --host 0.0.0.0 --port 8080
+//!
+//! # Serve only Streamable HTTP
+//! stuckbar serve --http --transport streamable-http
+//!
+//! # Serve under /stuckbar behind a reverse proxy, to at most 4 sessions
+//! stuckbar serve --http --base-path /stuckbar --max-sessions 4
+//!
+//! # Serve HTTPS with a self-signed certificate generated on first run
+//! stuckbar serve --http --tls-self-signed
 //! ```
 
-use crate::{ExplorerManager, SystemProcessRunner, check_platform};
+use crate::async_runner::BackendRunner;
+#[cfg(feature = "mcp-http")]
+use crate::auth::HttpAuth;
+use crate::config::LayeredConfig;
+#[cfg(feature = "mcp-http")]
+use crate::config::{HttpTransport, MAX_BODY_BYTES, SSE_KEEP_ALIVE_MS};
+use crate::coordinator::OperationCoordinator;
+use crate::dry_run::{DryRunPlan, DryRunReport, DryRunRunner, DryRunSteps};
+use crate::events::{EventSink, ManagerEvent};
+use crate::history::{HistoryLog, HistorySink, RECENT_ENTRIES, Trigger};
+use crate::inspect::{PowerShellInspector, ProcessInspector, ShellStatus, inspect_shell};
+use crate::limiter::{RestartLimiter, RestartPolicy};
+use crate::profile::{BUILTIN_PROFILE_NAMES, Profile, ProfileOutcome, SystemStepRunner};
+use crate::report::JsonSink;
+use crate::scope::SessionScope;
+use crate::window::{RESPONSE_TIMEOUT_MS, SystemWindowInspector, TaskbarHealth, check_taskbar};
+use crate::{
+    Backend, BoxedRunner, ExplorerManager, GRACE_PERIOD_MS, KillStrategy, Outcome, ProcessManager,
+    RESTART_DELAY_MS, RestartOptions, StuckbarError, TargetSpec, check_platform,
+};
 use rmcp::{
-    ErrorData as McpError, ServerHandler, ServiceExt, handler::server::router::tool::ToolRouter,
-    model::*, tool, tool_handler, tool_router, transport::stdio,
+    ErrorData as McpError, Peer, RoleServer, ServerHandler, ServiceExt,
+    handler::server::{router::tool::ToolRouter, wrapper::Parameters},
+    model::*,
+    schemars::JsonSchema,
+    serde::Deserialize,
+    service::RequestContext,
+    tool, tool_handler, tool_router,
+    transport::stdio,
 };
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+#[cfg(feature = "mcp-http")]
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 
 /// MCP Server for stuckbar operations
 ///
@@ -46,20 +123,509 @@ use tokio::sync::Mutex;
 /// enabling AI agents to fix stuck taskbars programmatically.
 #[derive(Clone)]
 pub struct StuckbarMcpServer {
-    /// Thread-safe reference to the explorer manager
-    manager: Arc<Mutex<ExplorerManager<SystemProcessRunner>>>,
+    /// Process backend of the managers created for each tool call
+    backend: Backend,
+    /// Shared by every server in the process, so overlapping restarts from
+    /// different sessions are coalesced
+    coordinator: Arc<OperationCoordinator>,
+    /// Refuses restarts that come too often; its state is shared with the CLI
+    limiter: Arc<RestartLimiter>,
+    /// Delay between kill and start of a restart
+    restart_delay_ms: u64,
+    /// File every finished operation is appended to
+    log_file: Option<PathBuf>,
+    /// Profiles `run_profile` may run, by name
+    profiles: BTreeMap<String, Profile>,
+    /// Simulate every tool call, as if it asked for a dry run
+    dry_run: bool,
+    /// Sessions whose instances are killed unless a call asks for others
+    session_scope: SessionScope,
+    /// Queries the shell processes for `get_explorer_status`
+    inspector: Arc<dyn ProcessInspector + Send + Sync>,
+    /// History served as a resource and, if `record_history`, appended to
+    history: Option<HistoryLog>,
+    record_history: bool,
+    /// Effective configuration served as a resource, as `config show` prints it
+    effective_config: Option<serde_json::Value>,
+    /// Tells subscribed sessions when the shell processes change; shared by
+    /// every session
+    watch: Arc<ResourceWatch>,
+    /// Interval at which `watch` checks the shell processes
+    watch_interval_ms: u64,
     /// Tool router for handling MCP tool calls
     tool_router: ToolRouter<Self>,
 }
 
+/// Sink that forwards the events of one tool call to its progress forwarder
+struct ProgressSink(mpsc::UnboundedSender<ManagerEvent>);
+
+impl EventSink for ProgressSink {
+    fn on_event(&self, _target: &TargetSpec, event: &ManagerEvent) {
+        // The receiver only goes away once the call has finished
+        let _ = self.0.send(event.clone());
+    }
+}
+
+/// Manager operation performed by a tool
+enum ToolOperation {
+    Kill(KillStrategy),
+    Start,
+    Restart(RestartOptions),
+}
+
+/// Arguments of the tools that terminate explorer
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(crate = "rmcp::serde", default)]
+#[schemars(crate = "rmcp::schemars")]
+pub struct KillArgs {
+    /// Ask explorer to exit cleanly first and only force-terminate it if it is still
+    /// running after the grace period (default: false)
+    pub graceful: bool,
+    /// Milliseconds to wait for a graceful exit (default: 3000)
+    pub grace_period_ms: Option<u64>,
+    /// Only report which processes would be terminated and what would be
+    /// launched, without doing it (default: false)
+    pub dry_run: bool,
+    /// Sessions whose explorer is terminated: a session ID, "current" for the
+    /// server's own session and account, or "all" (default: the server's setting)
+    pub session: Option<String>,
+}
+
+impl KillArgs {
+    fn strategy(&self) -> KillStrategy {
+        if self.graceful {
+            KillStrategy::Graceful {
+                grace_ms: self.grace_period_ms.unwrap_or(GRACE_PERIOD_MS),
+            }
+        } else {
+            KillStrategy::Force
+        }
+    }
+}
+
+/// Arguments of the restart tool
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(crate = "rmcp::serde", default)]
+#[schemars(crate = "rmcp::schemars")]
+pub struct RestartArgs {
+    #[serde(flatten)]
+    pub kill: KillArgs,
+    /// Reopen the File Explorer windows that were open before the restart (default: false)
+    pub restore_windows: bool,
+    /// Delete explorer's icon and thumbnail caches while it is down, so they are
+    /// rebuilt; fixes blank or wrong icons (default: false)
+    pub rebuild_icon_cache: bool,
+}
+
+impl RestartArgs {
+    fn options(&self) -> RestartOptions {
+        RestartOptions {
+            kill_strategy: self.kill.strategy(),
+            restore_windows: self.restore_windows,
+            rebuild_icon_cache: self.rebuild_icon_cache,
+        }
+    }
+}
+
+/// Arguments of the start tool
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(crate = "rmcp::serde", default)]
+#[schemars(crate = "rmcp::schemars")]
+pub struct StartArgs {
+    /// Only report what would be launched, without doing it (default: false)
+    pub dry_run: bool,
+}
+
+/// Arguments of the profile tool
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(crate = "rmcp::serde")]
+#[schemars(crate = "rmcp::schemars")]
+pub struct RunProfileArgs {
+    /// Name of the profile to run
+    pub profile: String,
+    /// Only report what the profile would do, without doing it (default: false)
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Sessions whose processes are terminated: a session ID, "current" or "all"
+    /// (default: the server's setting)
+    #[serde(default)]
+    pub session: Option<String>,
+}
+
 #[tool_router]
 impl StuckbarMcpServer {
-    /// Create a new MCP server instance
+    /// Create a new MCP server instance using the default backend
     pub fn new() -> Self {
+        Self::with_backend(Backend::default())
+    }
+
+    /// Create a new MCP server instance using the given process backend
+    pub fn with_backend(backend: Backend) -> Self {
+        let profiles = BUILTIN_PROFILE_NAMES
+            .iter()
+            .filter_map(|name| Some((name.to_string(), Profile::builtin(name)?)))
+            .collect();
         Self {
-            manager: Arc::new(Mutex::new(ExplorerManager::new(SystemProcessRunner))),
+            backend,
+            coordinator: OperationCoordinator::shared(&TargetSpec::explorer()),
+            limiter: Arc::new(RestartLimiter::for_target(
+                &TargetSpec::explorer(),
+                RestartPolicy::default(),
+            )),
+            restart_delay_ms: RESTART_DELAY_MS,
+            log_file: None,
+            profiles,
+            dry_run: false,
+            session_scope: SessionScope::Current,
+            inspector: Arc::new(PowerShellInspector),
+            history: None,
+            record_history: true,
+            effective_config: None,
+            watch: Arc::default(),
+            watch_interval_ms: RESOURCE_POLL_MS,
             tool_router: Self::tool_router(),
         }
+        .enumerate_profiles()
+    }
+
+    /// Limit the restarts requested through this server with `policy`
+    pub fn with_restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.limiter = Arc::new(RestartLimiter::for_target(&TargetSpec::explorer(), policy));
+        self
+    }
+
+    pub fn with_restart_delay(mut self, delay_ms: u64) -> Self {
+        self.restart_delay_ms = delay_ms;
+        self
+    }
+
+    /// Append every finished operation to the file at `path` as a line of JSON
+    pub fn with_log_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.log_file = Some(path.into());
+        self
+    }
+
+    /// Record every finished operation in `log` and serve its recent entries
+    pub fn with_history(mut self, log: HistoryLog) -> Self {
+        self.history = Some(log);
+        self
+    }
+
+    /// Whether operations are recorded in the history; it is served either way
+    pub fn with_history_recording(mut self, record: bool) -> Self {
+        self.record_history = record;
+        self
+    }
+
+    /// Serve the settings of `config` and where they were set, with tokens
+    /// redacted
+    pub fn with_effective_config(mut self, config: &LayeredConfig) -> Self {
+        self.effective_config = Some(serde_json::json!({
+            "file": config.file(),
+            "settings": config.entries(),
+        }));
+        self
+    }
+
+    /// Check the shell processes every `interval_ms` while clients are
+    /// subscribed to resources
+    pub fn with_watch_interval(mut self, interval_ms: u64) -> Self {
+        self.watch_interval_ms = interval_ms;
+        self
+    }
+
+    /// Also offer the declared `profiles`, replacing built-in ones of the same name
+    pub fn with_profiles(mut self, profiles: &BTreeMap<String, Profile>) -> Self {
+        self.profiles.extend(profiles.clone());
+        self.enumerate_profiles()
+    }
+
+    /// Only simulate tool calls, whether or not they ask for a dry run
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Kill the instances in `scope` unless a call asks for other sessions
+    pub fn with_session_scope(mut self, scope: SessionScope) -> Self {
+        self.session_scope = scope;
+        self
+    }
+
+    pub fn with_inspector(
+        mut self,
+        inspector: impl ProcessInspector + Send + Sync + 'static,
+    ) -> Self {
+        self.inspector = Arc::new(inspector);
+        self
+    }
+
+    /// Scope requested by a tool call, or the server's scope if it names none
+    fn session_scope(&self, session: Option<&str>) -> Result<SessionScope, StuckbarError> {
+        session.map_or(Ok(self.session_scope), str::parse)
+    }
+
+    /// List the profile names and descriptions in the `run_profile` input schema
+    fn enumerate_profiles(mut self) -> Self {
+        let Some(route) = self.tool_router.map.get_mut("run_profile") else {
+            return self;
+        };
+        let names: Vec<&String> = self.profiles.keys().collect();
+        let described: Vec<String> = self
+            .profiles
+            .iter()
+            .map(|(name, profile)| format!("{} ({})", name, profile.description))
+            .collect();
+
+        let mut schema = (*route.attr.input_schema).clone();
+        if let Some(property) = schema
+            .get_mut("properties")
+            .and_then(|properties| properties.get_mut("profile"))
+            .and_then(serde_json::Value::as_object_mut)
+        {
+            property.insert("enum".to_string(), serde_json::json!(names));
+            property.insert(
+                "description".to_string(),
+                format!("Profile to run: {}", described.join("; ")).into(),
+            );
+        }
+        route.attr.input_schema = Arc::new(schema);
+        self
+    }
+
+    /// Expose only the tools named in `tools`; an empty list keeps every tool
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StuckbarError::InvalidConfig`] if a name is not one of the tools.
+    pub fn with_allowed_tools(mut self, tools: &[String]) -> Result<Self, StuckbarError> {
+        if tools.is_empty() {
+            return Ok(self);
+        }
+        self.check_tools("mcp.allowed_tools", tools)?;
+        for name in self.tool_names() {
+            if !tools.contains(&name) {
+                self.tool_router.remove_route(&name);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Check that every name in `tools`, set as `key`, is an exposed tool
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StuckbarError::InvalidConfig`] naming the first unknown tool.
+    pub fn check_tools(&self, key: &str, tools: &[String]) -> Result<(), StuckbarError> {
+        let names = self.tool_names();
+        match tools.iter().find(|tool| !names.contains(tool)) {
+            Some(unknown) => Err(StuckbarError::InvalidConfig {
+                details: format!(
+                    "{}: unknown tool '{}', expected one of: {}",
+                    key,
+                    unknown,
+                    names.join(", ")
+                ),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Names of the exposed tools
+    fn tool_names(&self) -> Vec<String> {
+        self.tool_router
+            .list_all()
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect()
+    }
+
+    /// Manager for a single tool call that kills the instances in `scope`
+    fn manager(&self, scope: SessionScope) -> ExplorerManager<BackendRunner> {
+        let manager = ExplorerManager::new(self.backend.async_runner())
+            .with_restart_delay(self.restart_delay_ms)
+            .with_session_scope(scope)
+            .with_coordinator(self.coordinator.clone())
+            .with_restart_limiter(self.limiter.clone());
+        self.with_log(manager, None)
+    }
+
+    /// Blocking manager for one target of a profile
+    ///
+    /// It shares the coordinator and restart limits of its target with every other
+    /// manager in the process, so explorer restarts still coalesce.
+    fn profile_manager(
+        &self,
+        target: TargetSpec,
+        scope: SessionScope,
+        profile: &str,
+    ) -> ProcessManager<BoxedRunner> {
+        let limiter = RestartLimiter::for_target(&target, self.limiter.policy());
+        let manager = ProcessManager::for_target(self.backend.runner(), target.clone())
+            .with_restart_delay(self.restart_delay_ms)
+            .with_session_scope(scope)
+            .with_coordinator(OperationCoordinator::shared(&target))
+            .with_restart_limiter(Arc::new(limiter));
+        self.with_log(manager, Some(profile))
+    }
+
+    /// Blocking manager for `target` that records what it would do in `plan`
+    ///
+    /// Dry runs are not logged and neither coordinated nor rate limited.
+    fn dry_run_manager(
+        &self,
+        target: TargetSpec,
+        scope: SessionScope,
+        plan: &DryRunPlan,
+    ) -> ProcessManager<DryRunRunner<BoxedRunner>> {
+        ProcessManager::for_target(self.backend.runner(), target)
+            .with_restart_delay(self.restart_delay_ms)
+            .with_session_scope(scope)
+            .dry_run(plan)
+    }
+
+    /// Simulate an operation on a blocking thread and report what it would do
+    async fn dry_run_operation(
+        &self,
+        scope: SessionScope,
+        operation: ToolOperation,
+    ) -> Result<CallToolResult, McpError> {
+        let server = self.clone();
+        let report = tokio::task::spawn_blocking(move || {
+            let plan = DryRunPlan::new();
+            let manager = server.dry_run_manager(TargetSpec::explorer(), scope, &plan);
+            let (name, result) = match operation {
+                ToolOperation::Kill(strategy) => ("kill", manager.kill_with(strategy)),
+                ToolOperation::Start => ("start", manager.start()),
+                ToolOperation::Restart(options) => ("restart", manager.restart_with(options)),
+            };
+            DryRunReport::new(name, &plan, result.map(|_| ()))
+        })
+        .await
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        Ok(dry_run_result(&report))
+    }
+
+    /// Append the manager's operations to the log file and the history, if any,
+    /// recording them as part of `profile`
+    fn with_log<R>(&self, manager: ProcessManager<R>, profile: Option<&str>) -> ProcessManager<R> {
+        let manager = match self.history.clone().filter(|_| self.record_history) {
+            Some(log) => {
+                let sink = HistorySink::new(log, Trigger::Mcp);
+                manager.with_sink(match profile {
+                    Some(name) => sink.with_profile(name),
+                    None => sink,
+                })
+            }
+            None => manager,
+        };
+        // The STDIO transport owns stdout, so a log file that cannot be opened is skipped
+        match self.log_file.as_deref().map(JsonSink::append) {
+            Some(Ok(sink)) => manager.with_sink(sink),
+            _ => manager,
+        }
+    }
+
+    /// Resources the server offers
+    fn resources(&self) -> Vec<Resource> {
+        let mut resources = vec![
+            json_resource(
+                PROCESSES_URI,
+                "explorer-processes",
+                "Shell processes",
+                "Every running explorer.exe, StartMenuExperienceHost.exe, ShellExperienceHost.exe and SearchHost.exe process with its PID, session, uptime, memory, handles, CPU time and responsiveness".to_string(),
+            ),
+            json_resource(
+                HEALTH_URI,
+                "taskbar-health",
+                "Taskbar health",
+                "Whether the taskbar is responsive, hung or missing".to_string(),
+            ),
+        ];
+        if self.history.is_some() {
+            resources.push(json_resource(
+                HISTORY_URI,
+                "history",
+                "Recent restart history",
+                format!(
+                    "The last {} kills, starts and restarts with their trigger, profile, outcome, durations and error kind, oldest first",
+                    RECENT_ENTRIES
+                ),
+            ));
+        }
+        if self.effective_config.is_some() {
+            resources.push(json_resource(
+                CONFIG_URI,
+                "config",
+                "Effective configuration",
+                "Every setting the server runs with and where it was set, with tokens redacted"
+                    .to_string(),
+            ));
+        }
+        resources
+    }
+
+    /// Contents of the resource at `uri`
+    fn read(&self, uri: &str) -> Result<ReadResourceResult, McpError> {
+        let value = match (uri, &self.history, &self.effective_config) {
+            (PROCESSES_URI, _, _) => {
+                check_platform().map_err(|e| resource_error(&e))?;
+                let processes = inspect_shell(&*self.inspector).map_err(|e| resource_error(&e))?;
+                serde_json::json!({ "processes": processes })
+            }
+            (HEALTH_URI, _, _) => {
+                check_platform().map_err(|e| resource_error(&e))?;
+                serde_json::json!(check_taskbar(&SystemWindowInspector, RESPONSE_TIMEOUT_MS))
+            }
+            (HISTORY_URI, Some(log), _) => {
+                let entries = log
+                    .recent(RECENT_ENTRIES)
+                    .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+                serde_json::json!({ "entries": entries })
+            }
+            (CONFIG_URI, _, Some(config)) => config.clone(),
+            _ => {
+                return Err(McpError::resource_not_found(
+                    format!("unknown resource {}", uri),
+                    None,
+                ));
+            }
+        };
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: uri.to_string(),
+                mime_type: Some("application/json".to_string()),
+                text: value.to_string(),
+                meta: None,
+            }],
+        })
+    }
+
+    /// Run an operation with a fresh manager
+    ///
+    /// When the request carries a progress token, every event the operation emits
+    /// is sent to the client as a progress notification.
+    async fn run_operation(
+        &self,
+        meta: &Meta,
+        peer: Peer<RoleServer>,
+        scope: SessionScope,
+        operation: ToolOperation,
+    ) -> Result<Outcome, StuckbarError> {
+        let Some(token) = meta.get_progress_token() else {
+            let result = run(&self.manager(scope), operation).await;
+            self.watch.wake();
+            return result;
+        };
+
+        let (sender, forwarder) = forward_progress(token, peer);
+        let manager = self.manager(scope).with_sink(ProgressSink(sender));
+        let result = run(&manager, operation).await;
+        self.watch.wake();
+        // Dropping the sender ends the forwarder once the queued events are sent
+        drop(manager);
+        let _ = forwarder.await;
+        result
     }
 
     /// Kill the Windows Explorer process
@@ -67,22 +633,29 @@ impl StuckbarMcpServer {
     /// Forcefully terminates explorer.exe, which will cause the taskbar,
     /// desktop icons, and file explorer windows to disappear temporarily.
     #[tool(
-        description = "Terminate the Windows Explorer (explorer.exe) process. This will cause the taskbar and desktop to temporarily disappear. Use this when you need to forcefully stop explorer."
+        description = "Terminate the Windows Explorer (explorer.exe) process. This will cause the taskbar and desktop to temporarily disappear. Use this when you need to stop explorer. Pass graceful=true to let explorer save its state and exit cleanly before it is forced, session to choose whose explorer is terminated (a session ID, \"current\" or \"all\"; by default only the server's own), and dry_run=true to only see which processes would be terminated. Windows relaunches explorer after it is killed, so kills are rate limited like restart_explorer."
     )]
-    async fn kill_explorer(&self) -> Result<CallToolResult, McpError> {
+    async fn kill_explorer(
+        &self,
+        Parameters(args): Parameters<KillArgs>,
+        meta: Meta,
+        peer: Peer<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
         // Check platform first
         if let Err(e) = check_platform() {
-            return Ok(CallToolResult::error(vec![Content::text(e)]));
+            return Ok(error_result(&e));
         }
 
-        let manager = self.manager.lock().await;
-        let result = manager.kill_silent();
-
-        if result.success {
-            Ok(CallToolResult::success(vec![Content::text(result.message)]))
-        } else {
-            Ok(CallToolResult::error(vec![Content::text(result.message)]))
+        let scope = match self.session_scope(args.session.as_deref()) {
+            Ok(scope) => scope,
+            Err(e) => return Ok(error_result(&e)),
+        };
+        let operation = ToolOperation::Kill(args.strategy());
+        if args.dry_run || self.dry_run {
+            return self.dry_run_operation(scope, operation).await;
         }
+        let result = self.run_operation(&meta, peer, scope, operation).await;
+        Ok(tool_result(result))
     }
 
     /// Start the Windows Explorer process
@@ -90,22 +663,28 @@ impl StuckbarMcpServer {
     /// Launches explorer.exe, which will restore the taskbar, desktop icons,
     /// and enable file explorer functionality.
     #[tool(
-        description = "Start the Windows Explorer (explorer.exe) process. This will restore the taskbar and desktop. Use this after killing explorer or if explorer is not running."
+        description = "Start the Windows Explorer (explorer.exe) process. This will restore the taskbar and desktop. Use this after killing explorer or if explorer is not running. Pass dry_run=true to only see what would be launched."
     )]
-    async fn start_explorer(&self) -> Result<CallToolResult, McpError> {
+    async fn start_explorer(
+        &self,
+        Parameters(args): Parameters<StartArgs>,
+        meta: Meta,
+        peer: Peer<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
         // Check platform first
         if let Err(e) = check_platform() {
-            return Ok(CallToolResult::error(vec![Content::text(e)]));
+            return Ok(error_result(&e));
         }
 
-        let manager = self.manager.lock().await;
-        let result = manager.start_silent();
-
-        if result.success {
-            Ok(CallToolResult::success(vec![Content::text(result.message)]))
-        } else {
-            Ok(CallToolResult::error(vec![Content::text(result.message)]))
+        // Starting touches no existing instance, so the scope does not matter
+        let scope = self.session_scope;
+        if args.dry_run || self.dry_run {
+            return self.dry_run_operation(scope, ToolOperation::Start).await;
         }
+        let result = self
+            .run_operation(&meta, peer, scope, ToolOperation::Start)
+            .await;
+        Ok(tool_result(result))
     }
 
     /// Restart the Windows Explorer process
@@ -113,25 +692,431 @@ impl StuckbarMcpServer {
     /// Kills and then restarts explorer.exe with a small delay between operations.
     /// This is the recommended action for fixing a stuck taskbar.
     #[tool(
-        description = "Restart Windows Explorer (explorer.exe) by killing and restarting it. This is the recommended fix for a stuck or unresponsive Windows taskbar. The operation includes a brief delay between kill and start."
+        description = "Restart Windows Explorer (explorer.exe) by killing and restarting it. This is the recommended fix for a stuck or unresponsive Windows taskbar. The operation includes a brief delay between kill and start. Pass graceful=true to let explorer exit cleanly before it is forced, restore_windows=true to reopen the folder windows that were open, rebuild_icon_cache=true if icons are blank or wrong, session to choose whose explorer is restarted (a session ID, \"current\" or \"all\"; by default only the server's own), and dry_run=true to only get the steps the restart would take (processes with PIDs and sessions, delays, commands) without changing anything. If a restart is already in progress, this call joins it and returns its result. Restarts that come too often or keep crashing explorer are refused with a rate_limited error carrying retry_after_ms; wait that long before trying again."
     )]
-    async fn restart_explorer(&self) -> Result<CallToolResult, McpError> {
+    async fn restart_explorer(
+        &self,
+        Parameters(args): Parameters<RestartArgs>,
+        meta: Meta,
+        peer: Peer<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
         // Check platform first
         if let Err(e) = check_platform() {
-            return Ok(CallToolResult::error(vec![Content::text(e)]));
+            return Ok(error_result(&e));
         }
 
-        let manager = self.manager.lock().await;
-        let result = manager.restart_silent();
+        let scope = match self.session_scope(args.kill.session.as_deref()) {
+            Ok(scope) => scope,
+            Err(e) => return Ok(error_result(&e)),
+        };
+        let operation = ToolOperation::Restart(args.options());
+        if args.kill.dry_run || self.dry_run {
+            return self.dry_run_operation(scope, operation).await;
+        }
+        let result = self.run_operation(&meta, peer, scope, operation).await;
+        Ok(tool_result(result))
+    }
 
-        if result.success {
-            Ok(CallToolResult::success(vec![Content::text(result.message)]))
-        } else {
-            Ok(CallToolResult::error(vec![Content::text(result.message)]))
+    /// Run a named restart profile
+    ///
+    /// Restarts the profile's targets one after another on a blocking thread,
+    /// then runs its post-steps.
+    #[tool(
+        description = "Run a named restart profile: an ordered restart of one or more shell components (explorer, Start menu, shell flyouts, search) with its own kill strategy and delays, followed by post-steps such as checking that the taskbar responds. Use 'taskbar' for a stuck taskbar and 'full-shell' when several parts of the shell misbehave. Returns the outcome of every target and post-step. Restarts are rate limited like restart_explorer. Pass session to choose whose processes are restarted, as for restart_explorer, and dry_run=true to only get the steps the profile would take."
+    )]
+    async fn run_profile(
+        &self,
+        Parameters(args): Parameters<RunProfileArgs>,
+        meta: Meta,
+        peer: Peer<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        // Check platform first
+        if let Err(e) = check_platform() {
+            return Ok(error_result(&e));
+        }
+
+        let Some(profile) = self.profiles.get(&args.profile).cloned() else {
+            let names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            return Ok(error_result(&StuckbarError::InvalidConfig {
+                details: format!(
+                    "unknown profile '{}', expected one of: {}",
+                    args.profile,
+                    names.join(", ")
+                ),
+            }));
+        };
+
+        let scope = match self.session_scope(args.session.as_deref()) {
+            Ok(scope) => scope,
+            Err(e) => return Ok(error_result(&e)),
+        };
+        if args.dry_run || self.dry_run {
+            let server = self.clone();
+            let report = tokio::task::spawn_blocking(move || {
+                let plan = DryRunPlan::new();
+                let steps = DryRunSteps::new(SystemStepRunner, plan.clone());
+                let result = profile.run(
+                    &args.profile,
+                    |target| Ok(server.dry_run_manager(target, scope, &plan)),
+                    &steps,
+                );
+                DryRunReport::new(
+                    format!("profile {}", args.profile),
+                    &plan,
+                    result.map(|_| ()),
+                )
+            })
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+            return Ok(dry_run_result(&report));
+        }
+
+        let progress = meta
+            .get_progress_token()
+            .map(|token| forward_progress(token, peer));
+        let sender = progress.as_ref().map(|(sender, _)| sender.clone());
+        let server = self.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            profile.run(
+                &args.profile,
+                |target| {
+                    let manager = server.profile_manager(target, scope, &args.profile);
+                    Ok(match &sender {
+                        Some(sender) => manager.with_sink(ProgressSink(sender.clone())),
+                        None => manager,
+                    })
+                },
+                &SystemStepRunner,
+            )
+        })
+        .await
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        self.watch.wake();
+
+        if let Some((sender, forwarder)) = progress {
+            drop(sender);
+            let _ = forwarder.await;
+        }
+        Ok(profile_result(result))
+    }
+
+    /// Check whether the taskbar is responding
+    ///
+    /// Locates the taskbar window and verifies it still processes messages.
+    /// A hung or missing taskbar is a good reason to restart explorer.
+    #[tool(
+        description = "Check whether the Windows taskbar is responding. Reports 'responsive', 'hung' (explorer is running but the taskbar does not process messages) or 'missing' (no taskbar window). Use this to decide whether restart_explorer is needed."
+    )]
+    async fn check_taskbar_health(&self) -> Result<CallToolResult, McpError> {
+        // Check platform first
+        if let Err(e) = check_platform() {
+            return Ok(error_result(&e));
+        }
+
+        let health = tokio::task::spawn_blocking(|| {
+            check_taskbar(&SystemWindowInspector, RESPONSE_TIMEOUT_MS)
+        })
+        .await
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        Ok(health_result(&health))
+    }
+
+    /// Report the taskbar health and the shell processes
+    ///
+    /// Lists every instance of explorer and the other shell hosts with its
+    /// resource usage, without acting on any of them.
+    #[tool(
+        description = "Inspect the Windows shell without changing anything. Reports the taskbar health (as check_taskbar_health does) and every running explorer.exe, StartMenuExperienceHost.exe, ShellExperienceHost.exe and SearchHost.exe process with its PID, session, start time, uptime, working set, handle count, CPU time and whether it responds. Values that cannot be read are null. Use this to see which component is misbehaving before choosing restart_explorer or a run_profile profile."
+    )]
+    async fn get_explorer_status(&self) -> Result<CallToolResult, McpError> {
+        // Check platform first
+        if let Err(e) = check_platform() {
+            return Ok(error_result(&e));
+        }
+
+        let inspector = self.inspector.clone();
+        let status = tokio::task::spawn_blocking(move || {
+            ShellStatus::collect(&SystemWindowInspector, &*inspector, RESPONSE_TIMEOUT_MS)
+        })
+        .await
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        Ok(status_result(&status))
+    }
+}
+
+/// URI of the recent history resource
+const HISTORY_URI: &str = "stuckbar://history/recent";
+
+/// URI of the shell processes resource
+const PROCESSES_URI: &str = "stuckbar://explorer/processes";
+
+/// URI of the taskbar health resource
+const HEALTH_URI: &str = "stuckbar://taskbar/health";
+
+/// URI of the effective configuration resource
+const CONFIG_URI: &str = "stuckbar://config/effective";
+
+/// Resources that change when the shell processes do
+const WATCHED_URIS: &[&str] = &[PROCESSES_URI, HEALTH_URI, HISTORY_URI];
+
+/// Default interval at which the shell processes are checked while clients
+/// are subscribed to resources
+pub const RESOURCE_POLL_MS: u64 = 5_000;
+
+/// A resource served as JSON
+fn json_resource(uri: &str, name: &str, title: &str, description: String) -> Resource {
+    let mut resource = RawResource::new(uri, name);
+    resource.title = Some(title.to_string());
+    resource.description = Some(description);
+    resource.mime_type = Some("application/json".to_string());
+    resource.no_annotation()
+}
+
+/// Build an MCP error for a resource that cannot be read, carrying the error kind
+fn resource_error(error: &StuckbarError) -> McpError {
+    McpError::internal_error(
+        error.to_string(),
+        Some(serde_json::json!({ "error": error })),
+    )
+}
+
+/// A session subscribed to resources
+struct Subscriber {
+    peer: Peer<RoleServer>,
+    uris: BTreeSet<String>,
+}
+
+/// Sessions subscribed to resources, told when the shell processes change
+///
+/// While anyone is subscribed, a task checks the shell processes at an interval
+/// and whenever [`wake`](Self::wake) is called. When their PIDs differ from the
+/// last check, explorer crashed, was killed or was restarted, and every
+/// subscriber of a [watched](WATCHED_URIS) resource is sent `resources/updated`.
+#[derive(Default)]
+struct ResourceWatch {
+    subscribers: std::sync::Mutex<Vec<Subscriber>>,
+    /// The checking task, while there are subscribers
+    poller: std::sync::Mutex<Option<JoinHandle<()>>>,
+    wake: tokio::sync::Notify,
+}
+
+impl ResourceWatch {
+    /// Subscribe the session of `peer` to `uri`, starting the checks if needed
+    fn subscribe(
+        self: &Arc<Self>,
+        peer: Peer<RoleServer>,
+        uri: String,
+        inspector: Arc<dyn ProcessInspector + Send + Sync>,
+        interval_ms: u64,
+    ) {
+        {
+            let mut subscribers = lock(&self.subscribers);
+            match subscribers
+                .iter_mut()
+                .find(|subscriber| same_session(&subscriber.peer, &peer))
+            {
+                Some(subscriber) => {
+                    subscriber.uris.insert(uri);
+                }
+                None => subscribers.push(Subscriber {
+                    peer,
+                    uris: BTreeSet::from([uri]),
+                }),
+            }
+        }
+        let mut poller = lock(&self.poller);
+        if poller.is_none() {
+            *poller = Some(tokio::spawn(self.clone().poll(inspector, interval_ms)));
+        }
+    }
+
+    /// Unsubscribe the session of `peer` from `uri`
+    fn unsubscribe(&self, peer: &Peer<RoleServer>, uri: &str) {
+        let mut subscribers = lock(&self.subscribers);
+        for subscriber in subscribers.iter_mut() {
+            if same_session(&subscriber.peer, peer) {
+                subscriber.uris.remove(uri);
+            }
+        }
+        subscribers.retain(|subscriber| !subscriber.uris.is_empty());
+    }
+
+    /// Check the shell processes now rather than at the next interval
+    fn wake(&self) {
+        self.wake.notify_one();
+    }
+
+    /// Subscribers of `uris` to notify
+    fn updates(&self, uris: &[&str]) -> Vec<(Peer<RoleServer>, String)> {
+        lock(&self.subscribers)
+            .iter()
+            .flat_map(|subscriber| {
+                uris.iter()
+                    .filter(|uri| subscriber.uris.contains(**uri))
+                    .map(|uri| (subscriber.peer.clone(), uri.to_string()))
+            })
+            .collect()
+    }
+
+    /// Check the shell processes until nobody is subscribed
+    async fn poll(
+        self: Arc<Self>,
+        inspector: Arc<dyn ProcessInspector + Send + Sync>,
+        interval_ms: u64,
+    ) {
+        let mut last: Option<Vec<u32>> = None;
+        loop {
+            let inspector = inspector.clone();
+            let pids = tokio::task::spawn_blocking(move || {
+                let mut pids: Vec<u32> = inspect_shell(&*inspector)
+                    .ok()?
+                    .iter()
+                    .map(|details| details.pid)
+                    .collect();
+                pids.sort_unstable();
+                Some(pids)
+            })
+            .await
+            .ok()
+            .flatten();
+            // A failed check is not a change
+            if let Some(pids) = pids {
+                if last.as_ref().is_some_and(|last| *last != pids) {
+                    for (peer, uri) in self.updates(WATCHED_URIS) {
+                        let _ = peer
+                            .notify_resource_updated(ResourceUpdatedNotificationParam { uri })
+                            .await;
+                    }
+                }
+                last = Some(pids);
+            }
+
+            {
+                let mut poller = lock(&self.poller);
+                let mut subscribers = lock(&self.subscribers);
+                // Sessions that ended cannot unsubscribe
+                subscribers.retain(|subscriber| !subscriber.peer.is_transport_closed());
+                if subscribers.is_empty() {
+                    *poller = None;
+                    return;
+                }
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(std::time::Duration::from_millis(interval_ms)) => {}
+                _ = self.wake.notified() => {}
+            }
+        }
+    }
+}
+
+/// Whether `a` and `b` are peers of the same session
+///
+/// Every session keeps the client info it was initialized with in one shared
+/// allocation, which outlives a subscriber holding a copy of its peer.
+fn same_session(a: &Peer<RoleServer>, b: &Peer<RoleServer>) -> bool {
+    match (a.peer_info(), b.peer_info()) {
+        (Some(a), Some(b)) => std::ptr::eq(a, b),
+        _ => false,
+    }
+}
+
+/// Lock `mutex`, even if a thread panicked while holding it
+fn lock<T>(mutex: &std::sync::Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Send the events given to the returned sender to `peer` as progress
+/// notifications for `token`
+///
+/// The forwarder ends once every sender was dropped and the queued events are sent.
+fn forward_progress(
+    token: ProgressToken,
+    peer: Peer<RoleServer>,
+) -> (mpsc::UnboundedSender<ManagerEvent>, JoinHandle<()>) {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<ManagerEvent>();
+    let forwarder = tokio::spawn(async move {
+        let mut progress = 0.0;
+        while let Some(event) = receiver.recv().await {
+            progress += 1.0;
+            let param = ProgressNotificationParam {
+                progress_token: token.clone(),
+                progress,
+                total: None,
+                message: Some(event.name().to_string()),
+            };
+            // A client that went away no longer cares about progress
+            let _ = peer.notify_progress(param).await;
         }
+    });
+
+    (sender, forwarder)
+}
+
+/// Run a tool's operation through the manager's asynchronous API
+async fn run(
+    manager: &ExplorerManager<BackendRunner>,
+    operation: ToolOperation,
+) -> Result<Outcome, StuckbarError> {
+    match operation {
+        ToolOperation::Kill(strategy) => manager.kill_with_async(strategy).await,
+        ToolOperation::Start => manager.start_async().await,
+        ToolOperation::Restart(options) => manager.restart_with_async(options).await,
+    }
+}
+
+/// Convert an operation result into an MCP tool result
+fn tool_result(result: Result<Outcome, StuckbarError>) -> CallToolResult {
+    match result {
+        Ok(outcome) => CallToolResult::success(vec![Content::text(outcome.message)]),
+        Err(e) => error_result(&e),
+    }
+}
+
+/// Convert a profile result into a structured MCP tool result
+fn profile_result(result: Result<ProfileOutcome, StuckbarError>) -> CallToolResult {
+    match result {
+        Ok(outcome) => CallToolResult::structured(serde_json::json!(outcome)),
+        Err(e) => error_result(&e),
     }
 }
 
+/// Convert a dry run report into a structured MCP tool result
+///
+/// A dry run of an operation that would fail is an error result, so agents see
+/// the failure as they would for the real call.
+fn dry_run_result(report: &DryRunReport) -> CallToolResult {
+    let mut result = if report.success {
+        CallToolResult::success(vec![Content::text(report.message.clone())])
+    } else {
+        CallToolResult::error(vec![Content::text(report.message.clone())])
+    };
+    result.structured_content = Some(serde_json::json!(report));
+    result
+}
+
+/// Convert a taskbar health check into a structured MCP tool result
+fn health_result(health: &TaskbarHealth) -> CallToolResult {
+    CallToolResult::structured(serde_json::json!(health))
+}
+
+/// Convert a shell status into a structured MCP tool result
+fn status_result(status: &ShellStatus) -> CallToolResult {
+    CallToolResult::structured(serde_json::json!(status))
+}
+
+/// Build an MCP tool error carrying both a readable message and the error kind
+///
+/// The structured payload lets agents branch on `kind` instead of parsing text.
+fn error_result(error: &StuckbarError) -> CallToolResult {
+    let mut result = CallToolResult::error(vec![Content::text(error.to_string())]);
+    result.structured_content = Some(serde_json::json!({ "error": error }));
+    result
+}
+
 impl Default for StuckbarMcpServer {
     fn default() -> Self {
         Self::new()
@@ -143,7 +1128,11 @@ impl ServerHandler for StuckbarMcpServer {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: Default::default(),
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities: ServerCapabilities::builder()
+                .enable_tools()
+                .enable_resources()
+                .enable_resources_subscribe()
+                .build(),
             server_info: Implementation {
                 name: "stuckbar".to_string(),
                 version: env!("CARGO_PKG_VERSION").to_string(),
@@ -156,12 +1145,91 @@ impl ServerHandler for StuckbarMcpServer {
                 Available tools:\n\
                 - kill_explorer: Terminate explorer.exe\n\
                 - start_explorer: Start explorer.exe\n\
-                - restart_explorer: Restart explorer.exe (recommended for stuck taskbar)\n\n\
+                - restart_explorer: Restart explorer.exe (recommended for stuck taskbar)\n\
+                  Both kill_explorer and restart_explorer accept graceful and grace_period_ms;\n\
+                  restart_explorer also accepts restore_windows to reopen folder windows\n\
+                  and rebuild_icon_cache to clear broken icons; both are rate limited\n\
+                  and a rate_limited error says when to retry\n\
+                - run_profile: Run a named restart profile, e.g. full-shell to restart\n\
+                  explorer and the other shell hosts in order\n\
+                - check_taskbar_health: Check whether the taskbar is responding\n\
+                - get_explorer_status: Show the taskbar health and every shell process\n\
+                  with its session, uptime, memory, handles, CPU time and responsiveness\n\n\
+                Resources: stuckbar://explorer/processes (the shell processes),\n\
+                stuckbar://taskbar/health, stuckbar://history/recent (the latest kills,\n\
+                starts and restarts; frequent or failing restarts point to a chronic\n\
+                shell problem) and stuckbar://config/effective (the server's settings).\n\
+                Subscribe to the first three to be told when explorer crashes or restarts.\n\n\
+                Every tool but check_taskbar_health and get_explorer_status accepts dry_run\n\
+                to preview its steps.\n\
+                Tools that terminate explorer only touch the server's own session unless\n\
+                given session (a session ID or \"all\").\n\
                 Use 'restart_explorer' to fix a stuck or unresponsive Windows taskbar."
                     .to_string(),
             ),
         }
     }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        Ok(ListResourcesResult {
+            resources: self.resources(),
+            next_cursor: None,
+        })
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        let server = self.clone();
+        tokio::task::spawn_blocking(move || server.read(&request.uri))
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+    }
+
+    async fn subscribe(
+        &self,
+        request: SubscribeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        if !self
+            .resources()
+            .iter()
+            .any(|resource| resource.uri == request.uri)
+        {
+            return Err(McpError::resource_not_found(
+                format!("unknown resource {}", request.uri),
+                None,
+            ));
+        }
+        if context.peer.peer_info().is_none() {
+            return Err(McpError::invalid_request(
+                "subscribe after initializing the session",
+                None,
+            ));
+        }
+        self.watch.subscribe(
+            context.peer,
+            request.uri,
+            self.inspector.clone(),
+            self.watch_interval_ms,
+        );
+        Ok(())
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.watch.unsubscribe(&context.peer, &request.uri);
+        Ok(())
+    }
 }
 
 /// Run the MCP server with STDIO transport
@@ -172,61 +1240,100 @@ impl ServerHandler for StuckbarMcpServer {
 /// # Errors
 ///
 /// Returns an error if the server fails to start or encounters a runtime error.
-pub async fn run_stdio_server() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let server = StuckbarMcpServer::new();
+pub async fn run_stdio_server(
+    server: StuckbarMcpServer,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let service = server.serve(stdio()).await?;
     service.waiting().await?;
     Ok(())
 }
 
-/// Run the MCP server with SSE (Server-Sent Events) HTTP transport
+/// Default path of the Streamable HTTP endpoint
+#[cfg(feature = "mcp-http")]
+pub const STREAMABLE_HTTP_PATH: &str = "/mcp";
+
+/// Default path of the event stream of the SSE transport
+#[cfg(feature = "mcp-http")]
+pub const SSE_PATH: &str = "/sse";
+
+/// Default path requests are POSTed to with the SSE transport
+#[cfg(feature = "mcp-http")]
+pub const SSE_POST_PATH: &str = "/message";
+
+/// Header naming the Streamable HTTP session of a request
+#[cfg(feature = "mcp-http")]
+const SESSION_ID_HEADER: &str = "mcp-session-id";
+
+/// Run the MCP server over HTTP
 ///
-/// This function starts the MCP server using SSE over HTTP for communication.
-/// It binds to the specified host and port.
+/// This function starts the MCP server on the configured host and port, serving
+/// each of its transports from the same listener, over TLS if it has a
+/// certificate. It blocks until Ctrl+C is pressed.
 ///
 /// # Arguments
 ///
-/// * `host` - The host address to bind to (e.g., "127.0.0.1" or "0.0.0.0")
-/// * `port` - The port number to listen on
+/// * `config` - Where and how to serve (see [`HttpServerConfig`])
+/// * `server` - The server whose tools are exposed to every session
 ///
 /// # Errors
 ///
-/// Returns an error if the server fails to start or encounters a runtime error.
+/// Returns an error if the certificate cannot be loaded, or if the server fails
+/// to start or encounters a runtime error.
 #[cfg(feature = "mcp-http")]
 pub async fn run_http_server(
-    host: &str,
-    port: u16,
+    config: HttpServerConfig,
+    server: StuckbarMcpServer,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    use rmcp::transport::sse_server::{SseServer, SseServerConfig};
+    let bind_addr = format!("{}:{}", config.host, config.port);
+    let listener =
+        tokio::net::TcpListener::bind(bind_addr.parse::<std::net::SocketAddr>()?).await?;
 
-    let bind_addr = format!("{}:{}", host, port);
-    let config = SseServerConfig {
-        bind: bind_addr.parse()?,
-        sse_path: "/sse".to_string(),
-        post_path: "/message".to_string(),
-        ct: tokio_util::sync::CancellationToken::new(),
-        sse_keep_alive: None,
+    #[cfg(feature = "mcp-tls")]
+    let tls = match &config.tls {
+        Some(files) => {
+            let tls = files.load(&config.host)?;
+            eprintln!(
+                "TLS certificate SHA-256 fingerprint: {}",
+                crate::tls::fingerprint(&files.cert)?
+            );
+            Some(tls)
+        }
+        None => None,
     };
+    #[cfg(feature = "mcp-tls")]
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    #[cfg(not(feature = "mcp-tls"))]
+    let scheme = "http";
 
-    eprintln!("Starting stuckbar MCP server on http://{}/sse", bind_addr);
+    for transport in &config.transports {
+        eprintln!(
+            "Starting stuckbar MCP server on {}://{}{}",
+            scheme,
+            bind_addr,
+            config.path(*transport)
+        );
+    }
+    if !config.auth.is_enabled() {
+        eprintln!("Warning: no tokens are configured; every request is accepted");
+    }
     eprintln!("Press Ctrl+C to stop the server");
 
-    let (sse_server, router) = SseServer::new(config);
-    let listener = tokio::net::TcpListener::bind(sse_server.config.bind).await?;
-    let ct = sse_server.config.ct.child_token();
-
-    let axum_server = axum::serve(listener, router).with_graceful_shutdown(async move {
-        ct.cancelled().await;
-    });
-
+    let ct = tokio_util::sync::CancellationToken::new();
+    let shutdown = ct.clone();
+    let served = async move {
+        #[cfg(feature = "mcp-tls")]
+        if let Some(tls) = tls {
+            let listener = crate::tls::TlsListener::new(listener, tls)?;
+            return serve_http(listener, config, server, shutdown).await;
+        }
+        serve_http(listener, config, server, shutdown).await
+    };
     tokio::spawn(async move {
-        if let Err(e) = axum_server.await {
+        if let Err(e) = served.await {
             eprintln!("HTTP server error: {}", e);
         }
     });
 
-    let ct = sse_server.with_service(StuckbarMcpServer::new);
-
     // Wait for Ctrl+C
     tokio::signal::ctrl_c().await?;
     eprintln!("\nShutting down...");
@@ -235,7 +1342,306 @@ pub async fn run_http_server(
     Ok(())
 }
 
-/// Configuration for the MCP SSE HTTP server
+/// Serve `server` on `listener` as `config` describes, until `ct` is cancelled
+///
+/// `listener` is a [`TcpListener`](tokio::net::TcpListener) for plain HTTP, or
+/// a TLS listener such as `tls::TlsListener` with the `mcp-tls` feature; the
+/// host, port and certificate of `config` are not used.
+///
+/// # Errors
+///
+/// Returns an error if the listener's address cannot be read.
+#[cfg(feature = "mcp-http")]
+pub async fn serve_http<L>(
+    listener: L,
+    config: HttpServerConfig,
+    server: StuckbarMcpServer,
+    ct: tokio_util::sync::CancellationToken,
+) -> std::io::Result<()>
+where
+    L: axum::serve::Listener<Addr = std::net::SocketAddr>,
+{
+    let mut router = http_router(server, &config, listener.local_addr()?, ct.clone());
+    if !config.cors_origins.is_empty() {
+        router = router.layer(cors_layer(&config.cors_origins));
+    }
+    axum::serve(listener, router)
+        .with_graceful_shutdown(ct.cancelled_owned())
+        .await
+}
+
+/// Router serving `server` over each of the transports of `config`
+///
+/// - [`HttpTransport::StreamableHttp`] takes requests at `streamable_path`
+/// - [`HttpTransport::Sse`] streams events from `sse_path` and takes requests
+///   at `post_path`
+///
+/// Every path is under `base_path`. Every session gets its own copy of
+/// `server`; requests that would open a session beyond `max_sessions` are
+/// answered with `503 Service Unavailable`, and bodies larger than
+/// `max_body_bytes` with `413 Payload Too Large`. When `auth` has tokens,
+/// requests without a valid one are refused.
+///
+/// SSE sessions end when `ct` is cancelled; `bind` is the address the router is
+/// served on. Must be called within a Tokio runtime.
+#[cfg(feature = "mcp-http")]
+pub fn http_router(
+    server: StuckbarMcpServer,
+    config: &HttpServerConfig,
+    bind: std::net::SocketAddr,
+    ct: tokio_util::sync::CancellationToken,
+) -> axum::Router {
+    use axum::middleware::from_fn_with_state;
+    use rmcp::transport::sse_server::{SseServer, SseServerConfig};
+    use rmcp::transport::streamable_http_server::{
+        StreamableHttpServerConfig, StreamableHttpService,
+        session::local::{LocalSessionManager, SessionConfig},
+    };
+
+    let limit = SessionLimit::new(config.max_sessions);
+    let mut router = axum::Router::new();
+    if config.transports.contains(&HttpTransport::StreamableHttp) {
+        let (server, session_limit) = (server.clone(), limit.clone());
+        let sessions = LocalSessionManager {
+            sessions: Default::default(),
+            session_config: SessionConfig {
+                keep_alive: config.session_timeout,
+                ..Default::default()
+            },
+        };
+        let service = StreamableHttpService::new(
+            move || session_limit.session(server.clone()),
+            Arc::new(sessions),
+            StreamableHttpServerConfig {
+                sse_keep_alive: Some(config.sse_keep_alive),
+                stateful_mode: true,
+            },
+        );
+        let streamable = axum::Router::new()
+            .nest_service(&config.streamable_path, service)
+            .layer(from_fn_with_state(
+                (limit.clone(), HttpTransport::StreamableHttp),
+                limit_sessions,
+            ));
+        router = router.merge(streamable);
+    }
+    if config.transports.contains(&HttpTransport::Sse) {
+        let (sse_server, sse_router) = SseServer::new(SseServerConfig {
+            bind,
+            sse_path: config.sse_path.clone(),
+            post_path: config.post_path.clone(),
+            ct,
+            sse_keep_alive: Some(config.sse_keep_alive),
+        });
+        // The returned token is a child of `ct`, which the caller cancels
+        let _ = sse_server.with_service(move || server.clone());
+        let sse_router = sse_router.layer(from_fn_with_state(
+            (limit, HttpTransport::Sse),
+            limit_sessions,
+        ));
+        router = router.merge(sse_router);
+    }
+    if !config.base_path.is_empty() {
+        // The SSE transport announces its POST path under the prefix by itself
+        router = axum::Router::new().nest(&config.base_path, router);
+    }
+    if config.auth.is_enabled() {
+        router = config.auth.clone().protect(router);
+    }
+    // Outside authentication, which reads bodies through the limit
+    router.layer(from_fn_with_state(config.max_body_bytes, limit_body))
+}
+
+/// CORS policy letting browsers on `origins` call the server; `"*"` allows any
+#[cfg(feature = "mcp-http")]
+fn cors_layer(origins: &[String]) -> tower_http::cors::CorsLayer {
+    use axum::http::{HeaderName, HeaderValue, Method, header};
+    use tower_http::cors::{AllowOrigin, CorsLayer};
+
+    let allow_origin = if origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            origins
+                .iter()
+                .filter_map(|origin| HeaderValue::from_str(origin).ok()),
+        )
+    };
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([Method::GET, Method::POST, Method::DELETE])
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            header::ACCEPT,
+            HeaderName::from_static(crate::auth::API_KEY_HEADER),
+            HeaderName::from_static(SESSION_ID_HEADER),
+            HeaderName::from_static("mcp-protocol-version"),
+            HeaderName::from_static("last-event-id"),
+        ])
+        .expose_headers([
+            HeaderName::from_static(SESSION_ID_HEADER),
+            header::WWW_AUTHENTICATE,
+        ])
+}
+
+/// Answer requests declaring bodies over `max_body_bytes` with `413`, and stop
+/// reading any other body at `max_body_bytes`
+#[cfg(feature = "mcp-http")]
+async fn limit_body(
+    axum::extract::State(max_body_bytes): axum::extract::State<usize>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    let declared = request
+        .headers()
+        .get(axum::http::header::CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok()?.parse::<u64>().ok());
+    if declared.is_some_and(|length| length > max_body_bytes as u64) {
+        return crate::auth::reject(
+            axum::http::StatusCode::PAYLOAD_TOO_LARGE,
+            "request body too large",
+        );
+    }
+    let request = request
+        .map(|body| axum::body::Body::new(http_body_util::Limited::new(body, max_body_bytes)));
+    next.run(request).await
+}
+
+/// Caps the sessions served at once
+///
+/// A request that may open a session reserves a slot before it is handled, and
+/// the slot stays with that request. A Streamable HTTP session claims its
+/// request's slot when it is created and frees it when it closes; an SSE
+/// session holds its slot for as long as its event stream is open. Slots that
+/// were not claimed are freed once their request has been answered.
+#[cfg(feature = "mcp-http")]
+#[derive(Clone, Default)]
+struct SessionLimit {
+    /// Free slots, or `None` for no limit
+    slots: Option<Arc<tokio::sync::Semaphore>>,
+}
+
+#[cfg(feature = "mcp-http")]
+tokio::task_local! {
+    /// Slot reserved by the Streamable HTTP request being handled, if any
+    static RESERVED_SLOT: std::cell::RefCell<Option<tokio::sync::OwnedSemaphorePermit>>;
+}
+
+#[cfg(feature = "mcp-http")]
+impl SessionLimit {
+    fn new(max_sessions: Option<usize>) -> Self {
+        Self {
+            slots: max_sessions.map(|max| Arc::new(tokio::sync::Semaphore::new(max))),
+        }
+    }
+
+    /// Reserve a slot, `None` without a limit; an error if every slot is taken
+    fn reserve(
+        &self,
+    ) -> Result<Option<tokio::sync::OwnedSemaphorePermit>, tokio::sync::TryAcquireError> {
+        self.slots
+            .as_ref()
+            .map(|slots| slots.clone().try_acquire_owned())
+            .transpose()
+    }
+
+    /// Service of a new Streamable HTTP session, holding the slot its request
+    /// reserved
+    ///
+    /// Fails if there is a limit but the request reserved no slot.
+    fn session(&self, server: StuckbarMcpServer) -> std::io::Result<SessionService> {
+        let slot = match &self.slots {
+            Some(_) => Some(
+                RESERVED_SLOT
+                    .try_with(|slot| slot.borrow_mut().take())
+                    .ok()
+                    .flatten()
+                    .ok_or_else(|| std::io::Error::other("no session slot was reserved"))?,
+            ),
+            None => None,
+        };
+        Ok(SessionService {
+            server,
+            _slot: slot,
+        })
+    }
+}
+
+/// Answer requests that would open a session of `transport` beyond the limit
+/// with `503`
+#[cfg(feature = "mcp-http")]
+async fn limit_sessions(
+    axum::extract::State((limit, transport)): axum::extract::State<(SessionLimit, HttpTransport)>,
+    request: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    use axum::http::Method;
+    use http_body_util::BodyExt;
+
+    let opens_session = match transport {
+        HttpTransport::StreamableHttp => {
+            request.method() == Method::POST && !request.headers().contains_key(SESSION_ID_HEADER)
+        }
+        HttpTransport::Sse => request.method() == Method::GET,
+    };
+    if !opens_session {
+        return next.run(request).await;
+    }
+    let Ok(slot) = limit.reserve() else {
+        return crate::auth::reject(
+            axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            "too many sessions",
+        );
+    };
+    match transport {
+        // The session is created while the request is handled
+        HttpTransport::StreamableHttp => {
+            RESERVED_SLOT
+                .scope(std::cell::RefCell::new(slot), next.run(request))
+                .await
+        }
+        HttpTransport::Sse => next.run(request).await.map(|body| {
+            axum::body::Body::new(body.map_frame(move |frame| {
+                let _slot = &slot;
+                frame
+            }))
+        }),
+    }
+}
+
+/// The server of one Streamable HTTP session, holding its slot of the
+/// [`SessionLimit`]
+#[cfg(feature = "mcp-http")]
+struct SessionService {
+    server: StuckbarMcpServer,
+    _slot: Option<tokio::sync::OwnedSemaphorePermit>,
+}
+
+#[cfg(feature = "mcp-http")]
+impl rmcp::Service<RoleServer> for SessionService {
+    async fn handle_request(
+        &self,
+        request: ClientRequest,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ServerResult, McpError> {
+        self.server.handle_request(request, context).await
+    }
+
+    async fn handle_notification(
+        &self,
+        notification: ClientNotification,
+        context: rmcp::service::NotificationContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.server.handle_notification(notification, context).await
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        rmcp::Service::get_info(&self.server)
+    }
+}
+
+/// Configuration for the MCP HTTP server
 #[cfg(feature = "mcp-http")]
 #[derive(Debug, Clone)]
 pub struct HttpServerConfig {
@@ -243,10 +1649,48 @@ pub struct HttpServerConfig {
     pub host: String,
     /// Port number to listen on
     pub port: u16,
+    /// Transports served from the port (see [`http_router`])
+    pub transports: Vec<HttpTransport>,
+    /// Tokens clients must present
+    pub auth: HttpAuth,
+    /// Prefix of every path, e.g. `/stuckbar` behind a reverse proxy; empty
+    /// serves from the root
+    pub base_path: String,
+    /// Path of the Streamable HTTP endpoint (default: "/mcp")
+    pub streamable_path: String,
     /// Path for SSE endpoint (default: "/sse")
     pub sse_path: String,
     /// Path for message POST endpoint (default: "/message")
     pub post_path: String,
+    /// Interval between keep-alive pings on event streams
+    pub sse_keep_alive: Duration,
+    /// Origins browsers may call the server from; `"*"` allows any, none
+    /// disables CORS
+    pub cors_origins: Vec<String>,
+    /// Largest request body accepted
+    pub max_body_bytes: usize,
+    /// Sessions served at once; `None` is unlimited
+    ///
+    /// Set a [`session_timeout`](Self::session_timeout) too: a Streamable HTTP
+    /// session that its client abandons otherwise keeps its slot.
+    pub max_sessions: Option<usize>,
+    /// Time after which an idle Streamable HTTP session is closed
+    pub session_timeout: Option<Duration>,
+    /// Certificate and key to serve TLS with; plain HTTP if `None`
+    #[cfg(feature = "mcp-tls")]
+    pub tls: Option<crate::tls::TlsFiles>,
+}
+
+#[cfg(feature = "mcp-http")]
+impl HttpServerConfig {
+    /// Full path clients connect to for `transport`
+    pub fn path(&self, transport: HttpTransport) -> String {
+        let path = match transport {
+            HttpTransport::StreamableHttp => &self.streamable_path,
+            HttpTransport::Sse => &self.sse_path,
+        };
+        format!("{}{}", self.base_path, path)
+    }
 }
 
 #[cfg(feature = "mcp-http")]
@@ -255,8 +1699,19 @@ impl Default for HttpServerConfig {
         Self {
             host: "127.0.0.1".to_string(),
             port: 8080,
-            sse_path: "/sse".to_string(),
-            post_path: "/message".to_string(),
+            transports: vec![HttpTransport::StreamableHttp, HttpTransport::Sse],
+            auth: HttpAuth::default(),
+            base_path: String::new(),
+            streamable_path: STREAMABLE_HTTP_PATH.to_string(),
+            sse_path: SSE_PATH.to_string(),
+            post_path: SSE_POST_PATH.to_string(),
+            sse_keep_alive: Duration::from_millis(SSE_KEEP_ALIVE_MS),
+            cors_origins: Vec::new(),
+            max_body_bytes: MAX_BODY_BYTES as usize,
+            max_sessions: None,
+            session_timeout: None,
+            #[cfg(feature = "mcp-tls")]
+            tls: None,
         }
     }
 }
@@ -264,6 +1719,7 @@ impl Default for HttpServerConfig {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::inspect::ProcessDetails;
 
     #[test]
     fn test_server_creation() {
@@ -298,6 +1754,490 @@ mod tests {
 
         // Server should have tools capability enabled
         assert!(info.capabilities.tools.is_some());
+        assert!(info.capabilities.resources.is_some());
+    }
+
+    #[test]
+    fn test_tool_result_success() {
+        let result = tool_result(Ok(Outcome::new("done")));
+
+        assert_eq!(result.is_error, Some(false));
+        assert!(result.structured_content.is_none());
+    }
+
+    #[test]
+    fn test_tool_result_error_payload() {
+        let result = tool_result(Err(StuckbarError::NotRunning {
+            process: "explorer.exe".to_string(),
+        }));
+
+        assert_eq!(result.is_error, Some(true));
+        let payload = result.structured_content.unwrap();
+        assert_eq!(payload["error"]["kind"], "not_running");
+        assert_eq!(payload["error"]["message"], "explorer.exe is not running");
+    }
+
+    #[test]
+    fn test_progress_sink_forwards_events() {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<ManagerEvent>();
+        let sink = ProgressSink(sender);
+
+        sink.on_event(&TargetSpec::explorer(), &ManagerEvent::KillStarted);
+        drop(sink);
+
+        assert_eq!(receiver.try_recv(), Ok(ManagerEvent::KillStarted));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_servers_share_a_coordinator() {
+        let first = StuckbarMcpServer::new();
+        let second = StuckbarMcpServer::new();
+
+        assert!(Arc::ptr_eq(&first.coordinator, &second.coordinator));
+    }
+
+    #[test]
+    fn test_tool_result_rate_limited_payload() {
+        let result = tool_result(Err(StuckbarError::RateLimited {
+            reason: "restarts must be at least 5000ms apart".to_string(),
+            retry_after_ms: 1200,
+        }));
+
+        assert_eq!(result.is_error, Some(true));
+        let payload = result.structured_content.unwrap();
+        assert_eq!(payload["error"]["kind"], "rate_limited");
+        assert_eq!(payload["error"]["retry_after_ms"], 1200);
+    }
+
+    #[test]
+    fn test_server_restart_policy() {
+        let policy = RestartPolicy {
+            max_restarts: 1,
+            ..RestartPolicy::default()
+        };
+        let server = StuckbarMcpServer::new().with_restart_policy(policy);
+
+        assert_eq!(
+            server
+                .manager(SessionScope::Current)
+                .limiter
+                .unwrap()
+                .policy(),
+            policy
+        );
+    }
+
+    #[test]
+    fn test_kill_args_strategy() {
+        assert_eq!(KillArgs::default().strategy(), KillStrategy::Force);
+
+        let args: KillArgs = serde_json::from_value(serde_json::json!({"graceful": true})).unwrap();
+        assert_eq!(args.strategy(), KillStrategy::graceful());
+
+        let args: KillArgs =
+            serde_json::from_value(serde_json::json!({"graceful": true, "grace_period_ms": 500}))
+                .unwrap();
+        assert_eq!(args.strategy(), KillStrategy::Graceful { grace_ms: 500 });
+    }
+
+    #[test]
+    fn test_restart_args_options() {
+        assert_eq!(RestartArgs::default().options(), RestartOptions::default());
+
+        let args: RestartArgs =
+            serde_json::from_value(serde_json::json!({"graceful": true, "restore_windows": true}))
+                .unwrap();
+        assert_eq!(
+            args.options(),
+            RestartOptions {
+                kill_strategy: KillStrategy::graceful(),
+                restore_windows: true,
+                rebuild_icon_cache: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_health_result() {
+        let result = health_result(&TaskbarHealth::Hung {
+            reason: "no reply within 10ms".to_string(),
+        });
+
+        assert_eq!(result.is_error, Some(false));
+        let payload = result.structured_content.unwrap();
+        assert_eq!(payload["status"], "hung");
+        assert_eq!(payload["healthy"], false);
+    }
+
+    #[test]
+    fn test_status_result() {
+        use crate::mock::{MockProcessInspector, MockWindowInspector};
+
+        let server = StuckbarMcpServer::new().with_inspector(MockProcessInspector::new(vec![
+            ProcessDetails {
+                process: "explorer.exe".to_string(),
+                pid: 4312,
+                handle_count: Some(3120),
+                responding: Some(true),
+                ..Default::default()
+            },
+        ]));
+        let status = ShellStatus::collect(
+            &MockWindowInspector::responsive(),
+            &*server.inspector,
+            RESPONSE_TIMEOUT_MS,
+        );
+
+        let result = status_result(&status);
+        assert_eq!(result.is_error, Some(false));
+        let payload = result.structured_content.unwrap();
+        assert_eq!(payload["status"], "responsive");
+        assert_eq!(payload["processes"][0]["pid"], 4312);
+        assert_eq!(payload["processes"][0]["handle_count"], 3120);
+        assert!(payload["processes"][0]["uptime_ms"].is_null());
+    }
+
+    #[test]
+    fn test_history_resource() {
+        use crate::events::Action;
+        use crate::history::{HistoryEntry, HistoryLog};
+
+        let uris = |server: &StuckbarMcpServer| -> Vec<String> {
+            server
+                .resources()
+                .iter()
+                .map(|resource| resource.uri.clone())
+                .collect()
+        };
+        assert_eq!(
+            uris(&StuckbarMcpServer::new()),
+            vec![PROCESSES_URI, HEALTH_URI]
+        );
+        assert!(StuckbarMcpServer::new().read(HISTORY_URI).is_err());
+
+        let dir = std::env::temp_dir().join(format!("stuckbar-mcp-history-{}", std::process::id()));
+        let log = HistoryLog::new(dir.join("history.jsonl"));
+        let event = ManagerEvent::finished(Action::Restart, &Ok(Outcome::new("Restarted")));
+        for timestamp in 0..RECENT_ENTRIES as u64 + 5 {
+            let entry =
+                HistoryEntry::from_event(timestamp, Trigger::Mcp, &TargetSpec::explorer(), &event);
+            log.append(&entry.unwrap()).unwrap();
+        }
+        let server = StuckbarMcpServer::new().with_history(log);
+
+        assert_eq!(uris(&server), vec![PROCESSES_URI, HEALTH_URI, HISTORY_URI]);
+
+        let result = server.read(HISTORY_URI).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let ResourceContents::TextResourceContents { text, .. } = &result.contents[0] else {
+            panic!("expected text contents");
+        };
+        let value: serde_json::Value = serde_json::from_str(text).unwrap();
+        let entries = value["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), RECENT_ENTRIES);
+        assert_eq!(entries[0]["timestamp_ms"], 5);
+        assert_eq!(entries[0]["trigger"], "mcp");
+        assert!(server.read("stuckbar://unknown").is_err());
+    }
+
+    #[test]
+    fn test_effective_config_resource() {
+        use crate::config::{Layer, Source};
+
+        assert!(StuckbarMcpServer::new().read(CONFIG_URI).is_err());
+
+        let mut cli = Layer::new();
+        cli.set("mcp.tokens", vec!["s3cret"]);
+        let config = LayeredConfig::new().merge(cli, Source::Cli).unwrap();
+        let server = StuckbarMcpServer::new().with_effective_config(&config);
+        assert!(
+            server
+                .resources()
+                .iter()
+                .any(|resource| resource.uri == CONFIG_URI)
+        );
+
+        let result = server.read(CONFIG_URI).unwrap();
+        let ResourceContents::TextResourceContents { text, .. } = &result.contents[0] else {
+            panic!("expected text contents");
+        };
+        let value: serde_json::Value = serde_json::from_str(text).unwrap();
+        assert_eq!(value["file"], serde_json::Value::Null);
+        let settings = value["settings"].as_array().unwrap();
+        let tokens = settings
+            .iter()
+            .find(|entry| entry["key"] == "mcp.tokens")
+            .unwrap();
+        assert_eq!(tokens["value"], serde_json::json!(["<redacted>"]));
+        assert_eq!(tokens["source"], "cli");
+        assert!(!text.contains("s3cret"));
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_state_resources_need_windows() {
+        let server = StuckbarMcpServer::new();
+        for uri in [PROCESSES_URI, HEALTH_URI] {
+            let error = server.read(uri).unwrap_err();
+            assert_eq!(error.data.unwrap()["error"]["kind"], "unsupported_platform");
+        }
+    }
+
+    /// Inspector reporting an explorer whose PID the test changes
+    #[derive(Clone, Default)]
+    struct RestartingInspector {
+        pid: Arc<std::sync::atomic::AtomicU32>,
+        checks: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl ProcessInspector for RestartingInspector {
+        fn inspect(&self, _process_names: &[&str]) -> Result<Vec<ProcessDetails>, StuckbarError> {
+            use std::sync::atomic::Ordering;
+
+            self.checks.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![ProcessDetails {
+                process: "explorer.exe".to_string(),
+                pid: self.pid.load(Ordering::SeqCst),
+                ..Default::default()
+            }])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resource_subscriptions() {
+        use std::sync::atomic::Ordering;
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let inspector = RestartingInspector::default();
+        inspector.pid.store(100, Ordering::SeqCst);
+        let server = StuckbarMcpServer::new()
+            .with_inspector(inspector.clone())
+            .with_watch_interval(10);
+        let (client, transport) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move {
+            let service = server.serve(transport).await.unwrap();
+            let _ = service.waiting().await;
+        });
+
+        let (reader, mut writer) = tokio::io::split(client);
+        let mut lines = BufReader::new(reader).lines();
+        let mut send = async |message: serde_json::Value| {
+            let line = format!("{}\n", message);
+            writer.write_all(line.as_bytes()).await.unwrap();
+        };
+        let request = |id: u32, method: &str, params: serde_json::Value| serde_json::json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+
+        let exchange = async {
+            send(request(
+                1,
+                "initialize",
+                serde_json::json!({
+                    "protocolVersion": "2025-03-26",
+                    "capabilities": {},
+                    "clientInfo": {"name": "stuckbar-test", "version": "0.0.0"},
+                }),
+            ))
+            .await;
+            let line = lines.next_line().await.unwrap().unwrap();
+            let initialize: serde_json::Value = serde_json::from_str(&line).unwrap();
+            assert_eq!(
+                initialize["result"]["capabilities"]["resources"]["subscribe"],
+                true
+            );
+            send(serde_json::json!({"jsonrpc": "2.0", "method": "notifications/initialized"}))
+                .await;
+
+            let unknown = serde_json::json!({"uri": "stuckbar://unknown"});
+            send(request(2, "resources/subscribe", unknown)).await;
+            let line = lines.next_line().await.unwrap().unwrap();
+            assert!(line.contains("\"error\""), "{}", line);
+
+            let processes = serde_json::json!({"uri": PROCESSES_URI});
+            send(request(3, "resources/subscribe", processes.clone())).await;
+            let line = lines.next_line().await.unwrap().unwrap();
+            assert!(line.contains("\"result\""), "{}", line);
+
+            // Explorer restarts once the first check has seen it
+            while inspector.checks.load(Ordering::SeqCst) == 0 {
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            }
+            inspector.pid.store(200, Ordering::SeqCst);
+            let line = lines.next_line().await.unwrap().unwrap();
+            let notification: serde_json::Value = serde_json::from_str(&line).unwrap();
+            assert_eq!(notification["method"], "notifications/resources/updated");
+            assert_eq!(notification["params"]["uri"], PROCESSES_URI);
+
+            // Unsubscribed sessions are not told
+            send(request(4, "resources/unsubscribe", processes)).await;
+            let line = lines.next_line().await.unwrap().unwrap();
+            assert!(line.contains("\"id\":4"), "{}", line);
+            let checks = inspector.checks.load(Ordering::SeqCst);
+            inspector.pid.store(300, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            assert!(inspector.checks.load(Ordering::SeqCst) <= checks + 1);
+            send(request(5, "ping", serde_json::json!({}))).await;
+            let line = lines.next_line().await.unwrap().unwrap();
+            assert!(line.contains("\"id\":5"), "{}", line);
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(10), exchange)
+            .await
+            .expect("the exchange timed out");
+    }
+
+    #[test]
+    fn test_allowed_tools_hide_other_tools() {
+        let server = StuckbarMcpServer::new()
+            .with_allowed_tools(&["check_taskbar_health".to_string()])
+            .unwrap();
+
+        let names: Vec<String> = server
+            .tool_router
+            .list_all()
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect();
+        assert_eq!(names, vec!["check_taskbar_health"]);
+    }
+
+    #[test]
+    fn test_allowed_tools_reject_unknown_names() {
+        let result = StuckbarMcpServer::new().with_allowed_tools(&["format_disk".to_string()]);
+
+        let err = result.err().unwrap();
+        assert_eq!(err.kind(), "invalid_config");
+        assert!(err.to_string().contains("format_disk"));
+    }
+
+    #[test]
+    fn test_server_restart_delay() {
+        let server = StuckbarMcpServer::new().with_restart_delay(50);
+
+        assert_eq!(server.manager(SessionScope::Current).restart_delay_ms, 50);
+    }
+
+    /// The `profile` property of the `run_profile` input schema
+    fn profile_schema(server: &StuckbarMcpServer) -> serde_json::Value {
+        let route = &server.tool_router.map["run_profile"];
+        serde_json::Value::Object((*route.attr.input_schema).clone())["properties"]["profile"]
+            .clone()
+    }
+
+    #[test]
+    fn test_run_profile_schema_enumerates_builtin_profiles() {
+        let property = profile_schema(&StuckbarMcpServer::new());
+
+        assert_eq!(
+            property["enum"],
+            serde_json::json!(["full-shell", "start-menu", "taskbar"])
+        );
+        assert!(
+            property["description"]
+                .as_str()
+                .unwrap()
+                .contains("full-shell (")
+        );
+    }
+
+    #[test]
+    fn test_run_profile_schema_lists_declared_profiles() {
+        let declared = BTreeMap::from([(
+            "flyouts".to_string(),
+            Profile {
+                description: "Restart the flyouts".to_string(),
+                targets: vec!["shell-experience".to_string()],
+                ..Profile::default()
+            },
+        )]);
+        let server = StuckbarMcpServer::new().with_profiles(&declared);
+
+        let property = profile_schema(&server);
+        assert_eq!(
+            property["enum"],
+            serde_json::json!(["flyouts", "full-shell", "start-menu", "taskbar"])
+        );
+        assert!(server.profiles["flyouts"].description.contains("flyouts"));
+    }
+
+    #[test]
+    fn test_profiles_survive_allowed_tools() {
+        let server = StuckbarMcpServer::new()
+            .with_allowed_tools(&["check_taskbar_health".to_string()])
+            .unwrap()
+            .with_profiles(&BTreeMap::new());
+
+        assert!(!server.tool_router.has_route("run_profile"));
+    }
+
+    #[test]
+    fn test_profile_result() {
+        let result = profile_result(Ok(ProfileOutcome {
+            profile: "taskbar".to_string(),
+            message: "Profile taskbar restarted explorer".to_string(),
+            targets: Vec::new(),
+            steps: Vec::new(),
+        }));
+        assert_eq!(result.is_error, Some(false));
+        assert_eq!(result.structured_content.unwrap()["profile"], "taskbar");
+
+        let result = profile_result(Err(StuckbarError::Busy {
+            operation: "restart".to_string(),
+        }));
+        assert_eq!(result.is_error, Some(true));
+    }
+
+    #[test]
+    fn test_dry_run_args() {
+        let args: RestartArgs =
+            serde_json::from_value(serde_json::json!({"dry_run": true, "graceful": true})).unwrap();
+        assert!(args.kill.dry_run);
+        assert!(!StartArgs::default().dry_run);
+        let args: RunProfileArgs =
+            serde_json::from_value(serde_json::json!({"profile": "taskbar"})).unwrap();
+        assert!(!args.dry_run);
+    }
+
+    #[test]
+    fn test_dry_run_result() {
+        let plan = DryRunPlan::new();
+        plan.record(crate::dry_run::PlannedStep::Wait { ms: 500 });
+
+        let result = dry_run_result(&DryRunReport::new("restart", &plan, Ok(())));
+        assert_eq!(result.is_error, Some(false));
+        let structured = result.structured_content.unwrap();
+        assert_eq!(structured["steps"][0]["step"], "wait");
+
+        let error = StuckbarError::NotRunning {
+            process: "explorer.exe".to_string(),
+        };
+        let result = dry_run_result(&DryRunReport::new("kill", &plan, Err(error)));
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(
+            result.structured_content.unwrap()["error"]["kind"],
+            "not_running"
+        );
+    }
+
+    #[test]
+    fn test_session_args() {
+        let args: RestartArgs =
+            serde_json::from_value(serde_json::json!({"session": "2"})).unwrap();
+        assert_eq!(args.kill.session.as_deref(), Some("2"));
+        let args: RunProfileArgs =
+            serde_json::from_value(serde_json::json!({"profile": "taskbar"})).unwrap();
+        assert_eq!(args.session, None);
+
+        let server = StuckbarMcpServer::new().with_session_scope(SessionScope::All);
+        assert_eq!(server.session_scope(None), Ok(SessionScope::All));
+        assert_eq!(server.session_scope(Some("3")), Ok(SessionScope::Id(3)));
+        assert_eq!(
+            server.session_scope(Some("everyone")).unwrap_err().kind(),
+            "invalid_config"
+        );
+        assert_eq!(
+            server.manager(SessionScope::Id(3)).session_scope,
+            SessionScope::Id(3)
+        );
     }
 
     #[cfg(feature = "mcp-http")]
@@ -306,5 +2246,36 @@ mod tests {
         let config = HttpServerConfig::default();
         assert_eq!(config.host, "127.0.0.1");
         assert_eq!(config.port, 8080);
+        assert_eq!(config.path(HttpTransport::StreamableHttp), "/mcp");
+
+        let config = HttpServerConfig {
+            base_path: "/stuckbar".to_string(),
+            sse_path: "/events".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(config.path(HttpTransport::Sse), "/stuckbar/events");
+    }
+
+    #[cfg(feature = "mcp-http")]
+    #[test]
+    fn test_sessions_claim_their_own_request_slot() {
+        let limit = SessionLimit::new(Some(1));
+        assert!(limit.session(StuckbarMcpServer::new()).is_err());
+
+        let slot = limit.reserve().unwrap();
+        assert!(slot.is_some());
+        assert!(limit.reserve().is_err());
+        let session = RESERVED_SLOT.sync_scope(std::cell::RefCell::new(slot), || {
+            limit.session(StuckbarMcpServer::new())
+        });
+        let session = session.unwrap();
+        // The slot went with the session, not back to the pool
+        assert!(limit.reserve().is_err());
+        drop(session);
+        assert!(limit.reserve().unwrap().is_some());
+
+        let unlimited = SessionLimit::new(None);
+        assert!(unlimited.reserve().unwrap().is_none());
+        assert!(unlimited.session(StuckbarMcpServer::new()).is_ok());
     }
 }
@@ -0,0 +1,525 @@
+//! Test doubles shared by the unit tests of several modules
+
+use crate::events::{EventSink, ManagerEvent};
+use crate::icon_cache::CacheFileSystem;
+use crate::inspect::{ProcessDetails, ProcessInspector};
+use crate::session::{ExplorerWindow, ShellWindowEnumerator};
+use crate::window::{WindowHandle, WindowInspector};
+use crate::{Outcome, ProcessInfo, ProcessRunner, ProcessUsage, StuckbarError, TargetSpec};
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+pub(crate) fn kill_failed(details: &str) -> StuckbarError {
+    StuckbarError::KillFailed {
+        process: "explorer.exe".to_string(),
+        details: details.to_string(),
+    }
+}
+
+pub(crate) fn spawn_failed(details: &str) -> StuckbarError {
+    StuckbarError::SpawnFailed {
+        process: "explorer.exe".to_string(),
+        details: details.to_string(),
+    }
+}
+
+/// Session of the caller and of instances not given one
+pub(crate) const MOCK_SESSION: u32 = 1;
+
+/// Account of the caller and of instances not given one
+pub(crate) const MOCK_OWNER: &str = "S-1-5-21-1000";
+
+/// Mock process runner for testing
+///
+/// Simulates a process table: successful kills remove the running instances
+/// and successful starts add a new one. Sleeping advances a fake clock. Like on
+/// a single-user desktop, the caller and every instance run in
+/// [`MOCK_SESSION`] as [`MOCK_OWNER`] unless told otherwise.
+pub struct MockProcessRunner {
+    kill_results: RefCell<Vec<Result<Outcome, StuckbarError>>>,
+    start_results: RefCell<Vec<Result<Outcome, StuckbarError>>>,
+    sleep_calls: RefCell<Vec<u64>>,
+    killed: RefCell<Vec<String>>,
+    killed_pids: RefCell<Vec<u32>>,
+    exit_requests: RefCell<Vec<String>>,
+    exit_refusal: RefCell<Option<StuckbarError>>,
+    exits_on_request: Cell<bool>,
+    started: RefCell<Vec<TargetSpec>>,
+    running: RefCell<Vec<u32>>,
+    next_pid: Cell<u32>,
+    exits_on_kill: Cell<bool>,
+    respawns_on_kill: Cell<bool>,
+    spawn_appears: Cell<bool>,
+    crashes_after_start: Cell<bool>,
+    usage: RefCell<Vec<Vec<ProcessUsage>>>,
+    sessions: RefCell<Vec<(u32, u32)>>,
+    owners: RefCell<Vec<(u32, String)>>,
+    caller: RefCell<Option<ProcessInfo>>,
+    epoch: Instant,
+    clock_ms: Cell<u64>,
+}
+
+impl MockProcessRunner {
+    pub fn new() -> Self {
+        Self {
+            kill_results: RefCell::new(Vec::new()),
+            start_results: RefCell::new(Vec::new()),
+            sleep_calls: RefCell::new(Vec::new()),
+            killed: RefCell::new(Vec::new()),
+            killed_pids: RefCell::new(Vec::new()),
+            exit_requests: RefCell::new(Vec::new()),
+            exit_refusal: RefCell::new(None),
+            exits_on_request: Cell::new(false),
+            started: RefCell::new(Vec::new()),
+            running: RefCell::new(Vec::new()),
+            next_pid: Cell::new(1000),
+            exits_on_kill: Cell::new(true),
+            respawns_on_kill: Cell::new(false),
+            spawn_appears: Cell::new(true),
+            crashes_after_start: Cell::new(false),
+            usage: RefCell::new(Vec::new()),
+            sessions: RefCell::new(Vec::new()),
+            owners: RefCell::new(Vec::new()),
+            caller: RefCell::new(Some(ProcessInfo {
+                pid: 1,
+                session_id: Some(MOCK_SESSION),
+                owner: Some(MOCK_OWNER.to_string()),
+            })),
+            epoch: Instant::now(),
+            clock_ms: Cell::new(0),
+        }
+    }
+
+    pub fn with_kill_result(self, result: Result<Outcome, StuckbarError>) -> Self {
+        self.kill_results.borrow_mut().push(result);
+        self
+    }
+
+    pub fn with_start_result(self, result: Result<Outcome, StuckbarError>) -> Self {
+        self.start_results.borrow_mut().push(result);
+        self
+    }
+
+    pub fn with_running(self, pids: &[u32]) -> Self {
+        self.running.borrow_mut().extend_from_slice(pids);
+        self
+    }
+
+    /// Killed instances keep running
+    pub fn with_stuck_processes(self) -> Self {
+        self.exits_on_kill.set(false);
+        self
+    }
+
+    /// Running instances exit as soon as they are asked to
+    pub fn with_graceful_exit(self) -> Self {
+        self.exits_on_request.set(true);
+        self
+    }
+
+    /// Requests to exit fail with `error`
+    pub fn with_exit_refused(self, error: StuckbarError) -> Self {
+        *self.exit_refusal.borrow_mut() = Some(error);
+        self
+    }
+
+    /// A new instance appears right after a kill, as Windows does for shell hosts
+    pub fn with_respawn(self) -> Self {
+        self.respawns_on_kill.set(true);
+        self
+    }
+
+    /// Started instances never show up in the process list
+    pub fn with_spawn_vanishing(self) -> Self {
+        self.spawn_appears.set(false);
+        self
+    }
+
+    /// Started instances show up once and then exit
+    pub fn with_crash_after_start(self) -> Self {
+        self.crashes_after_start.set(true);
+        self
+    }
+
+    /// Queue a usage sample; samples are returned in the order they were added
+    pub fn with_usage(self, sample: Vec<ProcessUsage>) -> Self {
+        self.usage.borrow_mut().insert(0, sample);
+        self
+    }
+
+    /// Report `pid` as running in `session`
+    pub fn with_session(self, pid: u32, session: u32) -> Self {
+        self.sessions.borrow_mut().push((pid, session));
+        self
+    }
+
+    /// Report `pid` as running as `owner`
+    pub fn with_owner(self, pid: u32, owner: &str) -> Self {
+        self.owners.borrow_mut().push((pid, owner.to_string()));
+        self
+    }
+
+    /// Report the calling process as running in `session` as `owner`
+    pub fn with_caller(self, session: u32, owner: &str) -> Self {
+        *self.caller.borrow_mut() = Some(ProcessInfo {
+            pid: 1,
+            session_id: Some(session),
+            owner: Some(owner.to_string()),
+        });
+        self
+    }
+
+    /// The calling process cannot be identified
+    pub fn with_unknown_caller(self) -> Self {
+        *self.caller.borrow_mut() = None;
+        self
+    }
+
+    /// Instance `pid` runs in `session` as `owner`
+    pub fn with_instance(self, pid: u32, session: u32, owner: &str) -> Self {
+        self.with_running(&[pid])
+            .with_session(pid, session)
+            .with_owner(pid, owner)
+    }
+
+    pub fn get_sleep_calls(&self) -> Vec<u64> {
+        self.sleep_calls.borrow().clone()
+    }
+
+    pub fn get_killed(&self) -> Vec<String> {
+        self.killed.borrow().clone()
+    }
+
+    /// PIDs terminated one at a time
+    pub fn get_killed_pids(&self) -> Vec<u32> {
+        self.killed_pids.borrow().clone()
+    }
+
+    pub fn get_exit_requests(&self) -> Vec<String> {
+        self.exit_requests.borrow().clone()
+    }
+
+    pub fn get_started(&self) -> Vec<TargetSpec> {
+        self.started.borrow().clone()
+    }
+
+    pub fn elapsed_ms(&self) -> u64 {
+        self.clock_ms.get()
+    }
+
+    fn spawn_pid(&self) -> u32 {
+        let pid = self.next_pid.get();
+        self.next_pid.set(pid + 1);
+        self.running.borrow_mut().push(pid);
+        pid
+    }
+}
+
+impl Default for MockProcessRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcessRunner for MockProcessRunner {
+    fn kill_process(&self, process_name: &str) -> Result<Outcome, StuckbarError> {
+        self.killed.borrow_mut().push(process_name.to_string());
+        let result = self
+            .kill_results
+            .borrow_mut()
+            .pop()
+            .unwrap_or_else(|| Err(kill_failed("No mock result configured")));
+
+        if result.is_ok() && self.exits_on_kill.get() {
+            self.running.borrow_mut().clear();
+            if self.respawns_on_kill.get() {
+                self.spawn_pid();
+            }
+        }
+        result
+    }
+
+    fn start_process(&self, target: &TargetSpec) -> Result<Outcome, StuckbarError> {
+        self.started.borrow_mut().push(target.clone());
+        let result = self
+            .start_results
+            .borrow_mut()
+            .pop()
+            .unwrap_or_else(|| Err(spawn_failed("No mock result configured")));
+
+        result.map(|outcome| {
+            if self.spawn_appears.get() {
+                let pid = self.spawn_pid();
+                outcome.with_new_pid(pid)
+            } else {
+                outcome
+            }
+        })
+    }
+
+    fn list_pids(&self, _process_name: &str) -> Result<Vec<u32>, StuckbarError> {
+        let pids = self.running.borrow().clone();
+        if self.crashes_after_start.get() {
+            // Anything started by the mock dies after being observed once
+            self.running.borrow_mut().retain(|pid| *pid < 1000);
+        }
+        Ok(pids)
+    }
+
+    fn kill_pid(&self, pid: u32, process_name: &str) -> Result<(), StuckbarError> {
+        if !self.running.borrow().contains(&pid) {
+            return Err(StuckbarError::NotRunning {
+                process: process_name.to_string(),
+            });
+        }
+        self.killed_pids.borrow_mut().push(pid);
+        if self.exits_on_kill.get() {
+            self.running.borrow_mut().retain(|running| *running != pid);
+        }
+        Ok(())
+    }
+
+    fn list_processes(&self, process_name: &str) -> Result<Vec<ProcessInfo>, StuckbarError> {
+        let sessions = self.sessions.borrow();
+        let owners = self.owners.borrow();
+        Ok(self
+            .list_pids(process_name)?
+            .into_iter()
+            .map(|pid| ProcessInfo {
+                pid,
+                session_id: sessions
+                    .iter()
+                    .find(|(known, _)| *known == pid)
+                    .map_or(Some(MOCK_SESSION), |(_, session)| Some(*session)),
+                owner: owners
+                    .iter()
+                    .find(|(known, _)| *known == pid)
+                    .map_or(Some(MOCK_OWNER.to_string()), |(_, owner)| {
+                        Some(owner.clone())
+                    }),
+            })
+            .collect())
+    }
+
+    fn current_process(&self) -> Option<ProcessInfo> {
+        self.caller.borrow().clone()
+    }
+
+    fn request_exit(&self, process_name: &str) -> Result<(), StuckbarError> {
+        self.exit_requests
+            .borrow_mut()
+            .push(process_name.to_string());
+        if let Some(error) = self.exit_refusal.borrow().clone() {
+            return Err(error);
+        }
+        if self.exits_on_request.get() {
+            self.running.borrow_mut().clear();
+        }
+        Ok(())
+    }
+
+    fn process_usage(&self, _process_name: &str) -> Result<Vec<ProcessUsage>, StuckbarError> {
+        Ok(self.usage.borrow_mut().pop().unwrap_or_default())
+    }
+
+    fn sleep_ms(&self, ms: u64) {
+        self.sleep_calls.borrow_mut().push(ms);
+        self.clock_ms.set(self.clock_ms.get() + ms);
+    }
+
+    fn now(&self) -> Instant {
+        self.epoch + std::time::Duration::from_millis(self.clock_ms.get())
+    }
+}
+
+/// Mock shell window enumerator with a fixed set of open windows
+///
+/// Clones share the record of opened windows, so a clone handed to a manager can
+/// still be inspected by the test.
+#[derive(Clone, Default)]
+pub struct MockShellWindows {
+    windows: Vec<ExplorerWindow>,
+    failing_paths: Vec<String>,
+    list_error: Option<StuckbarError>,
+    opened: Arc<Mutex<Vec<ExplorerWindow>>>,
+}
+
+impl MockShellWindows {
+    pub fn new(windows: Vec<ExplorerWindow>) -> Self {
+        Self {
+            windows,
+            ..Default::default()
+        }
+    }
+
+    /// Opening a window on `path` fails
+    pub fn with_failing_path(mut self, path: &str) -> Self {
+        self.failing_paths.push(path.to_string());
+        self
+    }
+
+    /// Listing the open windows fails with `error`
+    pub fn with_list_error(mut self, error: StuckbarError) -> Self {
+        self.list_error = Some(error);
+        self
+    }
+
+    pub fn get_opened(&self) -> Vec<ExplorerWindow> {
+        self.opened.lock().unwrap().clone()
+    }
+}
+
+impl ShellWindowEnumerator for MockShellWindows {
+    fn open_windows(&self) -> Result<Vec<ExplorerWindow>, StuckbarError> {
+        match &self.list_error {
+            Some(error) => Err(error.clone()),
+            None => Ok(self.windows.clone()),
+        }
+    }
+
+    fn open_window(&self, window: &ExplorerWindow) -> Result<(), StuckbarError> {
+        if self.failing_paths.contains(&window.path) {
+            return Err(spawn_failed(&format!("cannot open {}", window.path)));
+        }
+        self.opened.lock().unwrap().push(window.clone());
+        Ok(())
+    }
+}
+
+/// Mock file system holding a fixed list of files
+///
+/// Clones share the files, so a clone handed to a manager can still be inspected
+/// by the test.
+#[derive(Clone, Default)]
+pub struct MockCacheFiles {
+    files: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+impl MockCacheFiles {
+    pub fn new(paths: &[&str]) -> Self {
+        Self {
+            files: Arc::new(Mutex::new(paths.iter().map(PathBuf::from).collect())),
+        }
+    }
+
+    pub fn get_files(&self) -> Vec<PathBuf> {
+        self.files.lock().unwrap().clone()
+    }
+}
+
+impl CacheFileSystem for MockCacheFiles {
+    fn list_files(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let files = self.files.lock().unwrap();
+        Ok(files
+            .iter()
+            .filter(|f| f.starts_with(dir))
+            .cloned()
+            .collect())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.files.lock().unwrap().retain(|f| f != path);
+        Ok(())
+    }
+}
+
+/// Sink that records every event it receives
+#[derive(Clone, Default)]
+pub struct RecordingSink {
+    events: Arc<Mutex<Vec<ManagerEvent>>>,
+}
+
+impl RecordingSink {
+    pub fn get_events(&self) -> Vec<ManagerEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    pub fn get_names(&self) -> Vec<&'static str> {
+        self.get_events().iter().map(ManagerEvent::name).collect()
+    }
+}
+
+impl EventSink for RecordingSink {
+    fn on_event(&self, _target: &TargetSpec, event: &ManagerEvent) {
+        self.events.lock().unwrap().push(event.clone());
+    }
+}
+
+/// Mock process inspector reporting a fixed set of instances
+pub struct MockProcessInspector {
+    processes: Vec<ProcessDetails>,
+    error: Option<StuckbarError>,
+}
+
+impl MockProcessInspector {
+    pub fn new(processes: Vec<ProcessDetails>) -> Self {
+        Self {
+            processes,
+            error: None,
+        }
+    }
+
+    /// Every query fails with `error`
+    pub fn failing(error: StuckbarError) -> Self {
+        Self {
+            processes: Vec::new(),
+            error: Some(error),
+        }
+    }
+}
+
+impl ProcessInspector for MockProcessInspector {
+    fn inspect(&self, process_names: &[&str]) -> Result<Vec<ProcessDetails>, StuckbarError> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+        Ok(self
+            .processes
+            .iter()
+            .filter(|details| {
+                process_names
+                    .iter()
+                    .any(|name| name.eq_ignore_ascii_case(&details.process))
+            })
+            .cloned()
+            .collect())
+    }
+}
+
+/// Mock window inspector whose taskbar is either responsive or missing
+pub struct MockWindowInspector {
+    taskbar: Option<WindowHandle>,
+}
+
+impl MockWindowInspector {
+    pub fn responsive() -> Self {
+        Self {
+            taskbar: Some(WindowHandle(0x1234)),
+        }
+    }
+
+    pub fn missing() -> Self {
+        Self { taskbar: None }
+    }
+}
+
+impl WindowInspector for MockWindowInspector {
+    fn find_window(&self, _class_name: &str) -> Option<WindowHandle> {
+        self.taskbar
+    }
+
+    fn is_hung(&self, _window: WindowHandle) -> bool {
+        false
+    }
+
+    fn responds_within(&self, _window: WindowHandle, _timeout_ms: u32) -> bool {
+        true
+    }
+
+    fn post_message(&self, _window: WindowHandle, _message: u32) -> bool {
+        true
+    }
+}
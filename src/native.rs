@@ -0,0 +1,327 @@
+//! # Native Process Backend
+//!
+//! A [`ProcessRunner`] that talks to the Win32 API directly instead of shelling out
+//! to `taskkill` and `tasklist`. Processes are enumerated with a Toolhelp snapshot
+//! and terminated one PID at a time with `TerminateProcess`, so the result for each
+//! instance is known and nothing depends on `PATH` or localized console output.
+//!
+//! Graceful exit requests post window messages: the taskbar's exit message for
+//! explorer and `WM_CLOSE` to the top-level windows of anything else. Starting
+//! processes and sampling resource usage are delegated to [`SystemProcessRunner`].
+//!
+//! This module requires the `native` feature. On platforms other than Windows every
+//! query fails with [`StuckbarError::UnsupportedPlatform`].
+
+pub use crate::PidResult;
+use crate::window::close_process_windows;
+use crate::{
+    Outcome, ProcessInfo, ProcessRunner, ProcessUsage, StuckbarError, SystemProcessRunner,
+    TargetSpec, posted_taskbar_exit, summarize_kill,
+};
+
+/// Process runner backed by the Toolhelp and process APIs
+pub struct NativeProcessRunner;
+
+impl NativeProcessRunner {
+    /// Terminate every instance of `process_name`, reporting the result per PID
+    pub fn terminate_all(&self, process_name: &str) -> Result<Vec<PidResult>, StuckbarError> {
+        Ok(self
+            .list_pids(process_name)?
+            .into_iter()
+            .map(|pid| PidResult {
+                pid,
+                result: sys::terminate(pid, process_name),
+            })
+            .collect())
+    }
+}
+
+impl ProcessRunner for NativeProcessRunner {
+    fn kill_process(&self, process_name: &str) -> Result<Outcome, StuckbarError> {
+        let results = self.terminate_all(process_name)?;
+        summarize_kill(process_name, results)
+    }
+
+    fn start_process(&self, target: &TargetSpec) -> Result<Outcome, StuckbarError> {
+        SystemProcessRunner.start_process(target)
+    }
+
+    /// Posts the taskbar's exit message for explorer, like [`SystemProcessRunner`],
+    /// and `WM_CLOSE` to the windows of other processes instead of running `taskkill`
+    fn request_exit(&self, process_name: &str) -> Result<(), StuckbarError> {
+        if posted_taskbar_exit(process_name)
+            || close_process_windows(&self.list_pids(process_name)?)
+        {
+            return Ok(());
+        }
+        Err(StuckbarError::KillFailed {
+            process: process_name.to_string(),
+            details: "no window to ask to close".to_string(),
+        })
+    }
+
+    fn list_pids(&self, process_name: &str) -> Result<Vec<u32>, StuckbarError> {
+        Ok(matching_pids(sys::snapshot()?, process_name))
+    }
+
+    fn list_processes(&self, process_name: &str) -> Result<Vec<ProcessInfo>, StuckbarError> {
+        Ok(matching_pids(sys::snapshot()?, process_name)
+            .into_iter()
+            .map(process_info)
+            .collect())
+    }
+
+    fn current_process(&self) -> Option<ProcessInfo> {
+        Some(process_info(std::process::id()))
+    }
+
+    fn kill_pid(&self, pid: u32, process_name: &str) -> Result<(), StuckbarError> {
+        sys::terminate(pid, process_name)
+    }
+
+    fn process_usage(&self, process_name: &str) -> Result<Vec<ProcessUsage>, StuckbarError> {
+        SystemProcessRunner.process_usage(process_name)
+    }
+
+    fn sleep_ms(&self, ms: u64) {
+        SystemProcessRunner.sleep_ms(ms);
+    }
+}
+
+/// PIDs of the snapshot entries whose executable name matches `process_name`
+fn matching_pids(entries: Vec<(u32, String)>, process_name: &str) -> Vec<u32> {
+    entries
+        .into_iter()
+        .filter(|(_, name)| name.eq_ignore_ascii_case(process_name))
+        .map(|(pid, _)| pid)
+        .collect()
+}
+
+/// Session and owner of `pid`, each `None` if it cannot be queried
+fn process_info(pid: u32) -> ProcessInfo {
+    ProcessInfo {
+        pid,
+        session_id: sys::session_id(pid),
+        owner: sys::owner(pid),
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use crate::StuckbarError;
+    use windows_sys::Win32::Foundation::{
+        CloseHandle, ERROR_ACCESS_DENIED, ERROR_INVALID_PARAMETER, GetLastError, HANDLE,
+        INVALID_HANDLE_VALUE, LocalFree,
+    };
+    use windows_sys::Win32::Security::Authorization::ConvertSidToStringSidW;
+    use windows_sys::Win32::Security::{GetTokenInformation, TOKEN_QUERY, TOKEN_USER, TokenUser};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, PROCESSENTRY32W, Process32FirstW, Process32NextW,
+        TH32CS_SNAPPROCESS,
+    };
+    use windows_sys::Win32::System::RemoteDesktop::ProcessIdToSessionId;
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, OpenProcessToken, PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_TERMINATE,
+        TerminateProcess,
+    };
+
+    /// Closes the wrapped handle when dropped
+    struct OwnedHandle(HANDLE);
+
+    impl Drop for OwnedHandle {
+        fn drop(&mut self) {
+            // SAFETY: the handle was returned by a successful Win32 call and is closed once
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+
+    /// List `(pid, executable name)` for every process on the system
+    pub(super) fn snapshot() -> Result<Vec<(u32, String)>, StuckbarError> {
+        // SAFETY: plain FFI call; the returned handle is checked below
+        let handle = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(StuckbarError::KillFailed {
+                process: "process snapshot".to_string(),
+                details: std::io::Error::last_os_error().to_string(),
+            });
+        }
+        let snapshot = OwnedHandle(handle);
+
+        let mut entries = Vec::new();
+        let mut entry = PROCESSENTRY32W {
+            dwSize: size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+        // SAFETY: `entry` is a properly sized PROCESSENTRY32W for the duration of each call
+        let mut more = unsafe { Process32FirstW(snapshot.0, &mut entry) } != 0;
+        while more {
+            let len = entry
+                .szExeFile
+                .iter()
+                .position(|c| *c == 0)
+                .unwrap_or(entry.szExeFile.len());
+            entries.push((
+                entry.th32ProcessID,
+                String::from_utf16_lossy(&entry.szExeFile[..len]),
+            ));
+            // SAFETY: as above
+            more = unsafe { Process32NextW(snapshot.0, &mut entry) } != 0;
+        }
+        Ok(entries)
+    }
+
+    /// Terminate a single process by PID
+    pub(super) fn terminate(pid: u32, process_name: &str) -> Result<(), StuckbarError> {
+        // SAFETY: plain FFI call; a null handle signals failure
+        let handle = unsafe { OpenProcess(PROCESS_TERMINATE, 0, pid) };
+        if handle.is_null() {
+            return Err(last_error(pid, process_name));
+        }
+        let process = OwnedHandle(handle);
+
+        // SAFETY: `process` was opened with PROCESS_TERMINATE
+        if unsafe { TerminateProcess(process.0, 1) } == 0 {
+            return Err(last_error(pid, process_name));
+        }
+        Ok(())
+    }
+
+    /// Session of a process, or `None` if it exited or cannot be queried
+    pub(super) fn session_id(pid: u32) -> Option<u32> {
+        let mut session = 0;
+        // SAFETY: `session` is a valid out pointer for the duration of the call
+        (unsafe { ProcessIdToSessionId(pid, &mut session) } != 0).then_some(session)
+    }
+
+    /// Account SID of a process as `S-1-5-…`, or `None` if it cannot be queried
+    pub(super) fn owner(pid: u32) -> Option<String> {
+        // SAFETY: plain FFI call; a null handle signals failure
+        let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+        if handle.is_null() {
+            return None;
+        }
+        let process = OwnedHandle(handle);
+
+        let mut token = std::ptr::null_mut();
+        // SAFETY: `process` was opened with query rights and `token` is a valid out pointer
+        if unsafe { OpenProcessToken(process.0, TOKEN_QUERY, &mut token) } == 0 {
+            return None;
+        }
+        let token = OwnedHandle(token);
+
+        // The first call only reports the size; the buffer is u64 so TOKEN_USER is aligned
+        let mut len = 0;
+        // SAFETY: a null buffer of length 0 is allowed when querying the size
+        unsafe { GetTokenInformation(token.0, TokenUser, std::ptr::null_mut(), 0, &mut len) };
+        if len == 0 {
+            return None;
+        }
+        let mut buffer = vec![0u64; (len as usize).div_ceil(size_of::<u64>())];
+        // SAFETY: `buffer` holds at least `len` bytes for the duration of the call
+        let queried = unsafe {
+            GetTokenInformation(
+                token.0,
+                TokenUser,
+                buffer.as_mut_ptr().cast(),
+                len,
+                &mut len,
+            )
+        };
+        if queried == 0 {
+            return None;
+        }
+        // SAFETY: the call succeeded, so the buffer starts with an aligned TOKEN_USER
+        let user = unsafe { &*buffer.as_ptr().cast::<TOKEN_USER>() };
+
+        let mut sid = std::ptr::null_mut();
+        // SAFETY: the SID points into `buffer`; `sid` is a valid out pointer
+        if unsafe { ConvertSidToStringSidW(user.User.Sid, &mut sid) } == 0 {
+            return None;
+        }
+        // SAFETY: on success `sid` is a NUL-terminated string allocated with LocalAlloc
+        let owner = unsafe {
+            let len = (0..).take_while(|&i| *sid.add(i) != 0).count();
+            let owner = String::from_utf16_lossy(std::slice::from_raw_parts(sid, len));
+            LocalFree(sid.cast());
+            owner
+        };
+        Some(owner)
+    }
+
+    fn last_error(pid: u32, process_name: &str) -> StuckbarError {
+        // SAFETY: reads the calling thread's last-error value
+        let code = unsafe { GetLastError() };
+        let details = format!(
+            "PID {}: {}",
+            pid,
+            std::io::Error::from_raw_os_error(code as i32)
+        );
+        match code {
+            ERROR_ACCESS_DENIED => StuckbarError::AccessDenied {
+                process: process_name.to_string(),
+                details,
+            },
+            // The process exited between the snapshot and the call
+            ERROR_INVALID_PARAMETER => StuckbarError::NotRunning {
+                process: process_name.to_string(),
+            },
+            _ => StuckbarError::KillFailed {
+                process: process_name.to_string(),
+                details,
+            },
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod sys {
+    use crate::StuckbarError;
+
+    fn unsupported() -> StuckbarError {
+        StuckbarError::UnsupportedPlatform {
+            os: std::env::consts::OS.to_string(),
+        }
+    }
+
+    pub(super) fn snapshot() -> Result<Vec<(u32, String)>, StuckbarError> {
+        Err(unsupported())
+    }
+
+    pub(super) fn terminate(_pid: u32, _process_name: &str) -> Result<(), StuckbarError> {
+        Err(unsupported())
+    }
+
+    pub(super) fn session_id(_pid: u32) -> Option<u32> {
+        None
+    }
+
+    pub(super) fn owner(_pid: u32) -> Option<String> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matching_pids() {
+        let entries = vec![
+            (4, "System".to_string()),
+            (100, "explorer.exe".to_string()),
+            (200, "SearchHost.exe".to_string()),
+            (300, "EXPLORER.EXE".to_string()),
+        ];
+        assert_eq!(matching_pids(entries, "explorer.exe"), vec![100, 300]);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_unsupported_off_windows() {
+        let err = NativeProcessRunner.list_pids("explorer.exe").unwrap_err();
+        assert_eq!(err.kind(), "unsupported_platform");
+        // Exit requests look for windows instead of running taskkill
+        let err = NativeProcessRunner.request_exit("notepad.exe").unwrap_err();
+        assert_eq!(err.kind(), "unsupported_platform");
+    }
+}
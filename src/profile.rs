@@ -0,0 +1,563 @@
+//! # Restart Profiles
+//!
+//! A profile is a named recipe for a restart: which targets to restart and in
+//! which order, how to kill them, how long to wait, and what to do once they are
+//! all back. A few are built in:
+//!
+//! | Profile      | Targets                                        |
+//! |--------------|------------------------------------------------|
+//! | `taskbar`    | `explorer`                                     |
+//! | `full-shell` | `explorer`, `start-menu`, `shell-experience`, `search` |
+//! | `start-menu` | `start-menu`                                   |
+//!
+//! More can be declared in the configuration file, and a declared profile
+//! replaces a built-in one of the same name:
+//!
+//! ```toml
+//! [profiles.flyouts]
+//! description = "Restart the notification center and Start menu"
+//! targets = ["shell-experience", "start-menu"]
+//! kill_strategy = "graceful"
+//! pause_ms = 1000
+//! post_steps = [
+//!     { step = "wait", ms = 2000 },
+//!     { step = "run", command = "ie4uinit.exe", args = ["-show"] },
+//! ]
+//! ```
+
+use crate::config::KillMode;
+use crate::target::PRESET_NAMES;
+use crate::window::{RESPONSE_TIMEOUT_MS, SystemWindowInspector, TaskbarHealth, check_taskbar};
+use crate::{
+    GRACE_PERIOD_MS, KillStrategy, Outcome, ProcessManager, ProcessRunner, RESTART_DELAY_MS,
+    RestartOptions, StuckbarError, TargetSpec,
+};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+/// Names of the built-in profiles, in display order
+pub const BUILTIN_PROFILE_NAMES: &[&str] = &["taskbar", "full-shell", "start-menu"];
+
+/// Delay in milliseconds between the targets of the `full-shell` profile
+pub const FULL_SHELL_PAUSE_MS: u64 = 1_000;
+
+/// An ordered restart of one or more targets
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub description: String,
+    /// Target presets restarted in this order (see [`PRESET_NAMES`])
+    pub targets: Vec<String>,
+    pub kill_strategy: KillMode,
+    /// Time a graceful exit may take before the process is forced
+    pub grace_period_ms: u64,
+    /// Delay between kill and start of each target
+    pub delay_ms: u64,
+    /// Delay between one target being back and the next one being killed
+    pub pause_ms: u64,
+    /// Reopen the File Explorer windows that were open before explorer restarted
+    pub restore_windows: bool,
+    /// Remove Explorer's icon and thumbnail caches while it is down
+    pub rebuild_icon_cache: bool,
+    /// Steps run once every target was restarted
+    pub post_steps: Vec<PostStep>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            description: String::new(),
+            targets: vec!["explorer".to_string()],
+            kill_strategy: KillMode::Force,
+            grace_period_ms: GRACE_PERIOD_MS,
+            delay_ms: RESTART_DELAY_MS,
+            pause_ms: 0,
+            restore_windows: false,
+            rebuild_icon_cache: false,
+            post_steps: Vec::new(),
+        }
+    }
+}
+
+/// Something done after every target of a profile was restarted
+///
+/// A failed step is reported but does not fail the profile: the targets were
+/// already restarted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case", deny_unknown_fields)]
+pub enum PostStep {
+    /// Pause before the next step
+    Wait { ms: u64 },
+    /// Check that the taskbar responds
+    CheckTaskbar,
+    /// Run a command and wait for it to exit successfully
+    Run {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+impl PostStep {
+    /// Stable, machine-readable identifier for the step
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Wait { .. } => "wait",
+            Self::CheckTaskbar => "check_taskbar",
+            Self::Run { .. } => "run",
+        }
+    }
+}
+
+/// Trait for abstracting the waits and post-steps of a profile (enables testing)
+pub trait StepRunner {
+    fn sleep_ms(&self, ms: u64);
+    fn check_taskbar(&self) -> TaskbarHealth;
+    /// Run `command` and wait for it to exit
+    fn run_command(&self, command: &str, args: &[String]) -> Result<(), StuckbarError>;
+}
+
+/// [`StepRunner`] acting on the real system
+pub struct SystemStepRunner;
+
+impl StepRunner for SystemStepRunner {
+    fn sleep_ms(&self, ms: u64) {
+        std::thread::sleep(std::time::Duration::from_millis(ms));
+    }
+
+    fn check_taskbar(&self) -> TaskbarHealth {
+        check_taskbar(&SystemWindowInspector, RESPONSE_TIMEOUT_MS)
+    }
+
+    fn run_command(&self, command: &str, args: &[String]) -> Result<(), StuckbarError> {
+        let status = Command::new(command)
+            .args(args)
+            .status()
+            .map_err(|e| crate::map_spawn_error(command, command, e))?;
+        if status.success() {
+            return Ok(());
+        }
+        Err(StuckbarError::SpawnFailed {
+            process: command.to_string(),
+            details: format!("exited with {}", status),
+        })
+    }
+}
+
+/// Result of one target of a profile
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct TargetOutcome {
+    /// Preset name of the target
+    pub target: String,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+/// Result of one post-step of a profile
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct StepReport {
+    /// The step's [`name`](PostStep::name)
+    pub step: &'static str,
+    pub success: bool,
+    pub message: String,
+}
+
+/// Successful outcome of a profile
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct ProfileOutcome {
+    pub profile: String,
+    pub message: String,
+    /// Outcome of every target, in the order they were restarted
+    pub targets: Vec<TargetOutcome>,
+    pub steps: Vec<StepReport>,
+}
+
+impl Profile {
+    /// Look up a built-in profile by name (see [`BUILTIN_PROFILE_NAMES`])
+    pub fn builtin(name: &str) -> Option<Self> {
+        let targets = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        match name {
+            "taskbar" => Some(Self {
+                description: "Restart explorer.exe, which hosts the taskbar".to_string(),
+                post_steps: vec![PostStep::CheckTaskbar],
+                ..Self::default()
+            }),
+            "full-shell" => Some(Self {
+                description: "Restart explorer and every shell host, reopening folder windows"
+                    .to_string(),
+                targets: targets(PRESET_NAMES),
+                kill_strategy: KillMode::Graceful,
+                pause_ms: FULL_SHELL_PAUSE_MS,
+                restore_windows: true,
+                post_steps: vec![PostStep::CheckTaskbar],
+                ..Self::default()
+            }),
+            "start-menu" => Some(Self {
+                description: "Restart the Start menu host only".to_string(),
+                targets: targets(&["start-menu"]),
+                ..Self::default()
+            }),
+            _ => None,
+        }
+    }
+
+    /// The configured [`KillStrategy`]
+    pub fn strategy(&self) -> KillStrategy {
+        self.kill_strategy.strategy(self.grace_period_ms)
+    }
+
+    /// Options of every restart the profile runs
+    pub fn options(&self) -> RestartOptions {
+        RestartOptions {
+            kill_strategy: self.strategy(),
+            restore_windows: self.restore_windows,
+            rebuild_icon_cache: self.rebuild_icon_cache,
+        }
+    }
+
+    /// Run the profile named `name`
+    ///
+    /// `manager` creates the manager of each target; the profile's delay and
+    /// restart options are applied to it. A target that is not running is started
+    /// instead. `steps` performs the pauses and post-steps.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StuckbarError::InvalidConfig`] if a target is not a preset, and
+    /// otherwise the error of the first target that could not be restarted; the
+    /// remaining targets are left alone.
+    pub fn run<R: ProcessRunner>(
+        &self,
+        name: &str,
+        mut manager: impl FnMut(TargetSpec) -> Result<ProcessManager<R>, StuckbarError>,
+        steps: &impl StepRunner,
+    ) -> Result<ProfileOutcome, StuckbarError> {
+        let specs = self.target_specs(name)?;
+
+        let mut targets = Vec::new();
+        for (index, (preset, spec)) in self.targets.iter().zip(specs).enumerate() {
+            if index > 0 && self.pause_ms > 0 {
+                steps.sleep_ms(self.pause_ms);
+            }
+            let manager = manager(spec)?.with_restart_delay(self.delay_ms);
+            let outcome = match manager.restart_with(self.options()) {
+                // Nothing to kill: the process crashed without being relaunched
+                Err(StuckbarError::NotRunning { .. }) => manager.start(),
+                result => result,
+            }?;
+            targets.push(TargetOutcome {
+                target: preset.clone(),
+                outcome,
+            });
+        }
+
+        let steps = self
+            .post_steps
+            .iter()
+            .map(|step| run_step(step, steps))
+            .collect();
+        Ok(ProfileOutcome {
+            profile: name.to_string(),
+            message: format!("Profile {} restarted {}", name, self.targets.join(", ")),
+            targets,
+            steps,
+        })
+    }
+
+    /// Resolve the target presets, failing on the first unknown name
+    fn target_specs(&self, name: &str) -> Result<Vec<TargetSpec>, StuckbarError> {
+        self.targets
+            .iter()
+            .map(|target| {
+                TargetSpec::preset(target).ok_or_else(|| StuckbarError::InvalidConfig {
+                    details: format!(
+                        "profile {}: unknown target '{}', expected one of: {}",
+                        name,
+                        target,
+                        PRESET_NAMES.join(", ")
+                    ),
+                })
+            })
+            .collect()
+    }
+}
+
+fn run_step(step: &PostStep, steps: &impl StepRunner) -> StepReport {
+    let (success, message) = match step {
+        PostStep::Wait { ms } => {
+            steps.sleep_ms(*ms);
+            (true, format!("Waited {}ms", ms))
+        }
+        PostStep::CheckTaskbar => {
+            let health = steps.check_taskbar();
+            (health.is_healthy(), health.describe())
+        }
+        PostStep::Run { command, args } => match steps.run_command(command, args) {
+            Ok(()) => (true, format!("Ran {}", command)),
+            Err(e) => (false, e.to_string()),
+        },
+    };
+    StepReport {
+        step: step.name(),
+        success,
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockProcessRunner;
+    use std::cell::RefCell;
+
+    /// Records the steps it is asked to perform
+    #[derive(Default)]
+    struct MockSteps {
+        sleeps: RefCell<Vec<u64>>,
+        commands: RefCell<Vec<String>>,
+        failing_command: Option<String>,
+    }
+
+    impl StepRunner for MockSteps {
+        fn sleep_ms(&self, ms: u64) {
+            self.sleeps.borrow_mut().push(ms);
+        }
+
+        fn check_taskbar(&self) -> TaskbarHealth {
+            TaskbarHealth::Responsive
+        }
+
+        fn run_command(&self, command: &str, _args: &[String]) -> Result<(), StuckbarError> {
+            self.commands.borrow_mut().push(command.to_string());
+            if self.failing_command.as_deref() == Some(command) {
+                return Err(StuckbarError::CommandMissing {
+                    command: command.to_string(),
+                });
+            }
+            Ok(())
+        }
+    }
+
+    /// Manager whose target restarts successfully
+    fn restartable(target: TargetSpec) -> Result<ProcessManager<MockProcessRunner>, StuckbarError> {
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_kill_result(Ok(Outcome::new("Killed")))
+            .with_start_result(Ok(Outcome::new("Started")));
+        // Windows relaunches the shell hosts on its own
+        let runner = match target.launch_command {
+            Some(_) => runner,
+            None => runner.with_respawn(),
+        };
+        Ok(ProcessManager::for_target(runner, target))
+    }
+
+    fn profile(targets: &[&str]) -> Profile {
+        Profile {
+            targets: targets.iter().map(|t| t.to_string()).collect(),
+            ..Profile::default()
+        }
+    }
+
+    #[test]
+    fn test_builtin_profiles() {
+        for name in BUILTIN_PROFILE_NAMES {
+            let profile = Profile::builtin(name).unwrap();
+            assert!(!profile.description.is_empty());
+            assert!(profile.target_specs(name).is_ok());
+        }
+        assert_eq!(
+            Profile::builtin("full-shell").unwrap().targets,
+            PRESET_NAMES
+        );
+        assert!(Profile::builtin("everything").is_none());
+    }
+
+    #[test]
+    fn test_profile_strategy() {
+        assert_eq!(Profile::default().strategy(), KillStrategy::Force);
+
+        let profile = Profile {
+            kill_strategy: KillMode::Graceful,
+            grace_period_ms: 800,
+            ..Profile::default()
+        };
+        assert_eq!(profile.strategy(), KillStrategy::Graceful { grace_ms: 800 });
+    }
+
+    #[test]
+    fn test_run_restarts_targets_in_order() {
+        let mut created = Vec::new();
+        let steps = MockSteps::default();
+        let profile = Profile {
+            pause_ms: 250,
+            ..profile(&["start-menu", "explorer", "search"])
+        };
+
+        let outcome = profile
+            .run(
+                "custom",
+                |target| {
+                    created.push(target.process_name.clone());
+                    restartable(target)
+                },
+                &steps,
+            )
+            .unwrap();
+
+        assert_eq!(
+            created,
+            vec![
+                "StartMenuExperienceHost.exe",
+                "explorer.exe",
+                "SearchHost.exe"
+            ]
+        );
+        let names: Vec<&str> = outcome.targets.iter().map(|t| t.target.as_str()).collect();
+        assert_eq!(names, vec!["start-menu", "explorer", "search"]);
+        // Only between targets, not before the first one
+        assert_eq!(*steps.sleeps.borrow(), vec![250, 250]);
+        assert_eq!(outcome.profile, "custom");
+    }
+
+    #[test]
+    fn test_run_stops_at_first_failure() {
+        let mut created = 0;
+        let result = profile(&["explorer", "search"]).run(
+            "custom",
+            |target| {
+                created += 1;
+                let runner = MockProcessRunner::new()
+                    .with_running(&[42])
+                    .with_kill_result(Err(crate::mock::kill_failed("Access denied")));
+                Ok(ProcessManager::for_target(runner, target))
+            },
+            &MockSteps::default(),
+        );
+
+        assert_eq!(result.unwrap_err().kind(), "kill_failed");
+        assert_eq!(created, 1);
+    }
+
+    #[test]
+    fn test_run_starts_target_that_is_not_running() {
+        let outcome = profile(&["explorer"])
+            .run(
+                "custom",
+                |target| {
+                    let runner = MockProcessRunner::new()
+                        .with_kill_result(Err(StuckbarError::NotRunning {
+                            process: "explorer.exe".to_string(),
+                        }))
+                        .with_start_result(Ok(Outcome::new("Started")));
+                    Ok(ProcessManager::for_target(runner, target))
+                },
+                &MockSteps::default(),
+            )
+            .unwrap();
+
+        assert!(outcome.targets[0].outcome.new_pid.is_some());
+    }
+
+    #[test]
+    fn test_run_rejects_unknown_target_before_acting() {
+        let mut created = 0;
+        let result = profile(&["explorer", "notepad"]).run(
+            "custom",
+            |target| {
+                created += 1;
+                restartable(target)
+            },
+            &MockSteps::default(),
+        );
+
+        let err = result.unwrap_err();
+        assert_eq!(err.kind(), "invalid_config");
+        assert!(err.to_string().contains("notepad"));
+        assert_eq!(created, 0);
+    }
+
+    #[test]
+    fn test_failed_post_step_is_reported() {
+        let steps = MockSteps {
+            failing_command: Some("missing.exe".to_string()),
+            ..MockSteps::default()
+        };
+        let profile = Profile {
+            post_steps: vec![
+                PostStep::Wait { ms: 2000 },
+                PostStep::Run {
+                    command: "missing.exe".to_string(),
+                    args: Vec::new(),
+                },
+                PostStep::CheckTaskbar,
+            ],
+            ..profile(&["explorer"])
+        };
+
+        let outcome = profile.run("custom", restartable, &steps).unwrap();
+
+        let results: Vec<(&str, bool)> = outcome
+            .steps
+            .iter()
+            .map(|step| (step.step, step.success))
+            .collect();
+        assert_eq!(
+            results,
+            vec![("wait", true), ("run", false), ("check_taskbar", true)]
+        );
+        assert_eq!(*steps.sleeps.borrow(), vec![2000]);
+        assert_eq!(*steps.commands.borrow(), vec!["missing.exe"]);
+    }
+
+    #[test]
+    fn test_profile_from_toml() {
+        let profile: Profile = toml::from_str(
+            r#"
+            targets = ["shell-experience", "start-menu"]
+            kill_strategy = "graceful"
+            post_steps = [
+                { step = "wait", ms = 500 },
+                { step = "run", command = "ie4uinit.exe", args = ["-show"] },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(profile.targets, vec!["shell-experience", "start-menu"]);
+        assert_eq!(profile.strategy(), KillStrategy::graceful());
+        assert_eq!(profile.delay_ms, RESTART_DELAY_MS);
+        assert_eq!(
+            profile.post_steps[1],
+            PostStep::Run {
+                command: "ie4uinit.exe".to_string(),
+                args: vec!["-show".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_unknown_post_step_is_rejected() {
+        let result: Result<Profile, _> = toml::from_str(r#"post_steps = [{ step = "reboot" }]"#);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_profile_outcome_json() {
+        let outcome = ProfileOutcome {
+            profile: "taskbar".to_string(),
+            message: "Profile taskbar restarted explorer".to_string(),
+            targets: vec![TargetOutcome {
+                target: "explorer".to_string(),
+                outcome: Outcome::new("Restarted"),
+            }],
+            steps: vec![run_step(&PostStep::CheckTaskbar, &MockSteps::default())],
+        };
+
+        let json = serde_json::to_value(&outcome).unwrap();
+        assert_eq!(json["targets"][0]["target"], "explorer");
+        assert_eq!(json["targets"][0]["message"], "Restarted");
+        assert_eq!(json["steps"][0]["step"], "check_taskbar");
+        assert_eq!(json["steps"][0]["success"], true);
+    }
+}
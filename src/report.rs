@@ -0,0 +1,213 @@
+//! # Reporting
+//!
+//! [`EventSink`]s that present manager operations on the console:
+//!
+//! - [`TextSink`] prints colored, human-readable progress
+//! - [`JsonSink`] writes one [`OperationReport`] JSON document per operation, to
+//!   stdout or appended to the log file set by `log.file`
+
+use crate::events::{Action, EventSink, ManagerEvent};
+use crate::{Outcome, StuckbarError, TargetSpec};
+use colored::Colorize;
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Stable, serializable summary of a finished operation
+///
+/// The [`Outcome`] fields are flattened into the document. On failure they are
+/// left empty, `message` holds the error message and `error` its kind.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OperationReport {
+    pub action: Action,
+    pub target: String,
+    pub success: bool,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+    pub error: Option<StuckbarError>,
+}
+
+impl OperationReport {
+    pub fn new(
+        action: Action,
+        target: &TargetSpec,
+        result: &Result<Outcome, StuckbarError>,
+    ) -> Self {
+        let (outcome, error) = match result {
+            Ok(outcome) => (outcome.clone(), None),
+            Err(e) => (Outcome::new(e.to_string()), Some(e.clone())),
+        };
+        Self {
+            action,
+            target: target.process_name.clone(),
+            success: error.is_none(),
+            outcome,
+            error,
+        }
+    }
+}
+
+/// Sink that prints colored progress messages
+///
+/// Progress goes to stdout, as does the result of a successful operation; errors
+/// go to stderr.
+pub struct TextSink;
+
+impl EventSink for TextSink {
+    fn on_event(&self, target: &TargetSpec, event: &ManagerEvent) {
+        let process = &target.process_name;
+        match event {
+            ManagerEvent::RestartStarted => {
+                println!("{}", format!("Restarting {}...", process).cyan().bold())
+            }
+            ManagerEvent::KillStarted => {
+                println!("{}", format!("Terminating {}...", process).yellow())
+            }
+            ManagerEvent::ExitRequested { grace_ms } => println!(
+                "{}",
+                format!("Asked {} to exit, waiting up to {}ms...", process, grace_ms).yellow()
+            ),
+            ManagerEvent::GraceExpired { grace_ms } => println!(
+                "{}",
+                format!("{} did not exit within {}ms, forcing it", process, grace_ms).yellow()
+            ),
+            ManagerEvent::IconCacheCleared { report } => {
+                println!("{}", format!("Icon cache: {}", report.summary()).yellow())
+            }
+            ManagerEvent::StartStarted => {
+                println!("{}", format!("Starting {}...", process).yellow())
+            }
+            ManagerEvent::OperationCompleted {
+                action: Action::Restart,
+                outcome,
+            } => println!("{}", format!("{}!", outcome.message).green().bold()),
+            ManagerEvent::OperationCompleted { outcome, .. } => {
+                println!("{}", outcome.message.green())
+            }
+            ManagerEvent::OperationFailed { error, .. } => {
+                eprintln!("{}", error.to_string().red())
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Sink that writes one [`OperationReport`] per line as JSON
+///
+/// Only the events that end an operation produce output.
+pub struct JsonSink<W: Write> {
+    out: Mutex<W>,
+}
+
+impl JsonSink<io::Stdout> {
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    }
+}
+
+impl JsonSink<File> {
+    /// Sink that appends to the file at `path`, creating it if needed
+    pub fn append(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::new(file))
+    }
+}
+
+impl<W: Write> JsonSink<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out: Mutex::new(out),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<W: Write> EventSink for JsonSink<W> {
+    fn on_event(&self, target: &TargetSpec, event: &ManagerEvent) {
+        let report = match event {
+            ManagerEvent::OperationCompleted { action, outcome } => {
+                OperationReport::new(*action, target, &Ok(outcome.clone()))
+            }
+            ManagerEvent::OperationFailed { action, error } => {
+                OperationReport::new(*action, target, &Err(error.clone()))
+            }
+            _ => return,
+        };
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        // A closed stdout leaves nobody to tell, so write errors are ignored
+        if let Ok(json) = serde_json::to_string(&report) {
+            let _ = writeln!(out, "{}", json);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_operation_report_success() {
+        let mut outcome = Outcome::new("Explorer.exe restarted successfully (PID 7)")
+            .with_killed_pids(vec![1, 2])
+            .with_new_pid(7);
+        outcome.timings.total_ms = 1500;
+        let report = OperationReport::new(Action::Restart, &TargetSpec::explorer(), &Ok(outcome));
+
+        let value = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(value["action"], "restart");
+        assert_eq!(value["target"], "explorer.exe");
+        assert_eq!(value["success"], true);
+        assert_eq!(value["killed_pids"], json!([1, 2]));
+        assert_eq!(value["new_pid"], 7);
+        assert_eq!(value["timings"]["total_ms"], 1500);
+        assert_eq!(value["error"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_operation_report_error() {
+        let error = StuckbarError::NotRunning {
+            process: "explorer.exe".to_string(),
+        };
+        let report = OperationReport::new(Action::Kill, &TargetSpec::explorer(), &Err(error));
+
+        let value = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(value["success"], false);
+        assert_eq!(value["message"], "explorer.exe is not running");
+        assert_eq!(value["killed_pids"], json!([]));
+        assert_eq!(value["new_pid"], serde_json::Value::Null);
+        assert_eq!(
+            value["error"],
+            json!({"kind": "not_running", "message": "explorer.exe is not running"})
+        );
+    }
+
+    #[test]
+    fn test_json_sink_writes_one_line_per_operation() {
+        let sink = JsonSink::new(Vec::new());
+        let target = TargetSpec::explorer();
+        let outcome = Outcome::new("Started").with_new_pid(7);
+
+        sink.on_event(&target, &ManagerEvent::StartStarted);
+        sink.on_event(&target, &ManagerEvent::WaitingForStart);
+        sink.on_event(&target, &ManagerEvent::StartCompleted { pid: 7 });
+        sink.on_event(
+            &target,
+            &ManagerEvent::finished(Action::Start, &Ok(outcome)),
+        );
+
+        let output = String::from_utf8(sink.into_inner()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 1);
+        let value: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(value["action"], "start");
+        assert_eq!(value["new_pid"], 7);
+    }
+}
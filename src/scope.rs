@@ -0,0 +1,219 @@
+//! # Session Scope
+//!
+//! `taskkill /F /IM explorer.exe` terminates every explorer.exe the caller has
+//! rights to, which on a terminal server or from an elevated prompt includes the
+//! shells of other users' sessions. A [`SessionScope`] limits the instances a
+//! [`ProcessManager`](crate::ProcessManager) acts on (see
+//! [`with_session_scope`](crate::ProcessManager::with_session_scope)):
+//!
+//! | Scope     | Instances acted on                                          |
+//! |-----------|-------------------------------------------------------------|
+//! | `current` | In the caller's session and owned by the caller's account   |
+//! | `<id>`    | In the session with that ID, whoever owns them              |
+//! | `all`     | Every instance the caller may act on                        |
+//!
+//! `current` is the default. The caller's session and account are those of
+//! [`ProcessRunner::current_process`](crate::ProcessRunner::current_process).
+//! Instances whose session or owner cannot be read are left alone by the scopes
+//! that need them.
+
+use crate::{ProcessInfo, StuckbarError};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Keywords accepted by [`SessionScope::from_str`] besides a session ID
+pub const SESSION_SCOPE_NAMES: &[&str] = &["current", "all"];
+
+/// Which sessions' instances of the target are acted on
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SessionScope {
+    /// Instances in the caller's session that run as the caller's account
+    #[default]
+    Current,
+    /// Instances in the session with this ID
+    Id(u32),
+    /// Every instance
+    All,
+}
+
+impl SessionScope {
+    /// Whether `process` is in scope for a caller described by `caller`
+    ///
+    /// [`Current`](Self::Current) covers nothing if the caller is unknown.
+    pub fn includes(&self, process: &ProcessInfo, caller: Option<&ProcessInfo>) -> bool {
+        match self {
+            Self::All => true,
+            Self::Id(id) => process.session_id == Some(*id),
+            Self::Current => caller.is_some_and(|caller| {
+                caller.session_id.is_some()
+                    && process.session_id == caller.session_id
+                    && caller.owner.is_some()
+                    && process.owner == caller.owner
+            }),
+        }
+    }
+
+    /// Whether the caller's own session is in scope
+    ///
+    /// Exit requests are delivered through the caller's taskbar, so they may only
+    /// be sent when this holds.
+    pub fn includes_caller_session(&self, caller: Option<&ProcessInfo>) -> bool {
+        match self {
+            Self::All | Self::Current => true,
+            Self::Id(id) => caller.is_some_and(|caller| caller.session_id == Some(*id)),
+        }
+    }
+
+    /// Mark which of `processes` are in scope for a caller described by `caller`
+    pub fn classify(
+        &self,
+        processes: Vec<ProcessInfo>,
+        caller: Option<&ProcessInfo>,
+    ) -> Vec<ScopedProcess> {
+        processes
+            .into_iter()
+            .map(|process| ScopedProcess {
+                in_scope: self.includes(&process, caller),
+                process,
+            })
+            .collect()
+    }
+}
+
+/// A running instance and whether a [`SessionScope`] covers it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScopedProcess {
+    #[serde(flatten)]
+    pub process: ProcessInfo,
+    pub in_scope: bool,
+}
+
+impl FromStr for SessionScope {
+    type Err = StuckbarError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "current" => Ok(Self::Current),
+            "all" => Ok(Self::All),
+            id => id
+                .parse()
+                .map(Self::Id)
+                .map_err(|_| StuckbarError::InvalidConfig {
+                    details: format!(
+                        "invalid session '{}', expected a session ID or one of: {}",
+                        value,
+                        SESSION_SCOPE_NAMES.join(", ")
+                    ),
+                }),
+        }
+    }
+}
+
+impl fmt::Display for SessionScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Current => write!(f, "current"),
+            Self::Id(id) => write!(f, "{}", id),
+            Self::All => write!(f, "all"),
+        }
+    }
+}
+
+impl Serialize for SessionScope {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: u32, session_id: Option<u32>, owner: Option<&str>) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            session_id,
+            owner: owner.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("current".parse::<SessionScope>(), Ok(SessionScope::Current));
+        assert_eq!("all".parse::<SessionScope>(), Ok(SessionScope::All));
+        assert_eq!("2".parse::<SessionScope>(), Ok(SessionScope::Id(2)));
+        let error = "mine".parse::<SessionScope>().unwrap_err();
+        assert_eq!(error.kind(), "invalid_config");
+        assert!(error.to_string().contains("current, all"));
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for scope in [
+            SessionScope::Current,
+            SessionScope::Id(3),
+            SessionScope::All,
+        ] {
+            assert_eq!(scope.to_string().parse::<SessionScope>(), Ok(scope));
+        }
+        assert_eq!(
+            serde_json::to_value(SessionScope::Id(3)).unwrap(),
+            serde_json::json!("3")
+        );
+    }
+
+    #[test]
+    fn test_current_requires_same_session_and_owner() {
+        let caller = process(1, Some(1), Some("S-1-5-21-1"));
+        let scope = SessionScope::Current;
+
+        assert!(scope.includes(&process(10, Some(1), Some("S-1-5-21-1")), Some(&caller)));
+        assert!(!scope.includes(&process(11, Some(2), Some("S-1-5-21-1")), Some(&caller)));
+        assert!(!scope.includes(&process(12, Some(1), Some("S-1-5-21-2")), Some(&caller)));
+        assert!(!scope.includes(&process(13, Some(1), None), Some(&caller)));
+        assert!(!scope.includes(&process(14, Some(1), Some("S-1-5-21-1")), None));
+    }
+
+    #[test]
+    fn test_session_id_ignores_owner() {
+        let scope = SessionScope::Id(2);
+        assert!(scope.includes(&process(10, Some(2), Some("S-1-5-21-2")), None));
+        assert!(!scope.includes(&process(11, Some(1), None), None));
+        assert!(!scope.includes(&process(12, None, None), None));
+        assert!(SessionScope::All.includes(&process(13, None, None), None));
+    }
+
+    #[test]
+    fn test_classify() {
+        let caller = process(1, Some(1), Some("S-1-5-21-1"));
+        let scoped = SessionScope::Current.classify(
+            vec![
+                process(10, Some(1), Some("S-1-5-21-1")),
+                process(11, Some(2), Some("S-1-5-21-2")),
+            ],
+            Some(&caller),
+        );
+
+        assert!(scoped[0].in_scope);
+        assert!(!scoped[1].in_scope);
+        assert_eq!(
+            serde_json::to_value(&scoped[1]).unwrap(),
+            serde_json::json!({
+                "pid": 11,
+                "session_id": 2,
+                "owner": "S-1-5-21-2",
+                "in_scope": false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_includes_caller_session() {
+        let caller = process(1, Some(1), None);
+        assert!(SessionScope::Current.includes_caller_session(Some(&caller)));
+        assert!(SessionScope::Id(1).includes_caller_session(Some(&caller)));
+        assert!(!SessionScope::Id(2).includes_caller_session(Some(&caller)));
+        assert!(!SessionScope::Id(1).includes_caller_session(None));
+    }
+}
@@ -0,0 +1,357 @@
+//! # Explorer Window Sessions
+//!
+//! Restarting explorer closes every open folder window. This module records the
+//! open windows (folder path and position) before a restart and reopens them once
+//! the new shell is up.
+//!
+//! Window enumeration sits behind [`ShellWindowEnumerator`], so [`SessionSnapshot`]
+//! can be tested without a desktop session. [`PowerShellShellWindows`] implements it
+//! through the `Shell.Application` COM object.
+
+use crate::{StuckbarError, map_spawn_error};
+use serde::Serialize;
+use std::process::Command;
+
+/// Position and size of a window in screen coordinates
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+pub struct WindowBounds {
+    pub left: i32,
+    pub top: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// An open File Explorer window
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct ExplorerWindow {
+    /// Folder shown in the window; shell folders use their `::{GUID}` path
+    pub path: String,
+    /// Window placement, if it could be read
+    pub bounds: Option<WindowBounds>,
+}
+
+impl ExplorerWindow {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            bounds: None,
+        }
+    }
+
+    pub fn with_bounds(mut self, bounds: WindowBounds) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+}
+
+/// Trait for abstracting shell window access (enables testing)
+pub trait ShellWindowEnumerator {
+    /// List the open File Explorer windows
+    fn open_windows(&self) -> Result<Vec<ExplorerWindow>, StuckbarError>;
+    /// Open a window on `window.path` and move it to `window.bounds`
+    fn open_window(&self, window: &ExplorerWindow) -> Result<(), StuckbarError>;
+}
+
+/// Explorer windows recorded before a restart
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct SessionSnapshot {
+    pub windows: Vec<ExplorerWindow>,
+}
+
+impl SessionSnapshot {
+    /// Record the currently open windows
+    pub fn capture(enumerator: &dyn ShellWindowEnumerator) -> Result<Self, StuckbarError> {
+        Ok(Self {
+            windows: enumerator.open_windows()?,
+        })
+    }
+
+    /// Reopen every recorded window, continuing past individual failures
+    pub fn restore(&self, enumerator: &dyn ShellWindowEnumerator) -> RestoreReport {
+        let mut report = RestoreReport::default();
+        for window in &self.windows {
+            match enumerator.open_window(window) {
+                Ok(()) => report.reopened.push(window.clone()),
+                Err(error) => report.failed.push(WindowFailure {
+                    window: window.clone(),
+                    error,
+                }),
+            }
+        }
+        report
+    }
+}
+
+/// A window that could not be reopened
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct WindowFailure {
+    pub window: ExplorerWindow,
+    pub error: StuckbarError,
+}
+
+/// Result of reopening the windows of a [`SessionSnapshot`]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize)]
+pub struct RestoreReport {
+    pub reopened: Vec<ExplorerWindow>,
+    pub failed: Vec<WindowFailure>,
+    /// Why the windows could not be recorded in the first place, if they could not
+    pub snapshot_error: Option<StuckbarError>,
+}
+
+impl RestoreReport {
+    /// Short summary such as "reopened 2 of 3 windows"
+    pub fn summary(&self) -> String {
+        if let Some(error) = &self.snapshot_error {
+            return format!("open windows could not be recorded: {}", error);
+        }
+        let total = self.reopened.len() + self.failed.len();
+        format!(
+            "reopened {} of {} window{}",
+            self.reopened.len(),
+            total,
+            if total == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// Script listing open Explorer windows as `left<TAB>top<TAB>width<TAB>height<TAB>path`
+///
+/// `Shell.Application` also lists other shell-hosted browser windows, so only those
+/// hosted by explorer.exe are kept.
+const LIST_WINDOWS_SCRIPT: &str = r#"[Console]::OutputEncoding = [Text.Encoding]::UTF8
+$shell = New-Object -ComObject Shell.Application
+foreach ($w in $shell.Windows()) {
+    if ($w.FullName -notlike '*\explorer.exe') { continue }
+    $path = $w.Document.Folder.Self.Path
+    if ($path) { "{0}`t{1}`t{2}`t{3}`t{4}" -f $w.Left, $w.Top, $w.Width, $w.Height, $path }
+}"#;
+
+/// Real implementation that drives `Shell.Application` through PowerShell
+pub struct PowerShellShellWindows;
+
+impl PowerShellShellWindows {
+    fn run(&self, script: &str) -> Result<std::process::Output, StuckbarError> {
+        Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-NonInteractive",
+                "-EncodedCommand",
+                &encode_command(script),
+            ])
+            .output()
+            .map_err(|e| map_spawn_error("powershell", "explorer.exe", e))
+    }
+}
+
+impl ShellWindowEnumerator for PowerShellShellWindows {
+    fn open_windows(&self) -> Result<Vec<ExplorerWindow>, StuckbarError> {
+        let output = self.run(LIST_WINDOWS_SCRIPT)?;
+        if !output.status.success() {
+            return Err(StuckbarError::SpawnFailed {
+                process: "powershell".to_string(),
+                details: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        Ok(parse_windows(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    fn open_window(&self, window: &ExplorerWindow) -> Result<(), StuckbarError> {
+        let output = self.run(&open_window_script(window))?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(StuckbarError::SpawnFailed {
+                process: window.path.clone(),
+                details: "the folder window did not open".to_string(),
+            })
+        }
+    }
+}
+
+/// Parse the output of [`LIST_WINDOWS_SCRIPT`], skipping malformed lines
+fn parse_windows(output: &str) -> Vec<ExplorerWindow> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.trim_end_matches('\r').splitn(5, '\t');
+            let mut next_i32 = || fields.next()?.trim().parse::<i32>().ok();
+            let bounds = WindowBounds {
+                left: next_i32()?,
+                top: next_i32()?,
+                width: next_i32()?,
+                height: next_i32()?,
+            };
+            let path = fields.next()?.trim();
+            (!path.is_empty()).then(|| ExplorerWindow::new(path).with_bounds(bounds))
+        })
+        .collect()
+}
+
+/// Script that opens `window` and, if it has bounds, waits up to 5s for it to
+/// appear and moves it into place; it exits with 1 if the window never appears
+fn open_window_script(window: &ExplorerWindow) -> String {
+    let path = quote_powershell(&window.path);
+    let mut script = format!(
+        "$shell = New-Object -ComObject Shell.Application\n$shell.Open({})\n",
+        path
+    );
+    if let Some(b) = window.bounds {
+        script.push_str(&format!(
+            "for ($i = 0; $i -lt 50; $i++) {{\n    \
+                 Start-Sleep -Milliseconds 100\n    \
+                 $w = $shell.Windows() | Where-Object {{ $_.Document.Folder.Self.Path -eq {} }} | Select-Object -Last 1\n    \
+                 if ($w) {{ $w.Left = {}; $w.Top = {}; $w.Width = {}; $w.Height = {}; exit 0 }}\n\
+             }}\n\
+             exit 1\n",
+            path, b.left, b.top, b.width, b.height
+        ));
+    }
+    script
+}
+
+/// Quote `value` as a PowerShell single-quoted string literal
+///
+/// PowerShell treats typographic single quotes as quotes too, so they are doubled
+/// along with the ASCII one.
+pub(crate) fn quote_powershell(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('\'');
+    for c in value.chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+            quoted.push(c);
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// Encode a script for `powershell -EncodedCommand` (base64 of UTF-16LE)
+///
+/// This sidesteps command-line quoting of the script entirely.
+pub(crate) fn encode_command(script: &str) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let bytes: Vec<u8> = script.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, b[0], b[1], b[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockShellWindows;
+
+    fn bounds() -> WindowBounds {
+        WindowBounds {
+            left: 10,
+            top: 20,
+            width: 800,
+            height: 600,
+        }
+    }
+
+    #[test]
+    fn test_snapshot_capture_and_restore() {
+        let windows = vec![
+            ExplorerWindow::new("C:\\Users\\me\\Documents").with_bounds(bounds()),
+            ExplorerWindow::new("D:\\Projects"),
+        ];
+        let shell = MockShellWindows::new(windows.clone());
+
+        let snapshot = SessionSnapshot::capture(&shell).unwrap();
+        let report = snapshot.restore(&shell);
+
+        assert_eq!(snapshot.windows, windows);
+        assert_eq!(report.reopened, windows);
+        assert!(report.failed.is_empty());
+        assert_eq!(shell.get_opened(), windows);
+        assert_eq!(report.summary(), "reopened 2 of 2 windows");
+    }
+
+    #[test]
+    fn test_restore_continues_past_failures() {
+        let windows = vec![
+            ExplorerWindow::new("\\\\server\\gone"),
+            ExplorerWindow::new("D:\\Projects"),
+        ];
+        let shell = MockShellWindows::new(Vec::new()).with_failing_path("\\\\server\\gone");
+
+        let report = SessionSnapshot {
+            windows: windows.clone(),
+        }
+        .restore(&shell);
+
+        assert_eq!(report.reopened, vec![windows[1].clone()]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].window, windows[0]);
+        assert_eq!(report.summary(), "reopened 1 of 2 windows");
+    }
+
+    #[test]
+    fn test_parse_windows() {
+        let output = "10\t20\t800\t600\tC:\\Users\\me\\Documents\r\n\
+                      -8\t-8\t1936\t1056\t::{20D04FE0-3AEA-1069-A2D8-08002B30309D}\r\n";
+        assert_eq!(
+            parse_windows(output),
+            vec![
+                ExplorerWindow::new("C:\\Users\\me\\Documents").with_bounds(bounds()),
+                ExplorerWindow::new("::{20D04FE0-3AEA-1069-A2D8-08002B30309D}").with_bounds(
+                    WindowBounds {
+                        left: -8,
+                        top: -8,
+                        width: 1936,
+                        height: 1056,
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_windows_skips_malformed_lines() {
+        let output = "oops\n10\t20\t800\n10\t20\t800\t600\t\n";
+        assert!(parse_windows(output).is_empty());
+    }
+
+    #[test]
+    fn test_quote_powershell() {
+        assert_eq!(quote_powershell("C:\\Temp"), "'C:\\Temp'");
+        assert_eq!(quote_powershell("Bob's files"), "'Bob''s files'");
+        assert_eq!(quote_powershell("Bob\u{2019}s"), "'Bob\u{2019}\u{2019}s'");
+    }
+
+    #[test]
+    fn test_open_window_script() {
+        let plain = open_window_script(&ExplorerWindow::new("D:\\Projects"));
+        assert!(plain.contains("$shell.Open('D:\\Projects')"));
+        assert!(!plain.contains("exit 1"));
+
+        let placed = open_window_script(&ExplorerWindow::new("D:\\Projects").with_bounds(bounds()));
+        assert!(placed.contains("$w.Left = 10; $w.Top = 20; $w.Width = 800; $w.Height = 600"));
+        assert!(placed.contains("exit 1"));
+    }
+
+    #[test]
+    fn test_encode_command() {
+        // UTF-16LE of "dir" is 64 00 69 00 72 00
+        assert_eq!(encode_command("dir"), "ZABpAHIA");
+        assert_eq!(encode_command("a"), "YQA=");
+        assert_eq!(encode_command(""), "");
+    }
+}
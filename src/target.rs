@@ -0,0 +1,218 @@
+//! # Target Processes
+//!
+//! Describes which process a [`ProcessManager`](crate::ProcessManager) acts on and
+//! how to launch it again.
+//!
+//! Besides `explorer.exe`, several other shell components can get stuck on their own
+//! and are worth restarting individually. Presets are provided for the common ones:
+//!
+//! | Preset             | Process                        |
+//! |--------------------|--------------------------------|
+//! | `explorer`         | `explorer.exe`                 |
+//! | `start-menu`       | `StartMenuExperienceHost.exe`  |
+//! | `shell-experience` | `ShellExperienceHost.exe`      |
+//! | `search`           | `SearchHost.exe`               |
+//!
+//! The packaged shell components are relaunched by Windows itself once they exit,
+//! so their presets have no launch command.
+
+use std::path::PathBuf;
+
+/// Names of the built-in target presets, in display order
+pub const PRESET_NAMES: &[&str] = &["explorer", "start-menu", "shell-experience", "search"];
+
+/// Description of a process to kill and (re)start
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetSpec {
+    /// Image name used to find and terminate the process (e.g. "explorer.exe")
+    pub process_name: String,
+    /// Command used to launch the process, or `None` if Windows relaunches it itself
+    pub launch_command: Option<String>,
+    /// Arguments passed to the launch command
+    pub args: Vec<String>,
+    /// Working directory for the launched process
+    pub working_dir: Option<PathBuf>,
+    /// Extra environment variables for the launched process
+    pub env: Vec<(String, String)>,
+}
+
+impl TargetSpec {
+    /// Create a target that is launched by running its own image name
+    pub fn new(process_name: impl Into<String>) -> Self {
+        let process_name = process_name.into();
+        Self {
+            launch_command: Some(process_name.clone()),
+            process_name,
+            args: Vec::new(),
+            working_dir: None,
+            env: Vec::new(),
+        }
+    }
+
+    /// Windows Explorer, which hosts the taskbar and desktop
+    pub fn explorer() -> Self {
+        Self::new("explorer.exe")
+    }
+
+    /// The Start menu host
+    pub fn start_menu() -> Self {
+        Self::new("StartMenuExperienceHost.exe").relaunched_by_system()
+    }
+
+    /// The host for the notification center, quick settings and other shell flyouts
+    pub fn shell_experience_host() -> Self {
+        Self::new("ShellExperienceHost.exe").relaunched_by_system()
+    }
+
+    /// The taskbar search host
+    pub fn search_host() -> Self {
+        Self::new("SearchHost.exe").relaunched_by_system()
+    }
+
+    /// Look up a built-in preset by name (see [`PRESET_NAMES`])
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "explorer" => Some(Self::explorer()),
+            "start-menu" => Some(Self::start_menu()),
+            "shell-experience" => Some(Self::shell_experience_host()),
+            "search" => Some(Self::search_host()),
+            _ => None,
+        }
+    }
+
+    pub fn with_launch_command(mut self, command: impl Into<String>) -> Self {
+        self.launch_command = Some(command.into());
+        self
+    }
+
+    /// Mark the target as relaunched by Windows, so starting it is a no-op
+    pub fn relaunched_by_system(mut self) -> Self {
+        self.launch_command = None;
+        self
+    }
+
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_working_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = Some(dir.into());
+        self
+    }
+
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Whether this target is Windows Explorer itself
+    pub fn is_explorer(&self) -> bool {
+        self.process_name.eq_ignore_ascii_case("explorer.exe")
+    }
+
+    /// Whether Windows starts the target again after it is killed
+    ///
+    /// Targets without a launch command always come back; explorer.exe does too,
+    /// through the `AutoRestartShell` setting. Killing them amounts to a restart.
+    pub fn restarts_when_killed(&self) -> bool {
+        self.launch_command.is_none() || self.is_explorer()
+    }
+
+    /// Process name with its first letter capitalized, for user-facing messages
+    pub fn display_name(&self) -> String {
+        let mut chars = self.process_name.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        }
+    }
+}
+
+impl Default for TargetSpec {
+    fn default() -> Self {
+        Self::explorer()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_launches_itself() {
+        let target = TargetSpec::new("foo.exe");
+        assert_eq!(target.process_name, "foo.exe");
+        assert_eq!(target.launch_command.as_deref(), Some("foo.exe"));
+        assert!(target.args.is_empty());
+        assert!(target.working_dir.is_none());
+        assert!(target.env.is_empty());
+    }
+
+    #[test]
+    fn test_default_is_explorer() {
+        assert_eq!(TargetSpec::default(), TargetSpec::explorer());
+        assert_eq!(TargetSpec::default().process_name, "explorer.exe");
+    }
+
+    #[test]
+    fn test_all_presets_resolve() {
+        for name in PRESET_NAMES {
+            assert!(
+                TargetSpec::preset(name).is_some(),
+                "missing preset {}",
+                name
+            );
+        }
+        assert!(TargetSpec::preset("notepad").is_none());
+    }
+
+    #[test]
+    fn test_shell_components_are_relaunched_by_system() {
+        assert!(TargetSpec::start_menu().launch_command.is_none());
+        assert!(TargetSpec::shell_experience_host().launch_command.is_none());
+        assert!(TargetSpec::search_host().launch_command.is_none());
+        assert!(TargetSpec::explorer().launch_command.is_some());
+    }
+
+    #[test]
+    fn test_restarts_when_killed() {
+        assert!(TargetSpec::explorer().restarts_when_killed());
+        assert!(TargetSpec::start_menu().restarts_when_killed());
+        assert!(!TargetSpec::new("app.exe").restarts_when_killed());
+    }
+
+    #[test]
+    fn test_builder_methods() {
+        let target = TargetSpec::new("app.exe")
+            .with_launch_command("C:\\Tools\\app.exe")
+            .with_args(["--quiet", "--tray"])
+            .with_working_dir("C:\\Tools")
+            .with_env("APP_MODE", "shell");
+
+        assert_eq!(target.launch_command.as_deref(), Some("C:\\Tools\\app.exe"));
+        assert_eq!(target.args, vec!["--quiet", "--tray"]);
+        assert_eq!(target.working_dir, Some(PathBuf::from("C:\\Tools")));
+        assert_eq!(
+            target.env,
+            vec![("APP_MODE".to_string(), "shell".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_is_explorer() {
+        assert!(TargetSpec::explorer().is_explorer());
+        assert!(TargetSpec::new("Explorer.EXE").is_explorer());
+        assert!(!TargetSpec::start_menu().is_explorer());
+    }
+
+    #[test]
+    fn test_display_name() {
+        assert_eq!(TargetSpec::explorer().display_name(), "Explorer.exe");
+        assert_eq!(TargetSpec::search_host().display_name(), "SearchHost.exe");
+    }
+}
@@ -0,0 +1,241 @@
+//! Parsing helpers for the output of `tasklist /FO CSV /NH`
+
+use crate::{ProcessInfo, ProcessUsage};
+
+/// Split one CSV line as written by `tasklist` into its fields
+///
+/// Fields are double-quoted and may contain commas (e.g. `"12,345 K"`);
+/// doubled quotes inside a field stand for a literal quote.
+pub(crate) fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.trim_end_matches(['\r', '\n']).chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Extract the PIDs of all rows whose image name matches `process_name`
+///
+/// Informational lines (such as the "No tasks are running" message) never match
+/// and are skipped, as are rows with an unparsable PID column.
+pub(crate) fn parse_pids(output: &str, process_name: &str) -> Vec<u32> {
+    output
+        .lines()
+        .map(split_csv_line)
+        .filter(|fields| {
+            fields
+                .first()
+                .is_some_and(|name| name.eq_ignore_ascii_case(process_name))
+        })
+        .filter_map(|fields| fields.get(1)?.trim().parse().ok())
+        .collect()
+}
+
+/// Extract the PID, session number and owner of all rows matching `process_name`
+///
+/// Rows are laid out as image name, PID, session name, session number and
+/// memory usage; verbose rows add the status and then the owning user name
+/// (`DOMAIN\user`), which reads `N/A` for processes the caller may not query.
+/// Columns that are missing or do not parse are reported as unknown.
+pub(crate) fn parse_processes(output: &str, process_name: &str) -> Vec<ProcessInfo> {
+    output
+        .lines()
+        .map(split_csv_line)
+        .filter(|fields| {
+            fields
+                .first()
+                .is_some_and(|name| name.eq_ignore_ascii_case(process_name))
+        })
+        .filter_map(|fields| parse_process(&fields))
+        .collect()
+}
+
+/// Extract the process described by the first row that has a PID
+pub(crate) fn parse_first_process(output: &str) -> Option<ProcessInfo> {
+    output
+        .lines()
+        .map(split_csv_line)
+        .find_map(|fields| parse_process(&fields))
+}
+
+fn parse_process(fields: &[String]) -> Option<ProcessInfo> {
+    Some(ProcessInfo {
+        pid: fields.get(1)?.trim().parse().ok()?,
+        session_id: fields.get(3).and_then(|id| id.trim().parse().ok()),
+        owner: fields
+            .get(6)
+            .filter(|owner| !owner.is_empty() && owner.as_str() != "N/A")
+            .cloned(),
+    })
+}
+
+/// Extract resource usage from verbose output (`tasklist /V /FO CSV /NH`)
+///
+/// Verbose rows are laid out as image name, PID, session name, session number,
+/// memory usage (`"142,608 K"`), status, user name, CPU time (`"0:01:23"`) and
+/// window title. Rows with unparsable numbers are skipped.
+pub(crate) fn parse_usage(output: &str, process_name: &str) -> Vec<ProcessUsage> {
+    output
+        .lines()
+        .map(split_csv_line)
+        .filter(|fields| {
+            fields
+                .first()
+                .is_some_and(|name| name.eq_ignore_ascii_case(process_name))
+        })
+        .filter_map(|fields| {
+            Some(ProcessUsage {
+                pid: fields.get(1)?.trim().parse().ok()?,
+                working_set_bytes: parse_memory_kb(fields.get(4)?)? * 1024,
+                cpu_time_ms: parse_cpu_time(fields.get(7)?)?,
+            })
+        })
+        .collect()
+}
+
+/// Parse a memory column such as `"142,608 K"` into kilobytes
+///
+/// Thousands separators vary by locale, so every non-digit is ignored.
+fn parse_memory_kb(value: &str) -> Option<u64> {
+    let digits: String = value.chars().filter(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+/// Parse a CPU time column in `h:mm:ss` form into milliseconds
+fn parse_cpu_time(value: &str) -> Option<u64> {
+    let mut parts = value.trim().split(':').map(|part| part.parse::<u64>().ok());
+    let (hours, minutes, seconds) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(((hours * 60 + minutes) * 60 + seconds) * 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\"explorer.exe\",\"4312\",\"Console\",\"1\",\"142,608 K\"\r\n\
+                          \"explorer.exe\",\"9876\",\"RDP-Tcp#0\",\"2\",\"98,112 K\"\r\n";
+
+    #[test]
+    fn test_split_csv_line_quoted_commas() {
+        let fields = split_csv_line("\"explorer.exe\",\"4312\",\"Console\",\"1\",\"142,608 K\"");
+        assert_eq!(
+            fields,
+            vec!["explorer.exe", "4312", "Console", "1", "142,608 K"]
+        );
+    }
+
+    #[test]
+    fn test_split_csv_line_escaped_quote() {
+        let fields = split_csv_line("\"a \"\"b\"\"\",\"c\"");
+        assert_eq!(fields, vec!["a \"b\"", "c"]);
+    }
+
+    #[test]
+    fn test_parse_pids() {
+        assert_eq!(parse_pids(SAMPLE, "explorer.exe"), vec![4312, 9876]);
+    }
+
+    #[test]
+    fn test_parse_pids_case_insensitive() {
+        assert_eq!(parse_pids(SAMPLE, "Explorer.EXE"), vec![4312, 9876]);
+    }
+
+    #[test]
+    fn test_parse_pids_no_tasks() {
+        let output = "INFO: No tasks are running which match the specified criteria.\r\n";
+        assert!(parse_pids(output, "explorer.exe").is_empty());
+    }
+
+    #[test]
+    fn test_parse_processes() {
+        assert_eq!(
+            parse_processes(SAMPLE, "explorer.exe"),
+            vec![
+                ProcessInfo {
+                    pid: 4312,
+                    session_id: Some(1),
+                    owner: None,
+                },
+                ProcessInfo {
+                    pid: 9876,
+                    session_id: Some(2),
+                    owner: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_processes_verbose_owner() {
+        let output = "\"explorer.exe\",\"4312\",\"Console\",\"1\",\"142,608 K\",\"Running\",\"PC\\\\me\",\"1:02:03\",\"N/A\"\r\n\
+                      \"explorer.exe\",\"9876\",\"RDP-Tcp#0\",\"2\",\"98,112 K\",\"Unknown\",\"N/A\",\"0:00:10\",\"N/A\"\r\n";
+        let processes = parse_processes(output, "explorer.exe");
+        assert_eq!(processes[0].owner.as_deref(), Some("PC\\\\me"));
+        assert_eq!(processes[1].owner, None);
+    }
+
+    #[test]
+    fn test_parse_first_process() {
+        let output = "\"stuckbar.exe\",\"512\",\"Console\",\"1\",\"4,000 K\",\"Running\",\"PC\\\\me\",\"0:00:00\",\"N/A\"\r\n";
+        let process = parse_first_process(output).unwrap();
+        assert_eq!(process.pid, 512);
+        assert_eq!(process.session_id, Some(1));
+        assert!(parse_first_process("INFO: No tasks are running.\r\n").is_none());
+    }
+
+    #[test]
+    fn test_parse_usage() {
+        let output = "\"explorer.exe\",\"4312\",\"Console\",\"1\",\"142,608 K\",\"Running\",\"PC\\\\me\",\"1:02:03\",\"N/A\"\r\n";
+        assert_eq!(
+            parse_usage(output, "explorer.exe"),
+            vec![ProcessUsage {
+                pid: 4312,
+                working_set_bytes: 142_608 * 1024,
+                cpu_time_ms: 3_723_000,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_usage_skips_malformed_rows() {
+        let output = "\"explorer.exe\",\"4312\",\"Console\",\"1\",\"N/A\",\"Running\",\"PC\\\\me\",\"1:02:03\",\"N/A\"\r\n";
+        assert!(parse_usage(output, "explorer.exe").is_empty());
+    }
+
+    #[test]
+    fn test_parse_memory_kb_locales() {
+        assert_eq!(parse_memory_kb("142,608 K"), Some(142_608));
+        assert_eq!(parse_memory_kb("142.608 K"), Some(142_608));
+        assert_eq!(parse_memory_kb("N/A"), None);
+    }
+
+    #[test]
+    fn test_parse_cpu_time() {
+        assert_eq!(parse_cpu_time("0:00:05"), Some(5_000));
+        assert_eq!(parse_cpu_time("12:30:00"), Some(45_000_000));
+        assert_eq!(parse_cpu_time("5"), None);
+        assert_eq!(parse_cpu_time("1:2:3:4"), None);
+    }
+
+    #[test]
+    fn test_parse_pids_ignores_other_images() {
+        let output = "\"SearchHost.exe\",\"100\",\"Console\",\"1\",\"1 K\"\r\n";
+        assert!(parse_pids(output, "explorer.exe").is_empty());
+    }
+}
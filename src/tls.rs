@@ -0,0 +1,352 @@
+//! # TLS
+//!
+//! Serves the HTTP MCP transports over TLS with rustls, so tokens (see
+//! [`crate::auth`]) do not cross the network in cleartext.
+//!
+//! The certificate and key are read from PEM files. For hosts without a
+//! certificate, [`ensure_self_signed`] generates a self-signed one on first run
+//! and keeps using it afterwards; clients then trust it by its
+//! [`fingerprint`] or by adding `cert.pem` to their trust store.
+//!
+//! [`TlsListener`] performs the handshakes for [`axum::serve()`], concurrently, so a
+//! slow client cannot hold up the others.
+
+use crate::StuckbarError;
+use rcgen::{CertificateParams, DnType, IsCa, KeyPair};
+use rustls::ServerConfig;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::server::TlsStream;
+
+/// Time a client may take to complete the handshake
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Days a generated certificate stays valid
+pub const SELF_SIGNED_VALID_DAYS: u64 = 3650;
+
+/// Common name of generated certificates
+const SELF_SIGNED_NAME: &str = "stuckbar";
+
+/// Where the HTTP server's certificate and key are kept, in PEM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsFiles {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    /// Generate a self-signed certificate if the files do not exist
+    pub self_signed: bool,
+}
+
+impl TlsFiles {
+    /// Load the files, first generating them for a server bound to `host` if
+    /// they are to be self-signed and do not exist yet
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StuckbarError::InvalidConfig`] if the files cannot be
+    /// generated or loaded (see [`server_config`]).
+    pub fn load(&self, host: &str) -> Result<Arc<ServerConfig>, StuckbarError> {
+        if self.self_signed && ensure_self_signed(&self.cert, &self.key, &self_signed_hosts(host))?
+        {
+            eprintln!(
+                "Generated a self-signed certificate at {}",
+                self.cert.display()
+            );
+        }
+        server_config(&self.cert, &self.key)
+    }
+}
+
+/// Load the certificate chain at `cert` and the private key at `key`
+///
+/// # Errors
+///
+/// Returns [`StuckbarError::InvalidConfig`] if a file cannot be read, holds no
+/// certificate or key, or if the key does not match the certificate.
+pub fn server_config(cert: &Path, key: &Path) -> Result<Arc<ServerConfig>, StuckbarError> {
+    let invalid = |path: &Path, details: String| StuckbarError::InvalidConfig {
+        details: format!("{}: {}", path.display(), details),
+    };
+    let certs = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| invalid(cert, e.to_string()))?;
+    if certs.is_empty() {
+        return Err(invalid(cert, "no certificate found".to_string()));
+    }
+    let private_key = PrivateKeyDer::from_pem_file(key).map_err(|e| invalid(key, e.to_string()))?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| {
+            builder
+                .with_no_client_auth()
+                .with_single_cert(certs, private_key)
+        })
+        .map_err(|e| invalid(cert, e.to_string()))?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+/// SHA-256 fingerprint of the first certificate in the PEM file `cert`, as
+/// colon-separated hex
+///
+/// # Errors
+///
+/// Returns [`StuckbarError::InvalidConfig`] if the file holds no certificate.
+pub fn fingerprint(cert: &Path) -> Result<String, StuckbarError> {
+    let der = CertificateDer::from_pem_file(cert).map_err(|e| StuckbarError::InvalidConfig {
+        details: format!("{}: {}", cert.display(), e),
+    })?;
+    let digest = ring::digest::digest(&ring::digest::SHA256, &der);
+    let hex: Vec<String> = digest
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect();
+    Ok(hex.join(":"))
+}
+
+/// A generated certificate and its private key, in PEM
+#[derive(Debug, Clone)]
+pub struct SelfSigned {
+    pub cert_pem: String,
+    /// PKCS#8 private key
+    pub key_pem: String,
+}
+
+/// Generate a self-signed ECDSA P-256 certificate valid for `hosts`, each a DNS
+/// name or an IP address
+///
+/// # Errors
+///
+/// Returns [`StuckbarError::InvalidConfig`] if a host is not a valid name or no
+/// key could be generated.
+pub fn generate_self_signed(hosts: &[String]) -> Result<SelfSigned, StuckbarError> {
+    let failed = |e: rcgen::Error| StuckbarError::InvalidConfig {
+        details: format!("cannot generate a self-signed certificate: {}", e),
+    };
+    let mut params = CertificateParams::new(hosts).map_err(failed)?;
+    params
+        .distinguished_name
+        .push(DnType::CommonName, SELF_SIGNED_NAME);
+    params.is_ca = IsCa::ExplicitNoCa;
+    let now = OffsetDateTime::now_utc();
+    params.not_before = now - Duration::from_secs(86_400);
+    params.not_after = now + Duration::from_secs(SELF_SIGNED_VALID_DAYS * 86_400);
+
+    let key_pair = KeyPair::generate().map_err(failed)?;
+    let cert = params.self_signed(&key_pair).map_err(failed)?;
+    Ok(SelfSigned {
+        cert_pem: cert.pem(),
+        key_pem: key_pair.serialize_pem(),
+    })
+}
+
+/// Write a self-signed certificate for `hosts` to `cert` and `key` unless both
+/// exist; true if one was written
+///
+/// On unix the key file is only readable by its owner. On Windows it inherits
+/// the ACL of its directory: the default `%LOCALAPPDATA%\stuckbar\tls` is only
+/// open to the user and administrators, so a key written elsewhere should go in
+/// a directory that is just as private.
+///
+/// # Errors
+///
+/// Returns [`StuckbarError::InvalidConfig`] if the files cannot be written.
+pub fn ensure_self_signed(
+    cert: &Path,
+    key: &Path,
+    hosts: &[String],
+) -> Result<bool, StuckbarError> {
+    if cert.exists() && key.exists() {
+        return Ok(false);
+    }
+    let generated = generate_self_signed(hosts)?;
+    let write = |path: &Path, contents: &str, private: bool| -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        if private {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        // Elsewhere the file inherits the directory's ACL
+        #[cfg(not(unix))]
+        let _ = private;
+        std::io::Write::write_all(&mut options.open(path)?, contents.as_bytes())
+    };
+    write(key, &generated.key_pem, true)
+        .and_then(|()| write(cert, &generated.cert_pem, false))
+        .map_err(|e| StuckbarError::InvalidConfig {
+            details: format!("cannot write the self-signed certificate: {}", e),
+        })?;
+    Ok(true)
+}
+
+/// Names a certificate generated for a server bound to `host` is valid for:
+/// the loopback names, this computer's name and `host` itself
+pub fn self_signed_hosts(host: &str) -> Vec<String> {
+    let mut hosts: Vec<String> = ["localhost", "127.0.0.1", "::1"]
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+    if let Ok(name) = std::env::var("COMPUTERNAME") {
+        hosts.push(name);
+    }
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let unspecified = host.parse::<IpAddr>().is_ok_and(|ip| ip.is_unspecified());
+    if !unspecified && !hosts.iter().any(|known| known.eq_ignore_ascii_case(host)) {
+        hosts.push(host.to_string());
+    }
+    hosts
+}
+
+/// Listener handing [`axum::serve()`] connections that completed a TLS handshake
+///
+/// Handshakes that fail or take longer than [`HANDSHAKE_TIMEOUT`] are dropped.
+pub struct TlsListener {
+    local_addr: SocketAddr,
+    handshaken: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    accept_task: tokio::task::JoinHandle<()>,
+}
+
+impl TlsListener {
+    /// Accept connections on `listener` with `config`
+    ///
+    /// Must be called within a Tokio runtime.
+    pub fn new(listener: TcpListener, config: Arc<ServerConfig>) -> std::io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let acceptor = TlsAcceptor::from(config);
+        let (sender, handshaken) = mpsc::channel(64);
+        let accept_task = tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    // Such as running out of file descriptors; let some close
+                    Err(_) => {
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        continue;
+                    }
+                };
+                let (acceptor, sender) = (acceptor.clone(), sender.clone());
+                tokio::spawn(async move {
+                    let handshake =
+                        tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream));
+                    if let Ok(Ok(stream)) = handshake.await {
+                        let _ = sender.send((stream, peer)).await;
+                    }
+                });
+            }
+        });
+        Ok(Self {
+            local_addr,
+            handshaken,
+            accept_task,
+        })
+    }
+}
+
+impl axum::serve::Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.handshaken.recv().await {
+            Some(connection) => connection,
+            // The accept task only ends when this listener is dropped
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+impl Drop for TlsListener {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("stuckbar-tls-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_self_signed_hosts() {
+        let hosts = self_signed_hosts("0.0.0.0");
+        assert!(hosts.starts_with(&[
+            "localhost".to_string(),
+            "127.0.0.1".to_string(),
+            "::1".to_string()
+        ]));
+        assert!(!hosts.contains(&"0.0.0.0".to_string()));
+        assert!(self_signed_hosts("192.168.1.5").contains(&"192.168.1.5".to_string()));
+        let localhost = self_signed_hosts("127.0.0.1");
+        assert_eq!(localhost.iter().filter(|h| *h == "127.0.0.1").count(), 1);
+    }
+
+    #[test]
+    fn test_generated_certificate_loads() {
+        let dir = temp_dir("generate");
+        let (cert, key) = (dir.join("cert.pem"), dir.join("key.pem"));
+
+        assert!(ensure_self_signed(&cert, &key, &self_signed_hosts("127.0.0.1")).unwrap());
+        let pem = fs::read_to_string(&cert).unwrap();
+        assert!(pem.starts_with("-----BEGIN CERTIFICATE-----\n"));
+        assert!(server_config(&cert, &key).is_ok());
+        let first = fingerprint(&cert).unwrap();
+        assert_eq!(first.len(), 32 * 3 - 1);
+
+        // Kept on later runs
+        assert!(!ensure_self_signed(&cert, &key, &[]).unwrap());
+        assert_eq!(fingerprint(&cert).unwrap(), first);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_server_config_rejects_bad_files() {
+        let dir = temp_dir("bad");
+        let (cert, key) = (dir.join("cert.pem"), dir.join("key.pem"));
+
+        let err = server_config(&cert, &key).unwrap_err();
+        assert_eq!(err.kind(), "invalid_config");
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&cert, "not a certificate").unwrap();
+        assert!(
+            server_config(&cert, &key)
+                .unwrap_err()
+                .to_string()
+                .contains("no certificate")
+        );
+
+        // A key that does not match the certificate
+        let one = generate_self_signed(&["localhost".to_string()]).unwrap();
+        let other = generate_self_signed(&["localhost".to_string()]).unwrap();
+        fs::write(&cert, one.cert_pem).unwrap();
+        fs::write(&key, other.key_pem).unwrap();
+        assert!(server_config(&cert, &key).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
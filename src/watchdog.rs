@@ -0,0 +1,897 @@
+//! # Watchdog
+//!
+//! Periodically checks the health of a shell process and restarts it when it stays
+//! unhealthy, so nobody has to notice the stuck taskbar first.
+//!
+//! Health is decided by [`HealthProbe`] implementations. A restart is triggered once
+//! the probes report failures on [`WatchdogConfig::failure_threshold`] consecutive
+//! checks, subject to a cooldown between restarts (which grows with
+//! [`BackoffPolicy`] while restarts keep being needed) and an hourly restart cap.
+//!
+//! All timing goes through the manager's [`ProcessRunner`], so the whole loop can be
+//! driven by a mock runner with a fake clock.
+
+use crate::{Outcome, ProcessManager, ProcessRunner, ProcessUsage, StuckbarError, TargetSpec};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Default interval in milliseconds between health checks
+pub const CHECK_INTERVAL_MS: u64 = 5_000;
+
+/// Default number of consecutive failed checks before restarting
+pub const FAILURE_THRESHOLD: u32 = 3;
+
+/// Default minimum time in milliseconds between two restarts
+pub const COOLDOWN_MS: u64 = 60_000;
+
+/// Default maximum number of restarts within one hour
+pub const MAX_RESTARTS_PER_HOUR: u32 = 4;
+
+/// Default CPU usage, in percent of one core, considered pegged
+pub const CPU_THRESHOLD_PERCENT: u64 = 90;
+
+/// Default working set growth in MB considered a leak
+pub const MEMORY_GROWTH_MB: u64 = 500;
+
+/// Names of the health probes the CLI can enable, in display order
+pub const PROBE_NAMES: &[&str] = &["process", "cpu", "memory", "window"];
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+const MB: u64 = 1024 * 1024;
+
+/// Result of a single health probe check
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ProbeStatus {
+    Healthy,
+    /// The probe detected a problem, with a human-readable reason
+    Unhealthy(String),
+    /// The probe could not decide (e.g. no baseline yet); not counted as a failure
+    Inconclusive(String),
+}
+
+/// Everything a probe may inspect during a check
+pub struct ProbeContext<'a> {
+    pub runner: &'a dyn ProcessRunner,
+    pub target: &'a TargetSpec,
+    pub now: Instant,
+}
+
+/// A single health check run on every watchdog tick
+pub trait HealthProbe {
+    /// Short identifier used in failure reasons
+    fn name(&self) -> &str;
+    fn check(&mut self, ctx: &ProbeContext<'_>) -> ProbeStatus;
+}
+
+/// Fails when no instance of the target process is running
+pub struct ProcessPresentProbe;
+
+impl HealthProbe for ProcessPresentProbe {
+    fn name(&self) -> &str {
+        "process"
+    }
+
+    fn check(&mut self, ctx: &ProbeContext<'_>) -> ProbeStatus {
+        match ctx.runner.is_running(&ctx.target.process_name) {
+            Ok(true) => ProbeStatus::Healthy,
+            Ok(false) => {
+                ProbeStatus::Unhealthy(format!("{} is not running", ctx.target.process_name))
+            }
+            Err(e) => ProbeStatus::Inconclusive(e.to_string()),
+        }
+    }
+}
+
+/// Fails when an instance keeps a CPU core pegged between two checks
+///
+/// Usage is measured as CPU time consumed per wall-clock time, so 100% means one
+/// fully busy core.
+pub struct CpuPeggedProbe {
+    threshold_percent: u64,
+    last: HashMap<u32, (u64, Instant)>,
+}
+
+impl CpuPeggedProbe {
+    pub fn new(threshold_percent: u64) -> Self {
+        Self {
+            threshold_percent,
+            last: HashMap::new(),
+        }
+    }
+}
+
+impl HealthProbe for CpuPeggedProbe {
+    fn name(&self) -> &str {
+        "cpu"
+    }
+
+    fn check(&mut self, ctx: &ProbeContext<'_>) -> ProbeStatus {
+        let usage = match ctx.runner.process_usage(&ctx.target.process_name) {
+            Ok(usage) => usage,
+            Err(e) => return ProbeStatus::Inconclusive(e.to_string()),
+        };
+
+        // Busiest instance that has a baseline from the previous check
+        let mut busiest: Option<(u32, u64)> = None;
+        let mut current = HashMap::new();
+        for ProcessUsage {
+            pid, cpu_time_ms, ..
+        } in usage
+        {
+            if let Some((last_cpu_ms, last_at)) = self.last.get(&pid) {
+                let wall_ms = ctx.now.saturating_duration_since(*last_at).as_millis() as u64;
+                let percent = (cpu_time_ms.saturating_sub(*last_cpu_ms) * 100).checked_div(wall_ms);
+                if let Some(percent) = percent
+                    && busiest.is_none_or(|(_, max)| percent > max)
+                {
+                    busiest = Some((pid, percent));
+                }
+            }
+            current.insert(pid, (cpu_time_ms, ctx.now));
+        }
+
+        // Forget instances that exited so a reused PID starts with a fresh baseline
+        self.last = current;
+
+        match busiest {
+            None => ProbeStatus::Inconclusive("collecting CPU baseline".to_string()),
+            Some((pid, percent)) if percent >= self.threshold_percent => {
+                ProbeStatus::Unhealthy(format!(
+                    "PID {} used {}% CPU (threshold {}%)",
+                    pid, percent, self.threshold_percent
+                ))
+            }
+            Some(_) => ProbeStatus::Healthy,
+        }
+    }
+}
+
+/// Fails when an instance's working set grows too far beyond its first sample,
+/// or exceeds an absolute limit
+pub struct MemoryGrowthProbe {
+    max_growth_bytes: u64,
+    limit_bytes: Option<u64>,
+    baselines: HashMap<u32, u64>,
+}
+
+impl MemoryGrowthProbe {
+    pub fn new(max_growth_bytes: u64) -> Self {
+        Self {
+            max_growth_bytes,
+            limit_bytes: None,
+            baselines: HashMap::new(),
+        }
+    }
+
+    pub fn with_limit(mut self, limit_bytes: u64) -> Self {
+        self.limit_bytes = Some(limit_bytes);
+        self
+    }
+}
+
+impl HealthProbe for MemoryGrowthProbe {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn check(&mut self, ctx: &ProbeContext<'_>) -> ProbeStatus {
+        let usage = match ctx.runner.process_usage(&ctx.target.process_name) {
+            Ok(usage) if !usage.is_empty() => usage,
+            Ok(_) => return ProbeStatus::Inconclusive("no usage data".to_string()),
+            Err(e) => return ProbeStatus::Inconclusive(e.to_string()),
+        };
+
+        self.baselines
+            .retain(|pid, _| usage.iter().any(|sample| sample.pid == *pid));
+
+        for sample in usage {
+            let baseline = *self
+                .baselines
+                .entry(sample.pid)
+                .or_insert(sample.working_set_bytes);
+
+            if let Some(limit) = self
+                .limit_bytes
+                .filter(|limit| sample.working_set_bytes > *limit)
+            {
+                return ProbeStatus::Unhealthy(format!(
+                    "PID {} uses {} MB (limit {} MB)",
+                    sample.pid,
+                    sample.working_set_bytes / MB,
+                    limit / MB
+                ));
+            }
+
+            let growth = sample.working_set_bytes.saturating_sub(baseline);
+            if growth > self.max_growth_bytes {
+                return ProbeStatus::Unhealthy(format!(
+                    "PID {} grew by {} MB (allowed {} MB)",
+                    sample.pid,
+                    growth / MB,
+                    self.max_growth_bytes / MB
+                ));
+            }
+        }
+        ProbeStatus::Healthy
+    }
+}
+
+/// How the cooldown grows when restarts keep being needed
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BackoffPolicy {
+    /// Factor applied to the cooldown for every further restart
+    pub multiplier: u32,
+    /// Upper bound for the grown cooldown
+    pub max_cooldown_ms: u64,
+    /// Quiet period without restarts after which the cooldown is reset
+    pub reset_after_ms: u64,
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        Self {
+            multiplier: 2,
+            max_cooldown_ms: 15 * 60 * 1000,
+            reset_after_ms: 60 * 60 * 1000,
+        }
+    }
+}
+
+/// Tunables for the watchdog loop
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct WatchdogConfig {
+    /// Interval between health checks
+    pub interval_ms: u64,
+    /// Consecutive failed checks required to trigger a restart
+    pub failure_threshold: u32,
+    /// Minimum time between two restarts
+    pub cooldown_ms: u64,
+    /// Maximum number of restarts within any one-hour window
+    pub max_restarts_per_hour: u32,
+    pub backoff: BackoffPolicy,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: CHECK_INTERVAL_MS,
+            failure_threshold: FAILURE_THRESHOLD,
+            cooldown_ms: COOLDOWN_MS,
+            max_restarts_per_hour: MAX_RESTARTS_PER_HOUR,
+            backoff: BackoffPolicy::default(),
+        }
+    }
+}
+
+/// Why a due restart was not performed
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SuppressReason {
+    /// The previous restart was too recent
+    Cooldown { remaining_ms: u64 },
+    /// The hourly restart cap was reached
+    HourlyLimit { retry_after_ms: u64 },
+}
+
+/// What happened during one watchdog tick
+///
+/// Serializes with an `event` field naming the variant (e.g. `"restart_failed"`).
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WatchdogEvent {
+    Healthy,
+    /// Probes failed, but not yet often enough to restart
+    Unhealthy {
+        failures: u32,
+        reasons: Vec<String>,
+    },
+    /// A restart was due but is held back
+    Suppressed {
+        reason: SuppressReason,
+        reasons: Vec<String>,
+    },
+    Restarted {
+        outcome: Outcome,
+        reasons: Vec<String>,
+    },
+    RestartFailed {
+        error: StuckbarError,
+        reasons: Vec<String>,
+    },
+}
+
+/// Health-checking loop that restarts the managed process when it gets stuck
+pub struct Watchdog<R: ProcessRunner> {
+    pub manager: ProcessManager<R>,
+    pub config: WatchdogConfig,
+    probes: Vec<Box<dyn HealthProbe>>,
+    consecutive_failures: u32,
+    restarts: VecDeque<Instant>,
+    backoff_level: u32,
+}
+
+impl<R: ProcessRunner> Watchdog<R> {
+    pub fn new(manager: ProcessManager<R>, config: WatchdogConfig) -> Self {
+        Self {
+            manager,
+            config,
+            probes: Vec::new(),
+            consecutive_failures: 0,
+            restarts: VecDeque::new(),
+            backoff_level: 0,
+        }
+    }
+
+    pub fn with_probe(mut self, probe: impl HealthProbe + 'static) -> Self {
+        self.probes.push(Box::new(probe));
+        self
+    }
+
+    /// Run probes once and restart if the failure threshold is reached
+    pub fn tick(&mut self) -> WatchdogEvent {
+        let now = self.manager.runner.now();
+        let ctx = ProbeContext {
+            runner: &self.manager.runner,
+            target: &self.manager.target,
+            now,
+        };
+        let reasons: Vec<String> = self
+            .probes
+            .iter_mut()
+            .filter_map(|probe| match probe.check(&ctx) {
+                ProbeStatus::Unhealthy(reason) => Some(format!("{}: {}", probe.name(), reason)),
+                _ => None,
+            })
+            .collect();
+
+        if reasons.is_empty() {
+            self.consecutive_failures = 0;
+            return WatchdogEvent::Healthy;
+        }
+
+        self.consecutive_failures += 1;
+        if self.consecutive_failures < self.config.failure_threshold {
+            return WatchdogEvent::Unhealthy {
+                failures: self.consecutive_failures,
+                reasons,
+            };
+        }
+
+        if let Some(reason) = self.suppress_reason(now) {
+            return WatchdogEvent::Suppressed { reason, reasons };
+        }
+
+        self.consecutive_failures = 0;
+        self.backoff_level += 1;
+        self.restarts.push_back(now);
+
+        let result = match self.manager.restart() {
+            // Nothing to kill: the process crashed without being relaunched. The
+            // limiter admitted this as a restart, so it learns how the start went.
+            Err(StuckbarError::NotRunning { .. }) => {
+                let result = self.manager.start();
+                if let Some(limiter) = &self.manager.limiter {
+                    limiter.record(SystemTime::now(), &result);
+                }
+                result
+            }
+            result => result,
+        };
+        match result {
+            Ok(outcome) => WatchdogEvent::Restarted { outcome, reasons },
+            Err(error) => WatchdogEvent::RestartFailed { error, reasons },
+        }
+    }
+
+    /// Run ticks until `on_event` returns `false`, sleeping the check interval in between
+    pub fn run(&mut self, mut on_event: impl FnMut(&WatchdogEvent) -> bool) {
+        loop {
+            let event = self.tick();
+            if !on_event(&event) {
+                return;
+            }
+            self.manager.runner.sleep_ms(self.config.interval_ms);
+        }
+    }
+
+    /// Cooldown currently in effect, grown by the backoff policy
+    pub fn current_cooldown_ms(&self) -> u64 {
+        let backoff = &self.config.backoff;
+        let factor =
+            (backoff.multiplier.max(1) as u64).saturating_pow(self.backoff_level.saturating_sub(1));
+        self.config
+            .cooldown_ms
+            .saturating_mul(factor)
+            .min(backoff.max_cooldown_ms.max(self.config.cooldown_ms))
+    }
+
+    fn suppress_reason(&mut self, now: Instant) -> Option<SuppressReason> {
+        while self
+            .restarts
+            .front()
+            .is_some_and(|at| now.saturating_duration_since(*at) >= HOUR)
+        {
+            self.restarts.pop_front();
+        }
+
+        let last = *self.restarts.back()?;
+        let since_last_ms = now.saturating_duration_since(last).as_millis() as u64;
+        if since_last_ms >= self.config.backoff.reset_after_ms {
+            self.backoff_level = 0;
+        }
+
+        let cooldown_ms = self.current_cooldown_ms();
+        if since_last_ms < cooldown_ms {
+            return Some(SuppressReason::Cooldown {
+                remaining_ms: cooldown_ms - since_last_ms,
+            });
+        }
+
+        if self.restarts.len() >= self.config.max_restarts_per_hour as usize {
+            let oldest = *self.restarts.front()?;
+            let retry_after = HOUR.saturating_sub(now.saturating_duration_since(oldest));
+            return Some(SuppressReason::HourlyLimit {
+                retry_after_ms: retry_after.as_millis() as u64,
+            });
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limiter::{RestartLimiter, RestartPolicy};
+    use crate::mock::MockProcessRunner;
+    use crate::{LivenessPolicy, ProcessUsage};
+    use std::sync::Arc;
+
+    /// Probe that returns a scripted sequence of statuses, then a fixed one
+    struct ScriptedProbe {
+        script: VecDeque<ProbeStatus>,
+        then: ProbeStatus,
+    }
+
+    impl ScriptedProbe {
+        fn new(script: impl IntoIterator<Item = ProbeStatus>) -> Self {
+            Self {
+                script: script.into_iter().collect(),
+                then: ProbeStatus::Healthy,
+            }
+        }
+
+        fn failing(times: usize) -> Self {
+            Self::new(std::iter::repeat_n(stuck(), times))
+        }
+
+        fn always_failing() -> Self {
+            Self {
+                script: VecDeque::new(),
+                then: stuck(),
+            }
+        }
+    }
+
+    impl HealthProbe for ScriptedProbe {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+
+        fn check(&mut self, _ctx: &ProbeContext<'_>) -> ProbeStatus {
+            self.script.pop_front().unwrap_or_else(|| self.then.clone())
+        }
+    }
+
+    fn stuck() -> ProbeStatus {
+        ProbeStatus::Unhealthy("stuck".to_string())
+    }
+
+    fn restartable_runner(restarts: usize) -> MockProcessRunner {
+        let mut runner = MockProcessRunner::new().with_running(&[42]);
+        for _ in 0..restarts {
+            runner = runner
+                .with_kill_result(Ok(Outcome::new("Killed")))
+                .with_start_result(Ok(Outcome::new("Started")));
+        }
+        runner
+    }
+
+    fn watchdog(runner: MockProcessRunner, config: WatchdogConfig) -> Watchdog<MockProcessRunner> {
+        let manager = ProcessManager::new(runner)
+            .with_restart_delay(0)
+            .with_liveness(LivenessPolicy {
+                settle_ms: 0,
+                ..Default::default()
+            });
+        Watchdog::new(manager, config)
+    }
+
+    fn usage(pid: u32, working_set_mb: u64, cpu_time_ms: u64) -> ProcessUsage {
+        ProcessUsage {
+            pid,
+            working_set_bytes: working_set_mb * MB,
+            cpu_time_ms,
+        }
+    }
+
+    fn ctx<'a>(runner: &'a MockProcessRunner, target: &'a TargetSpec) -> ProbeContext<'a> {
+        ProbeContext {
+            runner,
+            target,
+            now: runner.now(),
+        }
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config = WatchdogConfig::default();
+        assert_eq!(config.interval_ms, CHECK_INTERVAL_MS);
+        assert_eq!(config.failure_threshold, FAILURE_THRESHOLD);
+        assert_eq!(config.cooldown_ms, COOLDOWN_MS);
+        assert_eq!(config.max_restarts_per_hour, MAX_RESTARTS_PER_HOUR);
+    }
+
+    #[test]
+    fn test_healthy_without_probes() {
+        let mut dog = watchdog(restartable_runner(0), WatchdogConfig::default());
+        assert_eq!(dog.tick(), WatchdogEvent::Healthy);
+    }
+
+    #[test]
+    fn test_restarts_after_consecutive_failures() {
+        let mut dog = watchdog(restartable_runner(1), WatchdogConfig::default())
+            .with_probe(ScriptedProbe::failing(3));
+
+        assert!(matches!(
+            dog.tick(),
+            WatchdogEvent::Unhealthy { failures: 1, .. }
+        ));
+        assert!(matches!(
+            dog.tick(),
+            WatchdogEvent::Unhealthy { failures: 2, .. }
+        ));
+        match dog.tick() {
+            WatchdogEvent::Restarted { outcome, reasons } => {
+                assert_eq!(outcome.killed_pids, vec![42]);
+                assert_eq!(reasons, vec!["scripted: stuck"]);
+            }
+            other => panic!("expected restart, got {:?}", other),
+        }
+        assert_eq!(dog.tick(), WatchdogEvent::Healthy);
+    }
+
+    #[test]
+    fn test_healthy_check_resets_failure_count() {
+        let probe = ScriptedProbe::new([stuck(), stuck(), ProbeStatus::Healthy, stuck()]);
+        let mut dog = watchdog(restartable_runner(0), WatchdogConfig::default()).with_probe(probe);
+
+        dog.tick();
+        dog.tick();
+        assert_eq!(dog.tick(), WatchdogEvent::Healthy);
+        assert!(matches!(
+            dog.tick(),
+            WatchdogEvent::Unhealthy { failures: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn test_inconclusive_probe_is_not_a_failure() {
+        let probe = ScriptedProbe::new([ProbeStatus::Inconclusive("no data".to_string())]);
+        let config = WatchdogConfig {
+            failure_threshold: 1,
+            ..Default::default()
+        };
+        let mut dog = watchdog(restartable_runner(0), config).with_probe(probe);
+
+        assert_eq!(dog.tick(), WatchdogEvent::Healthy);
+    }
+
+    #[test]
+    fn test_cooldown_suppresses_restart() {
+        let config = WatchdogConfig {
+            failure_threshold: 1,
+            cooldown_ms: 10_000,
+            ..Default::default()
+        };
+        let mut dog =
+            watchdog(restartable_runner(2), config).with_probe(ScriptedProbe::always_failing());
+
+        assert!(matches!(dog.tick(), WatchdogEvent::Restarted { .. }));
+        dog.manager.runner.sleep_ms(4_000);
+        match dog.tick() {
+            WatchdogEvent::Suppressed {
+                reason: SuppressReason::Cooldown { remaining_ms },
+                ..
+            } => assert_eq!(remaining_ms, 6_000),
+            other => panic!("expected cooldown, got {:?}", other),
+        }
+        dog.manager.runner.sleep_ms(6_000);
+        assert!(matches!(dog.tick(), WatchdogEvent::Restarted { .. }));
+    }
+
+    #[test]
+    fn test_backoff_grows_cooldown() {
+        let config = WatchdogConfig {
+            failure_threshold: 1,
+            cooldown_ms: 1_000,
+            max_restarts_per_hour: 100,
+            ..Default::default()
+        };
+        let mut dog =
+            watchdog(restartable_runner(3), config).with_probe(ScriptedProbe::always_failing());
+
+        assert!(matches!(dog.tick(), WatchdogEvent::Restarted { .. }));
+        assert_eq!(dog.current_cooldown_ms(), 1_000);
+        dog.manager.runner.sleep_ms(1_000);
+        assert!(matches!(dog.tick(), WatchdogEvent::Restarted { .. }));
+        assert_eq!(dog.current_cooldown_ms(), 2_000);
+        dog.manager.runner.sleep_ms(1_000);
+        assert!(matches!(dog.tick(), WatchdogEvent::Suppressed { .. }));
+        dog.manager.runner.sleep_ms(1_000);
+        assert!(matches!(dog.tick(), WatchdogEvent::Restarted { .. }));
+        assert_eq!(dog.current_cooldown_ms(), 4_000);
+    }
+
+    #[test]
+    fn test_backoff_resets_after_quiet_period() {
+        let config = WatchdogConfig {
+            failure_threshold: 1,
+            cooldown_ms: 1_000,
+            max_restarts_per_hour: 100,
+            backoff: BackoffPolicy {
+                reset_after_ms: 30_000,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut dog =
+            watchdog(restartable_runner(3), config).with_probe(ScriptedProbe::always_failing());
+
+        dog.tick();
+        dog.manager.runner.sleep_ms(1_000);
+        dog.tick();
+        assert_eq!(dog.current_cooldown_ms(), 2_000);
+
+        dog.manager.runner.sleep_ms(30_000);
+        assert!(matches!(dog.tick(), WatchdogEvent::Restarted { .. }));
+        assert_eq!(dog.current_cooldown_ms(), 1_000);
+    }
+
+    #[test]
+    fn test_hourly_limit_suppresses_restart() {
+        let config = WatchdogConfig {
+            failure_threshold: 1,
+            cooldown_ms: 0,
+            max_restarts_per_hour: 2,
+            ..Default::default()
+        };
+        let mut dog =
+            watchdog(restartable_runner(3), config).with_probe(ScriptedProbe::always_failing());
+
+        assert!(matches!(dog.tick(), WatchdogEvent::Restarted { .. }));
+        dog.manager.runner.sleep_ms(60_000);
+        assert!(matches!(dog.tick(), WatchdogEvent::Restarted { .. }));
+        dog.manager.runner.sleep_ms(60_000);
+        match dog.tick() {
+            WatchdogEvent::Suppressed {
+                reason: SuppressReason::HourlyLimit { retry_after_ms },
+                ..
+            } => assert_eq!(retry_after_ms, 60 * 60 * 1000 - 120_000),
+            other => panic!("expected hourly limit, got {:?}", other),
+        }
+
+        dog.manager.runner.sleep_ms(60 * 60 * 1000);
+        assert!(matches!(dog.tick(), WatchdogEvent::Restarted { .. }));
+    }
+
+    #[test]
+    fn test_restart_failure_is_reported() {
+        let runner = MockProcessRunner::new()
+            .with_running(&[42])
+            .with_kill_result(Err(StuckbarError::AccessDenied {
+                process: "explorer.exe".to_string(),
+                details: "denied".to_string(),
+            }));
+        let config = WatchdogConfig {
+            failure_threshold: 1,
+            ..Default::default()
+        };
+        let mut dog = watchdog(runner, config).with_probe(ScriptedProbe::failing(1));
+
+        match dog.tick() {
+            WatchdogEvent::RestartFailed { error, .. } => assert_eq!(error.kind(), "access_denied"),
+            other => panic!("expected failure, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_missing_process_is_started() {
+        let runner = MockProcessRunner::new()
+            .with_kill_result(Err(StuckbarError::NotRunning {
+                process: "explorer.exe".to_string(),
+            }))
+            .with_start_result(Ok(Outcome::new("Started")));
+        let config = WatchdogConfig {
+            failure_threshold: 1,
+            ..Default::default()
+        };
+        let mut dog = watchdog(runner, config).with_probe(ProcessPresentProbe);
+
+        match dog.tick() {
+            WatchdogEvent::Restarted { outcome, reasons } => {
+                assert_eq!(outcome.new_pid, Some(1000));
+                assert_eq!(reasons, vec!["process: explorer.exe is not running"]);
+            }
+            other => panic!("expected restart, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_missing_process_start_counts_for_limiter() {
+        let runner = MockProcessRunner::new()
+            .with_kill_result(Err(StuckbarError::NotRunning {
+                process: "explorer.exe".to_string(),
+            }))
+            .with_start_result(Ok(Outcome::new("Started")))
+            .with_crash_after_start();
+        let limiter = Arc::new(RestartLimiter::new(RestartPolicy {
+            crash_window_ms: 30_000,
+            breaker_threshold: 1,
+            breaker_cooldown_ms: 60_000,
+            ..RestartPolicy::unlimited()
+        }));
+        let config = WatchdogConfig {
+            failure_threshold: 1,
+            cooldown_ms: 0,
+            ..Default::default()
+        };
+        let mut dog = watchdog(runner, config).with_probe(ProcessPresentProbe);
+        dog.manager.limiter = Some(limiter);
+
+        assert!(matches!(dog.tick(), WatchdogEvent::Restarted { .. }));
+        // The started instance is gone by the next restart, which opens the breaker
+        match dog.tick() {
+            WatchdogEvent::RestartFailed { error, .. } => assert_eq!(error.kind(), "rate_limited"),
+            other => panic!("expected rate limit, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_run_sleeps_interval_until_stopped() {
+        let mut dog = watchdog(restartable_runner(0), WatchdogConfig::default());
+        let mut ticks = 0;
+
+        dog.run(|_| {
+            ticks += 1;
+            ticks < 3
+        });
+
+        assert_eq!(ticks, 3);
+        assert_eq!(
+            dog.manager.runner.get_sleep_calls(),
+            vec![CHECK_INTERVAL_MS, CHECK_INTERVAL_MS]
+        );
+    }
+
+    // Probe tests
+    #[test]
+    fn test_process_present_probe() {
+        let target = TargetSpec::explorer();
+        let runner = MockProcessRunner::new().with_running(&[42]);
+        assert_eq!(
+            ProcessPresentProbe.check(&ctx(&runner, &target)),
+            ProbeStatus::Healthy
+        );
+
+        let runner = MockProcessRunner::new();
+        assert!(matches!(
+            ProcessPresentProbe.check(&ctx(&runner, &target)),
+            ProbeStatus::Unhealthy(_)
+        ));
+    }
+
+    #[test]
+    fn test_cpu_probe_needs_baseline() {
+        let target = TargetSpec::explorer();
+        let runner = MockProcessRunner::new().with_usage(vec![usage(42, 100, 0)]);
+        let mut probe = CpuPeggedProbe::new(90);
+
+        assert!(matches!(
+            probe.check(&ctx(&runner, &target)),
+            ProbeStatus::Inconclusive(_)
+        ));
+    }
+
+    #[test]
+    fn test_cpu_probe_detects_pegged_core() {
+        let target = TargetSpec::explorer();
+        let runner = MockProcessRunner::new()
+            .with_usage(vec![usage(42, 100, 1_000)])
+            .with_usage(vec![usage(42, 100, 5_900)])
+            .with_usage(vec![usage(42, 100, 6_000)]);
+        let mut probe = CpuPeggedProbe::new(90);
+
+        probe.check(&ctx(&runner, &target));
+        runner.sleep_ms(5_000);
+        assert_eq!(
+            probe.check(&ctx(&runner, &target)),
+            ProbeStatus::Unhealthy("PID 42 used 98% CPU (threshold 90%)".to_string())
+        );
+        runner.sleep_ms(5_000);
+        assert_eq!(probe.check(&ctx(&runner, &target)), ProbeStatus::Healthy);
+    }
+
+    #[test]
+    fn test_cpu_probe_new_pid_starts_fresh_baseline() {
+        let target = TargetSpec::explorer();
+        let runner = MockProcessRunner::new()
+            .with_usage(vec![usage(42, 100, 0)])
+            .with_usage(vec![usage(43, 100, 60_000)]);
+        let mut probe = CpuPeggedProbe::new(90);
+
+        probe.check(&ctx(&runner, &target));
+        runner.sleep_ms(5_000);
+        assert!(matches!(
+            probe.check(&ctx(&runner, &target)),
+            ProbeStatus::Inconclusive(_)
+        ));
+    }
+
+    #[test]
+    fn test_memory_probe_detects_growth() {
+        let target = TargetSpec::explorer();
+        let runner = MockProcessRunner::new()
+            .with_usage(vec![usage(42, 100, 0)])
+            .with_usage(vec![usage(42, 150, 0)])
+            .with_usage(vec![usage(42, 400, 0)]);
+        let mut probe = MemoryGrowthProbe::new(200 * MB);
+
+        assert_eq!(probe.check(&ctx(&runner, &target)), ProbeStatus::Healthy);
+        assert_eq!(probe.check(&ctx(&runner, &target)), ProbeStatus::Healthy);
+        assert_eq!(
+            probe.check(&ctx(&runner, &target)),
+            ProbeStatus::Unhealthy("PID 42 grew by 300 MB (allowed 200 MB)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_memory_probe_absolute_limit() {
+        let target = TargetSpec::explorer();
+        let runner = MockProcessRunner::new().with_usage(vec![usage(42, 900, 0)]);
+        let mut probe = MemoryGrowthProbe::new(u64::MAX).with_limit(512 * MB);
+
+        assert_eq!(
+            probe.check(&ctx(&runner, &target)),
+            ProbeStatus::Unhealthy("PID 42 uses 900 MB (limit 512 MB)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_memory_probe_without_data_is_inconclusive() {
+        let target = TargetSpec::explorer();
+        let runner = MockProcessRunner::new();
+        let mut probe = MemoryGrowthProbe::new(MB);
+
+        assert!(matches!(
+            probe.check(&ctx(&runner, &target)),
+            ProbeStatus::Inconclusive(_)
+        ));
+    }
+
+    #[test]
+    fn test_event_serializes_with_event_tag() {
+        let event = WatchdogEvent::Suppressed {
+            reason: SuppressReason::Cooldown { remaining_ms: 1500 },
+            reasons: vec!["stuck".to_string()],
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({
+                "event": "suppressed",
+                "reason": {"kind": "cooldown", "remaining_ms": 1500},
+                "reasons": ["stuck"],
+            })
+        );
+    }
+}
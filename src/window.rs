@@ -0,0 +1,525 @@
+//! # Taskbar Window Health
+//!
+//! A running explorer.exe whose taskbar no longer paints is the classic "stuck
+//! taskbar", and the process list alone cannot show it. This module locates the
+//! taskbar window (`Shell_TrayWnd`) and checks whether its thread still pumps
+//! messages, the same way Windows decides to show "(Not Responding)".
+//!
+//! The Win32 calls sit behind [`WindowInspector`], so [`check_taskbar`] and
+//! [`TaskbarProbe`] can be tested with a fake inspector on any platform.
+
+use crate::StuckbarError;
+use crate::watchdog::{HealthProbe, ProbeContext, ProbeStatus};
+use serde::{Serialize, Serializer, ser::SerializeStruct};
+
+/// Window class of the primary taskbar
+pub const TASKBAR_WINDOW_CLASS: &str = "Shell_TrayWnd";
+
+/// Default time in milliseconds the taskbar has to answer a message
+pub const RESPONSE_TIMEOUT_MS: u32 = 2_000;
+
+/// Message (`WM_USER + 436`) the taskbar's hidden "Exit Explorer" menu entry sends
+/// to make explorer save its state and exit cleanly
+pub const EXIT_EXPLORER_MESSAGE: u32 = 0x0400 + 436;
+
+/// `WM_CLOSE`, used when the exit message cannot be posted
+pub const CLOSE_MESSAGE: u32 = 0x0010;
+
+/// Opaque handle to a top-level window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowHandle(pub isize);
+
+/// Trait for abstracting window queries (enables testing)
+pub trait WindowInspector {
+    /// Find the top-level window with the given class name
+    fn find_window(&self, class_name: &str) -> Option<WindowHandle>;
+    /// Whether Windows already considers the window hung
+    fn is_hung(&self, window: WindowHandle) -> bool;
+    /// Whether the window answers a no-op message within `timeout_ms`
+    fn responds_within(&self, window: WindowHandle, timeout_ms: u32) -> bool;
+    /// Post `message` to the window without waiting for it to be handled
+    fn post_message(&self, window: WindowHandle, message: u32) -> bool;
+}
+
+/// Health of the taskbar window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskbarHealth {
+    Responsive,
+    /// The window exists but does not process messages
+    Hung {
+        reason: String,
+    },
+    /// No taskbar window exists (explorer is not running or still starting)
+    Missing,
+}
+
+impl TaskbarHealth {
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, Self::Responsive)
+    }
+
+    /// Stable, machine-readable identifier for the health state
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Responsive => "responsive",
+            Self::Hung { .. } => "hung",
+            Self::Missing => "missing",
+        }
+    }
+
+    /// Human-readable description of the health state
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Responsive => "Taskbar is responding".to_string(),
+            Self::Hung { reason } => format!("Taskbar is not responding: {}", reason),
+            Self::Missing => "Taskbar window not found".to_string(),
+        }
+    }
+
+    /// `Ok` if the taskbar responds
+    ///
+    /// # Errors
+    ///
+    /// Returns [`StuckbarError::Unhealthy`] if it is hung or missing.
+    pub fn ensure_healthy(&self) -> Result<(), StuckbarError> {
+        if self.is_healthy() {
+            return Ok(());
+        }
+        Err(StuckbarError::Unhealthy {
+            details: self.describe(),
+        })
+    }
+}
+
+/// Serializes as `{"status": ..., "healthy": ..., "message": ...}`
+impl Serialize for TaskbarHealth {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut health = serializer.serialize_struct("TaskbarHealth", 3)?;
+        health.serialize_field("status", self.label())?;
+        health.serialize_field("healthy", &self.is_healthy())?;
+        health.serialize_field("message", &self.describe())?;
+        health.end()
+    }
+}
+
+/// Locate the taskbar and check that it responds within `timeout_ms`
+pub fn check_taskbar(inspector: &dyn WindowInspector, timeout_ms: u32) -> TaskbarHealth {
+    let Some(window) = inspector.find_window(TASKBAR_WINDOW_CLASS) else {
+        return TaskbarHealth::Missing;
+    };
+
+    // Windows flags a window as hung after ~5s without pumping messages;
+    // checking that first avoids waiting out the timeout needlessly
+    if inspector.is_hung(window) {
+        return TaskbarHealth::Hung {
+            reason: "Windows reports the window as hung".to_string(),
+        };
+    }
+
+    if !inspector.responds_within(window, timeout_ms) {
+        return TaskbarHealth::Hung {
+            reason: format!("no reply within {}ms", timeout_ms),
+        };
+    }
+
+    TaskbarHealth::Responsive
+}
+
+/// Ask explorer to exit cleanly through its taskbar window
+///
+/// Posts [`EXIT_EXPLORER_MESSAGE`], falling back to [`CLOSE_MESSAGE`]. Returns
+/// whether a message was posted; explorer exits asynchronously afterwards.
+pub fn request_taskbar_exit(inspector: &dyn WindowInspector) -> bool {
+    let Some(window) = inspector.find_window(TASKBAR_WINDOW_CLASS) else {
+        return false;
+    };
+    inspector.post_message(window, EXIT_EXPLORER_MESSAGE)
+        || inspector.post_message(window, CLOSE_MESSAGE)
+}
+
+/// Ask the processes `pids` to exit by posting [`CLOSE_MESSAGE`] to their
+/// visible top-level windows, as `taskkill` without `/F` does
+///
+/// Returns whether a message was posted; the processes exit asynchronously
+/// afterwards.
+#[cfg(windows)]
+pub fn close_process_windows(pids: &[u32]) -> bool {
+    win32::close_windows(pids)
+}
+
+/// Ask the processes `pids` to exit; there are no windows to close on platforms
+/// other than Windows, so nothing is posted
+#[cfg(not(windows))]
+pub fn close_process_windows(_pids: &[u32]) -> bool {
+    false
+}
+
+/// Watchdog probe that fails while the taskbar window is hung or missing
+pub struct TaskbarProbe<W: WindowInspector> {
+    inspector: W,
+    timeout_ms: u32,
+}
+
+impl<W: WindowInspector> TaskbarProbe<W> {
+    pub fn new(inspector: W) -> Self {
+        Self {
+            inspector,
+            timeout_ms: RESPONSE_TIMEOUT_MS,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout_ms: u32) -> Self {
+        self.timeout_ms = timeout_ms;
+        self
+    }
+}
+
+impl<W: WindowInspector> HealthProbe for TaskbarProbe<W> {
+    fn name(&self) -> &str {
+        "window"
+    }
+
+    fn check(&mut self, _ctx: &ProbeContext<'_>) -> ProbeStatus {
+        match check_taskbar(&self.inspector, self.timeout_ms) {
+            TaskbarHealth::Responsive => ProbeStatus::Healthy,
+            health => ProbeStatus::Unhealthy(health.describe()),
+        }
+    }
+}
+
+/// Real implementation backed by the Win32 API
+///
+/// On other platforms no window is ever found.
+pub struct SystemWindowInspector;
+
+#[cfg(windows)]
+impl WindowInspector for SystemWindowInspector {
+    fn find_window(&self, class_name: &str) -> Option<WindowHandle> {
+        win32::find_window(class_name)
+    }
+
+    fn is_hung(&self, window: WindowHandle) -> bool {
+        win32::is_hung(window)
+    }
+
+    fn responds_within(&self, window: WindowHandle, timeout_ms: u32) -> bool {
+        win32::responds_within(window, timeout_ms)
+    }
+
+    fn post_message(&self, window: WindowHandle, message: u32) -> bool {
+        win32::post_message(window, message)
+    }
+}
+
+#[cfg(not(windows))]
+impl WindowInspector for SystemWindowInspector {
+    fn find_window(&self, _class_name: &str) -> Option<WindowHandle> {
+        None
+    }
+
+    fn is_hung(&self, _window: WindowHandle) -> bool {
+        false
+    }
+
+    fn responds_within(&self, _window: WindowHandle, _timeout_ms: u32) -> bool {
+        false
+    }
+
+    fn post_message(&self, _window: WindowHandle, _message: u32) -> bool {
+        false
+    }
+}
+
+#[cfg(windows)]
+pub(crate) mod win32 {
+    use super::WindowHandle;
+    use windows_sys::Win32::Foundation::{HWND, LPARAM};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        EnumWindows, FindWindowW, GetWindowThreadProcessId, IsHungAppWindow, IsWindowVisible,
+        PostMessageW, SMTO_ABORTIFHUNG, SendMessageTimeoutW, WM_CLOSE, WM_NULL,
+    };
+    use windows_sys::core::BOOL;
+
+    /// Encode a string as NUL-terminated UTF-16
+    pub(crate) fn wide(value: &str) -> Vec<u16> {
+        value.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+
+    pub(crate) fn hwnd(window: WindowHandle) -> HWND {
+        window.0 as HWND
+    }
+
+    pub(crate) fn find_window(class_name: &str) -> Option<WindowHandle> {
+        let class = wide(class_name);
+        // SAFETY: `class` is NUL-terminated and outlives the call; a null window
+        // name matches any title
+        let hwnd = unsafe { FindWindowW(class.as_ptr(), std::ptr::null()) };
+        (!hwnd.is_null()).then_some(WindowHandle(hwnd as isize))
+    }
+
+    pub(crate) fn is_hung(window: WindowHandle) -> bool {
+        // SAFETY: IsHungAppWindow accepts any handle and returns FALSE for invalid ones
+        unsafe { IsHungAppWindow(hwnd(window)) != 0 }
+    }
+
+    pub(crate) fn responds_within(window: WindowHandle, timeout_ms: u32) -> bool {
+        let mut result = 0usize;
+        // SAFETY: `result` is a valid out pointer for the duration of the call
+        let sent = unsafe {
+            SendMessageTimeoutW(
+                hwnd(window),
+                WM_NULL,
+                0,
+                0,
+                SMTO_ABORTIFHUNG,
+                timeout_ms,
+                &mut result,
+            )
+        };
+        sent != 0
+    }
+
+    pub(crate) fn post_message(window: WindowHandle, message: u32) -> bool {
+        // SAFETY: PostMessageW accepts any handle and fails for invalid ones
+        unsafe { PostMessageW(hwnd(window), message, 0, 0) != 0 }
+    }
+
+    /// Windows of the processes being closed, and whether one was posted to
+    struct CloseSearch<'a> {
+        pids: &'a [u32],
+        posted: bool,
+    }
+
+    unsafe extern "system" fn close_if_owned(window: HWND, search: LPARAM) -> BOOL {
+        // SAFETY: `search` is the CloseSearch that close_windows passed to EnumWindows
+        let search = unsafe { &mut *(search as *mut CloseSearch<'_>) };
+        let mut pid = 0;
+        // SAFETY: `window` comes from the enumeration and `pid` is a valid out pointer
+        unsafe { GetWindowThreadProcessId(window, &mut pid) };
+        // SAFETY: both calls accept any handle
+        if search.pids.contains(&pid)
+            && unsafe { IsWindowVisible(window) } != 0
+            && unsafe { PostMessageW(window, WM_CLOSE, 0, 0) } != 0
+        {
+            search.posted = true;
+        }
+        // Keep enumerating
+        1
+    }
+
+    pub(crate) fn close_windows(pids: &[u32]) -> bool {
+        let mut search = CloseSearch {
+            pids,
+            posted: false,
+        };
+        // SAFETY: EnumWindows calls `close_if_owned` synchronously, while `search` is alive
+        unsafe {
+            EnumWindows(
+                Some(close_if_owned),
+                &mut search as *mut CloseSearch<'_> as LPARAM,
+            )
+        };
+        search.posted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProcessRunner;
+    use crate::TargetSpec;
+    use crate::mock::MockProcessRunner;
+    use std::cell::RefCell;
+
+    /// Fake inspector with a scripted taskbar state
+    #[derive(Default)]
+    struct FakeInspector {
+        window: Option<WindowHandle>,
+        hung: bool,
+        responds: bool,
+        accepts_posts: Vec<u32>,
+        pings: RefCell<Vec<u32>>,
+        posted: RefCell<Vec<u32>>,
+    }
+
+    impl FakeInspector {
+        fn responsive() -> Self {
+            Self {
+                window: Some(WindowHandle(0x1234)),
+                responds: true,
+                ..Default::default()
+            }
+        }
+    }
+
+    impl WindowInspector for FakeInspector {
+        fn find_window(&self, class_name: &str) -> Option<WindowHandle> {
+            assert_eq!(class_name, TASKBAR_WINDOW_CLASS);
+            self.window
+        }
+
+        fn is_hung(&self, _window: WindowHandle) -> bool {
+            self.hung
+        }
+
+        fn responds_within(&self, _window: WindowHandle, timeout_ms: u32) -> bool {
+            self.pings.borrow_mut().push(timeout_ms);
+            self.responds
+        }
+
+        fn post_message(&self, _window: WindowHandle, message: u32) -> bool {
+            self.posted.borrow_mut().push(message);
+            self.accepts_posts.contains(&message)
+        }
+    }
+
+    #[test]
+    fn test_check_taskbar_responsive() {
+        let inspector = FakeInspector::responsive();
+        let health = check_taskbar(&inspector, 500);
+
+        assert_eq!(health, TaskbarHealth::Responsive);
+        assert!(health.is_healthy());
+        assert_eq!(*inspector.pings.borrow(), vec![500]);
+    }
+
+    #[test]
+    fn test_check_taskbar_missing() {
+        let inspector = FakeInspector::default();
+        let health = check_taskbar(&inspector, 500);
+
+        assert_eq!(health, TaskbarHealth::Missing);
+        assert_eq!(health.label(), "missing");
+        assert!(inspector.pings.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_check_taskbar_flagged_hung_skips_ping() {
+        let inspector = FakeInspector {
+            hung: true,
+            ..FakeInspector::responsive()
+        };
+        let health = check_taskbar(&inspector, 500);
+
+        assert_eq!(health.label(), "hung");
+        assert!(inspector.pings.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_check_taskbar_ping_timeout() {
+        let inspector = FakeInspector {
+            responds: false,
+            ..FakeInspector::responsive()
+        };
+
+        assert_eq!(
+            check_taskbar(&inspector, 750),
+            TaskbarHealth::Hung {
+                reason: "no reply within 750ms".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_request_taskbar_exit_posts_exit_message() {
+        let inspector = FakeInspector {
+            accepts_posts: vec![EXIT_EXPLORER_MESSAGE, CLOSE_MESSAGE],
+            ..FakeInspector::responsive()
+        };
+
+        assert!(request_taskbar_exit(&inspector));
+        assert_eq!(*inspector.posted.borrow(), vec![EXIT_EXPLORER_MESSAGE]);
+    }
+
+    #[test]
+    fn test_request_taskbar_exit_falls_back_to_close() {
+        let inspector = FakeInspector {
+            accepts_posts: vec![CLOSE_MESSAGE],
+            ..FakeInspector::responsive()
+        };
+
+        assert!(request_taskbar_exit(&inspector));
+        assert_eq!(
+            *inspector.posted.borrow(),
+            vec![EXIT_EXPLORER_MESSAGE, CLOSE_MESSAGE]
+        );
+    }
+
+    #[test]
+    fn test_request_taskbar_exit_without_taskbar() {
+        let inspector = FakeInspector::default();
+
+        assert!(!request_taskbar_exit(&inspector));
+        assert!(inspector.posted.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_taskbar_health_describe() {
+        assert_eq!(
+            TaskbarHealth::Responsive.describe(),
+            "Taskbar is responding"
+        );
+        assert_eq!(
+            TaskbarHealth::Missing.describe(),
+            "Taskbar window not found"
+        );
+    }
+
+    #[test]
+    fn test_unhealthy_taskbar_is_an_error() {
+        assert!(TaskbarHealth::Responsive.ensure_healthy().is_ok());
+
+        let error = TaskbarHealth::Missing.ensure_healthy().unwrap_err();
+        assert_eq!(error.kind(), "unhealthy");
+        assert_eq!(error.to_string(), "Taskbar window not found");
+    }
+
+    #[test]
+    fn test_taskbar_health_serializes() {
+        let health = TaskbarHealth::Hung {
+            reason: "no reply within 10ms".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&health).unwrap(),
+            serde_json::json!({
+                "status": "hung",
+                "healthy": false,
+                "message": "Taskbar is not responding: no reply within 10ms",
+            })
+        );
+    }
+
+    #[test]
+    fn test_taskbar_probe() {
+        let runner = MockProcessRunner::new();
+        let target = TargetSpec::explorer();
+        let ctx = ProbeContext {
+            runner: &runner,
+            target: &target,
+            now: runner.now(),
+        };
+
+        let mut probe = TaskbarProbe::new(FakeInspector::responsive());
+        assert_eq!(probe.name(), "window");
+        assert_eq!(probe.check(&ctx), ProbeStatus::Healthy);
+
+        let hung = FakeInspector {
+            responds: false,
+            ..FakeInspector::responsive()
+        };
+        let mut probe = TaskbarProbe::new(hung).with_timeout(100);
+        assert_eq!(
+            probe.check(&ctx),
+            ProbeStatus::Unhealthy("Taskbar is not responding: no reply within 100ms".to_string())
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_system_inspector_finds_nothing_off_windows() {
+        assert_eq!(
+            check_taskbar(&SystemWindowInspector, 10),
+            TaskbarHealth::Missing
+        );
+    }
+}